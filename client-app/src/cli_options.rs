@@ -13,4 +13,10 @@ pub struct OrangeCliArgs {
     pub assets_directory: Option<PathBuf>,
     #[arg(short, long, value_name = "DIR")]
     pub default_resources: Option<PathBuf>,
+    /// Check the resource packs for broken models and blockstates, then exit
+    #[arg(long)]
+    pub validate_resources: bool,
+    /// Bake the resource packs and export every model to OBJ and glTF in DIR, then exit
+    #[arg(long, value_name = "DIR")]
+    pub export_models: Option<PathBuf>,
 }
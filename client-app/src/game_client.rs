@@ -3,6 +3,7 @@
 use rustc_hash::FxHashMap as HashMap;
 
 use crate::client::camera::{Camera, CameraController, Projection};
+use orange_rs::font::BitmapFont;
 use orange_rs::math_helper::angle;
 use crate::rendering::textures::{DepthTextureWrapper, DiffuseTextureWrapper};
 use ultraviolet::Mat4;
@@ -21,6 +22,8 @@ pub struct Client {
     cursor_visible: bool,
 
     pub textures: TexMapType,
+    /// The glyph widths of the font texture of the resource packs
+    font: BitmapFont,
     pub depth_texture: DepthTextureWrapper,
 
     pipelines: HashMap<String, wgpu::RenderPipeline>,
@@ -56,6 +59,7 @@ impl Client {
             cursor_visible: true,
 
             textures: HashMap::default(),
+            font: BitmapFont::monospace(),
             depth_texture,
 
             pipelines: HashMap::default(),
@@ -67,6 +71,14 @@ impl Client {
 
 
 
+    pub fn get_font(&self) -> &BitmapFont {
+        &self.font
+    }
+
+    pub fn set_font(&mut self, font: BitmapFont) {
+        self.font = font;
+    }
+
     pub fn get_texture<T: AsRef<str>>(&self, id: T) -> Option<&DiffuseTextureWrapper> {
         self.textures.get(id.as_ref())
     }
@@ -99,6 +111,10 @@ impl Client {
         self.pipelines.insert(id.as_ref().to_string(), pipeline);
     }
 
+    pub fn remove_pipeline<T: AsRef<str>>(&mut self, id: T) {
+        self.pipelines.remove(id.as_ref());
+    }
+
     pub fn get_buffer<T: AsRef<str>>(&self, id: T) -> Option<&wgpu::Buffer> {
         self.buffers.get(id.as_ref())
     }
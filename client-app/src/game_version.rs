@@ -1,17 +1,17 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crunch::Rotation;
 use image::{GenericImage, GenericImageView};
 use crate::rendering::textures::DiffuseTextureWrapper;
-use rustc_hash::FxHashMap as HashMap;
-use ultraviolet::Vec2;
-use orange_rs::{block::Block, sprites::Sprite, models::{BlockstateParseError, self, generate_blockstate_model, model::{VoxelModel, BakedModel}}};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet};
+use ultraviolet::{Vec2, Vec3};
+use orange_rs::{block::Block, sprites::{Sprite, mipmap}, models::{BlockstateParseError, self, generate_blockstate_model, model::{VoxelModel, BlockstateModel, BuiltinModel}, item::{self, DisplayContext, ModelTransform, ItemModel}, validation::{self, ValidationReport}, export::{self, ExportMesh}}, util::pos::BlockPos};
 use orange_rs::block::properties::PropertyDefinition;
 use orange_rs::minecraft::asset_loader::AssetLoader;
-use orange_rs::minecraft::{blocks, asset_loader};
+use orange_rs::minecraft::{blocks, asset_loader, colors::{self, Colormap}};
 use orange_rs::minecraft::filetypes::{MCModel, MCAtlasSource};
 use orange_rs::minecraft::identifier::Identifier;
-use orange_rs::minecraft::registry::Registry;
+use orange_rs::minecraft::registry::{FrozenRegistry, Registry};
 use orange_rs::resource_loader;
 
 use crate::game_client::Client;
@@ -74,21 +74,36 @@ fn register_blocks(registry: &mut Registry) {
 
 }
 
+fn register_block_colors(registry: &mut Registry) {
+    for (block, provider) in colors::block_color_providers() {
+        registry.get_block_colors_mut().with_provider_nc(block, provider);
+    }
+}
+
 pub fn register_content(registry: &mut Registry) {
     register_properties(registry);
     register_blocks(registry);
+    register_block_colors(registry);
 }
 
-// TODO: Check for infinite recursion through already visited models
-fn make_model(registry: &Registry, identifier: &Identifier, model_files: &HashMap<Identifier, MCModel>, voxel_models: &mut HashMap<Identifier, VoxelModel>) -> Option<VoxelModel> {
-    let already_visited = false;
-    if already_visited { return None; }
+/// `visiting` holds the chain of models currently being made, a model that is already in it has a cyclic parent
+fn make_model(registry: &Registry, identifier: &Identifier, model_files: &HashMap<Identifier, MCModel>, voxel_models: &mut HashMap<Identifier, VoxelModel>, visiting: &mut Vec<Identifier>) -> Option<VoxelModel> {
+    if visiting.contains(identifier) {
+        let chain: Vec<String> = visiting.iter().chain(std::iter::once(identifier)).map(Identifier::to_string).collect();
+        log::warn!("Model parents form a cycle: {}", chain.join(" -> "));
+        return None;
+    }
+
+    if let Some(builtin) = BuiltinModel::from_identifier(identifier) {
+        return Some(VoxelModel::new().with_builtin(builtin));
+    }
 
     if !model_files.contains_key(identifier) {
         log::warn!("No model file for {}", identifier);
         return None;
     }
-    model_files.get(identifier).and_then(|model_file| {
+    visiting.push(identifier.clone());
+    let model = model_files.get(identifier).and_then(|model_file| {
         let mut model = match model_file.get_parent() {
             Some(parent) => {
                 // Apply models ontop of parent
@@ -96,7 +111,7 @@ fn make_model(registry: &Registry, identifier: &Identifier, model_files: &HashMa
                 let parent_model = if voxel_models.contains_key(&parent_id) {
                     voxel_models.get(&parent_id)
                 } else {
-                    make_model(registry, &parent_id, model_files, voxel_models).and_then(|model| {
+                    make_model(registry, &parent_id, model_files, voxel_models, visiting).and_then(|model| {
                         voxel_models.insert(parent_id.clone(), model);
                         voxel_models.get(&parent_id)
                     })
@@ -126,30 +141,197 @@ fn make_model(registry: &Registry, identifier: &Identifier, model_files: &HashMa
                 let voxel_element = element.to_voxel_element();
                 model.with_element_nc(voxel_element);
             }
+            for (context_name, display) in model_file.display() {
+                match DisplayContext::from_name(&context_name) {
+                    Some(context) => model.with_display_nc(context, ModelTransform::from(&display)),
+                    None => log::warn!("Model {} has an unknown display context {}", identifier, context_name),
+                }
+            }
         }
 
         model
-    })
+    });
+    visiting.pop();
+    model
+}
+
+/// Where resource packs are loaded from, the default pack plus every pack in the resource packs folder
+pub struct ResourcePaths {
+    pub assets_directory: PathBuf,
+    pub default_resources: PathBuf,
+    pub resource_packs_directory: PathBuf,
 }
 
-pub fn load_resources(assets_directory: &PathBuf, default_resources: Option<PathBuf>) -> AssetLoader {
-    let default_resourcepack = default_resources.unwrap_or_else(|| assets_directory.join("b173.zip"));
-    let resource_locations = vec![default_resourcepack];
+impl ResourcePaths {
+    pub fn new(assets_directory: PathBuf, default_resources: Option<PathBuf>, resource_packs_directory: PathBuf) -> Self {
+        let default_resources = default_resources.unwrap_or_else(|| assets_directory.join("b173.zip"));
+        Self { assets_directory, default_resources, resource_packs_directory }
+    }
+
+    /// Highest priority first, packs in the resource packs folder are ordered by name
+    pub fn sources(&self) -> Vec<PathBuf> {
+        let mut packs: Vec<PathBuf> = std::fs::read_dir(&self.resource_packs_directory)
+            .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+            .unwrap_or_default();
+        packs.sort();
+        packs.push(self.default_resources.clone());
+        packs
+    }
+
+    /// The paths to watch for changes, new packs dropped into the resource packs folder are picked up too
+    pub fn watched(&self) -> Vec<PathBuf> {
+        vec![self.resource_packs_directory.clone(), self.default_resources.clone()]
+    }
+}
+
+pub fn load_resources(resource_paths: &ResourcePaths) -> AssetLoader {
+    let resource_locations = resource_paths.sources();
     let mut resource_loader = resource_loader::ResourceLoader::new();
     resource_loader.set_sources(&resource_locations);
 
     let mut asset_loader = asset_loader::AssetLoader::new();
-    asset_loader.preload("b173", assets_directory);
+    asset_loader.preload("b173", &resource_paths.assets_directory);
     resource_loader.reload_system(&mut asset_loader);
 
     asset_loader
 }
 
-pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader: &AssetLoader, device: &wgpu::Device, queue: &wgpu::Queue) {
+/// The names of every sprite the game atlas will contain
+fn atlas_sprite_identifiers(asset_loader: &AssetLoader) -> FxHashSet<Identifier> {
+    let mut sprites: FxHashSet<Identifier> = asset_loader.atlases().get(&Identifier::from_str("game")).iter()
+        .flat_map(|atlas| atlas.sources.iter())
+        .flat_map(|source| match source {
+            MCAtlasSource::Unstitch { regions, .. } => regions.iter().map(|region| Identifier::from_str(&region.sprite)).collect(),
+            _ => vec![],
+        })
+        .collect();
+    sprites.insert(Identifier::from_str("block/missing"));
+    sprites
+}
+
+/// Load the resource packs without creating a window and check them for broken models and blockstates
+pub fn validate_resources(resource_paths: &ResourcePaths) -> ValidationReport {
+    let mut registry = Registry::new();
+    register_content(&mut registry);
+    let asset_loader = load_resources(resource_paths);
+    let sprites = atlas_sprite_identifiers(&asset_loader);
+    validation::validate_resources(asset_loader.models(), asset_loader.blockstates(), &sprites, registry.get_blockstate_register(), registry.get_property_register())
+}
 
+/// The name the atlas is written under next to exported geometry, the exported materials refer to it
+const EXPORT_ATLAS_FILE_NAME: &str = "atlas.png";
 
-    let model_files = asset_loader.models();
-    let blockstate_files = asset_loader.blockstates();
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+    #[error("Could not write the export: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not write the atlas: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Write objects to `directory` as both `name.obj` and `name.gltf`, along with the atlas they are textured by
+pub fn write_export(directory: &Path, name: &str, objects: &[(&str, &ExportMesh)], atlas: &image::RgbaImage) -> Result<(), ExportError> {
+    std::fs::create_dir_all(directory)?;
+    atlas.save(directory.join(EXPORT_ATLAS_FILE_NAME))?;
+    export::export_obj(&directory.join(format!("{name}.obj")), objects, EXPORT_ATLAS_FILE_NAME)?;
+    export::export_gltf(&directory.join(format!("{name}.gltf")), objects, EXPORT_ATLAS_FILE_NAME)?;
+    Ok(())
+}
+
+/**
+ * Bake the resource packs without creating a window and export every blockstate and item model,
+ * laid out on a grid with a block of space between them, blockstates first then items as shown in the gui
+ */
+pub fn export_models(resource_paths: &ResourcePaths, directory: &Path) -> Result<(), ExportError> {
+    let mut registry = Registry::new();
+    register_content(&mut registry);
+    let asset_loader = load_resources(resource_paths);
+    let atlas = stitch_atlas(&mut registry, &asset_loader);
+    bake_models(&mut registry, &asset_loader, &atlas.image);
+
+    let mut blockstate_ids: Vec<&Identifier> = registry.get_model_register().keys().collect();
+    blockstate_ids.sort_by_key(|id| id.to_string());
+    let mut item_ids: Vec<&Identifier> = registry.get_item_model_register().keys().collect();
+    item_ids.sort_by_key(|id| id.to_string());
+
+    let columns = ((blockstate_ids.len() + item_ids.len()) as f32).sqrt().ceil().max(1.0) as usize;
+    let grid_offset = |index: usize| Vec3::new((index % columns) as f32 * 2.0, 0.0, (index / columns) as f32 * 2.0);
+    let mut objects: Vec<(String, ExportMesh)> = vec![];
+    for id in blockstate_ids {
+        let mut mesh = ExportMesh::new();
+        for model in registry.get_model_register()[id].select(&BlockPos::zero()) {
+            mesh.add_model(model, grid_offset(objects.len()));
+        }
+        objects.push((id.to_string(), mesh));
+    }
+    for id in item_ids {
+        let mut mesh = ExportMesh::new();
+        if let Some(model) = registry.get_item_model_register()[id].get(DisplayContext::Gui) {
+            // Item models are centered on the origin
+            mesh.add_model(model, grid_offset(objects.len()) + Vec3::broadcast(0.5));
+        }
+        objects.push((id.to_string(), mesh));
+    }
+
+    let objects: Vec<(&str, &ExportMesh)> = objects.iter().map(|(name, mesh)| (name.as_str(), mesh)).collect();
+    write_export(directory, "models", &objects, &atlas.image)
+}
+
+/// Number of mip levels generated below the full size atlas
+const ATLAS_MIP_LEVELS: u32 = 4;
+
+/// The mcmeta of a sprite is looked up by the sprite name first, then by the texture it was cut from
+fn find_sprite_properties(asset_loader: &AssetLoader, target_id: &Identifier, source_id: &Identifier) -> mipmap::SpriteMipProperties {
+    let target_meta = Identifier::new(target_id.get_namespace(), format!("{}.png", target_id.get_name()));
+    let properties = [&target_meta, source_id].into_iter()
+        .filter_map(|id| asset_loader.mcmeta().get(id))
+        .find_map(|meta| meta.get_properties())
+        .map(mipmap::SpriteMipProperties::from);
+    properties.unwrap_or_default()
+}
+
+/// The game atlas before it is uploaded, the sprite register already points into it
+pub struct StitchedAtlas {
+    pub image: image::RgbaImage,
+    mip_regions: Vec<mipmap::MipRegion>,
+}
+
+/// Bake the sprites and models of the loaded resource packs, returns the atlas image the models point into
+pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader: &AssetLoader, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+    let atlas = stitch_atlas(registry, asset_loader);
+    upload_atlas(client, &atlas, device, queue);
+    bake_models(registry, asset_loader, &atlas.image);
+    load_colormaps(registry, asset_loader);
+    atlas.image
+}
+
+/// Register the content and bake the loaded resources into a registry, frozen so any thread can read it
+pub fn create_registry(client: &mut Client, asset_loader: &AssetLoader, device: &wgpu::Device, queue: &wgpu::Queue) -> (FrozenRegistry, image::RgbaImage) {
+    let mut registry = Registry::new();
+    register_content(&mut registry);
+    let atlas_image = bake_resources(&mut registry, client, asset_loader, device, queue);
+    (registry.freeze(), atlas_image)
+}
+
+/// The first colormap sprite the packs have, or a colormap of a single color
+fn find_colormap(asset_loader: &AssetLoader, sprites: &[&str], fallback: u32) -> Colormap {
+    sprites.iter()
+        .find_map(|sprite| asset_loader.sprites().get(&Identifier::from_str(sprite)))
+        .map(|image| Colormap::new(image.to_rgba8(), colors::rgb(fallback)))
+        .unwrap_or_else(|| {
+            log::warn!("No colormap found at {:?}, using a single color", sprites);
+            Colormap::fallback(colors::rgb(fallback))
+        })
+}
+
+fn load_colormaps(registry: &mut Registry, asset_loader: &AssetLoader) {
+    let grass = find_colormap(asset_loader, &colors::GRASS_COLORMAP_SPRITES, colors::GRASS_FALLBACK_COLOR);
+    let foliage = find_colormap(asset_loader, &colors::FOLIAGE_COLORMAP_SPRITES, colors::FOLIAGE_FALLBACK_COLOR);
+    registry.get_block_colors_mut().set_colormaps(grass, foliage);
+}
+
+/// Pack every sprite of the game atlas into one image and register where each sprite ended up
+pub fn stitch_atlas(registry: &mut Registry, asset_loader: &AssetLoader) -> StitchedAtlas {
 
     #[derive(Clone, Debug)]
     struct Thingy {
@@ -188,28 +370,58 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
         }
     }
     items.push(crunch::Item::new(Thingy { source_id: Identifier::from_str("block/missing"), target_id: Identifier::from_str("block/missing"), start_x: 0, start_y: 0 }, 2, 2, Rotation::None));
-    let result = crunch::pack_into_po2(4096, items).expect("Couldnt Pack Properly");
-    let mut game_texture = image::RgbaImage::new(result.w.try_into().unwrap(), result.h.try_into().unwrap());
+
+    // Sprites are packed into cells aligned to the smallest mip level so that no two sprites share a pixel at any level,
+    // the rest of the cell around the sprite is filled with bled copies of it
+    let cell_align = 1usize << ATLAS_MIP_LEVELS;
+    let cell_units = |size: usize| size.div_ceil(cell_align);
+    let items = items.into_iter().map(|item| {
+        let (w, h) = (cell_units(item.w), cell_units(item.h));
+        crunch::Item::new((item.data, item.w, item.h), w, h, Rotation::None)
+    });
+    let result = crunch::pack_into_po2(4096 / cell_align, items).expect("Couldnt Pack Properly");
+    let mut game_texture = image::RgbaImage::new((result.w * cell_align).try_into().unwrap(), (result.h * cell_align).try_into().unwrap());
     let atlas_width = game_texture.width();
     let atlas_height = game_texture.height();
+    let mut mip_regions = vec![];
     // log::warn!("Texture Packing Result: {:?}", result.items);
     // log::warn!("Packed Items: {}x{}", atlas_width, atlas_height);
     for item in result.items {
-        let sprite_width = item.rect.w as u32;
-        let sprite_height = item.rect.h as u32;
-        let sprite_atlas_x = item.rect.x as u32;
-        let sprite_atlas_y = item.rect.y as u32;
-        let sprite_image_x = item.data.start_x as u32;
-        let sprite_image_y = item.data.start_y as u32;
-        // log::warn!("Item {} -> {}: {:?}", item.data.source_id, item.data.target_id, item.rect);
-        let other = asset_loader.sprites().get(&item.data.source_id).expect("Couldnt get source texture")
+        let (data, sprite_width, sprite_height) = item.data;
+        let sprite_width = sprite_width as u32;
+        let sprite_height = sprite_height as u32;
+        // Sprites sit in the corner of their cell
+        let sprite_atlas_x = (item.rect.x * cell_align) as u32;
+        let sprite_atlas_y = (item.rect.y * cell_align) as u32;
+        let sprite_image_x = data.start_x as u32;
+        let sprite_image_y = data.start_y as u32;
+        // log::warn!("Item {} -> {}: {:?}", data.source_id, data.target_id, item.rect);
+        let other = asset_loader.sprites().get(&data.source_id).expect("Couldnt get source texture")
             .view(sprite_image_x, sprite_image_y, sprite_width, sprite_height);
-        game_texture.copy_from(&*other, item.rect.x as u32, item.rect.y as u32);
+        game_texture.copy_from(&*other, sprite_atlas_x, sprite_atlas_y).expect("Sprite did not fit in its atlas cell");
+
+        let properties = find_sprite_properties(asset_loader, &data.target_id, &data.source_id);
+        let cell = mipmap::MipRegion {
+            x: sprite_atlas_x,
+            y: sprite_atlas_y,
+            width: (item.rect.w * cell_align) as u32,
+            height: (item.rect.h * cell_align) as u32,
+            properties,
+        };
+        mipmap::bleed_sprite(&mut game_texture, sprite_atlas_x, sprite_atlas_y, sprite_width, sprite_height, &cell);
+        mip_regions.push(cell);
+
         let uv_min = Vec2::new((sprite_atlas_x as f32) / (atlas_width as f32), (sprite_atlas_y as f32) / (atlas_height as f32));
         let uv_max = Vec2::new(((sprite_atlas_x + sprite_width) as f32) / (atlas_width as f32), ((sprite_atlas_y + sprite_height) as f32) / (atlas_height as f32));
-        registry.get_sprite_register_mut().insert(item.data.target_id.clone(), Sprite { uv_min, uv_max, parent_texture: item.data.source_id.clone() });
+        registry.get_sprite_register_mut().insert(data.target_id.clone(), Sprite { uv_min, uv_max, parent_texture: data.source_id.clone() });
     }
+    StitchedAtlas { image: game_texture, mip_regions }
+}
 
+fn upload_atlas(client: &mut Client, atlas: &StitchedAtlas, device: &wgpu::Device, queue: &wgpu::Queue) {
+    let game_texture = &atlas.image;
+    let (atlas_width, atlas_height) = game_texture.dimensions();
+    let mip_levels = mipmap::generate_mip_levels(game_texture, ATLAS_MIP_LEVELS, &atlas.mip_regions);
 
     let tex_dims = wgpu::Extent3d {
         width: atlas_width,
@@ -219,7 +431,7 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
     let tex_format = wgpu::TextureFormat::Rgba8UnormSrgb;
     let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
         size: tex_dims,
-        mip_level_count: 1,
+        mip_level_count: 1 + mip_levels.len() as u32,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format: tex_format,
@@ -227,16 +439,23 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         label: Some("diffuse_texture"),
     });
-    queue.write_texture(
-        diffuse_texture.as_image_copy(),
-        game_texture.as_raw(),
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(4 * atlas_width),
-            rows_per_image: Some(atlas_height),
-        },
-        tex_dims,
-        );
+    for (mip_level, image) in std::iter::once(game_texture).chain(mip_levels.iter()).enumerate() {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &diffuse_texture,
+                mip_level: mip_level as u32,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width()),
+                rows_per_image: Some(image.height()),
+            },
+            wgpu::Extent3d { width: image.width(), height: image.height(), depth_or_array_layers: 1 },
+            );
+    }
     let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
     let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -245,7 +464,7 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
         address_mode_w: wgpu::AddressMode::ClampToEdge,
         mag_filter: wgpu::FilterMode::Nearest,
         min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Linear,
         ..Default::default()
     });
 
@@ -258,10 +477,16 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
         client.get_layout(ATLAS_LAYOUT_NAME).unwrap(),
         );
     client.insert_texture(ATLAS_TEXTURE_NAME, texture);
+}
+
+/// Bake the blockstate and item models, the sprites have to be stitched into `game_texture` first
+pub fn bake_models(registry: &mut Registry, asset_loader: &AssetLoader, game_texture: &image::RgbaImage) {
+    let model_files = asset_loader.models();
+    let blockstate_files = asset_loader.blockstates();
 
     let mut voxel_models = HashMap::default();
     for model_file_id in model_files.keys() {
-        let voxel_model = make_model(registry, &model_file_id, &model_files, &mut voxel_models);
+        let voxel_model = make_model(registry, &model_file_id, &model_files, &mut voxel_models, &mut vec![]);
         if let Some(voxel_model) = voxel_model { voxel_models.insert(model_file_id.clone(), voxel_model); } else { log::warn!("Couln't make voxel model for {}", model_file_id); }
     }
     
@@ -271,7 +496,7 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
 
     let missing_model_file = voxel_models.get(&Identifier::from_str("minecraft:block/missing")).expect("Did not have a missing model");
 
-    let mut mapped_models: Vec<(Identifier, BakedModel)> = vec![];
+    let mut mapped_models: Vec<(Identifier, BlockstateModel)> = vec![];
 
     let textures = registry.get_sprite_register();
     for state in registry.get_blockstate_register().get_elements() {
@@ -281,19 +506,50 @@ pub fn bake_resources(registry: &mut Registry, client: &mut Client, asset_loader
         let a = blockstate_files.get(block_id)
             .ok_or(BlockstateParseError::NoBlockstateFile)
             .and_then(|blockstate_file| {
-                generate_blockstate_model(&blockstate_file, identifier.get_identifier_string(), &voxel_models, textures)
+                generate_blockstate_model(&blockstate_file, &state.get_property_names(registry.get_property_register()), &voxel_models, textures)
             });
         let blockstate_model = match a {
             Ok(model) => model,
             Err(e) => {
                 log::warn!("Failed to create model for blockstate {}, reason: {}", identifier, e);
-                missing_model_file.clone().bake(textures)
+                missing_model_file.clone().bake(textures).into()
             }
         };
         mapped_models.push((state.get_state_identifier().clone(), blockstate_model));
     }
 
+    // Items are every model in the item folder, builtin/generated ones are extruded from their layer sprites
+    let mut item_models: Vec<(Identifier, ItemModel)> = vec![];
+    for (model_id, voxel_model) in voxel_models.iter().filter(|(id, _)| id.get_name().starts_with("item/")) {
+        let mut voxel_model = voxel_model.clone();
+        if voxel_model.builtin() == Some(BuiltinModel::Generated) {
+            let layers: Vec<(String, image::RgbaImage)> = (0..).map(|layer| format!("layer{layer}"))
+                .take_while(|layer| voxel_model.has_texture(layer))
+                .filter_map(|layer| {
+                    let sprite = textures.get(&voxel_model.resolve_texture(&layer))?;
+                    Some((layer, sprite_image(game_texture, sprite)))
+                })
+                .collect();
+            for element in item::generate_item_elements(&layers) {
+                voxel_model.with_element_nc(element);
+            }
+        }
+        let display = voxel_model.display().clone();
+        item_models.push((model_id.clone(), ItemModel::bake(&voxel_model.bake(textures), &display)));
+    }
+
     for mapped_model in mapped_models {
         registry.get_model_register_mut().insert(mapped_model.0, mapped_model.1);
     }
+    registry.get_item_model_register_mut().extend(item_models);
+}
+
+/// The pixels of a sprite, cut back out of the atlas
+fn sprite_image(atlas: &image::RgbaImage, sprite: &Sprite) -> image::RgbaImage {
+    let (width, height) = (atlas.width() as f32, atlas.height() as f32);
+    let x = (sprite.uv_min.x * width).round() as u32;
+    let y = (sprite.uv_min.y * height).round() as u32;
+    let sprite_width = ((sprite.uv_max.x - sprite.uv_min.x) * width).round() as u32;
+    let sprite_height = ((sprite.uv_max.y - sprite.uv_min.y) * height).round() as u32;
+    image::imageops::crop_imm(atlas, x, y, sprite_width, sprite_height).to_image()
 }
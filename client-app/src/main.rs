@@ -6,17 +6,26 @@ mod game_version;
 mod client;
 mod game_client;
 mod rendering;
+mod resource_watcher;
+mod skin_cache;
 
-use std::{collections::VecDeque, sync::{Arc, RwLock}, fs::File, io::Read, net::{SocketAddr, Ipv4Addr}, str::FromStr};
+use std::{sync::{Arc, RwLock}, fs::File, io::Read, net::{SocketAddr, Ipv4Addr}, str::FromStr};
 use clap::Parser;
 use game_client::Client;
 use env_logger::Builder;
 use log::{LevelFilter, warn};
-use mc_resource_handler::{TERRAIN_OPAQUE_PIPELINE, CAMERA_BIND_GROUP_NAME, LIGHTMAP_TEXTURE_NAME, TERRAIN_TRANSPARENT_PIPELINE, ATLAS_TEXTURE_NAME};
+use mc_resource_handler::{TERRAIN_OPAQUE_PIPELINE, TERRAIN_TILED_PIPELINE, CAMERA_BIND_GROUP_NAME, LIGHTMAP_TEXTURE_NAME, TERRAIN_TRANSPARENT_PIPELINE, ATLAS_TEXTURE_NAME, SKY_BIND_GROUP_NAME, SKY_BUFFER_NAME};
 use crate::{
         rendering::{
             ElapsedTime,
-            tessellator::TerrainTessellator
+            tessellator::TerrainTessellator,
+            meshing::MeshingPool,
+            mesh::Mesh,
+            sky::{SkyRenderer, SkyUniform},
+            weather::WeatherRenderer,
+            particles::{ParticleRenderer, ParticleView},
+            entities::EntityRenderer,
+            block_entities::BlockEntityRenderer,
         },
     client::{
         camera::CameraControllerMovement,
@@ -30,11 +39,15 @@ use rine::RineApplication;
 use ultraviolet::{DVec3, IVec3, Vec3};
 use winit::event::{DeviceEvent, VirtualKeyCode};
 use winit_input_helper::WinitInputHelper;
-use orange_rs::{minecraft::registry::Registry, packets::prot14::Packet, entities::{EntityTransform, EntityMotion, EntityController, EntityCamera}, util::{pos::{EntityPos, ChunkPos, Position}, workers::WorkerThread}};
+use orange_rs::{minecraft::registry::FrozenRegistry, packets::prot14::Packet, entities::{EntityTransform, EntityMotion, EntityController, EntityCamera, EntityKind}, minecraft::skins::SkinSource, util::{pos::{EntityPos, ChunkPos, Position}, workers::WorkerThread}};
+use orange_rs::minecraft::lang::Translator;
+use orange_rs::models::export::ExportMesh;
 use orange_rs::util::frustrum::Frustrum;
-use orange_rs::util::pos::NewChunkPosition;
+use orange_rs::world::snapshot::SectionSnapshot;
+use orange_rs::world::visibility::{self, SectionVisibility};
+use orange_rs::world::{lightmap::{self, DimensionLight, TICKS_PER_DAY}, sky::{self, FogRange, SkyState}, weather::Precipitation};
 use orange_rs::world::ChunkStorageTrait;
-use crate::{test_world::TestWorld, orange_options::OrangeOptions};
+use crate::{test_world::TestWorld, orange_options::OrangeOptions, game_version::ResourcePaths, resource_watcher::ResourceWatcher, skin_cache::SkinCache};
 
 #[derive(thiserror::Error, Debug)]
 enum ServerConnectError {
@@ -54,7 +67,7 @@ fn join_server(username: String, protocol_id: i32, address: String, port: u32, w
 
     network_thread.send_packet(Packet::Handshake { handshake_data: username.clone() });
     let mut player_id: i32 = 0;
-    world.player = Some(world.entities.push((EntityTransform { position: EntityPos::zero(), rotation: Vec3::zero() }, EntityMotion { velocity: Vec3::zero() }, EntityController { on_ground: true, stance: 1.6 }, EntityCamera { } )));
+    world.player = Some(world.entities.push((EntityTransform::new(EntityPos::zero(), Vec3::zero()), EntityMotion { velocity: Vec3::zero() }, EntityController { on_ground: true, stance: 1.6 }, EntityCamera { } )));
     
     let mut do_login = true;
     while do_login {
@@ -138,25 +151,56 @@ struct OrangeClient {
     client: Client,
     winit_input_helper: WinitInputHelper,
     minecraft: MinecraftClient,
-    registry: Arc<RwLock<Registry>>,
-    tessellator: Arc<RwLock<TerrainTessellator>>,
-    tessellate_queue: VecDeque<IVec3>,
+    /// Replaced as a whole when resources are reloaded
+    registry: Arc<FrozenRegistry>,
+    /// Meshes dirty sections off the render thread
+    meshing_pool: MeshingPool,
+    /// Whether the options ask for greedy meshing, it only happens while the packs have a tiled terrain shader
+    greedy_meshing: bool,
+    sky: SkyRenderer,
+    weather: WeatherRenderer,
+    particles: ParticleRenderer,
+    entities: EntityRenderer,
+    block_entities: BlockEntityRenderer,
+    skins: SkinCache,
+    translator: Translator,
+    resource_paths: ResourcePaths,
+    resource_watcher: Option<ResourceWatcher>,
+    /// Set by the reload key bind, resources are reloaded at the start of the next frame
+    reload_requested: bool,
+    /// The atlas the baked models point into, written next to exported chunks
+    atlas_image: image::RgbaImage,
+    /// Where F3+E exports the chunks around the player
+    exports_directory: std::path::PathBuf,
+    /// Set by the export key bind, the chunks are exported during the next frame
+    export_requested: bool,
 
     server_ip: String,
     server_port: String,
+    /// Why the last connection attempt failed, already translated
+    disconnect_reason: Option<String>,
     debug: bool,
 }
 
 impl OrangeClient {
+    /// Translate the reason a connection failed, servers may send either plain text or a translation key
+    fn describe_connect_error(&self, error: &(dyn std::error::Error + 'static)) -> String {
+        match error.downcast_ref::<ServerConnectError>() {
+            Some(ServerConnectError::Kick(reason)) => format!("{}: {}", self.translator.translate("disconnect.disconnected", &[]), self.translator.translate(reason, &[])),
+            _ => format!("{}: {}", self.translator.translate("connect.failed", &[]), error),
+        }
+    }
+
     pub fn join_server_connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         match &self.game_state {
             GameState::MainMenu => {
+                self.disconnect_reason = None;
                 self.game_state.to_joining_server();
 
                 let ip_v4 = Ipv4Addr::from_str(&self.server_ip)?;
                 let ip_port = self.server_port.parse::<u16>()?;
                 let ip = SocketAddr::new(std::net::IpAddr::V4(ip_v4), ip_port);
-                let mut test_world = TestWorld::new(CHUNK_HEIGHT, &self.registry.read().unwrap());
+                let mut test_world = TestWorld::new(CHUNK_HEIGHT, &self.registry);
                 let mut network_thread = join_server(self.username.clone(), 14, ip.ip().to_string(), ip.port() as u32, &mut test_world)?;
                 let test_world_o = Arc::new(RwLock::new(test_world));
 
@@ -239,6 +283,62 @@ impl OrangeClient {
         }
     }
 
+    /**
+     * Reload every resource pack and rebake the atlas and models from them,
+     * every chunk is marked dirty so it gets remeshed with the new models
+     */
+    fn reload_resources(&mut self, window_client: &rine::RineWindowClient) {
+        log::warn!("Reloading resources");
+        let reload_time = instant::Instant::now();
+        let device = window_client.device();
+        let queue = window_client.queue();
+        let config = window_client.config();
+        let asset_loader = game_version::load_resources(&self.resource_paths);
+        mc_resource_handler::create_resources(&mut self.client, device, queue, config, &asset_loader);
+        let (registry, atlas_image) = game_version::create_registry(&mut self.client, &asset_loader, device, queue);
+        self.registry = Arc::new(registry);
+        self.atlas_image = atlas_image;
+        self.skins.reupload(&mut self.client, device, queue);
+        self.entities.load_models(&asset_loader);
+        // Merged faces can only be drawn while the packs have a tiled terrain shader
+        self.meshing_pool.set_greedy_meshing(self.greedy_meshing && self.client.get_pipeline(TERRAIN_TILED_PIPELINE).is_some());
+        let locale = self.translator.locale().to_string();
+        self.translator = asset_loader.translator(&locale);
+
+        if let GameState::InGame { test_world, .. } = &self.game_state {
+            if let Ok(world) = test_world.read() {
+                world.chunk_storage.for_each_chunk(|_, chunk| chunk.set_dirty(true));
+            }
+        }
+        log::warn!("Reloaded resources in {:?}", reload_time.elapsed());
+    }
+
+    /// Tessellate the chunks between two chunk columns and export them next to the atlas
+    fn export_chunk_region(&self, test_world: &Arc<RwLock<TestWorld>>, min_extent: ChunkPos, max_extent: ChunkPos) {
+        let Ok(world) = test_world.read() else { return; };
+        let mut tessellator = TerrainTessellator::new();
+        let (mut opaque, mut transparent) = (ExportMesh::new(), ExportMesh::new());
+        for x in min_extent.x..=max_extent.x {
+            for z in min_extent.y..=max_extent.y {
+                for y in 0..world.get_height() as i32 {
+                    let pos = IVec3::new(x, y, z);
+                    if let Some(snapshot) = SectionSnapshot::capture(&world.chunk_storage, pos, world.get_climate()) {
+                        tessellator.tessellate_chunk_section(&snapshot, &self.registry);
+                        let (section_opaque, section_transparent) = tessellator.build_export();
+                        opaque.append(&section_opaque);
+                        transparent.append(&section_transparent);
+                    }
+                }
+            }
+        }
+        let seconds = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let name = format!("chunks-{seconds}");
+        match game_version::write_export(&self.exports_directory, &name, &[("opaque", &opaque), ("transparent", &transparent)], &self.atlas_image) {
+            Ok(()) => log::warn!("Exported {} triangles to {}", opaque.triangle_count() + transparent.triangle_count(), self.exports_directory.join(name).display()),
+            Err(e) => log::error!("Could not export the chunks: {e}"),
+        }
+    }
+
     pub fn update(&mut self) {
         // match self.game_state {
         //     GameState::MainMenu => {
@@ -259,7 +359,7 @@ impl RineApplication for OrangeClient {
 
         let cli = cli_options::OrangeCliArgs::parse();
 
-        let home_path =  cli.orange_directory.unwrap_or_else(||dirs::data_dir().unwrap().join(".orange"));
+        let home_path = orange_home_path(&cli);
         log::warn!("Orange(config) Path: {}", home_path.display());
 
         if !home_path.exists() {
@@ -273,7 +373,10 @@ impl RineApplication for OrangeClient {
         }
 
         let orange_options_path = home_path.join("options.toml");
-        let orange_assets_path = cli.assets_directory.unwrap_or_else(|| home_path.join("assets"));
+        let resource_paths = orange_resource_paths(&cli, &home_path);
+        if let Err(e) = std::fs::create_dir_all(&resource_paths.resource_packs_directory) {
+            log::error!("Could not create resource packs folder! {e}");
+        }
 
         // Get or default the options
         let orange_options: OrangeOptions = orange_options_path.exists()
@@ -295,21 +398,31 @@ impl RineApplication for OrangeClient {
         let winit_input_helper = WinitInputHelper::new();
         let minecraft = MinecraftClient::new(CHUNK_HEIGHT);
         minecraft.set_screen::<MainMenu>();
-        let registry = Arc::new(RwLock::new(Registry::new()));
-        if let Ok(mut registry) = registry.write() {
-            game_version::register_content(&mut registry);
-            let asset_loader = game_version::load_resources(&orange_assets_path, cli.default_resources.clone());
-            let device = window_client.device();
-            let queue = window_client.queue();
-            let config = window_client.config();
-            mc_resource_handler::create_resources(&mut client, device, queue, config, &asset_loader);
-            game_version::bake_resources(&mut registry, &mut client, &asset_loader, device, queue);
-        }
+        let asset_loader = game_version::load_resources(&resource_paths);
+        let device = window_client.device();
+        let queue = window_client.queue();
+        let config = window_client.config();
+        mc_resource_handler::create_resources(&mut client, device, queue, config, &asset_loader);
+        let (registry, atlas_image) = game_version::create_registry(&mut client, &asset_loader, device, queue);
+        let registry = Arc::new(registry);
+        let translator = asset_loader.translator(orange_options.language());
+        let resource_watcher = match ResourceWatcher::new(&resource_paths.watched()) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                log::warn!("Resource packs will not be reloaded automatically: {e}");
+                None
+            }
+        };
 
-        // The tessellator to be used to mesh the chunks, intended for multithreaded usage (TODO)
-        let shared_tessellator = Arc::new(RwLock::new(TerrainTessellator::new()));
+        let skins_directory = home_path.join("skins");
+        if let Err(e) = std::fs::create_dir_all(&skins_directory) {
+            log::error!("Could not create skins folder! {e}");
+        }
+        let skins = SkinCache::new(SkinSource::new(skins_directory).with_url_template(orange_options.skin_url_template().map(str::to_string)));
 
-        let tessellate_queue = VecDeque::<IVec3>::new();
+        let greedy_meshing = orange_options.greedy_meshing();
+        let meshing_pool = MeshingPool::new(orange_options.meshing_threads(), greedy_meshing && client.get_pipeline(TERRAIN_TILED_PIPELINE).is_some());
+        log::warn!("Meshing sections on {} threads", meshing_pool.thread_count());
 
         log::warn!("================");
         Self {
@@ -320,16 +433,35 @@ impl RineApplication for OrangeClient {
             winit_input_helper,
             minecraft,
             registry,
-            tessellator: shared_tessellator,
-            tessellate_queue,
+            meshing_pool,
+            greedy_meshing,
+            sky: SkyRenderer::new(),
+            weather: WeatherRenderer::new(),
+            particles: ParticleRenderer::new(),
+            entities: EntityRenderer::new(&asset_loader),
+            block_entities: BlockEntityRenderer::new(),
+            skins,
+            translator,
+            resource_paths,
+            resource_watcher,
+            reload_requested: false,
+            atlas_image,
+            exports_directory: home_path.join("exports"),
+            export_requested: false,
             server_ip: param_ip,
             server_port: param_port.to_string(),
+            disconnect_reason: None,
             debug: false
         }
     }
 
     fn draw(&mut self, window_client: &rine::RineWindowClient, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let packs_changed = self.resource_watcher.as_mut().is_some_and(|watcher| watcher.poll());
+        if std::mem::take(&mut self.reload_requested) || packs_changed {
+            self.reload_resources(window_client);
+        }
         let device = window_client.device();
+        self.skins.poll(&mut self.client, device, window_client.queue());
         let client = &self.client;
 
         match &self.game_state {
@@ -348,8 +480,67 @@ impl RineApplication for OrangeClient {
                 let player_chunk_pos: ChunkPos = player_pos.to_chunk_pos();
                 let min_extent = player_chunk_pos - render_distance_as_vec;
                 let max_extent = player_chunk_pos + render_distance_as_vec;
+                if std::mem::take(&mut self.export_requested) {
+                    self.export_chunk_region(test_world, min_extent, max_extent);
+                }
+                let sky_state = match test_world.read() {
+                    Ok(world) => {
+                        mc_resource_handler::update_lightmap_texture(client, window_client.queue(), world.get_time(), 0.0, world.get_dimension_id());
+                        let temperature = world.get_climate().climate_at(player_pos.x.floor() as i32, player_pos.z.floor() as i32).temperature;
+                        SkyState::at(world.get_time(), 0.0, temperature, DimensionLight::from_id(world.get_dimension_id()))
+                    },
+                    Err(_) => SkyState::at(TICKS_PER_DAY / 4, 0.0, 0.5, DimensionLight::OVERWORLD),
+                };
+                // Transparent quads within a section are drawn back to front, sorted before the render pass borrows the meshes
+                let camera_position = client.camera.position();
+                let sky_uniform = SkyUniform::new(camera_position, &sky_state, FogRange::for_render_distance(render_distance as u32));
+                window_client.queue().write_buffer(client.get_buffer(SKY_BUFFER_NAME).unwrap(), 0, bytemuck::cast_slice(&[sky_uniform]));
+                // The sky is shrunk to fit inside the far plane, it is drawn behind everything anyway
+                self.sky.build(&sky_state, client.projection.zfar * 0.5 / sky::SKY_DISTANCE);
+                self.sky.upload(device, window_client.queue());
+                if let Ok(world) = test_world.read() {
+                    let weather = world.get_weather();
+                    // Only dimensions with a sky have weather
+                    let strength = if sky_state.has_sky { weather.get_strength(0.0) } else { 0.0 };
+                    let precipitation_of = |x, z| Precipitation::from_climate(&world.get_climate().climate_at(x, z));
+                    self.weather.build(camera_position, strength, world.get_time(), 0.0, weather.get_bolts(), |x, z| world.get_precipitation_height(x, z), precipitation_of);
+
+                    // Particles are lit like the terrain around them, the sky light dims through the night
+                    let (_, right, up) = client.camera.vectors();
+                    let view = ParticleView { camera: camera_position, right, up, partial_tick: 0.0 };
+                    let sky_darkening = lightmap::sky_darkening(sky_state.celestial_angle);
+                    let ambient = DimensionLight::from_id(world.get_dimension_id()).ambient;
+                    let brightness_of = |position: Vec3| {
+                        let block = IVec3::new(position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+                        let (sky_light, block_light) = world.get_light(block).unwrap_or((15, 0));
+                        lightmap::brightness((sky_light as f32 - sky_darkening).max(block_light as f32), ambient)
+                    };
+                    self.particles.build(world.get_particles().get_particles(), &self.registry, &view, brightness_of);
+                    let entities = world.get_entities();
+                    for (kind, _, _) in &entities {
+                        if let EntityKind::Player { name } = kind {
+                            self.skins.request(name);
+                        }
+                    }
+                    self.entities.build(&entities, client, &self.registry, &view, brightness_of, |id, damage| world.get_item_state(id, damage));
+                    self.block_entities.build(world.get_block_entities(), client, &self.registry, &view, brightness_of);
+                }
+                self.weather.upload(device, window_client.queue());
+                self.particles.upload(device, window_client.queue());
+                self.entities.upload(device, window_client.queue());
+                self.block_entities.upload(device, window_client.queue());
+                for x in min_extent.x..=max_extent.x {
+                    for z in min_extent.y..=max_extent.y {
+                        for y in 0..CHUNK_HEIGHT as i32 {
+                            if let Ok(mesh) = self.minecraft.client_chunk_storage.get_chunk_mut(IVec3::new(x, y, z)) {
+                                mesh.sort_transparent(window_client.queue(), camera_position);
+                            }
+                        }
+                    }
+                }
                 {
-                    let sky_color = DVec3::new(0.1, 0.2, 0.3);
+                    // The frame is cleared to the fog color, so the horizon and the fog match
+                    let sky_color = DVec3::new(sky_state.fog_color.x as f64, sky_state.fog_color.y as f64, sky_state.fog_color.z as f64);
                     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         label: Some("Render Pass"),
                         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -375,34 +566,33 @@ impl RineApplication for OrangeClient {
                         }),
                     });
 
+                    self.sky.draw(&mut render_pass, client);
+
                     render_pass.set_pipeline(client.get_pipeline(TERRAIN_OPAQUE_PIPELINE).unwrap());
                     render_pass.set_bind_group(0, client.get_bind_group(CAMERA_BIND_GROUP_NAME).unwrap(), &[]);
                     render_pass.set_bind_group(1, client.get_texture(ATLAS_TEXTURE_NAME).expect("Couldnt load terrain texture").bind_group(), &[]);
                     render_pass.set_bind_group(2, client.get_texture(LIGHTMAP_TEXTURE_NAME).expect("Couldn't load lightmap texture").bind_group(), &[]);
+                    render_pass.set_bind_group(3, client.get_bind_group(SKY_BIND_GROUP_NAME).unwrap(), &[]);
 
                     let directions = client.camera.vectors();
                     let aspect = client.projection.aspect;
                     let fovy = client.projection.fovy;
                     let znear = client.projection.znear;
                     let zfar = client.projection.zfar;
-                    let camera_position = client.camera.position();
                     let frustrum = Frustrum::new(camera_position, directions.0, directions.1, directions.2, aspect, fovy, znear, zfar);
 
-                    // AABB in frustrum culling?
-                    let mut render_list: Vec<IVec3> = vec![];
-                    let vec16 = Vec3::new(16.0, 16.0, 16.0);
-                    for x in min_extent.x..=max_extent.x {
-                        for z in min_extent.y..=max_extent.y {
-                            for y in 0..CHUNK_HEIGHT as i32 {
-                                let chunk_pos_min = Vec3::new((x << 4) as f32, (y << 4) as f32, (z << 4) as f32);
-                                let chunk_pos_max = chunk_pos_min + vec16;
-
-                                if !frustrum.aabb_intersects(chunk_pos_min, chunk_pos_max) { continue; }
-                                render_list.push(IVec3::new(x, y, z));
-                            }
-                        }
-                    }
+                    // Sections behind terrain are culled by searching through the sides of sections that see each other, from the section of the camera
                     let camera_pos_i = camera_position.to_block_pos();
+                    let camera_section = IVec3::new(camera_pos_i.x >> 4, camera_pos_i.y >> 4, camera_pos_i.z >> 4);
+                    let storage = &self.minecraft.client_chunk_storage;
+                    let vec16 = Vec3::new(16.0, 16.0, 16.0);
+                    let bounds = (IVec3::new(min_extent.x, 0, min_extent.y), IVec3::new(max_extent.x, CHUNK_HEIGHT as i32 - 1, max_extent.y));
+                    let is_visible = |section: IVec3| {
+                        let chunk_pos_min = Vec3::new((section.x << 4) as f32, (section.y << 4) as f32, (section.z << 4) as f32);
+                        frustrum.aabb_intersects(chunk_pos_min, chunk_pos_min + vec16)
+                    };
+                    let section_visibility = |section: IVec3| storage.get_chunk(section).map(|mesh| mesh.visibility).unwrap_or_else(|_| SectionVisibility::all());
+                    let mut render_list = visibility::visible_sections(camera_section, bounds, section_visibility, is_visible);
                     let vec8 = IVec3::new(8, 8, 8);
                     // Sort by center of chunks; if sorting by min point, chunks to the x+/y+/z+ are likely to be drawn before the chunk of the player
                     render_list.sort_unstable_by(|a, b| {
@@ -415,6 +605,14 @@ impl RineApplication for OrangeClient {
                             mesh.draw(&mut render_pass);
                         }
                     }
+                    if let Some(tiled_pipeline) = client.get_pipeline(TERRAIN_TILED_PIPELINE) {
+                        render_pass.set_pipeline(tiled_pipeline);
+                        for chunk_pos in &render_list {
+                            if let Ok(mesh) = self.minecraft.client_chunk_storage.get_chunk(*chunk_pos) {
+                                mesh.draw_tiled(&mut render_pass);
+                            }
+                        }
+                    }
 
                     render_pass.set_pipeline(client.get_pipeline(TERRAIN_TRANSPARENT_PIPELINE).unwrap());
                     render_list.reverse();
@@ -423,52 +621,39 @@ impl RineApplication for OrangeClient {
                             mesh.draw_transparent(&mut render_pass);
                         }
                     }
-                }
-                {
 
-                    let registry = self.registry.read().unwrap();
-                    let blocks = registry.get_block_register();
-                    let states = registry.get_blockstate_register();
-                    let textures = registry.get_sprite_register();
-                    let models = registry.get_model_register();
-                    // The maximum number of tessellations to be done every frame
-                    let max_tessellations = 8;
-                    // let max_tessellations = 256;
-                    let mut num_tessellations = 0;
-                    if let Ok(server_world) = test_world.read() {
-                        let mut tessellator = self.tessellator.write().unwrap();
-                        for x in min_extent.x..=max_extent.x {
-                            for z in min_extent.y..=max_extent.y {
-                                for y in 0..server_world.get_height() as i32 {
-                                    let pos = IVec3::new(x, y, z);
-                                    match server_world.chunk_storage.get_chunk(pos) {
-                                        Ok(chunk) if chunk.is_dirty() => {
-                                            num_tessellations += 1;
-                                            let section_position = NewChunkPosition::new(x, y, z).to_entity_pos();
-
-                                            // let nearby_chunks = server_world.chunk_storage.get_nearby_chunks(pos);
-                                            tessellator.tessellate_chunk_section(chunk, section_position, pos, blocks, states, models, textures, &server_world.chunk_storage);
-                                            let mesh = tessellator.build(device);
-                                            self.minecraft.client_chunk_storage.set_chunk(mesh, pos).unwrap();
-                                            self.tessellate_queue.push_back(pos);
-                                        },
-                                        _ => {}
-                                    };
-                                    if num_tessellations > max_tessellations { break; }
+                    self.entities.draw(&mut render_pass, client);
+                    self.block_entities.draw(&mut render_pass, client);
+                    self.particles.draw(&mut render_pass, client);
+                    self.weather.draw(&mut render_pass, client);
+                }
+                for section in self.meshing_pool.receive() {
+                    let mesh = Mesh::from_buffers(device, &section.buffers);
+                    self.minecraft.client_chunk_storage.set_chunk(mesh, section.position).unwrap();
+                }
+                if let Ok(server_world) = test_world.read() {
+                    // Dirty sections nearest to the player are meshed first
+                    let mut dirty_sections = vec![];
+                    for x in min_extent.x..=max_extent.x {
+                        for z in min_extent.y..=max_extent.y {
+                            for y in 0..server_world.get_height() as i32 {
+                                let pos = IVec3::new(x, y, z);
+                                if server_world.chunk_storage.get_chunk(pos).is_ok_and(|chunk| chunk.is_dirty()) {
+                                    dirty_sections.push(pos);
                                 }
                             }
                         }
                     }
-                    if self.tessellate_queue.len() > 0 {
-                        if let Ok(mut server_world) = test_world.write() {
-                            for pos in &self.tessellate_queue {
-                                if let Ok(chunk) = server_world.chunk_storage.get_chunk_mut(*pos) {
-                                    chunk.set_dirty(false);
-                                }
-                            }
+                    let player_section = IVec3::new(player_chunk_pos.x, (player_pos.y as i32) >> 4, player_chunk_pos.y);
+                    dirty_sections.sort_unstable_by_key(|pos| (*pos - player_section).mag_sq());
+                    for pos in dirty_sections {
+                        if self.meshing_pool.is_full() { break; }
+                        let Some(snapshot) = SectionSnapshot::capture(&server_world.chunk_storage, pos, server_world.get_climate()) else { continue; };
+                        if let Ok(chunk) = server_world.chunk_storage.get_chunk(pos) {
+                            chunk.set_dirty(false);
                         }
+                        self.meshing_pool.submit(snapshot, &self.registry);
                     }
-                    self.tessellate_queue.clear();
                 }
             }
         }
@@ -531,6 +716,12 @@ impl RineApplication for OrangeClient {
                 if event_helper.key_pressed(Key::F3) {
                     self.debug = !self.debug;
                 }
+                if event_helper.key_held(Key::F3) && event_helper.key_pressed(Key::T) {
+                    self.reload_requested = true;
+                }
+                if event_helper.key_held(Key::F3) && event_helper.key_pressed(Key::E) {
+                    self.export_requested = true;
+                }
                 if event_helper.key_held(Key::Space) {
                     client
                         .camera_controller
@@ -583,7 +774,7 @@ impl RineApplication for OrangeClient {
                     ui.label(format!("Camera Block Position: {:?}", player_pos_int));
                     ui.label(format!("Camera Chunk: ({:?}, {:?})", player_pos_chunk, player_pos_chunk_inner));
                     ui.label(format!("Block on player: {:?}", player_block));
-                    if ui.button("Quit").clicked() {
+                    if ui.button(self.translator.translate("menu.disconnect", &[])).clicked() {
                         leaving_server = true;
                         return;
                     }
@@ -594,7 +785,10 @@ impl RineApplication for OrangeClient {
             },
             GameState::JoiningServer { test_world, server_thread } => {
                 egui::Window::new("Orange Window").auto_sized().show(ctx, |ui| {
-                    if ui.button("Cancel").clicked() {
+                    if let Some(reason) = &self.disconnect_reason {
+                        ui.label(reason);
+                    }
+                    if ui.button(self.translator.translate("gui.cancel", &[])).clicked() {
                         self.join_server_cancel();
                     }
                 });
@@ -602,11 +796,13 @@ impl RineApplication for OrangeClient {
             GameState::MainMenu => {
                 egui::Window::new("Orange Window").auto_sized().show(ctx, |ui| {
                     egui::text_edit::TextEdit::singleline(&mut self.username).char_limit(16).show(ui).response.changed();
-                    ui.label("Server Ip:");
+                    ui.label(self.translator.translate("multiplayer.ipinfo", &[]));
                     ui.text_edit_singleline(&mut self.server_ip);
                     egui::text_edit::TextEdit::singleline(&mut self.server_port).char_limit(5).show(ui).response.changed();
-                    if ui.button("Join Server").clicked() {
-                        self.join_server_connect();
+                    if ui.button(self.translator.translate("multiplayer.connect", &[])).clicked() {
+                        if let Err(e) = self.join_server_connect() {
+                            self.disconnect_reason = Some(self.describe_connect_error(e.as_ref()));
+                        }
                     } 
                 });
             },
@@ -642,10 +838,36 @@ impl RineApplication for OrangeClient {
  *
 **/
 
+fn orange_home_path(cli: &cli_options::OrangeCliArgs) -> std::path::PathBuf {
+    cli.orange_directory.clone().unwrap_or_else(||dirs::data_dir().unwrap().join(".orange"))
+}
+
+fn orange_resource_paths(cli: &cli_options::OrangeCliArgs, home_path: &std::path::Path) -> ResourcePaths {
+    let orange_assets_path = cli.assets_directory.clone().unwrap_or_else(|| home_path.join("assets"));
+    ResourcePaths::new(orange_assets_path, cli.default_resources.clone(), home_path.join("resourcepacks"))
+}
+
 fn main() {
 
     // env_logger::init();
     Builder::new().filter_level(LevelFilter::Warn).init();
 
+    let cli = cli_options::OrangeCliArgs::parse();
+    if cli.validate_resources {
+        let report = game_version::validate_resources(&orange_resource_paths(&cli, &orange_home_path(&cli)));
+        println!("{report}");
+        std::process::exit(if report.is_empty() { 0 } else { 1 });
+    }
+    if let Some(directory) = &cli.export_models {
+        match game_version::export_models(&orange_resource_paths(&cli, &orange_home_path(&cli)), directory) {
+            Ok(()) => println!("Exported models to {}", directory.display()),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        std::process::exit(0);
+    }
+
     rine::start_rine_application::<OrangeClient>();
 }
@@ -1,41 +1,104 @@
 use orange_rs::minecraft::asset_loader::AssetLoader;
 use orange_rs::minecraft::identifier::Identifier;
+use orange_rs::font::BitmapFont;
+use orange_rs::models::entity::ENTITY_MODEL_KINDS;
+use orange_rs::world::lightmap::{self, DimensionLight, LIGHTMAP_SIZE, TICKS_PER_DAY};
+use orange_rs::world::sky::{FogRange, SkyState};
 use rustc_hash::FxHashMap as HashMap;
 
-use image::{DynamicImage, EncodableLayout, GenericImageView, Rgb32FImage};
+use image::{DynamicImage, Rgb32FImage, RgbaImage};
 use wgpu::util::DeviceExt;
 
-use crate::{rendering::{textures::DiffuseTextureWrapper, verticies::TerrainVertex}, game_client::Client};
+use crate::{rendering::{sky::{SkyUniform, SkyVertex}, textures::DiffuseTextureWrapper, verticies::{TerrainVertex, TiledTerrainVertex}}, game_client::Client};
 
 pub type TexMapType = HashMap<String, DiffuseTextureWrapper>;
 
 pub static ATLAS_LAYOUT_NAME: &str = "atlas_layout";
 pub static LIGHTMAP_LAYOUT_NAME: &str = "lightmap_layout";
 pub static CAMERA_LAYOUT_NAME: &str = "camera_layout";
+pub static SKY_LAYOUT_NAME: &str = "sky_layout";
 
 pub static CAMERA_BUFFER_NAME: &str = "camera_buffer";
 
 pub static CAMERA_BIND_GROUP_NAME: &str = "camera_bind_group";
 
+pub static SKY_BUFFER_NAME: &str = "sky_buffer";
+pub static SKY_BIND_GROUP_NAME: &str = "sky_bind_group";
+
 pub static TERRAIN_OPAQUE_PIPELINE: &str = "shader";
 pub static TERRAIN_TRANSPARENT_PIPELINE: &str = "shader_transparent";
+/// Faces merged by greedy meshing, the shader wraps their uvs back into their sprite with the tile attributes
+pub static TERRAIN_TILED_PIPELINE: &str = "shader_tiled";
+pub static SKY_PIPELINE: &str = "sky";
+/// The sky shader again, adding to what is behind it for the stars, sun and moon
+pub static SKY_ADDITIVE_PIPELINE: &str = "sky_additive";
+/// Takes the same vertices and bind groups as the sky shader, but is depth tested against the terrain
+pub static WEATHER_PIPELINE: &str = "weather";
+/// The weather shader again, adding to what is behind it for lightning
+pub static WEATHER_ADDITIVE_PIPELINE: &str = "weather_additive";
+/// Takes the same vertices and bind groups as the sky shader, with the texture of a model or the atlas for items
+pub static ENTITY_PIPELINE: &str = "entity";
 
 pub static ATLAS_TEXTURE_NAME: &str = "minecraft:game";
 pub static LIGHTMAP_TEXTURE_NAME: &str = "minecraft:lightmap";
+pub static SUN_TEXTURE_NAME: &str = "minecraft:environment/sun";
+pub static MOON_TEXTURE_NAME: &str = "minecraft:environment/moon_phases";
+pub static RAIN_TEXTURE_NAME: &str = "minecraft:environment/rain";
+pub static SNOW_TEXTURE_NAME: &str = "minecraft:environment/snow";
+/// The particle sheet of b1.7.3, a grid of sixteen by sixteen cells
+pub static PARTICLES_TEXTURE_NAME: &str = "minecraft:particle/particles";
+/// The default font of b1.7.3, a grid of sixteen by sixteen glyphs
+pub static FONT_TEXTURE_NAME: &str = "minecraft:font/default";
+pub static SIGN_TEXTURE_NAME: &str = "minecraft:entity/sign";
+pub static CHEST_TEXTURE_NAME: &str = "minecraft:entity/chest/normal";
+/// A single white pixel, for sky geometry colored by its vertices alone
+pub static WHITE_TEXTURE_NAME: &str = "orange:white";
 
 pub fn create_resources(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, asset_loader: &AssetLoader) {
 
     generate_atlas_texture_bind_group_layout(client, device);
     generate_lightmap_texture_bind_group_layout(client, device);
     generate_camera_bind_group_layout(client, device);
+    generate_sky_bind_group_layout(client, device);
 
     generate_camera_buffer(client, device);
     generate_camera_bind_group(client, device);
+    generate_sky_buffer(client, device);
+    generate_sky_bind_group(client, device);
 
-    generate_terrain_opaque_pipeline(client, device, config, asset_loader.shaders().get(&Identifier::from_str(TERRAIN_OPAQUE_PIPELINE)).expect("Did not have the terrain opaque shaders"));
+    generate_terrain_opaque_pipeline(client, device, config, asset_loader.shaders().get(&Identifier::from_str(TERRAIN_OPAQUE_PIPELINE)).expect("Did not have the terrain opaque shaders"), TERRAIN_OPAQUE_PIPELINE, TerrainVertex::desc());
     generate_terrain_transparent_pipeline(client, device, config, asset_loader.shaders().get(&Identifier::from_str(TERRAIN_TRANSPARENT_PIPELINE)).expect("Did not have the terrain opaque shaders"));
 
+    match asset_loader.shaders().get(&Identifier::from_str(TERRAIN_TILED_PIPELINE)) {
+        Some(shader_data) => generate_terrain_opaque_pipeline(client, device, config, shader_data, TERRAIN_TILED_PIPELINE, TiledTerrainVertex::desc()),
+        None => {
+            client.remove_pipeline(TERRAIN_TILED_PIPELINE);
+            log::warn!("Did not have the tiled terrain shaders, terrain won't be greedy meshed");
+        },
+    }
+    match asset_loader.shaders().get(&Identifier::from_str(SKY_PIPELINE)) {
+        Some(shader_data) => {
+            generate_sky_pipeline(client, device, config, shader_data, SKY_PIPELINE, wgpu::BlendState::ALPHA_BLENDING, wgpu::CompareFunction::Always);
+            generate_sky_pipeline(client, device, config, shader_data, SKY_ADDITIVE_PIPELINE, ADDITIVE_BLENDING, wgpu::CompareFunction::Always);
+        },
+        None => log::warn!("Did not have the sky shaders, the sky will only be the fog color"),
+    }
+    match asset_loader.shaders().get(&Identifier::from_str(WEATHER_PIPELINE)) {
+        Some(shader_data) => {
+            generate_sky_pipeline(client, device, config, shader_data, WEATHER_PIPELINE, wgpu::BlendState::ALPHA_BLENDING, wgpu::CompareFunction::LessEqual);
+            generate_sky_pipeline(client, device, config, shader_data, WEATHER_ADDITIVE_PIPELINE, ADDITIVE_BLENDING, wgpu::CompareFunction::LessEqual);
+        },
+        None => log::warn!("Did not have the weather shaders, rain, snow and lightning won't be drawn"),
+    }
+    match asset_loader.shaders().get(&Identifier::from_str(ENTITY_PIPELINE)) {
+        Some(shader_data) => generate_entity_pipeline(client, device, config, shader_data),
+        None => log::warn!("Did not have the entity shaders, entities won't be drawn"),
+    }
+
     generate_lightmap_texture(client, device, queue);
+    generate_sky_textures(client, device, queue, asset_loader);
+    generate_entity_textures(client, device, queue, asset_loader);
+    generate_font_texture(client, device, queue, asset_loader);
 }
 
 pub fn generate_basic_2d_texture_bind_group_layout<T: AsRef<str>>(label: T, device: &wgpu::Device) -> wgpu::BindGroupLayout {
@@ -92,6 +155,25 @@ pub fn generate_camera_bind_group_layout(client: &mut Client, device: &wgpu::Dev
     client.insert_layout(CAMERA_LAYOUT_NAME, layout);
 }
 
+/// The sky uniform, read by the sky shaders to follow the camera and by the terrain shaders for fog
+pub fn generate_sky_bind_group_layout(client: &mut Client, device: &wgpu::Device) {
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some(SKY_LAYOUT_NAME),
+    });
+
+    client.insert_layout(SKY_LAYOUT_NAME, layout);
+}
+
 pub fn generate_camera_buffer(client: &mut Client, device: &wgpu::Device) {
     let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some(CAMERA_BUFFER_NAME),
@@ -113,30 +195,53 @@ pub fn generate_camera_bind_group(client: &mut Client, device: &wgpu::Device) {
     client.insert_bind_group(CAMERA_BIND_GROUP_NAME, bind_group);
 }
 
-pub fn generate_terrain_opaque_pipeline(client: &mut Client, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader_data: &String) {
+pub fn generate_sky_buffer(client: &mut Client, device: &wgpu::Device) {
+    let sky_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(SKY_BUFFER_NAME),
+        contents: bytemuck::cast_slice(&[SkyUniform::new(client.camera.position(), &SkyState::at(TICKS_PER_DAY / 4, 0.0, 0.5, DimensionLight::OVERWORLD), FogRange::for_render_distance(0))]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    client.insert_buffer(SKY_BUFFER_NAME, sky_buffer);
+}
+
+pub fn generate_sky_bind_group(client: &mut Client, device: &wgpu::Device) {
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: client.get_layout(SKY_LAYOUT_NAME).unwrap(),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: client.get_buffer(SKY_BUFFER_NAME).unwrap().as_entire_binding(),
+        }],
+        label: Some(SKY_BIND_GROUP_NAME),
+    });
+    client.insert_bind_group(SKY_BIND_GROUP_NAME, bind_group);
+}
+
+/// An opaque terrain pipeline called `name`, drawing vertices laid out as `vertex_layout`
+pub fn generate_terrain_opaque_pipeline(client: &mut Client, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader_data: &String, name: &str, vertex_layout: wgpu::VertexBufferLayout) {
 
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some(TERRAIN_OPAQUE_PIPELINE),
+        label: Some(name),
         source: wgpu::ShaderSource::Wgsl(shader_data.into()),
     });
 
     let camera_bind_group_layout = client.get_layout(CAMERA_LAYOUT_NAME).unwrap();
     let atlas_bind_group_layout = client.get_layout(ATLAS_LAYOUT_NAME).unwrap();
     let lightmap_bind_group_layout = client.get_layout(LIGHTMAP_LAYOUT_NAME).unwrap();
+    let sky_bind_group_layout = client.get_layout(SKY_LAYOUT_NAME).unwrap();
 
     let pipeline_layout =
         device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some(format!("{}_layout", TERRAIN_OPAQUE_PIPELINE).as_str()),
-            bind_group_layouts: &[camera_bind_group_layout, atlas_bind_group_layout, lightmap_bind_group_layout],
+            label: Some(format!("{}_layout", name).as_str()),
+            bind_group_layouts: &[camera_bind_group_layout, atlas_bind_group_layout, lightmap_bind_group_layout, sky_bind_group_layout],
             push_constant_ranges: &[],
         });
     let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some(TERRAIN_OPAQUE_PIPELINE),
+        label: Some(name),
         layout: Some(&pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
-            buffers: &[TerrainVertex::desc()],
+            buffers: &[vertex_layout],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
@@ -175,7 +280,7 @@ pub fn generate_terrain_opaque_pipeline(client: &mut Client, device: &wgpu::Devi
         multiview: None,
     });
 
-    client.insert_pipeline(TERRAIN_OPAQUE_PIPELINE, pipeline);
+    client.insert_pipeline(name, pipeline);
 }
 
 pub fn generate_terrain_transparent_pipeline(client: &mut Client, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader_data: &String) {
@@ -188,11 +293,12 @@ pub fn generate_terrain_transparent_pipeline(client: &mut Client, device: &wgpu:
     let camera_bind_group_layout = client.get_layout(CAMERA_LAYOUT_NAME).unwrap();
     let atlas_bind_group_layout = client.get_layout(ATLAS_LAYOUT_NAME).unwrap();
     let lightmap_bind_group_layout = client.get_layout(LIGHTMAP_LAYOUT_NAME).unwrap();
+    let sky_bind_group_layout = client.get_layout(SKY_LAYOUT_NAME).unwrap();
 
     let pipeline_layout =
         device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some(format!("{}_layout", TERRAIN_TRANSPARENT_PIPELINE).as_str()),
-            bind_group_layouts: &[camera_bind_group_layout, atlas_bind_group_layout, lightmap_bind_group_layout],
+            bind_group_layouts: &[camera_bind_group_layout, atlas_bind_group_layout, lightmap_bind_group_layout, sky_bind_group_layout],
             push_constant_ranges: &[],
         });
     let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -243,30 +349,248 @@ pub fn generate_terrain_transparent_pipeline(client: &mut Client, device: &wgpu:
     client.insert_pipeline(TERRAIN_TRANSPARENT_PIPELINE, pipeline);
 }
 
-pub fn generate_lightmap_texture(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue) {
-    let width = 16;
-    let height = 1;
-    let widthpheight = (width - 1) + (height - 1);
-    let mut rgb_tex = Rgb32FImage::new(width, height);
-
-    for x in 0..width {
-        for y in 0..height {
-            let xf: f32 = x as f32;
-            let r = xf / 15.0;
-            let px = r / ((3.0 - 3.0 * r) + 1.0) * 0.95 + 0.05;
-            rgb_tex.put_pixel(x, y, image::Rgb::<f32>([px, px, px]));
+const ADDITIVE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::SrcAlpha, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+    alpha: wgpu::BlendComponent { src_factor: wgpu::BlendFactor::Zero, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add },
+};
+
+/**
+ * A pipeline for geometry placed around the camera, it never writes depth and is seen from both sides.
+ * The sky is drawn behind everything with `depth_compare` always passing, weather is hidden behind the terrain
+ */
+pub fn generate_sky_pipeline(client: &mut Client, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader_data: &String, name: &str, blend: wgpu::BlendState, depth_compare: wgpu::CompareFunction) {
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(name),
+        source: wgpu::ShaderSource::Wgsl(shader_data.into()),
+    });
+
+    let camera_bind_group_layout = client.get_layout(CAMERA_LAYOUT_NAME).unwrap();
+    let sky_bind_group_layout = client.get_layout(SKY_LAYOUT_NAME).unwrap();
+    let atlas_bind_group_layout = client.get_layout(ATLAS_LAYOUT_NAME).unwrap();
+
+    let pipeline_layout =
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(format!("{}_layout", name).as_str()),
+            bind_group_layouts: &[camera_bind_group_layout, sky_bind_group_layout, atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(name),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[SkyVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    });
+
+    client.insert_pipeline(name, pipeline);
+}
+
+/// A pipeline for entity models, depth tested and written like the terrain. Faces of models are seen from both sides, mirrored boxes turn them around
+pub fn generate_entity_pipeline(client: &mut Client, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, shader_data: &String) {
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(ENTITY_PIPELINE),
+        source: wgpu::ShaderSource::Wgsl(shader_data.into()),
+    });
+
+    let camera_bind_group_layout = client.get_layout(CAMERA_LAYOUT_NAME).unwrap();
+    let sky_bind_group_layout = client.get_layout(SKY_LAYOUT_NAME).unwrap();
+    let atlas_bind_group_layout = client.get_layout(ATLAS_LAYOUT_NAME).unwrap();
+
+    let pipeline_layout =
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(format!("{}_layout", ENTITY_PIPELINE).as_str()),
+            bind_group_layouts: &[camera_bind_group_layout, sky_bind_group_layout, atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(ENTITY_PIPELINE),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[SkyVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    });
+
+    client.insert_pipeline(ENTITY_PIPELINE, pipeline);
+}
+
+/// Upload a whole image as a texture of its own, sampled without filtering like the atlas
+fn create_image_texture(client: &Client, device: &wgpu::Device, queue: &wgpu::Queue, image: &RgbaImage, label: &str, address_mode: wgpu::AddressMode) -> DiffuseTextureWrapper {
+    let tex_dims = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let tex_format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: tex_dims,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: tex_format,
+        view_formats: &[tex_format],
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        label: Some(label),
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        image.as_raw(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        tex_dims,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+    DiffuseTextureWrapper::new(texture, (image.width(), image.height()).into(), view, sampler, device, client.get_layout(ATLAS_LAYOUT_NAME).unwrap())
+}
+
+/// The sun, moon, rain, snow and particles of the resource packs, packs without them have none of them
+pub fn generate_sky_textures(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue, asset_loader: &AssetLoader) {
+    let white = create_image_texture(client, device, queue, &RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])), WHITE_TEXTURE_NAME, wgpu::AddressMode::ClampToEdge);
+    client.insert_texture(WHITE_TEXTURE_NAME, white);
+    // Rain and snow scroll down their columns, so they repeat
+    let textures = [(SUN_TEXTURE_NAME, wgpu::AddressMode::ClampToEdge), (MOON_TEXTURE_NAME, wgpu::AddressMode::ClampToEdge), (RAIN_TEXTURE_NAME, wgpu::AddressMode::Repeat), (SNOW_TEXTURE_NAME, wgpu::AddressMode::Repeat), (PARTICLES_TEXTURE_NAME, wgpu::AddressMode::ClampToEdge)];
+    for (name, address_mode) in textures {
+        match asset_loader.sprites().get(&Identifier::from_str(name)) {
+            Some(sprite) => {
+                let texture = create_image_texture(client, device, queue, &sprite.to_rgba8(), name, address_mode);
+                client.insert_texture(name, texture);
+            },
+            None => log::warn!("Did not have the {name} texture"),
         }
     }
+}
 
+/// The textures of every entity and block entity model, models whose texture a pack doesn't have aren't drawn
+pub fn generate_entity_textures(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue, asset_loader: &AssetLoader) {
+    let block_entity_textures = [SIGN_TEXTURE_NAME, CHEST_TEXTURE_NAME];
+    for name in ENTITY_MODEL_KINDS.iter().map(|kind| kind.texture()).chain(block_entity_textures) {
+        match asset_loader.sprites().get(&Identifier::from_str(name)) {
+            Some(sprite) => {
+                let texture = create_image_texture(client, device, queue, &sprite.to_rgba8(), name, wgpu::AddressMode::ClampToEdge);
+                client.insert_texture(name, texture);
+            },
+            None => log::warn!("Did not have the {name} texture"),
+        }
+    }
+}
 
+/// The font sheet of the resource packs, its glyphs are measured for laying out text. Packs without one have no text
+pub fn generate_font_texture(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue, asset_loader: &AssetLoader) {
+    match BitmapFont::load(asset_loader) {
+        Ok((font, sheet)) => {
+            let texture = create_image_texture(client, device, queue, &sheet, FONT_TEXTURE_NAME, wgpu::AddressMode::ClampToEdge);
+            client.insert_texture(FONT_TEXTURE_NAME, texture);
+            client.set_font(font);
+        },
+        Err(e) => log::warn!("Could not load the font, text won't be drawn: {e}"),
+    }
+}
 
-    let tex = DynamicImage::ImageRgb32F(rgb_tex);
+/// Upload the skin of a player as a texture of its own, replacing the skin it had before
+pub fn generate_skin_texture(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue, name: &str, skin: &RgbaImage) {
+    let texture = create_image_texture(client, device, queue, skin, name, wgpu::AddressMode::ClampToEdge);
+    client.insert_texture(name, texture);
+}
 
-    // tex.to_rgb16().save_with_format("../orange-mc-assets/assets/minecraft/textures/lightmap.png", image::ImageFormat::Png).unwrap();
+/// The lightmap as srgb pixels, a row for every sky light level and a column for every block light level
+fn lightmap_pixels(lightmap: &[f32; LIGHTMAP_SIZE * LIGHTMAP_SIZE]) -> Vec<u8> {
+    let mut rgb_tex = Rgb32FImage::new(LIGHTMAP_SIZE as u32, LIGHTMAP_SIZE as u32);
+    for (index, brightness) in lightmap.iter().enumerate() {
+        rgb_tex.put_pixel((index % LIGHTMAP_SIZE) as u32, (index / LIGHTMAP_SIZE) as u32, image::Rgb::<f32>([*brightness; 3]));
+    }
+    DynamicImage::ImageRgb32F(rgb_tex).to_rgba8().into_raw()
+}
 
-    let dims = tex.dimensions();
-    let width = dims.0;
-    let height = dims.1;
+/**
+ * Create the lightmap texture, lit as at noon in the overworld until the first [`update_lightmap_texture`].
+ * Terrain shaders sample it at ((block light + 0.5) / 16, (sky light + 0.5) / 16)
+ */
+pub fn generate_lightmap_texture(client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue) {
+    let pixels = lightmap_pixels(&lightmap::generate_lightmap(TICKS_PER_DAY / 4, 0.0, DimensionLight::OVERWORLD));
+    let width = LIGHTMAP_SIZE as u32;
+    let height = LIGHTMAP_SIZE as u32;
+    let dims = (width, height);
 
     let tex_dims = wgpu::Extent3d {
         width,
@@ -287,7 +611,7 @@ pub fn generate_lightmap_texture(client: &mut Client, device: &wgpu::Device, que
     });
     queue.write_texture(
         diffuse_texture.as_image_copy(),
-        tex.to_rgba8().as_bytes(),
+        &pixels,
         wgpu::ImageDataLayout {
             offset: 0,
             bytes_per_row: Some(4 * width),
@@ -318,3 +642,20 @@ pub fn generate_lightmap_texture(client: &mut Client, device: &wgpu::Device, que
 
     client.insert_texture(LIGHTMAP_TEXTURE_NAME, texture);
 }
+
+/// Recompute the lightmap for the time of day and dimension of the world, cheap enough to do every frame
+pub fn update_lightmap_texture(client: &Client, queue: &wgpu::Queue, time: u64, partial_tick: f32, dimension_id: i8) {
+    let Some(texture) = client.get_texture(LIGHTMAP_TEXTURE_NAME) else { return; };
+    let pixels = lightmap_pixels(&lightmap::generate_lightmap(time, partial_tick, DimensionLight::from_id(dimension_id)));
+    let size = LIGHTMAP_SIZE as u32;
+    queue.write_texture(
+        texture.get_texture().as_image_copy(),
+        &pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size),
+            rows_per_image: Some(size),
+        },
+        wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+    );
+}
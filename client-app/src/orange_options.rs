@@ -1,14 +1,30 @@
+use orange_rs::minecraft::lang::DEFAULT_LOCALE;
 use serde_derive::{Deserialize, Serialize};
 
+fn default_language() -> String {
+    DEFAULT_LOCALE.to_string()
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct OrangeOptions {
     server_ip: String,
     offline_username: String,
+    #[serde(default = "default_language")]
+    language: String,
+    /// Threads meshing chunk sections, 0 picks one less than the number of cores
+    #[serde(default)]
+    meshing_threads: usize,
+    /// Merge flat terrain into larger quads, only while the resource packs have a `shader_tiled` shader that wraps the tile attributes
+    #[serde(default)]
+    greedy_meshing: bool,
+    /// Where skins missing from the skins folder are downloaded from, `{name}` is replaced by the name of the player
+    #[serde(default)]
+    skin_url_template: Option<String>,
 }
 
 impl OrangeOptions {
     pub fn new() -> Self {
-        Self { server_ip: String::new(), offline_username: "".into() }
+        Self { server_ip: String::new(), offline_username: "".into(), language: default_language(), meshing_threads: 0, greedy_meshing: false, skin_url_template: None }
     }
     pub fn server_ip(&self) -> &str {
         &self.server_ip
@@ -16,4 +32,16 @@ impl OrangeOptions {
     pub fn offline_username(&self) -> &str {
         &self.offline_username
     }
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+    pub fn meshing_threads(&self) -> usize {
+        self.meshing_threads
+    }
+    pub fn greedy_meshing(&self) -> bool {
+        self.greedy_meshing
+    }
+    pub fn skin_url_template(&self) -> Option<&str> {
+        self.skin_url_template.as_deref()
+    }
 }
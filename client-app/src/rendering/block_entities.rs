@@ -0,0 +1,221 @@
+use std::ops::Range;
+
+use orange_rs::minecraft::registry::FrozenRegistry;
+use orange_rs::models::entity::{self, EntityModel, EntityModelKind, EntityPose, ENTITY_MODEL_KINDS};
+use orange_rs::world::block_entities::{self, BlockEntities, BlockEntity, Chest, MobSpawner, PistonHead, Sign};
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::{IVec3, Mat4, Vec2, Vec3, Vec4};
+use wgpu::{Buffer, Device, Queue, RenderPass};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler::{ATLAS_TEXTURE_NAME, CAMERA_BIND_GROUP_NAME, CHEST_TEXTURE_NAME, ENTITY_PIPELINE, FONT_TEXTURE_NAME, SIGN_TEXTURE_NAME, SKY_BIND_GROUP_NAME};
+use crate::rendering::entities::face_shade;
+use crate::rendering::particles::ParticleView;
+use crate::rendering::sky::{self, SkyVertex};
+
+/// Signs are drawn at two thirds of the size of their model
+const SIGN_SCALE: f32 = 2.0 / 3.0;
+/// Sign text is this many text units to a pixel of the sign model
+const SIGN_TEXT_SCALE: f32 = SIGN_SCALE / 60.0;
+/// The lines of a sign are this many text units apart
+const SIGN_LINE_HEIGHT: f32 = 10.0;
+/// The mob in a spawner is shrunk to fit inside the cage
+const SPAWNER_MOB_SCALE: f32 = 0.4375;
+
+fn block_origin(block: IVec3) -> Vec3 {
+    Vec3::new(block.x as f32, block.y as f32, block.z as f32)
+}
+
+/**
+ * The block entities of the world drawn like entities: sign boards with their text, chests with their lids, the mobs turning in spawners
+ * and the heads of moving pistons. Everything is built again every frame and drawn a texture at a time
+ */
+pub struct BlockEntityRenderer {
+    sign: EntityModel,
+    wall_sign: EntityModel,
+    chest: EntityModel,
+    spawner_models: HashMap<EntityModelKind, EntityModel>,
+    vertices: Vec<SkyVertex>,
+    indices: Vec<u32>,
+    /// The indices drawn with each texture
+    draws: Vec<(String, Range<u32>)>,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl BlockEntityRenderer {
+    pub fn new() -> Self {
+        Self {
+            sign: EntityModel::sign(true),
+            wall_sign: EntityModel::sign(false),
+            chest: EntityModel::chest(),
+            spawner_models: ENTITY_MODEL_KINDS.iter().map(|kind| (*kind, kind.model())).collect(),
+            vertices: vec![],
+            indices: vec![],
+            draws: vec![],
+            vertex_buffer: None,
+            index_buffer: None,
+        }
+    }
+
+    fn model(vertices: &mut Vec<SkyVertex>, indices: &mut Vec<u32>, model: &EntityModel, pose: &EntityPose, transform: Mat4, camera: Vec3, brightness: f32) {
+        let mesh = model.evaluate(pose, transform);
+        let texture_size = Vec2::from(model.texture_size);
+        let first = vertices.len() as u32;
+        vertices.extend(mesh.vertices.iter().map(|vertex| {
+            let shade = brightness * face_shade(vertex.normal);
+            SkyVertex::new(vertex.position - camera, Vec4::new(shade, shade, shade, 1.0), vertex.uv / texture_size)
+        }));
+        indices.extend(mesh.indices.iter().map(|index| first + index));
+    }
+
+    /// Where the board of a sign is, both the model and the text hang from it
+    fn sign_transform(block: IVec3, sign: &Sign) -> Mat4 {
+        let mut transform = Mat4::from_translation(block_origin(block) + Vec3::new(0.5, 0.75 * SIGN_SCALE, 0.5))
+            * Mat4::from_rotation_y(-sign.get_yaw().to_radians());
+        if !sign.standing {
+            transform = transform * Mat4::from_translation(Vec3::new(0.0, -0.3125, -0.4375));
+        }
+        transform
+    }
+
+    fn chest_transform(block: IVec3, chest: &Chest) -> Mat4 {
+        Mat4::from_translation(block_origin(block) + Vec3::new(0.0, 1.0, 1.0))
+            * Mat4::from_nonuniform_scale(Vec3::new(1.0, -1.0, -1.0))
+            * Mat4::from_translation(Vec3::broadcast(0.5))
+            * Mat4::from_rotation_y(block_entities::face_yaw(chest.facing).to_radians())
+            * Mat4::from_translation(Vec3::broadcast(-0.5))
+    }
+
+    /// The mob turns in the middle of the cage, leaning back a little
+    fn spawner_transform(block: IVec3, spawner: &MobSpawner, partial_tick: f32) -> Mat4 {
+        Mat4::from_translation(block_origin(block) + Vec3::new(0.5, 0.4, 0.5))
+            * Mat4::from_rotation_y(spawner.get_rotation(partial_tick).to_radians())
+            * Mat4::from_rotation_x((-30.0f32).to_radians())
+            * Mat4::from_translation(Vec3::new(0.0, -0.4, 0.0))
+            * Mat4::from_scale(SPAWNER_MOB_SCALE)
+            * entity::entity_transform(Vec3::zero(), 0.0)
+    }
+
+    /// The quads of the block model of a piston head, moved along with the head
+    fn piston_head(&mut self, registry: &FrozenRegistry, block: IVec3, head: &PistonHead, camera: Vec3, brightness: f32, partial_tick: f32) {
+        let Some(handle) = registry.get_blockstate_register().get_handle_from_index(head.state) else { return; };
+        let Some(quads) = registry.get_state_model(handle).and_then(|model| Some(model.parts().first()?.alternatives().first()?.0.shapes())) else { return; };
+        let origin = block_origin(block) + head.get_offset(partial_tick) - camera;
+        for quad in quads {
+            let shade = brightness * face_shade(quad.normal);
+            let color = Vec4::new(shade, shade, shade, 1.0);
+            let first = self.vertices.len() as u32;
+            self.vertices.extend(quad.pos.iter().zip(quad.uvs).map(|(pos, uv)| SkyVertex::new(origin + *pos, color, uv)));
+            self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+        }
+    }
+
+    /// The lines of a sign centered on its board, in black like the text of b1.7.3 unless a formatting code colors it
+    fn sign_text(&mut self, client: &Client, block: IVec3, sign: &Sign, camera: Vec3) {
+        let transform = Self::sign_transform(block, sign)
+            * Mat4::from_translation(Vec3::new(0.0, 0.5 * SIGN_SCALE, 0.07 * SIGN_SCALE))
+            * Mat4::from_nonuniform_scale(Vec3::new(SIGN_TEXT_SCALE, -SIGN_TEXT_SCALE, SIGN_TEXT_SCALE));
+        let font = client.get_font();
+        for (index, line) in sign.lines.iter().enumerate() {
+            let origin = Vec2::new(-font.get_string_width(line) / 2.0, index as f32 * SIGN_LINE_HEIGHT - SIGN_LINE_HEIGHT * 2.0);
+            for glyph in font.layout_line(line, origin, Vec3::zero()) {
+                let color = Vec4::new(glyph.color.x, glyph.color.y, glyph.color.z, 1.0);
+                let first = self.vertices.len() as u32;
+                self.vertices.extend(glyph.corners().map(|(corner, uv)| SkyVertex::new(transform.transform_point3(Vec3::new(corner.x, corner.y, 0.0)) - camera, color, uv)));
+                self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+            }
+        }
+    }
+
+    fn push_draw(&mut self, texture: &str, start: u32) {
+        let end = self.indices.len() as u32;
+        if end > start {
+            self.draws.push((texture.to_string(), start..end));
+        }
+    }
+
+    /**
+     * Build the block entities as seen from `view`, `brightness_of` is how lit a position is.
+     * Mobs in spawners whose model isn't known are left out
+     */
+    pub fn build(&mut self, block_entities: &BlockEntities, client: &Client, registry: &FrozenRegistry, view: &ParticleView, brightness_of: impl Fn(Vec3) -> f32) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draws.clear();
+        let brightness_at = |block: IVec3| brightness_of(block_origin(block) + Vec3::broadcast(0.5));
+
+        let start = self.indices.len() as u32;
+        for (block, entity) in block_entities.iter() {
+            let BlockEntity::Sign(sign) = entity else { continue; };
+            let model = if sign.standing { &self.sign } else { &self.wall_sign };
+            let transform = Self::sign_transform(*block, sign) * Mat4::from_nonuniform_scale(Vec3::new(SIGN_SCALE, -SIGN_SCALE, -SIGN_SCALE));
+            Self::model(&mut self.vertices, &mut self.indices, model, &EntityPose::default(), transform, view.camera, brightness_at(*block));
+        }
+        self.push_draw(SIGN_TEXTURE_NAME, start);
+
+        let start = self.indices.len() as u32;
+        for (block, entity) in block_entities.iter() {
+            let BlockEntity::Chest(chest) = entity else { continue; };
+            let pose = EntityPose { openness: chest.get_openness(view.partial_tick), ..Default::default() };
+            Self::model(&mut self.vertices, &mut self.indices, &self.chest, &pose, Self::chest_transform(*block, chest), view.camera, brightness_at(*block));
+        }
+        self.push_draw(CHEST_TEXTURE_NAME, start);
+
+        let mut spawners: HashMap<EntityModelKind, Vec<(IVec3, &MobSpawner)>> = HashMap::default();
+        for (block, entity) in block_entities.iter() {
+            let BlockEntity::MobSpawner(spawner) = entity else { continue; };
+            let Some(kind) = EntityModelKind::from_mob_name(&spawner.mob) else { continue; };
+            spawners.entry(kind).or_default().push((*block, spawner));
+        }
+        for (kind, spawners) in spawners {
+            let Some(model) = self.spawner_models.get(&kind) else { continue; };
+            let start = self.indices.len() as u32;
+            for (block, spawner) in spawners {
+                let transform = Self::spawner_transform(block, spawner, view.partial_tick);
+                Self::model(&mut self.vertices, &mut self.indices, model, &EntityPose::default(), transform, view.camera, brightness_at(block));
+            }
+            self.push_draw(kind.texture(), start);
+        }
+
+        let start = self.indices.len() as u32;
+        for (block, entity) in block_entities.iter() {
+            let BlockEntity::Piston(head) = entity else { continue; };
+            self.piston_head(registry, *block, head, view.camera, brightness_at(*block), view.partial_tick);
+        }
+        self.push_draw(ATLAS_TEXTURE_NAME, start);
+
+        let start = self.indices.len() as u32;
+        for (block, entity) in block_entities.iter() {
+            let BlockEntity::Sign(sign) = entity else { continue; };
+            self.sign_text(client, *block, sign, view.camera);
+        }
+        self.push_draw(FONT_TEXTURE_NAME, start);
+    }
+
+    /// Write the built block entities to their buffers, growing them when they don't fit
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.indices.is_empty() { return; }
+        sky::write_growing_buffer(device, queue, &mut self.vertex_buffer, bytemuck::cast_slice(&self.vertices), wgpu::BufferUsages::VERTEX, "Block Entity Vertex Buffer");
+        sky::write_growing_buffer(device, queue, &mut self.index_buffer, bytemuck::cast_slice(&self.indices), wgpu::BufferUsages::INDEX, "Block Entity Index Buffer");
+    }
+
+    /// Draw the block entities with the entity shader, textures that aren't loaded are skipped
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, client: &'a Client) {
+        if self.indices.is_empty() { return; }
+        let Some(pipeline) = client.get_pipeline(ENTITY_PIPELINE) else { return; };
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else { return; };
+        let (Some(camera), Some(sky)) = (client.get_bind_group(CAMERA_BIND_GROUP_NAME), client.get_bind_group(SKY_BIND_GROUP_NAME)) else { return; };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera, &[]);
+        render_pass.set_bind_group(1, sky, &[]);
+        for (texture, indices) in &self.draws {
+            let Some(texture) = client.get_texture(texture) else { continue; };
+            render_pass.set_bind_group(2, texture.bind_group(), &[]);
+            render_pass.draw_indexed(indices.clone(), 0, 0..1);
+        }
+    }
+}
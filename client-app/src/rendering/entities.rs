@@ -0,0 +1,215 @@
+use std::ops::Range;
+
+use orange_rs::entities::{EntityAnimation, EntityKind, EntityTransform};
+use orange_rs::minecraft::asset_loader::AssetLoader;
+use orange_rs::minecraft::identifier::Identifier;
+use orange_rs::minecraft::registry::FrozenRegistry;
+use orange_rs::models::entity::{self, EntityModel, EntityModelKind, EntityPose, ENTITY_MODEL_KINDS};
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::{Vec2, Vec3, Vec4};
+use wgpu::{Buffer, Device, Queue, RenderPass};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler::{ATLAS_TEXTURE_NAME, CAMERA_BIND_GROUP_NAME, ENTITY_PIPELINE, FONT_TEXTURE_NAME, SKY_BIND_GROUP_NAME};
+use crate::rendering::particles::ParticleView;
+use crate::rendering::sky::{self, SkyVertex};
+use crate::skin_cache;
+
+/// Items without a block model are drawn with this sprite until items have textures of their own
+const MISSING_SPRITE: &str = "minecraft:block/missing";
+/// Nameplates float this high above the feet of a player
+const NAMEPLATE_HEIGHT: f32 = 2.3;
+/// Blocks to a unit of nameplate text
+const NAMEPLATE_SCALE: f32 = 1.6 / 60.0;
+
+/// How much light a face gets from the side it faces, like the faces of blocks
+pub fn face_shade(normal: Vec3) -> f32 {
+    let vertical = if normal.y > 0.0 { 1.0 } else { 0.5 };
+    normal.x * normal.x * 0.6 + normal.y * normal.y * vertical + normal.z * normal.z * 0.8
+}
+
+/**
+ * The models of every entity and the sprites of dropped items, built again every frame in the same vertices as the sky.
+ * Models are drawn a texture at a time, players with a skin of their own each get a draw of their own. Items are drawn from the atlas
+ * and the names of players over their heads from the font
+ */
+pub struct EntityRenderer {
+    entity_models: HashMap<EntityModelKind, EntityModel>,
+    vertices: Vec<SkyVertex>,
+    indices: Vec<u32>,
+    /// The indices drawn with each texture
+    models: Vec<(String, Range<u32>)>,
+    items: Range<u32>,
+    nameplates: Range<u32>,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl EntityRenderer {
+    pub fn new(asset_loader: &AssetLoader) -> Self {
+        Self {
+            entity_models: Self::models(asset_loader),
+            vertices: vec![],
+            indices: vec![],
+            models: vec![],
+            items: 0..0,
+            nameplates: 0..0,
+            vertex_buffer: None,
+            index_buffer: None,
+        }
+    }
+
+    fn model_kind(kind: &EntityKind) -> Option<EntityModelKind> {
+        match kind {
+            EntityKind::Player { .. } => Some(EntityModelKind::Player),
+            EntityKind::Mob(mob_type) => EntityModelKind::from_mob_type(*mob_type),
+            EntityKind::Object(object_type) => EntityModelKind::from_object_type(*object_type),
+            EntityKind::Item { .. } => None,
+        }
+    }
+
+    /// Where in the atlas a dropped item samples from, the first face of its block or the missing sprite
+    fn item_uv_bounds(registry: &FrozenRegistry, state: Option<usize>) -> Option<(Vec2, Vec2)> {
+        let block_bounds = state.and_then(|state| {
+            let handle = registry.get_blockstate_register().get_handle_from_index(state)?;
+            let quad = registry.get_state_model(handle)?.parts().first()?.alternatives().first()?.0.shapes().first()?;
+            let min = quad.uvs.iter().fold(Vec2::broadcast(f32::MAX), |min, uv| min.min_by_component(*uv));
+            let max = quad.uvs.iter().fold(Vec2::broadcast(f32::MIN), |max, uv| max.max_by_component(*uv));
+            Some((min, max))
+        });
+        block_bounds.or_else(|| registry.get_sprite_register().get(&Identifier::from_str(MISSING_SPRITE)).map(|sprite| (sprite.uv_min, sprite.uv_max)))
+    }
+
+    /// The model of every kind of entity, from the resource packs when they have one
+    fn models(asset_loader: &AssetLoader) -> HashMap<EntityModelKind, EntityModel> {
+        ENTITY_MODEL_KINDS.iter().map(|kind| (*kind, kind.load_model(asset_loader.entity_models()))).collect()
+    }
+
+    /// Take the models of the resource packs again after they are reloaded
+    pub fn load_models(&mut self, asset_loader: &AssetLoader) {
+        self.entity_models = Self::models(asset_loader);
+    }
+
+    /// The texture an entity is drawn with, players without a loaded skin have the default skin
+    fn texture_name(kind: &EntityKind, model_kind: EntityModelKind, client: &Client) -> Option<String> {
+        if let EntityKind::Player { name } = kind {
+            let skin = skin_cache::skin_texture_name(name);
+            if client.get_texture(&skin).is_some() {
+                return Some(skin);
+            }
+        }
+        client.get_texture(model_kind.texture()).map(|_| model_kind.texture().to_string())
+    }
+
+    fn model(&mut self, kind: EntityModelKind, transform: &EntityTransform, animation: &EntityAnimation, camera: Vec3, brightness: f32) {
+        let pose = EntityPose {
+            limb_swing: animation.limb_swing,
+            limb_swing_amount: animation.limb_swing_amount,
+            age: animation.age,
+            head_yaw: transform.get_head_yaw(),
+            head_pitch: transform.rotation.y,
+            ..Default::default()
+        };
+        let Some(model) = self.entity_models.get(&kind) else { return; };
+        let mesh = model.evaluate(&pose, entity::entity_transform(transform.position, transform.body_yaw));
+        let texture_size = Vec2::from(model.texture_size);
+        let first = self.vertices.len() as u32;
+        self.vertices.extend(mesh.vertices.iter().map(|vertex| {
+            let shade = brightness * face_shade(vertex.normal);
+            SkyVertex::new(vertex.position - camera, Vec4::new(shade, shade, shade, 1.0), vertex.uv / texture_size)
+        }));
+        self.indices.extend(mesh.indices.iter().map(|index| first + index));
+    }
+
+    /**
+     * Build the entities as seen from `view`. `brightness_of` is how lit a position is and `item_state_of` the blockstate
+     * a dropped item of an id and damage is drawn as. Models whose texture isn't loaded aren't drawn
+     */
+    pub fn build(&mut self, entities: &[(EntityKind, EntityTransform, EntityAnimation)], client: &Client, registry: &FrozenRegistry, view: &ParticleView, brightness_of: impl Fn(Vec3) -> f32, item_state_of: impl Fn(i16, i16) -> Option<usize>) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.models.clear();
+        let mut by_texture: HashMap<String, Vec<(EntityModelKind, &EntityTransform, &EntityAnimation)>> = HashMap::default();
+        for (kind, transform, animation) in entities {
+            let Some(model_kind) = Self::model_kind(kind) else { continue; };
+            let Some(texture) = Self::texture_name(kind, model_kind, client) else { continue; };
+            by_texture.entry(texture).or_default().push((model_kind, transform, animation));
+        }
+        for (texture, models) in by_texture {
+            let start = self.indices.len() as u32;
+            for (model_kind, transform, animation) in models {
+                self.model(model_kind, transform, animation, view.camera, brightness_of(transform.position));
+            }
+            self.models.push((texture, start..self.indices.len() as u32));
+        }
+
+        let items_start = self.indices.len() as u32;
+        for (kind, transform, animation) in entities {
+            let EntityKind::Item { id, count, damage } = *kind else { continue; };
+            let Some((uv_min, uv_max)) = Self::item_uv_bounds(registry, item_state_of(id, damage)) else { continue; };
+            let uvs = [uv_min, Vec2::new(uv_max.x, uv_min.y), Vec2::new(uv_min.x, uv_max.y), uv_max];
+            let brightness = brightness_of(transform.position);
+            let color = Vec4::new(brightness, brightness, brightness, 1.0);
+            for corners in entity::item_drop_billboards(animation.age + view.partial_tick, animation.hover_start, count, view.right, view.up) {
+                let first = self.vertices.len() as u32;
+                self.vertices.extend(corners.iter().zip(uvs).map(|(corner, uv)| SkyVertex::new(transform.position + *corner - view.camera, color, uv)));
+                self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+            }
+        }
+        self.items = items_start..self.indices.len() as u32;
+
+        let nameplates_start = self.indices.len() as u32;
+        for (kind, transform, _) in entities {
+            let EntityKind::Player { name } = kind else { continue; };
+            self.nameplate(client, name, transform.position, view);
+        }
+        self.nameplates = nameplates_start..self.indices.len() as u32;
+    }
+
+    /// The name of a player centered over their head and turned to face the camera, as bright in the dark as in the day
+    fn nameplate(&mut self, client: &Client, name: &str, position: Vec3, view: &ParticleView) {
+        let font = client.get_font();
+        let anchor = position + Vec3::new(0.0, NAMEPLATE_HEIGHT, 0.0) - view.camera;
+        let origin = Vec2::new(-font.get_string_width(name) / 2.0, 0.0);
+        for glyph in font.layout_line(name, origin, Vec3::one()) {
+            let color = Vec4::new(glyph.color.x, glyph.color.y, glyph.color.z, 1.0);
+            let first = self.vertices.len() as u32;
+            self.vertices.extend(glyph.corners().map(|(corner, uv)| SkyVertex::new(anchor + (view.right * corner.x - view.up * corner.y) * NAMEPLATE_SCALE, color, uv)));
+            self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+        }
+    }
+
+    /// Write the built entities to their buffers, growing them when they don't fit
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.indices.is_empty() { return; }
+        sky::write_growing_buffer(device, queue, &mut self.vertex_buffer, bytemuck::cast_slice(&self.vertices), wgpu::BufferUsages::VERTEX, "Entity Vertex Buffer");
+        sky::write_growing_buffer(device, queue, &mut self.index_buffer, bytemuck::cast_slice(&self.indices), wgpu::BufferUsages::INDEX, "Entity Index Buffer");
+    }
+
+    /// Draw the entities after the terrain, it takes over the bind groups the terrain was drawn with, nothing is drawn without the entity shader of a resource pack
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, client: &'a Client) {
+        if self.indices.is_empty() { return; }
+        let Some(pipeline) = client.get_pipeline(ENTITY_PIPELINE) else { return; };
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else { return; };
+        let (Some(camera), Some(sky)) = (client.get_bind_group(CAMERA_BIND_GROUP_NAME), client.get_bind_group(SKY_BIND_GROUP_NAME)) else { return; };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera, &[]);
+        render_pass.set_bind_group(1, sky, &[]);
+        for (texture, indices) in &self.models {
+            let Some(texture) = client.get_texture(texture) else { continue; };
+            render_pass.set_bind_group(2, texture.bind_group(), &[]);
+            render_pass.draw_indexed(indices.clone(), 0, 0..1);
+        }
+        if let Some(atlas) = client.get_texture(ATLAS_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, atlas.bind_group(), &[]);
+            render_pass.draw_indexed(self.items.clone(), 0, 0..1);
+        }
+        if let Some(font) = client.get_texture(FONT_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, font.bind_group(), &[]);
+            render_pass.draw_indexed(self.nameplates.clone(), 0, 0..1);
+        }
+    }
+}
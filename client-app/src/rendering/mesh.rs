@@ -1,4 +1,8 @@
-use wgpu::{Buffer, RenderPass};
+use orange_rs::{util::quad_sort::TransparentQuads, world::visibility::SectionVisibility};
+use ultraviolet::Vec3;
+use wgpu::{Buffer, Device, Queue, RenderPass, util::DeviceExt};
+
+use super::{tessellator::SectionBuffers, verticies::TerrainVertex};
 
 pub struct Mesh {
     pub opaque_vertex_buffer: Buffer,
@@ -9,6 +13,12 @@ pub struct Mesh {
     pub num_vertices_transparent: u32,
     pub num_indices_opaque: u32,
     pub num_indices_transparent: u32,
+    /// The vertex and index buffers and index count of the faces merged by greedy meshing, sections without any have none
+    pub tiled: Option<(Buffer, Buffer, u32)>,
+    /// Which sides of the section see each other, sections uploaded without knowing it are seen through
+    pub visibility: SectionVisibility,
+    /// The transparent quads, sorted back to front and written over the transparent index buffer as the camera moves
+    pub transparent_quads: TransparentQuads,
 }
 
 impl Mesh {
@@ -31,6 +41,41 @@ impl Mesh {
             transparent_index_buffer,
             num_indices_opaque,
             num_indices_transparent,
+            tiled: None,
+            visibility: SectionVisibility::all(),
+            transparent_quads: TransparentQuads::default(),
+        }
+    }
+
+    /// Uploads the buffers of a meshed section
+    pub fn from_buffers(device: &Device, buffers: &SectionBuffers) -> Self {
+        let create_buffer = |contents: &[u8], usage: wgpu::BufferUsages, label: &str| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage })
+        };
+        let mesh = Self::new(
+            create_buffer(bytemuck::cast_slice(&buffers.opaque_vertices), wgpu::BufferUsages::VERTEX, "Vertex Buffer"),
+            create_buffer(bytemuck::cast_slice(&buffers.transparent_vertices), wgpu::BufferUsages::VERTEX, "Vertex Buffer"),
+            buffers.opaque_vertices.len() as u32,
+            buffers.transparent_vertices.len() as u32,
+            create_buffer(bytemuck::cast_slice(&buffers.opaque_indices), wgpu::BufferUsages::INDEX, "Index Buffer"),
+            create_buffer(bytemuck::cast_slice(&buffers.transparent_indices), wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST, "Index Buffer"),
+            buffers.opaque_indices.len() as u32,
+            buffers.transparent_indices.len() as u32,
+        );
+        let tiled = (!buffers.tiled_indices.is_empty()).then(|| (
+            create_buffer(bytemuck::cast_slice(&buffers.tiled_vertices), wgpu::BufferUsages::VERTEX, "Tiled Vertex Buffer"),
+            create_buffer(bytemuck::cast_slice(&buffers.tiled_indices), wgpu::BufferUsages::INDEX, "Tiled Index Buffer"),
+            buffers.tiled_indices.len() as u32,
+        ));
+        let transparent_positions: Vec<Vec3> = buffers.transparent_vertices.iter().map(TerrainVertex::position).collect();
+        let transparent_quads = TransparentQuads::new(&transparent_positions, buffers.transparent_indices.clone());
+        Self { tiled, visibility: buffers.visibility, transparent_quads, ..mesh }
+    }
+
+    /// Sort the transparent quads back to front if the camera moved far enough since they were last sorted
+    pub fn sort_transparent(&mut self, queue: &Queue, camera: Vec3) {
+        if self.transparent_quads.sort_if_moved(camera) {
+            queue.write_buffer(&self.transparent_index_buffer, 0, bytemuck::cast_slice(self.transparent_quads.indices()));
         }
     }
 
@@ -39,6 +84,13 @@ impl Mesh {
         render_pass.set_index_buffer(self.opaque_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         render_pass.draw_indexed(0..self.num_indices_opaque, 0, 0..1);
     }
+    /// Draw the faces merged by greedy meshing, with the tiled terrain pipeline set
+    pub fn draw_tiled<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        let Some((vertex_buffer, index_buffer, num_indices)) = &self.tiled else { return; };
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..*num_indices, 0, 0..1);
+    }
     pub fn draw_transparent<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
         render_pass.set_vertex_buffer(0, self.transparent_vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.transparent_index_buffer.slice(..), wgpu::IndexFormat::Uint32);
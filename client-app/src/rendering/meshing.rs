@@ -0,0 +1,121 @@
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, RecvTimeoutError, Sender}};
+use std::time::Duration;
+
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::IVec3;
+
+use orange_rs::minecraft::registry::FrozenRegistry;
+use orange_rs::util::workers::WorkerThread;
+use orange_rs::world::snapshot::SectionSnapshot;
+
+use super::tessellator::{SectionBuffers, TerrainTessellator};
+
+/// How long an idle worker waits for a job before checking if it should stop
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Sections queued per worker, more are only snapshotted once these are done so the nearest sections go first
+const JOBS_PER_WORKER: usize = 4;
+
+struct MeshingJob {
+    snapshot: SectionSnapshot,
+    registry: Arc<FrozenRegistry>,
+    revision: u64,
+}
+
+/// The buffers of a section meshed by a worker
+pub struct MeshedSection {
+    pub position: IVec3,
+    pub buffers: SectionBuffers,
+    revision: u64,
+}
+
+/**
+ * Worker threads that mesh snapshots of sections.
+ * Every submission of a section gets a newer revision, results older than the latest revision of their section are dropped
+ */
+pub struct MeshingPool {
+    // Dropped before the workers, so idle workers see the channel close instead of waiting on it
+    jobs: Sender<MeshingJob>,
+    results: Receiver<MeshedSection>,
+    workers: Vec<WorkerThread>,
+    /// Whether the workers merge faces, it is turned off while the resource packs have no tiled terrain shader
+    greedy_meshing: Arc<AtomicBool>,
+    revisions: HashMap<IVec3, u64>,
+    next_revision: u64,
+    in_flight: usize,
+    capacity: usize,
+}
+
+impl MeshingPool {
+    /// A pool of `threads` workers, or one less than the number of cores when `threads` is 0
+    pub fn new(threads: usize, greedy_meshing: bool) -> Self {
+        let threads = match threads {
+            0 => std::thread::available_parallelism().map(|cores| cores.get().saturating_sub(1)).unwrap_or(1).max(1),
+            threads => threads,
+        };
+        let (jobs, job_receiver) = mpsc::channel::<MeshingJob>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let greedy_meshing = Arc::new(AtomicBool::new(greedy_meshing));
+        let workers = (0..threads).map(|_| {
+            let jobs = job_receiver.clone();
+            let results = result_sender.clone();
+            let greedy_meshing = greedy_meshing.clone();
+            let mut tessellator = TerrainTessellator::new();
+            let mut worker = WorkerThread::new();
+            worker.spawn(move |running| {
+                if !running { return; }
+                let job = match jobs.lock().map(|jobs| jobs.recv_timeout(JOB_POLL_INTERVAL)) {
+                    Ok(Ok(job)) => job,
+                    Ok(Err(RecvTimeoutError::Timeout)) => return,
+                    // The pool is shutting down
+                    _ => { std::thread::sleep(JOB_POLL_INTERVAL); return; },
+                };
+                let greedy_meshing = greedy_meshing.load(Ordering::Relaxed);
+                if tessellator.is_greedy_meshing() != greedy_meshing {
+                    tessellator = TerrainTessellator::new().with_greedy_meshing(greedy_meshing);
+                }
+                tessellator.tessellate_chunk_section(&job.snapshot, &job.registry);
+                // The pool may already be gone, then nobody wants the mesh
+                let _ = results.send(MeshedSection { position: job.snapshot.get_position(), buffers: tessellator.take_buffers(), revision: job.revision });
+            });
+            worker
+        }).collect();
+        Self { jobs, results, workers, greedy_meshing, revisions: HashMap::default(), next_revision: 0, in_flight: 0, capacity: threads * JOBS_PER_WORKER }
+    }
+
+    /// Whether the workers already have enough queued
+    pub fn is_full(&self) -> bool {
+        self.in_flight >= self.capacity
+    }
+
+    pub fn submit(&mut self, snapshot: SectionSnapshot, registry: &Arc<FrozenRegistry>) {
+        self.next_revision += 1;
+        self.revisions.insert(snapshot.get_position(), self.next_revision);
+        let job = MeshingJob { snapshot, registry: registry.clone(), revision: self.next_revision };
+        if self.jobs.send(job).is_ok() {
+            self.in_flight += 1;
+        }
+    }
+
+    /// The sections meshed since the last call, without the ones meshed again since they were submitted
+    pub fn receive(&mut self) -> Vec<MeshedSection> {
+        let mut meshed = vec![];
+        while let Ok(section) = self.results.try_recv() {
+            self.in_flight = self.in_flight.saturating_sub(1);
+            if self.revisions.get(&section.position) == Some(&section.revision) {
+                self.revisions.remove(&section.position);
+                meshed.push(section);
+            }
+        }
+        meshed
+    }
+
+    /// Sections submitted from now on are meshed with or without greedy meshing
+    pub fn set_greedy_meshing(&self, greedy_meshing: bool) {
+        self.greedy_meshing.store(greedy_meshing, Ordering::Relaxed);
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+}
@@ -1,9 +1,15 @@
 use instant::Duration;
 
+pub mod block_entities;
+pub mod entities;
 pub mod mesh;
+pub mod meshing;
+pub mod particles;
+pub mod sky;
 pub mod tessellator;
 pub mod textures;
 pub mod verticies;
+pub mod weather;
 pub mod world_renders;
 
 pub struct ElapsedTime {
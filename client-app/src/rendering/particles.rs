@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use orange_rs::minecraft::registry::FrozenRegistry;
+use orange_rs::world::particles::{Particle, ParticleTexture};
+use ultraviolet::{Vec2, Vec3, Vec4};
+use wgpu::{Buffer, Device, Queue, RenderPass};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler::{ATLAS_TEXTURE_NAME, CAMERA_BIND_GROUP_NAME, PARTICLES_TEXTURE_NAME, SKY_BIND_GROUP_NAME, WEATHER_PIPELINE};
+use crate::rendering::sky::{self, SkyVertex};
+
+/// Where particles are seen from, they turn to face the camera along its `right` and `up`
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleView {
+    pub camera: Vec3,
+    pub right: Vec3,
+    pub up: Vec3,
+    pub partial_tick: f32,
+}
+
+/// The indices of the particles drawn from each texture
+#[derive(Clone, Debug, Default)]
+struct ParticleParts {
+    sheet: Range<u32>,
+    blocks: Range<u32>,
+}
+
+/**
+ * The billboards of every particle, built again every frame in the same vertices as the sky and drawn with the weather shader.
+ * Particles on the particle sheet and debris from the atlas are drawn separately
+ */
+pub struct ParticleRenderer {
+    vertices: Vec<SkyVertex>,
+    indices: Vec<u32>,
+    parts: ParticleParts,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl ParticleRenderer {
+    pub fn new() -> Self {
+        Self {
+            vertices: vec![],
+            indices: vec![],
+            parts: ParticleParts::default(),
+            vertex_buffer: None,
+            index_buffer: None,
+        }
+    }
+
+    /// Where in the atlas debris of a blockstate samples from, the sprite of the first face of its model
+    fn block_uv_bounds(registry: &FrozenRegistry, state: usize) -> Option<(Vec2, Vec2)> {
+        let handle = registry.get_blockstate_register().get_handle_from_index(state)?;
+        let model = registry.get_state_model(handle)?;
+        let quad = model.parts().first()?.alternatives().first()?.0.shapes().first()?;
+        let min = quad.uvs.iter().fold(Vec2::broadcast(f32::MAX), |min, uv| min.min_by_component(*uv));
+        let max = quad.uvs.iter().fold(Vec2::broadcast(f32::MIN), |max, uv| max.max_by_component(*uv));
+        Some((min, max))
+    }
+
+    fn particle(&mut self, particle: &Particle, uv_min: Vec2, uv_max: Vec2, view: &ParticleView, brightness: f32) {
+        let first = self.vertices.len() as u32;
+        let shade = if particle.is_emissive() { 1.0 } else { brightness };
+        let color = Vec4::new(particle.color.x * shade, particle.color.y * shade, particle.color.z * shade, 1.0);
+        let uvs = [uv_min, Vec2::new(uv_max.x, uv_min.y), Vec2::new(uv_min.x, uv_max.y), uv_max];
+        let corners = particle.billboard(view.partial_tick, view.right, view.up);
+        self.vertices.extend(corners.iter().zip(uvs).map(|(corner, uv)| SkyVertex::new(*corner - view.camera, color, uv)));
+        self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+    }
+
+    /// Build the particles as seen from `view`, `brightness_of` is how lit a position is. Debris of states without a model isn't drawn
+    pub fn build(&mut self, particles: &VecDeque<Particle>, registry: &FrozenRegistry, view: &ParticleView, brightness_of: impl Fn(Vec3) -> f32) {
+        self.vertices.clear();
+        self.indices.clear();
+        for particle in particles.iter().filter(|particle| matches!(particle.texture, ParticleTexture::Sheet(_))) {
+            let (uv_min, uv_max) = particle.texture.uv_bounds();
+            self.particle(particle, uv_min, uv_max, view, brightness_of(particle.position));
+        }
+        let sheet_end = self.indices.len() as u32;
+
+        for particle in particles {
+            let ParticleTexture::Block { state, .. } = particle.texture else { continue; };
+            let Some((sprite_min, sprite_max)) = Self::block_uv_bounds(registry, state) else { continue; };
+            let (uv_min, uv_max) = particle.texture.uv_bounds();
+            let size = sprite_max - sprite_min;
+            self.particle(particle, sprite_min + uv_min * size, sprite_min + uv_max * size, view, brightness_of(particle.position));
+        }
+        let blocks_end = self.indices.len() as u32;
+
+        self.parts = ParticleParts { sheet: 0..sheet_end, blocks: sheet_end..blocks_end };
+    }
+
+    /// Write the built particles to their buffers, growing them when they don't fit
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.indices.is_empty() { return; }
+        sky::write_growing_buffer(device, queue, &mut self.vertex_buffer, bytemuck::cast_slice(&self.vertices), wgpu::BufferUsages::VERTEX, "Particle Vertex Buffer");
+        sky::write_growing_buffer(device, queue, &mut self.index_buffer, bytemuck::cast_slice(&self.indices), wgpu::BufferUsages::INDEX, "Particle Index Buffer");
+    }
+
+    /// Draw the particles after the terrain, nothing is drawn without the weather shaders of a resource pack
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, client: &'a Client) {
+        if self.indices.is_empty() { return; }
+        let Some(pipeline) = client.get_pipeline(WEATHER_PIPELINE) else { return; };
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else { return; };
+        let (Some(camera), Some(sky)) = (client.get_bind_group(CAMERA_BIND_GROUP_NAME), client.get_bind_group(SKY_BIND_GROUP_NAME)) else { return; };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera, &[]);
+        render_pass.set_bind_group(1, sky, &[]);
+        if let Some(sheet) = client.get_texture(PARTICLES_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, sheet.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.sheet.clone(), 0, 0..1);
+        }
+        if let Some(atlas) = client.get_texture(ATLAS_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, atlas.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.blocks.clone(), 0, 0..1);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::ops::Range;
+
+use orange_rs::world::sky::{self, FogRange, SkyState};
+use ultraviolet::{Vec2, Vec3, Vec4};
+use wgpu::{Buffer, Device, Queue, RenderPass, util::DeviceExt};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler::{CAMERA_BIND_GROUP_NAME, MOON_TEXTURE_NAME, SKY_ADDITIVE_PIPELINE, SKY_BIND_GROUP_NAME, SKY_PIPELINE, SUN_TEXTURE_NAME, WHITE_TEXTURE_NAME};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyVertex {
+    /// Relative to the camera, the sky moves with it
+    position: Vec3,
+    color: Vec4,
+    texture: Vec2,
+}
+
+impl SkyVertex {
+    pub fn new(position: Vec3, color: Vec4, texture: Vec2) -> Self {
+        Self { position, color, texture }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x4,
+        2 => Float32x2,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBS,
+        }
+    }
+}
+
+/**
+ * The uniform of the sky and terrain shaders.
+ * Sky shaders place their vertices at `camera_position.xyz` + position, terrain shaders blend to `fog_color.rgb`
+ * from `fog_range.x` to `fog_range.y` blocks away from the camera
+ */
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkyUniform {
+    camera_position: Vec4,
+    fog_color: Vec4,
+    fog_range: Vec4,
+}
+
+impl SkyUniform {
+    pub fn new(camera_position: Vec3, state: &SkyState, fog: FogRange) -> Self {
+        Self {
+            camera_position: Vec4::new(camera_position.x, camera_position.y, camera_position.z, 1.0),
+            fog_color: Vec4::new(state.fog_color.x, state.fog_color.y, state.fog_color.z, 1.0),
+            fog_range: Vec4::new(fog.start, fog.end, 0.0, 0.0),
+        }
+    }
+}
+
+/// Write `contents` to a buffer rebuilt every frame, replacing the buffer when they no longer fit
+pub fn write_growing_buffer(device: &Device, queue: &Queue, buffer: &mut Option<Buffer>, contents: &[u8], usage: wgpu::BufferUsages, label: &str) {
+    match buffer {
+        Some(buffer) if buffer.size() >= contents.len() as u64 => queue.write_buffer(buffer, 0, contents),
+        _ => *buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents, usage: usage | wgpu::BufferUsages::COPY_DST })),
+    }
+}
+
+/// The indices of every part of the sky, parts are drawn in this order
+#[derive(Clone, Debug, Default)]
+struct SkyParts {
+    backdrop: Range<u32>,
+    stars: Range<u32>,
+    sun: Range<u32>,
+    moon: Range<u32>,
+}
+
+/**
+ * The geometry of the sky, built again every frame from the [`SkyState`].
+ * The backdrop is blended over the fog color the frame is cleared to, the stars, sun and moon are added on top
+ */
+pub struct SkyRenderer {
+    stars: Vec<[Vec3; 4]>,
+    vertices: Vec<SkyVertex>,
+    indices: Vec<u32>,
+    parts: SkyParts,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl SkyRenderer {
+    pub fn new() -> Self {
+        Self {
+            stars: sky::star_quads(),
+            vertices: vec![],
+            indices: vec![],
+            parts: SkyParts::default(),
+            vertex_buffer: None,
+            index_buffer: None,
+        }
+    }
+
+    fn fan(&mut self, center: Vec3, center_color: Vec4, rim: &[Vec3], rim_color: Vec4, scale: f32) {
+        let first = self.vertices.len() as u32;
+        self.vertices.push(SkyVertex::new(center * scale, center_color, Vec2::zero()));
+        self.vertices.extend(rim.iter().map(|point| SkyVertex::new(*point * scale, rim_color, Vec2::zero())));
+        for segment in 1..rim.len() as u32 {
+            self.indices.extend([first, first + segment, first + segment + 1]);
+        }
+    }
+
+    /// A quad with corners ordered top left, top right, bottom left, bottom right, the sky is drawn from both sides
+    fn quad(&mut self, corners: [Vec3; 4], color: Vec4, uv_min: Vec2, uv_max: Vec2, scale: f32) {
+        let first = self.vertices.len() as u32;
+        let uvs = [uv_min, Vec2::new(uv_max.x, uv_min.y), Vec2::new(uv_min.x, uv_max.y), uv_max];
+        self.vertices.extend(corners.iter().zip(uvs).map(|(corner, uv)| SkyVertex::new(*corner * scale, color, uv)));
+        self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+    }
+
+    /// Build the sky as it looks in `state`, `scale` shrinks it to fit inside the far plane
+    pub fn build(&mut self, state: &SkyState, scale: f32) {
+        self.vertices.clear();
+        self.indices.clear();
+        let sky_color = Vec4::new(state.sky_color.x, state.sky_color.y, state.sky_color.z, 1.0);
+        let fog_color = Vec4::new(state.fog_color.x, state.fog_color.y, state.fog_color.z, 1.0);
+
+        let (center, rim) = sky::sky_disc();
+        self.fan(center, sky_color, &rim, fog_color, scale);
+        if let Some(glow) = state.sunrise {
+            let (center, rim) = sky::sunrise_fan(state.celestial_angle, glow.w);
+            self.fan(center, glow, &rim, Vec4::new(glow.x, glow.y, glow.z, 0.0), scale);
+        }
+        let backdrop_end = self.indices.len() as u32;
+
+        if state.has_sky {
+            let star_color = Vec4::broadcast(state.star_brightness);
+            if state.star_brightness > 0.0 {
+                for star in self.stars.clone() {
+                    self.quad(star.map(|corner| sky::rotate_with_sky(state.celestial_angle, corner)), star_color, Vec2::zero(), Vec2::one(), scale);
+                }
+            }
+        }
+        let stars_end = self.indices.len() as u32;
+
+        if state.has_sky {
+            self.quad(sky::sun_quad(state.celestial_angle), Vec4::one(), Vec2::zero(), Vec2::one(), scale);
+        }
+        let sun_end = self.indices.len() as u32;
+
+        if state.has_sky {
+            let (uv_min, uv_max) = sky::moon_phase_uvs(state.moon_phase);
+            self.quad(sky::moon_quad(state.celestial_angle), Vec4::one(), uv_min, uv_max, scale);
+        }
+        let moon_end = self.indices.len() as u32;
+
+        self.parts = SkyParts { backdrop: 0..backdrop_end, stars: backdrop_end..stars_end, sun: stars_end..sun_end, moon: sun_end..moon_end };
+    }
+
+    /// Write the built sky to its buffers, growing them when the sky doesn't fit
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        write_growing_buffer(device, queue, &mut self.vertex_buffer, bytemuck::cast_slice(&self.vertices), wgpu::BufferUsages::VERTEX, "Sky Vertex Buffer");
+        write_growing_buffer(device, queue, &mut self.index_buffer, bytemuck::cast_slice(&self.indices), wgpu::BufferUsages::INDEX, "Sky Index Buffer");
+    }
+
+    /// Draw the sky, nothing is drawn without the sky shaders of a resource pack
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, client: &'a Client) {
+        let (Some(pipeline), Some(additive_pipeline)) = (client.get_pipeline(SKY_PIPELINE), client.get_pipeline(SKY_ADDITIVE_PIPELINE)) else { return; };
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else { return; };
+        let (Some(camera), Some(sky), Some(white)) = (client.get_bind_group(CAMERA_BIND_GROUP_NAME), client.get_bind_group(SKY_BIND_GROUP_NAME), client.get_texture(WHITE_TEXTURE_NAME)) else { return; };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera, &[]);
+        render_pass.set_bind_group(1, sky, &[]);
+        render_pass.set_bind_group(2, white.bind_group(), &[]);
+        render_pass.draw_indexed(self.parts.backdrop.clone(), 0, 0..1);
+
+        render_pass.set_pipeline(additive_pipeline);
+        render_pass.draw_indexed(self.parts.stars.clone(), 0, 0..1);
+        if let Some(sun) = client.get_texture(SUN_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, sun.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.sun.clone(), 0, 0..1);
+        }
+        if let Some(moon) = client.get_texture(MOON_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, moon.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.moon.clone(), 0, 0..1);
+        }
+    }
+}
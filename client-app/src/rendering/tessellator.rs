@@ -1,30 +1,78 @@
 use std::ops::Add;
-use rustc_hash::FxHashMap as HashMap;
 
-use ultraviolet::{IVec3, Vec2, Vec3};
-use wgpu::{Device, util::DeviceExt};
+use ultraviolet::{IVec3, Vec2, Vec3, Vec4};
 
-use orange_rs::{block::{Block, BlockState}, direction::DIRECTIONS, world::chunk::{Chunk, CHUNK_SECTION_AXIS_SIZE, TLightData}, minecraft::registry::SpriteRegister};
-use orange_rs::models::model::BakedModel;
+use orange_rs::{direction::DIRECTIONS, world::chunk::{CHUNK_SECTION_AXIS_SIZE, TLightData}};
+use orange_rs::models::export::ExportMesh;
+use orange_rs::models::{greedy::{self, FaceTiling}, model::ModelQuad};
 use orange_rs::sprites::Sprite;
-use orange_rs::direction::{DirectionAll, DIRECTIONS_ALL};
-use orange_rs::minecraft::{registry::Register, identifier::Identifier};
+use orange_rs::direction::{Direction, DirectionAll, DIRECTIONS_ALL};
 
-use orange_rs::world::{chunk::TBlockData, ChunkStorage, ChunkStorageTrait};
+use orange_rs::world::{block_entities, chunk::TBlockData, fluid::{self, FluidNeighbour}, snapshot::SectionSnapshot, visibility::SectionVisibility};
+use orange_rs::block::BlockState;
+use orange_rs::minecraft::{colors::BlockColorProvider, identifier::Identifier, registry::{FrozenRegistry, StateHandle}};
+use orange_rs::util::pos::{NewChunkPosition, Position};
 
-use super::{mesh::Mesh, verticies::TerrainVertex};
+use super::verticies::{TerrainVertex, TiledTerrainVertex};
 
 enum TessellatorLayer {
     Opaque,
     Transparent,
 }
 
+/// The vertices and indices of a meshed section, built on any thread and uploaded on the render thread
+pub struct SectionBuffers {
+    pub opaque_vertices: Vec<TerrainVertex>,
+    pub opaque_indices: Vec<u32>,
+    pub transparent_vertices: Vec<TerrainVertex>,
+    pub transparent_indices: Vec<u32>,
+    /// Opaque faces merged by greedy meshing, drawn with the tiled terrain pipeline
+    pub tiled_vertices: Vec<TiledTerrainVertex>,
+    pub tiled_indices: Vec<u32>,
+    /// Which sides of the section see each other, for culling sections hidden behind terrain
+    pub visibility: SectionVisibility,
+}
+
+/// An opaque full face waiting to be merged with its neighbours, faces only merge when every vertex would look the same
+#[derive(Clone, Copy, PartialEq)]
+struct GreedyFace {
+    /// The corners of the face within its block
+    corners: [Vec3; 4],
+    normal: Vec3,
+    tiling: FaceTiling,
+    light: u32,
+    color: Vec3,
+}
+
+/// An opaque full face as it would be drawn unmerged, kept until the rest of its block is known
+#[derive(Clone, Copy)]
+struct DeferredQuad {
+    pos: [Vec3; 4],
+    lights: [u32; 4],
+    colors: [Vec3; 4],
+    normal: Vec3,
+    uvs: [Vec2; 4],
+    flip: bool,
+}
+
+const SECTION_SLICE_AREA: usize = CHUNK_SECTION_AXIS_SIZE * CHUNK_SECTION_AXIS_SIZE;
+/// A slice of faces for every layer of every direction
+const GREEDY_FACE_SLOTS: usize = 6 * CHUNK_SECTION_AXIS_SIZE * SECTION_SLICE_AREA;
+
 pub struct TerrainTessellator {
     opaque_vertex_buffer: Vec<TerrainVertex>,
     transparent_vertex_buffer: Vec<TerrainVertex>,
     opaque_index_buffer: Vec<u32>,
     transparent_index_buffer: Vec<u32>,
+    tiled_vertex_buffer: Vec<TiledTerrainVertex>,
+    tiled_index_buffer: Vec<u32>,
     layer: TessellatorLayer,
+    visibility: SectionVisibility,
+    greedy_meshing: bool,
+    /// The faces of the section being meshed that may merge, by direction, layer along the direction, then position in the layer
+    greedy_faces: Vec<Option<GreedyFace>>,
+    /// The slots the block being meshed has faces in, with the face as it would be drawn unmerged while it is still deferred
+    block_greedy_slots: Vec<(usize, Option<DeferredQuad>)>,
 }
 
 impl TerrainTessellator {
@@ -35,10 +83,30 @@ impl TerrainTessellator {
             transparent_vertex_buffer: vec![],
             opaque_index_buffer: vec![],
             transparent_index_buffer: vec![],
+            tiled_vertex_buffer: vec![],
+            tiled_index_buffer: vec![],
             layer: TessellatorLayer::Opaque,
+            visibility: SectionVisibility::all(),
+            greedy_meshing: false,
+            greedy_faces: vec![],
+            block_greedy_slots: vec![],
         }
     }
 
+    /**
+     * Merge neighbouring opaque full faces that share a texture, tint, light and ao into a single quad.
+     * Merged quads stretch their texture coordinates past the sprite, they are kept apart for the tiled terrain shader to wrap them
+     */
+    pub fn with_greedy_meshing(mut self, greedy_meshing: bool) -> Self {
+        self.greedy_meshing = greedy_meshing;
+        self.greedy_faces = if greedy_meshing { vec![None; GREEDY_FACE_SLOTS] } else { vec![] };
+        self
+    }
+
+    pub fn is_greedy_meshing(&self) -> bool {
+        self.greedy_meshing
+    }
+
     /// Builder function that tessellates the vertices of a cube into a buffer
     /// # Arguments
     /// * `Pos` The position of the cube
@@ -74,7 +142,7 @@ impl TerrainTessellator {
     /// # Arguments
     /// `pos_min` the minimum extent of a cube
     /// `pos_max` the maximum extent of the cube
-    /// `color` the color of the quad
+    /// `colors` the color of each vertex
     /// `direction` the cardinal direction of the quad, determines its vertex orientation and normals
     /// `uv_min` the minimum extent of the uv
     /// `uv_max` the maximum extent of the uv
@@ -83,7 +151,7 @@ impl TerrainTessellator {
         &mut self,
         pos: [Vec3; 4],
         lights: [u32; 4],
-        color: Vec3,
+        colors: [Vec3; 4],
         normal: Vec3,
         uv: [Vec2; 4],
         flip_vertex_order: bool,
@@ -91,13 +159,13 @@ impl TerrainTessellator {
 
         let prev_vert_len = self.opaque_vertex_buffer.len() as u32;
         // Top Left
-        self.vertex(TerrainVertex::new(pos[0], color, normal, uv[0], lights[0]));
+        self.vertex(TerrainVertex::new(pos[0], colors[0], normal, uv[0], lights[0]));
         // Top Right
-        self.vertex(TerrainVertex::new(pos[1], color, normal, uv[1]/*Vec2::new(uv_max.x, uv_min.y)*/, lights[1]));
+        self.vertex(TerrainVertex::new(pos[1], colors[1], normal, uv[1]/*Vec2::new(uv_max.x, uv_min.y)*/, lights[1]));
         // Bottom Left
-        self.vertex(TerrainVertex::new(pos[2], color, normal, uv[2]/*Vec2::new(uv_min.x, uv_max.y)*/, lights[2]));
+        self.vertex(TerrainVertex::new(pos[2], colors[2], normal, uv[2]/*Vec2::new(uv_min.x, uv_max.y)*/, lights[2]));
         // Bottom Right
-        self.vertex(TerrainVertex::new(pos[3], color, normal, uv[3], lights[3]));
+        self.vertex(TerrainVertex::new(pos[3], colors[3], normal, uv[3], lights[3]));
 
         if flip_vertex_order {
             self.opaque_index_buffer.push(prev_vert_len + 0);
@@ -122,7 +190,7 @@ impl TerrainTessellator {
         &mut self,
         pos: [Vec3; 4],
         lights: [u32; 4],
-        color: Vec3,
+        colors: [Vec3; 4],
         normal: Vec3,
         uv: [Vec2; 4],
         flip_vertex_order: bool,
@@ -130,11 +198,11 @@ impl TerrainTessellator {
 
         let prev_vert_len = self.transparent_vertex_buffer.len() as u32;
         // Top Left
-        self.vertex_transparent(TerrainVertex::new(pos[0], color, normal, uv[0], lights[0]));
+        self.vertex_transparent(TerrainVertex::new(pos[0], colors[0], normal, uv[0], lights[0]));
         // Top Right
         self.vertex_transparent(TerrainVertex::new(
             pos[1],
-            color,
+            colors[1],
             normal,
             uv[1],
             lights[1],
@@ -142,13 +210,13 @@ impl TerrainTessellator {
         // Bottom Left
         self.vertex_transparent(TerrainVertex::new(
             pos[2],
-            color,
+            colors[2],
             normal,
             uv[2],
             lights[2],
         ));
         // Bottom Right
-        self.vertex_transparent(TerrainVertex::new(pos[3], color, normal, uv[3], lights[3]));
+        self.vertex_transparent(TerrainVertex::new(pos[3], colors[3], normal, uv[3], lights[3]));
 
         if flip_vertex_order {
             self.transparent_index_buffer.push(prev_vert_len + 0);
@@ -169,6 +237,29 @@ impl TerrainTessellator {
         self
     }
 
+    /// Builder function that tessellates an opaque quad stretched over several blocks, every vertex shares the light and color
+    /// # Arguments
+    /// `pos` the corners of the quad
+    /// `tiles` where each corner is on the face, in blocks
+    /// `tiling` how the texture maps onto a single block of the face
+    pub fn tiled_quad(
+        &mut self,
+        pos: [Vec3; 4],
+        light: u32,
+        color: Vec3,
+        normal: Vec3,
+        tiles: [Vec2; 4],
+        tiling: &FaceTiling,
+    ) -> &mut Self {
+        let prev_vert_len = self.tiled_vertex_buffer.len() as u32;
+        let tile_axes = Vec4::new(tiling.u_axis.x, tiling.u_axis.y, tiling.v_axis.x, tiling.v_axis.y);
+        for (pos, tile) in pos.into_iter().zip(tiles) {
+            self.tiled_vertex_buffer.push(TiledTerrainVertex::new(pos, color, normal, tiling.uv(tile.x, tile.y), light, tile, tile_axes));
+        }
+        self.tiled_index_buffer.extend([0, 2, 3, 0, 3, 1].map(|index| prev_vert_len + index));
+        self
+    }
+
     /// Adds a vertex to a buffer, private because it doesn't update the index buffer
     fn vertex(&mut self, vert: TerrainVertex) -> &mut Self {
         self.opaque_vertex_buffer.push(vert);
@@ -180,41 +271,41 @@ impl TerrainTessellator {
         self
     }
 
-    /// Constructs a mesh from the buffers of vertices, then empties the buffers
-    pub fn build(&mut self, device: &Device) -> Mesh {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(self.opaque_vertex_buffer.as_slice()),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(self.opaque_index_buffer.as_slice()),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        let num_vertices = self.opaque_vertex_buffer.len() as u32;
-        let num_indices = self.opaque_index_buffer.len() as u32;
+    /// Takes the buffers of vertices to be uploaded as a mesh, leaving the tessellator empty
+    pub fn take_buffers(&mut self) -> SectionBuffers {
+        SectionBuffers {
+            opaque_vertices: std::mem::take(&mut self.opaque_vertex_buffer),
+            opaque_indices: std::mem::take(&mut self.opaque_index_buffer),
+            transparent_vertices: std::mem::take(&mut self.transparent_vertex_buffer),
+            transparent_indices: std::mem::take(&mut self.transparent_index_buffer),
+            tiled_vertices: std::mem::take(&mut self.tiled_vertex_buffer),
+            tiled_indices: std::mem::take(&mut self.tiled_index_buffer),
+            visibility: self.visibility,
+        }
+    }
 
-        let vertex_buffer_transparent = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(self.transparent_vertex_buffer.as_slice()),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-        let index_buffer_transparent = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(self.transparent_index_buffer.as_slice()),
-            usage: wgpu::BufferUsages::INDEX,
-        });
-        let num_vertices_transparent = self.transparent_vertex_buffer.len() as u32;
-        let num_indices_transparent = self.transparent_index_buffer.len() as u32;
-        let mesh = Mesh::new(vertex_buffer, vertex_buffer_transparent, num_vertices, num_vertices_transparent, index_buffer, index_buffer_transparent, num_indices, num_indices_transparent);
+    fn export_buffers(vertices: &[TerrainVertex], indices: &[u32]) -> ExportMesh {
+        let mut mesh = ExportMesh::new();
+        for vertex in vertices {
+            mesh.add_vertex(vertex.position(), vertex.normal(), vertex.texture(), vertex.color());
+        }
+        for triangle in indices.chunks_exact(3) {
+            mesh.add_triangle(triangle[0], triangle[1], triangle[2]);
+        }
+        mesh
+    }
+
+    /// Constructs meshes to export (opaque, transparent) from the buffers of vertices instead of a gpu mesh, then empties the buffers
+    pub fn build_export(&mut self) -> (ExportMesh, ExportMesh) {
+        let opaque = Self::export_buffers(&self.opaque_vertex_buffer, &self.opaque_index_buffer);
+        let transparent = Self::export_buffers(&self.transparent_vertex_buffer, &self.transparent_index_buffer);
 
         self.opaque_vertex_buffer.clear();
         self.transparent_vertex_buffer.clear();
         self.opaque_index_buffer.clear();
         self.transparent_index_buffer.clear();
 
-        mesh
+        (opaque, transparent)
     }
 
     // pub fn into_mesh(&mut self, queue: &Queue, mesh: &mut Mesh) {
@@ -240,11 +331,11 @@ impl TerrainTessellator {
     // }
 
     // TODO: Use states rather than blocks
-    fn get_occlusions(nearby_blocks: &[TBlockData; 26], states: &Register<BlockState>, source_block_transparent: bool, source_block_id: usize) -> u32 {
+    fn get_occlusions(nearby_blocks: &[TBlockData; 26], registry: &FrozenRegistry, source_block_transparent: bool, source_block_id: usize) -> u32 {
         let mut occlusions = 0u32;
         for dir in &DIRECTIONS {
             let state_id = nearby_blocks[dir.ordinal()] as usize;
-            if let Some(state) = states.get_element_from_index(state_id).as_ref() {
+            if let Some(state) = registry.get_state(state_id) {
                 let block = state.get_block();
                 let block_id = state.get_block_id();
                 let block_transparent = block.is_transparent();
@@ -262,64 +353,38 @@ impl TerrainTessellator {
         occlusions
     }
 
-    fn get_nearby_blocks(chunk: &Chunk, nearby_chunks: &ChunkStorage<Chunk>, intra_chunk_position: IVec3, chunk_position: IVec3) -> [TBlockData; 26] {
+    fn get_nearby_blocks(snapshot: &SectionSnapshot, intra_chunk_position: IVec3) -> [TBlockData; 26] {
         let mut nearby_blocks = [0; 26];
         for dir in &DIRECTIONS_ALL {
-            let dir_index = dir.ordinal();
             let new_pos = intra_chunk_position + dir.get_int_vector();
-
-            // 16 -> 1, -1 -> -1, [0, 15] -> 0
-            let cx = new_pos.x >> 4;
-            let cy = new_pos.y >> 4;
-            let cz = new_pos.z >> 4;
-
-            let state_index =  if cx | cy | cz != 0 {
-                if let Ok(chunk) = nearby_chunks.get_chunk(chunk_position + IVec3::new(cx, cy, cz)) {
-                    chunk.get_block_at_pos((new_pos.x as u32) & 15, (new_pos.y as u32) & 15, (new_pos.z as u32) & 15)
-                } else {
-                    continue;
-                }
-            } else {
-                chunk.get_block_at_pos(new_pos.x as u32, new_pos.y as u32, new_pos.z as u32)
-            };
-            nearby_blocks[dir_index] = state_index;
+            nearby_blocks[dir.ordinal()] = snapshot.get_block(new_pos.x, new_pos.y, new_pos.z);
         }
         nearby_blocks
     }
 
-    fn get_nearby_lights(chunk: &Chunk, nearby_chunks: &ChunkStorage<Chunk>, intra_chunk_position: IVec3, chunk_position: IVec3) -> [(TLightData, TLightData); 26] {
+    fn get_nearby_lights(snapshot: &SectionSnapshot, intra_chunk_position: IVec3) -> [(TLightData, TLightData); 26] {
         let mut nearby_blocks = [(0u8, 0u8); 26];
         for dir in &DIRECTIONS_ALL {
-            let dir_index = dir.ordinal();
             let new_pos = intra_chunk_position + dir.get_int_vector();
-
-            // 16 -> 1, -1 -> -1, [0, 15] -> 0
-            let cx = new_pos.x >> 4;
-            let cy = new_pos.y >> 4;
-            let cz = new_pos.z >> 4;
-
-            nearby_blocks[dir_index] =  if cx | cy | cz != 0 {
-                if let Ok(chunk) = nearby_chunks.get_chunk(chunk_position + IVec3::new(cx, cy, cz)) {
-                    chunk.get_light_at_pos((new_pos.x as u32) & 15, (new_pos.y as u32) & 15, (new_pos.z as u32) & 15)
-                } else {
-                    continue;
-                }
-            } else {
-                chunk.get_light_at_pos(new_pos.x as u32, new_pos.y as u32, new_pos.z as u32)
-            };
+            nearby_blocks[dir.ordinal()] = snapshot.get_light(new_pos.x, new_pos.y, new_pos.z);
         }
         nearby_blocks
     }
 
-    fn ao_inside(block: std::rc::Rc<Block>) -> u8 {
-        block.is_full_block() as u8
+    /// Cells of blocks that fill them and can't be seen through, sides can't see each other through them
+    fn is_opaque_cell(state: &BlockState) -> bool {
+        let block = state.get_block();
+        block.is_full_block() && !block.is_transparent()
     }
 
-    fn get_ao_for_corner(side1: TBlockData, side2: TBlockData, corner: TBlockData, states: &Register<BlockState> ) -> u8 {
-        let state_to_block_fn = |state: std::rc::Rc<BlockState>| { state.get_block() };
-        let a = states.get_element_from_index(side1 as usize).map(state_to_block_fn).map(Self::ao_inside).unwrap_or(1u8);
-        let b = states.get_element_from_index(side2 as usize).map(state_to_block_fn).map(Self::ao_inside).unwrap_or(1u8);
-        let c = states.get_element_from_index(corner as usize).map(state_to_block_fn).map(Self::ao_inside).unwrap_or(1u8);
+    fn ao_inside(state: &BlockState) -> u8 {
+        state.get_block().is_full_block() as u8
+    }
+
+    fn get_ao_for_corner(side1: TBlockData, side2: TBlockData, corner: TBlockData, registry: &FrozenRegistry) -> u8 {
+        let a = registry.get_state(side1 as usize).map(Self::ao_inside).unwrap_or(1u8);
+        let b = registry.get_state(side2 as usize).map(Self::ao_inside).unwrap_or(1u8);
+        let c = registry.get_state(corner as usize).map(Self::ao_inside).unwrap_or(1u8);
 
         if a + b == 2 {
             return 0u8;
@@ -393,38 +458,38 @@ impl TerrainTessellator {
         ]
     }
 
-    fn get_nearby_ao_data(nearby_blocks: &[TBlockData; 26], states: &Register<BlockState>) -> [u8; 24] {
+    fn get_nearby_ao_data(nearby_blocks: &[TBlockData; 26], registry: &FrozenRegistry) -> [u8; 24] {
         let mut ao = [3; 24];
         // north
-        ao[0] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], states);
-        ao[1] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], states);
-        ao[2] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], states);
-        ao[3] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], states);
+        ao[0] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], registry);
+        ao[1] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], registry);
+        ao[2] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], registry);
+        ao[3] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], registry);
         // south
-        ao[4] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], states);
-        ao[5] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], states);
-        ao[6] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], states);
-        ao[7] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], states);
+        ao[4] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], registry);
+        ao[5] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], registry);
+        ao[6] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], registry);
+        ao[7] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], registry);
         // east
-        ao[8] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], states);
-        ao[9] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], states);
-        ao[10] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], states);
-        ao[11] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], states);
+        ao[8] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], registry);
+        ao[9] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], registry);
+        ao[10] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::SE.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], registry);
+        ao[11] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::NE.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], registry);
         // west
-        ao[12] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], states);
-        ao[13] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], states);
-        ao[14] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], states);
-        ao[15] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], states);
+        ao[12] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], registry);
+        ao[13] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], registry);
+        ao[14] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::NW.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], registry);
+        ao[15] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::SW.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], registry);
         // up
-        ao[16] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], states);
-        ao[17] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], states);
-        ao[18] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], states);
-        ao[19] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], states);
+        ao[16] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::NEU.ordinal()], registry);
+        ao[17] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::EU.ordinal()], nearby_blocks[DirectionAll::SEU.ordinal()], registry);
+        ao[18] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::NU.ordinal()], nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::NWU.ordinal()], registry);
+        ao[19] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SU.ordinal()], nearby_blocks[DirectionAll::WU.ordinal()], nearby_blocks[DirectionAll::SWU.ordinal()], registry);
         // down
-        ao[20] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], states);
-        ao[21] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], states);
-        ao[22] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], states);
-        ao[23] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], states);
+        ao[20] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::SED.ordinal()], registry);
+        ao[21] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::ED.ordinal()], nearby_blocks[DirectionAll::NED.ordinal()], registry);
+        ao[22] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::SD.ordinal()], nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::SWD.ordinal()], registry);
+        ao[23] = Self::get_ao_for_corner(nearby_blocks[DirectionAll::ND.ordinal()], nearby_blocks[DirectionAll::WD.ordinal()], nearby_blocks[DirectionAll::NWD.ordinal()], registry);
 
         ao
     }
@@ -498,43 +563,64 @@ impl TerrainTessellator {
         ]
     }
 
-    pub fn tessellate_chunk_section(&mut self, section: &Chunk, chunk_real_position: Vec3, chunk_pos: IVec3, blocks: &Register<Block>, states: &Register<BlockState>, models: &HashMap<Identifier, BakedModel>, sprites: &SpriteRegister, nearby_chunks: &ChunkStorage<Chunk>) {
+    /// Mesh a snapshot of a section into the buffers, can be done on any thread
+    pub fn tessellate_chunk_section(&mut self, snapshot: &SectionSnapshot, registry: &FrozenRegistry) {
         let smooth_shading = true;
-        for y in 0..CHUNK_SECTION_AXIS_SIZE as u32 {
-            for x in 0..CHUNK_SECTION_AXIS_SIZE as u32 {
-                for z in 0..CHUNK_SECTION_AXIS_SIZE as u32 {
+        let chunk_real_position = NewChunkPosition { vec: snapshot.get_position() }.to_entity_pos();
+        self.visibility = SectionVisibility::compute(|x, y, z| {
+            registry.get_state(snapshot.get_block(x, y, z) as usize).is_some_and(Self::is_opaque_cell)
+        });
+        for y in 0..CHUNK_SECTION_AXIS_SIZE as i32 {
+            for x in 0..CHUNK_SECTION_AXIS_SIZE as i32 {
+                for z in 0..CHUNK_SECTION_AXIS_SIZE as i32 {
                     let real_relative_position = Vec3::new(x as f32, y as f32, z as f32);
                     let real_world_position = chunk_real_position + real_relative_position;
-                    let state_id = section.get_block_at_pos(x, y, z);
+                    let state_id = snapshot.get_block(x, y, z);
                     // let metadata = (block_id >> 8) & 0b00001111;
                     // let block_id = (state_id & 0b0000000011111111) as usize;
-                    let (sky_light, block_light) = section.get_light_at_pos(x, y, z);
+                    let (sky_light, block_light) = snapshot.get_light(x, y, z);
                     // Air, stop
                     if state_id == 0 { continue; }
+                    self.block_greedy_slots.clear();
 
-                    let state = match states.get_element_from_index(state_id.into()) {
+                    let state = match registry.get_blockstate_register().get_handle_from_index(state_id.into()) {
                         Some(state) => state,
                         _ => continue,
                     };
 
-                    let block_id = state.get_block_id();
-                    let block = state.get_block();
-                    let is_transparent = block.is_transparent();
+                    let block_state = registry.get_blockstate_register().get(state);
+                    // Chests and signs are drawn by their block entities
+                    if block_entities::draws_block(block_state.get_block_identifier()) { continue; }
+                    let block_id = block_state.get_block_id();
+                    let is_transparent = block_state.get_block().is_transparent();
 
-                    let model = match models.get(state.get_state_identifier()) {
-                        Some(model) => model,
-                        _ => continue,
-                    };
+                    let color_provider = registry.get_state_color(state);
 
-                    let intra_chunk_position = IVec3::new(x as i32, y as i32, z as i32);
-                    let nearby_blocks = Self::get_nearby_blocks(section, &nearby_chunks, intra_chunk_position, chunk_pos);
-                    let nearby_lights = Self::get_nearby_lights(section, &nearby_chunks, intra_chunk_position, chunk_pos);
-                    let occlusions = Self::get_occlusions(&nearby_blocks, states, is_transparent, block_id);
+                    let intra_chunk_position = IVec3::new(x, y, z);
+                    let nearby_blocks = Self::get_nearby_blocks(snapshot, intra_chunk_position);
+                    let nearby_lights = Self::get_nearby_lights(snapshot, intra_chunk_position);
+                    let occlusions = Self::get_occlusions(&nearby_blocks, registry, is_transparent, block_id);
 
                     let lights = Self::get_nearby_lighting_data(&nearby_lights, block_light, sky_light);
-                    let ao = if model.ambient_occlusion() { Self::get_nearby_ao_data(&nearby_blocks, states) } else { [3; 24] };
 
-                    for quad in model.shapes() {
+                    // Fluids have no model, their shape comes from their neighbours
+                    if registry.get_state_fluid(state).is_some() {
+                        self.tessellate_fluid(snapshot, registry, intra_chunk_position, state, &lights, occlusions);
+                        continue;
+                    }
+
+                    let model = match registry.get_state_model(state) {
+                        Some(model) => model,
+                        _ => continue,
+                    };
+                    let block_pos = real_world_position.to_block_pos();
+                    let nearby_ao = if model.select(&block_pos).any(|model| model.ambient_occlusion()) { Self::get_nearby_ao_data(&nearby_blocks, registry) } else { [3; 24] };
+                    let quads = model.select(&block_pos).flat_map(|model| {
+                        let ao = if model.ambient_occlusion() { nearby_ao } else { [3; 24] };
+                        model.shapes().iter().map(move |quad| (quad, ao))
+                    });
+
+                    for (quad, ao) in quads {
 
                         if let Some(dir) = quad.cullface {
                             if (occlusions & dir.ordinal_bitwise()) > 0 {
@@ -565,15 +651,278 @@ impl TerrainTessellator {
                         let flip = light_flip;
                         // let flip = ao_flip;
 
+                        let vertex_colors = Self::vertex_colors(snapshot, registry, color_provider, quad.tint_index, intra_chunk_position, &quad.pos, quad.color);
+
+                        let deferred = DeferredQuad { pos: positions, lights, colors: vertex_colors, normal: quad.normal, uvs, flip };
+                        if !is_transparent && self.defer_greedy_face(quad, intra_chunk_position, deferred) {
+                            continue;
+                        }
+
                         if is_transparent {
-                            self.quad_transparent(positions, lights, quad.color, quad.normal, uvs, flip);
+                            self.quad_transparent(positions, lights, vertex_colors, quad.normal, uvs, flip);
                         } else {
-                            self.quad(positions, lights, quad.color, quad.normal, uvs, flip);
+                            self.quad(positions, lights, vertex_colors, quad.normal, uvs, flip);
                         }
                     }
 
                 } // z
             } // x
         } // y
+
+        if self.greedy_meshing {
+            self.emit_greedy_faces(chunk_real_position);
+        }
+    }
+
+    /// Every vertex takes the climate of the column it is in, so tints blend between columns
+    fn vertex_colors(snapshot: &SectionSnapshot, registry: &FrozenRegistry, color_provider: Option<&BlockColorProvider>, tint_index: i32, block: IVec3, corners: &[Vec3; 4], color: Vec3) -> [Vec3; 4] {
+        match color_provider {
+            Some(provider) if provider.applies_to(tint_index) => corners.map(|corner| {
+                let climate = snapshot.get_climate(block.x + corner.x.floor() as i32, block.z + corner.z.floor() as i32);
+                color * registry.get_block_colors().color(provider, climate)
+            }),
+            _ => [color; 4],
+        }
+    }
+
+    /// The uv of a point of a sprite given as fractions of the sprite, missing sprites show the missing texture
+    fn sprite_uvs(registry: &FrozenRegistry, sprite: &Identifier, local_uvs: [Vec2; 4]) -> [Vec2; 4] {
+        let sprites = registry.get_sprite_register();
+        match sprites.get(sprite).or_else(|| sprites.get(&Identifier::from_str("minecraft:block/missing"))) {
+            Some(sprite) => local_uvs.map(|uv| sprite.uv_min + uv * (sprite.uv_max - sprite.uv_min)),
+            None => local_uvs,
+        }
+    }
+
+    /**
+     * Tessellate a fluid cell, its top corners are as high as the fluid around them and the top of flowing fluid turns with the flow.
+     * Faces against the same fluid are never drawn, so a body of water is a single surface
+     */
+    fn tessellate_fluid(
+        &mut self,
+        snapshot: &SectionSnapshot,
+        registry: &FrozenRegistry,
+        block: IVec3,
+        state: StateHandle,
+        lights: &[(u8, u8); 8],
+        occlusions: u32,
+    ) {
+        let Some(&fluid_state) = registry.get_state_fluid(state) else { return; };
+        let color_provider = registry.get_state_color(state);
+        let origin = NewChunkPosition { vec: snapshot.get_position() }.to_entity_pos() + Vec3::new(block.x as f32, block.y as f32, block.z as f32);
+        let neighbour = |x: i32, y: i32, z: i32| {
+            let state_id = snapshot.get_block(block.x + x, block.y + y, block.z + z);
+            let Some(handle) = registry.get_blockstate_register().get_handle_from_index(state_id.into()) else { return FluidNeighbour::Open; };
+            match registry.get_state_fluid(handle) {
+                Some(other) if other.fluid == fluid_state.fluid => FluidNeighbour::Same(*other),
+                Some(_) => FluidNeighbour::Open,
+                None if registry.get_blockstate_register().get(handle).get_block().is_full_block() => FluidNeighbour::Solid,
+                None => FluidNeighbour::Open,
+            }
+        };
+        let is_same = |offset: IVec3| matches!(neighbour(offset.x, offset.y, offset.z), FluidNeighbour::Same(_));
+
+        let heights = fluid::corner_heights(neighbour);
+        let flow = fluid::flow_direction(fluid_state, neighbour);
+        let flow_sprite = fluid_state.fluid.flow_sprite();
+
+        let mut faces: Vec<(Direction, [Vec3; 4], [Vec2; 4])> = vec![];
+        if !is_same(Direction::Up.get_int_vector()) {
+            let top_uvs = fluid::top_uvs(flow);
+            let sprite = if flow.mag_sq() > 0.0 { flow_sprite.clone() } else { fluid_state.fluid.still_sprite() };
+            let corners = [Vec3::new(0.0, heights[0], 0.0), Vec3::new(1.0, heights[2], 0.0), Vec3::new(0.0, heights[1], 1.0), Vec3::new(1.0, heights[3], 1.0)];
+            faces.push((Direction::Up, corners, Self::sprite_uvs(registry, &sprite, [top_uvs[0], top_uvs[2], top_uvs[1], top_uvs[3]])));
+        }
+        if !is_same(Direction::Down.get_int_vector()) && occlusions & Direction::Down.ordinal_bitwise() == 0 {
+            let corners = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0)];
+            let uvs = [Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)];
+            faces.push((Direction::Down, corners, Self::sprite_uvs(registry, &fluid_state.fluid.still_sprite(), uvs)));
+        }
+        // The top corners of every side, left then right when looking at it
+        let sides = [
+            (Direction::North, Vec3::new(0.0, heights[0], 0.0), Vec3::new(0.0, heights[1], 1.0)),
+            (Direction::South, Vec3::new(1.0, heights[3], 1.0), Vec3::new(1.0, heights[2], 0.0)),
+            (Direction::East, Vec3::new(1.0, heights[2], 0.0), Vec3::new(0.0, heights[0], 0.0)),
+            (Direction::West, Vec3::new(0.0, heights[1], 1.0), Vec3::new(1.0, heights[3], 1.0)),
+        ];
+        for (direction, left, right) in sides {
+            if is_same(direction.get_int_vector()) || occlusions & direction.ordinal_bitwise() > 0 { continue; }
+            let corners = [left, right, Vec3::new(left.x, 0.0, left.z), Vec3::new(right.x, 0.0, right.z)];
+            // Sides show the left half of the flow sprite, cut off at the height of the fluid
+            let uvs = [Vec2::new(0.0, (1.0 - left.y) * 0.5), Vec2::new(0.5, (1.0 - right.y) * 0.5), Vec2::new(0.0, 0.5), Vec2::new(0.5, 0.5)];
+            faces.push((direction, corners, Self::sprite_uvs(registry, &flow_sprite, uvs)));
+        }
+
+        for (direction, corners, uvs) in faces {
+            let positions = corners.map(|corner| corner + origin);
+            let lights = Self::sample_light_for_pos_multiple(&corners, lights, &[3, 3, 3, 3]);
+            let colors = Self::vertex_colors(snapshot, registry, color_provider, 0, block, &corners, Vec3::one());
+            self.quad_transparent(positions, lights, colors, direction.get_float_vector(), uvs, false);
+        }
+    }
+
+    fn greedy_slot(direction: Direction, block: IVec3) -> usize {
+        let (normal_axis, u_axis, v_axis) = greedy::face_axes(direction);
+        let layer = direction.ordinal() * CHUNK_SECTION_AXIS_SIZE + block[normal_axis] as usize;
+        layer * SECTION_SLICE_AREA + block[v_axis] as usize * CHUNK_SECTION_AXIS_SIZE + block[u_axis] as usize
+    }
+
+    /**
+     * Keep an opaque full face of a single light and color to merge once the section is done, false when it has to be emitted as is.
+     * A second face on the same side of a block, like the overlay on the sides of grass, has to be drawn over the first one,
+     * so the first one is emitted unmerged right before it and neither of them merges
+     */
+    fn defer_greedy_face(&mut self, quad: &ModelQuad, block: IVec3, deferred: DeferredQuad) -> bool {
+        if !self.greedy_meshing { return false; }
+        let Some(side) = quad.cullface else { return false; };
+        let slot = Self::greedy_slot(side, block);
+        if let Some(index) = self.block_greedy_slots.iter().position(|(taken, _)| *taken == slot) {
+            if let Some(below) = self.block_greedy_slots[index].1.take() {
+                self.greedy_faces[slot] = None;
+                self.quad(below.pos, below.lights, below.colors, below.normal, below.uvs, below.flip);
+            }
+            return false;
+        }
+        let face = greedy::full_face(quad)
+            .filter(|_| deferred.lights.iter().all(|light| *light == deferred.lights[0]) && deferred.colors.iter().all(|color| *color == deferred.colors[0]))
+            .and_then(|direction| FaceTiling::from_quad(quad, direction));
+        let Some(tiling) = face else {
+            self.block_greedy_slots.push((slot, None));
+            return false;
+        };
+        self.greedy_faces[slot] = Some(GreedyFace { corners: quad.pos, normal: quad.normal, tiling, light: deferred.lights[0], color: deferred.colors[0] });
+        self.block_greedy_slots.push((slot, Some(deferred)));
+        true
+    }
+
+    /// Merge the deferred faces of every slice of the section into quads
+    fn emit_greedy_faces(&mut self, section_origin: Vec3) {
+        let mut faces = std::mem::take(&mut self.greedy_faces);
+        for direction in DIRECTIONS {
+            let (normal_axis, u_axis, v_axis) = greedy::face_axes(direction);
+            for layer in 0..CHUNK_SECTION_AXIS_SIZE {
+                let start = (direction.ordinal() * CHUNK_SECTION_AXIS_SIZE + layer) * SECTION_SLICE_AREA;
+                for rect in greedy::merge_slice(&mut faces[start..start + SECTION_SLICE_AREA], CHUNK_SECTION_AXIS_SIZE) {
+                    let mut origin = section_origin;
+                    origin[normal_axis] += layer as f32;
+                    origin[u_axis] += rect.u as f32;
+                    origin[v_axis] += rect.v as f32;
+                    let face = rect.face;
+                    let pos = face.corners.map(|corner| origin + greedy::stretch_corner(corner, direction, rect.width, rect.height));
+                    let tiles = face.corners.map(|corner| Vec2::new(corner[u_axis] * rect.width as f32, corner[v_axis] * rect.height as f32));
+                    self.tiled_quad(pos, face.light, face.color, face.normal, tiles, &face.tiling);
+                }
+            }
+        }
+        self.greedy_faces = faces;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orange_rs::block::{block_factory::BlockFactory, Block};
+    use orange_rs::minecraft::registry::Registry;
+    use orange_rs::models::model::{BlockstateModel, VoxelElement, VoxelFace, VoxelModel, WeightedBakedModel};
+    use orange_rs::world::{ChunkStorage, ChunkStoragePlanar, ChunkStorageTrait, biome::{Climate, ClimateSource}, chunk::Chunk};
+
+    const GRASS: TBlockData = 1;
+    const HORIZONTAL: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+    struct FlatClimate;
+
+    impl ClimateSource for FlatClimate {
+        fn climate_at(&self, _x: i32, _z: i32) -> Climate {
+            Climate::new(0.5, 0.5)
+        }
+    }
+
+    /// Air and a grass block, a full cube with a tinted overlay over each of its sides
+    fn grass_registry() -> FrozenRegistry {
+        let mut registry = Registry::new();
+        for block in [BlockFactory::new("air").transparent(true).full_block(false).build(), BlockFactory::new("grass").build()] {
+            let block_id = registry.get_block_register_mut().insert(block);
+            let block = registry.get_block_register().get_element_from_index(block_id).unwrap();
+            for state in Block::map_states(block, &mut registry) {
+                registry.get_blockstate_register_mut().insert_pointer(state);
+            }
+        }
+        for (index, sprite) in ["block/grass_top", "block/dirt", "block/grass_side", "block/grass_side_overlay"].iter().enumerate() {
+            let uv_min = Vec2::new(index as f32 * 0.25, 0.0);
+            registry.get_sprite_register_mut().insert(Identifier::from_str(sprite), Sprite { parent_texture: Identifier::from_str("block_atlas"), uv_min, uv_max: uv_min + Vec2::new(0.25, 0.25) });
+        }
+
+        let mut base = VoxelElement::new(Vec3::zero(), Vec3::broadcast(16.0))
+            .with_face(VoxelFace::new("#top").with_cullface(Direction::Up), Direction::Up)
+            .with_face(VoxelFace::new("#bottom").with_cullface(Direction::Down), Direction::Down);
+        let mut overlay = VoxelElement::new(Vec3::zero(), Vec3::broadcast(16.0));
+        for side in HORIZONTAL {
+            base = base.with_face(VoxelFace::new("#side").with_cullface(side), side);
+            overlay = overlay.with_face(VoxelFace::new("#overlay").with_cullface(side).with_tint(0), side);
+        }
+        let model = VoxelModel::new()
+            .with_texture("top", "block/grass_top")
+            .with_texture("bottom", "block/dirt")
+            .with_texture("side", "block/grass_side")
+            .with_texture("overlay", "block/grass_side_overlay")
+            .with_element(base)
+            .with_element(overlay)
+            .bake(registry.get_sprite_register());
+        let state = registry.get_blockstate_register().get_element_from_index(GRASS as usize).unwrap();
+        registry.get_model_register_mut().insert(state.get_state_identifier().clone(), BlockstateModel::new(vec![WeightedBakedModel::new(vec![(model, 1)])]));
+        registry.freeze()
+    }
+
+    fn snapshot(blocks: impl Fn(u32, u32, u32) -> bool) -> SectionSnapshot {
+        let mut storage = ChunkStorage::Planar(ChunkStoragePlanar::new(1));
+        let mut section = Chunk::create_empty();
+        for (x, y, z) in (0..16).flat_map(|x| (0..16).flat_map(move |y| (0..16).map(move |z| (x, y, z)))) {
+            if blocks(x, y, z) {
+                section.set_block_at_pos(x, y, z, GRASS);
+            }
+        }
+        storage.set_chunk(section, IVec3::zero()).unwrap();
+        SectionSnapshot::capture(&storage, IVec3::zero(), &FlatClimate).unwrap()
+    }
+
+    fn tessellate(snapshot: &SectionSnapshot, registry: &FrozenRegistry, greedy_meshing: bool) -> SectionBuffers {
+        let mut tessellator = TerrainTessellator::new().with_greedy_meshing(greedy_meshing);
+        tessellator.tessellate_chunk_section(snapshot, registry);
+        tessellator.take_buffers()
+    }
+
+    #[test]
+    fn overlays_stay_over_their_base_face() {
+        let registry = grass_registry();
+        let buffers = tessellate(&snapshot(|x, y, z| (x, y, z) == (0, 0, 0)), &registry, true);
+        let side_sprite = registry.get_sprite_register().get(&Identifier::from_str("block/grass_side")).unwrap();
+        let overlay_sprite = registry.get_sprite_register().get(&Identifier::from_str("block/grass_side_overlay")).unwrap();
+        let on_sprite = |uv: Vec2, sprite: &Sprite| uv.x >= sprite.uv_min.x && uv.x <= sprite.uv_max.x;
+
+        // Each side is a base quad then its overlay in the opaque buffer, the top and bottom are merged on their own
+        let quads: Vec<&[TerrainVertex]> = buffers.opaque_vertices.chunks(4).collect();
+        assert_eq!(quads.len(), 8);
+        for pair in quads.chunks(2) {
+            assert!(pair[0].iter().all(|vertex| on_sprite(vertex.texture(), side_sprite)));
+            assert!(pair[1].iter().all(|vertex| on_sprite(vertex.texture(), overlay_sprite)));
+            assert_eq!(pair[0].iter().map(TerrainVertex::position).collect::<Vec<_>>(), pair[1].iter().map(TerrainVertex::position).collect::<Vec<_>>());
+        }
+        assert_eq!(buffers.tiled_vertices.len(), 2 * 4);
+    }
+
+    #[test]
+    fn flat_terrain_needs_fewer_vertices() {
+        // A layer of grass with a 3 by 3 patch dug out of it
+        let flat = snapshot(|x, y, z| y == 0 && !((5..8).contains(&x) && (9..12).contains(&z)));
+        let registry = grass_registry();
+        let faces = tessellate(&flat, &registry, false);
+        let merged = tessellate(&flat, &registry, true);
+
+        // 247 tops and bottoms, and 76 sides of a base and an overlay each
+        assert_eq!(faces.opaque_vertices.len(), (247 * 2 + 76 * 2) * 4);
+        assert!(faces.tiled_vertices.is_empty());
+        // Sides stay as they are, tops and bottoms each merge into 4 rectangles around the patch
+        assert_eq!(merged.opaque_vertices.len(), 76 * 2 * 4);
+        assert_eq!(merged.tiled_vertices.len(), 4 * 2 * 4);
     }
 }
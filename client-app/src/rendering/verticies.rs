@@ -1,4 +1,4 @@
-use ultraviolet::{Vec2, Vec3};
+use ultraviolet::{Vec2, Vec3, Vec4};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -27,6 +27,11 @@ impl TerrainVertex {
         }
     }
 
+    pub fn position(&self) -> Vec3 { self.position }
+    pub fn color(&self) -> Vec3 { self.color }
+    pub fn normal(&self) -> Vec3 { self.normal }
+    pub fn texture(&self) -> Vec2 { self.texture }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
         0 => Float32x3,
@@ -42,3 +47,60 @@ impl TerrainVertex {
         }
     }
 }
+
+/**
+ * A vertex of a face stretched over several blocks by greedy meshing, drawn with its own pipeline.
+ * Its texture coordinate keeps going past the sprite and the tiled terrain shader wraps it back
+ */
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TiledTerrainVertex {
+    position: Vec3,
+    color: Vec3,
+    normal: Vec3,
+    texture: Vec2,
+    ao: u32,
+    /// Where the vertex is on the face, in blocks
+    tile: Vec2,
+    /// The uv steps of one block along the face (u step, v step), the texture repeats at uv = texture - floor(tile.x) * u step - floor(tile.y) * v step
+    tile_axes: Vec4,
+}
+
+impl TiledTerrainVertex {
+    pub fn new(
+        position: Vec3,
+        color: Vec3,
+        normal: Vec3,
+        texture: Vec2,
+        ao: u32,
+        tile: Vec2,
+        tile_axes: Vec4,
+    ) -> Self {
+        Self {
+            position,
+            color,
+            normal,
+            texture,
+            ao,
+            tile,
+            tile_axes,
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBS: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x3,
+        3 => Float32x2,
+        4 => Uint32,
+        5 => Float32x2,
+        6 => Float32x4,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBS,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+use orange_rs::world::weather::{self, LightningBolt, Precipitation, WeatherColumn};
+use ultraviolet::{IVec3, Vec2, Vec3, Vec4};
+use wgpu::{Buffer, Device, Queue, RenderPass};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler::{CAMERA_BIND_GROUP_NAME, RAIN_TEXTURE_NAME, SKY_BIND_GROUP_NAME, SNOW_TEXTURE_NAME, WEATHER_ADDITIVE_PIPELINE, WEATHER_PIPELINE, WHITE_TEXTURE_NAME};
+use crate::rendering::sky::{self, SkyVertex};
+
+const LIGHTNING_COLOR: Vec4 = Vec4::new(0.45, 0.45, 0.5, 0.3);
+
+/// The indices of every part of the weather, parts are drawn in this order
+#[derive(Clone, Debug, Default)]
+struct WeatherParts {
+    rain: Range<u32>,
+    snow: Range<u32>,
+    lightning: Range<u32>,
+}
+
+/**
+ * The geometry of rain, snow and lightning around the camera, built again every frame in the same vertices as the sky.
+ * Sheets of rain and snow are blended over the terrain, lightning is added on top of it
+ */
+pub struct WeatherRenderer {
+    vertices: Vec<SkyVertex>,
+    indices: Vec<u32>,
+    parts: WeatherParts,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+}
+
+impl WeatherRenderer {
+    pub fn new() -> Self {
+        Self {
+            vertices: vec![],
+            indices: vec![],
+            parts: WeatherParts::default(),
+            vertex_buffer: None,
+            index_buffer: None,
+        }
+    }
+
+    /// A quad with corners ordered top left, top right, bottom left, bottom right, moved to be relative to the camera
+    fn quad(&mut self, corners: [Vec3; 4], uvs: [Vec2; 4], color: Vec4, camera: Vec3) {
+        let first = self.vertices.len() as u32;
+        self.vertices.extend(corners.iter().zip(uvs).map(|(corner, uv)| SkyVertex::new(*corner - camera, color, uv)));
+        self.indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+    }
+
+    fn sheets(&mut self, columns: &[WeatherColumn], precipitation: Precipitation, camera: Vec3, strength: f32, ticks: u64, partial_tick: f32) {
+        for column in columns.iter().filter(|column| column.precipitation == precipitation) {
+            let (corners, uvs, alpha) = weather::sheet_quad(column, camera, ticks, partial_tick);
+            self.quad(corners, uvs, Vec4::new(1.0, 1.0, 1.0, alpha * strength), camera);
+        }
+    }
+
+    /**
+     * Build the weather around the camera. `height_of` is the precipitation height of a column and `precipitation_of`
+     * what falls on it, nothing but lightning is built while it doesn't rain
+     */
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(&mut self, camera: Vec3, strength: f32, ticks: u64, partial_tick: f32, bolts: &[LightningBolt], height_of: impl Fn(i32, i32) -> i32, precipitation_of: impl Fn(i32, i32) -> Precipitation) {
+        self.vertices.clear();
+        self.indices.clear();
+        let camera_block = IVec3::new(camera.x.floor() as i32, camera.y.floor() as i32, camera.z.floor() as i32);
+
+        let columns = if strength > 0.0 { weather::weather_columns(camera_block, &height_of, &precipitation_of) } else { vec![] };
+        self.sheets(&columns, Precipitation::Rain, camera, strength, ticks, partial_tick);
+        let rain_end = self.indices.len() as u32;
+        self.sheets(&columns, Precipitation::Snow, camera, strength, ticks, partial_tick);
+        let snow_end = self.indices.len() as u32;
+
+        for bolt in bolts.iter().filter(|bolt| bolt.is_visible()) {
+            for quad in weather::lightning_quads(bolt.vertex_seed) {
+                self.quad(quad.map(|corner| corner + bolt.position), [Vec2::zero(); 4], LIGHTNING_COLOR, camera);
+            }
+        }
+        let lightning_end = self.indices.len() as u32;
+
+        self.parts = WeatherParts { rain: 0..rain_end, snow: rain_end..snow_end, lightning: snow_end..lightning_end };
+    }
+
+    /// Write the built weather to its buffers, growing them when it doesn't fit
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.indices.is_empty() { return; }
+        sky::write_growing_buffer(device, queue, &mut self.vertex_buffer, bytemuck::cast_slice(&self.vertices), wgpu::BufferUsages::VERTEX, "Weather Vertex Buffer");
+        sky::write_growing_buffer(device, queue, &mut self.index_buffer, bytemuck::cast_slice(&self.indices), wgpu::BufferUsages::INDEX, "Weather Index Buffer");
+    }
+
+    /// Draw the weather after the terrain, nothing is drawn without the weather shaders of a resource pack
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, client: &'a Client) {
+        if self.indices.is_empty() { return; }
+        let (Some(pipeline), Some(additive_pipeline)) = (client.get_pipeline(WEATHER_PIPELINE), client.get_pipeline(WEATHER_ADDITIVE_PIPELINE)) else { return; };
+        let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) else { return; };
+        let (Some(camera), Some(sky), Some(white)) = (client.get_bind_group(CAMERA_BIND_GROUP_NAME), client.get_bind_group(SKY_BIND_GROUP_NAME), client.get_texture(WHITE_TEXTURE_NAME)) else { return; };
+
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera, &[]);
+        render_pass.set_bind_group(1, sky, &[]);
+        if let Some(rain) = client.get_texture(RAIN_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, rain.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.rain.clone(), 0, 0..1);
+        }
+        if let Some(snow) = client.get_texture(SNOW_TEXTURE_NAME) {
+            render_pass.set_bind_group(2, snow.bind_group(), &[]);
+            render_pass.draw_indexed(self.parts.snow.clone(), 0, 0..1);
+        }
+
+        render_pass.set_pipeline(additive_pipeline);
+        render_pass.set_bind_group(2, white.bind_group(), &[]);
+        render_pass.draw_indexed(self.parts.lightning.clone(), 0, 0..1);
+    }
+}
@@ -0,0 +1,51 @@
+use std::{path::PathBuf, sync::{Arc, atomic::{AtomicBool, Ordering}}};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher, EventKind};
+
+/// How long the packs have to stay untouched before a reload, editors tend to write files in bursts
+const RELOAD_DEBOUNCE: instant::Duration = instant::Duration::from_millis(500);
+
+/**
+ * Watches resource pack folders and archives for changes
+ * The watcher thread only raises a flag, the reload itself happens when the client polls
+ */
+pub struct ResourceWatcher {
+    _watcher: RecommendedWatcher,
+    changed: Arc<AtomicBool>,
+    last_change: Option<instant::Instant>,
+}
+
+impl ResourceWatcher {
+    pub fn new(sources: &[PathBuf]) -> notify::Result<Self> {
+        let changed = Arc::new(AtomicBool::new(false));
+        let changed_copy = changed.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) => {
+                    changed_copy.store(true, Ordering::Release);
+                },
+                Ok(_) => {},
+                Err(e) => { log::warn!("Resource watcher error: {e}"); },
+            }
+        })?;
+        for source in sources.iter().filter(|source| source.exists()) {
+            watcher.watch(source, RecursiveMode::Recursive)?;
+        }
+        Ok(Self { _watcher: watcher, changed, last_change: None })
+    }
+
+    /// Returns true once the watched packs have changed and settled
+    pub fn poll(&mut self) -> bool {
+        let now = instant::Instant::now();
+        if self.changed.swap(false, Ordering::AcqRel) {
+            self.last_change = Some(now);
+        }
+        match self.last_change {
+            Some(last_change) if now - last_change >= RELOAD_DEBOUNCE => {
+                self.last_change = None;
+                true
+            },
+            _ => false,
+        }
+    }
+}
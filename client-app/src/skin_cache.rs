@@ -0,0 +1,78 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+
+use image::RgbaImage;
+use orange_rs::minecraft::skins::{SkinError, SkinSource};
+use orange_rs::util::workers::WorkerThread;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+use crate::game_client::Client;
+use crate::mc_resource_handler;
+
+/// How long the idle loader waits for a name before checking if it should stop
+const REQUEST_POLL_INTERVAL: instant::Duration = instant::Duration::from_millis(50);
+
+/// The texture the skin of a player is uploaded as
+pub fn skin_texture_name(name: &str) -> String {
+    format!("orange:skin/{name}")
+}
+
+/**
+ * Loads the skins of players on a thread of its own, reading files and downloading is too slow for a frame.
+ * Every name is only requested once, players whose skin fails to load keep the default skin
+ */
+pub struct SkinCache {
+    requests: Sender<String>,
+    results: Receiver<(String, Result<RgbaImage, SkinError>)>,
+    _loader: WorkerThread,
+    requested: HashSet<String>,
+    /// Skins are kept to be uploaded again when the resources are reloaded
+    loaded: HashMap<String, RgbaImage>,
+}
+
+impl SkinCache {
+    pub fn new(source: SkinSource) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<String>();
+        let (result_sender, results) = mpsc::channel();
+        let mut loader = WorkerThread::new();
+        loader.spawn(move |running| {
+            if !running { return; }
+            let name = match request_receiver.recv_timeout(REQUEST_POLL_INTERVAL) {
+                Ok(name) => name,
+                Err(RecvTimeoutError::Timeout) => return,
+                // The cache is gone
+                Err(RecvTimeoutError::Disconnected) => { std::thread::sleep(REQUEST_POLL_INTERVAL); return; },
+            };
+            let skin = source.load(&name);
+            let _ = result_sender.send((name, skin));
+        });
+        Self { requests, results, _loader: loader, requested: HashSet::default(), loaded: HashMap::default() }
+    }
+
+    /// Start loading the skin of a player, if it wasn't already
+    pub fn request(&mut self, name: &str) {
+        if self.requested.insert(name.to_string()) {
+            let _ = self.requests.send(name.to_string());
+        }
+    }
+
+    /// Upload the skins that finished loading since the last poll
+    pub fn poll(&mut self, client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for (name, skin) in self.results.try_iter() {
+            match skin {
+                Ok(skin) => {
+                    mc_resource_handler::generate_skin_texture(client, device, queue, &skin_texture_name(&name), &skin);
+                    self.loaded.insert(name, skin);
+                },
+                Err(SkinError::NotFound(_)) => {},
+                Err(e) => log::warn!("Could not load the skin of {name}, using the default skin: {e}"),
+            }
+        }
+    }
+
+    /// Upload every loaded skin again, after the resources were reloaded
+    pub fn reupload(&self, client: &mut Client, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for (name, skin) in &self.loaded {
+            mc_resource_handler::generate_skin_texture(client, device, queue, &skin_texture_name(name), skin);
+        }
+    }
+}
@@ -1,16 +1,19 @@
 use std::io::Read;
+use std::sync::Mutex;
 use legion::EntityStore;
 use orange_networking::network_interface::NetworkThread;
 use orange_rs::minecraft::prot14::generate_block_to_state_map;
-use orange_rs::minecraft::registry::Registry;
+use orange_rs::minecraft::registry::FrozenRegistry;
+use orange_rs::minecraft::identifier::Identifier;
 use orange_rs::util::nibble;
 use ultraviolet::{IVec2, IVec3, Vec3};
-use orange_rs::entities::{EntityController, EntityTransform};
+use orange_rs::entities::{EntityAnimation, EntityController, EntityKind, EntityTransform};
 use orange_rs::packets::prot14::{MultiBlockChangeData, Packet};
 use orange_rs::util::pos::{BlockPos, EntityPos, NewChunkPosition};
 use orange_rs::world::chunk::{Chunk, CHUNK_SECTION_AXIS_SIZE, TBlockData};
-use orange_rs::world::{ChunkStorage, ChunkStoragePlanar, ChunkStorageTrait};
-use rustc_hash::FxHashMap as HashMap;
+use orange_rs::world::{ChunkStorage, ChunkStoragePlanar, ChunkStorageTrait, biome::{ClimateSource, NoiseClimate}, fluid::Fluid, particles::{self, ParticleSystem}, weather::{self, LightningBolt, Precipitation, WeatherState}, lightmap::DimensionLight, block_entities::{self, BlockEntities, Chest}};
+use orange_rs::direction::Direction;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 
 pub struct TestWorld {
     height: usize,
@@ -18,20 +21,58 @@ pub struct TestWorld {
     spawn_position: BlockPos,
     dimension_id: i8,
     seed: i64,
-    has_weather: bool,
+    /// There is no terrain generator on the client, the climate comes from noise seeded by the world seed
+    climate: NoiseClimate,
+    weather: WeatherState,
+    particles: ParticleSystem,
+    block_entities: BlockEntities,
     pub chunk_storage: ChunkStorage<Chunk>,
     pub entities: legion::World,
 
     pub player: Option<legion::Entity>,
+    /// The entities the server spawned, by the id it sent them with
+    entity_ids: HashMap<i32, legion::Entity>,
 
     block_to_state_map: HashMap<u16, usize>,
+    /// The block id and metadata of every state the server can send, the other way around from `block_to_state_map`
+    state_to_block_map: HashMap<usize, u16>,
+    /// Whether every state is a full block, particles collide with full blocks
+    full_block_states: Vec<bool>,
+    /// The window id and block of the chest the player has open
+    open_chest: Option<(i8, IVec3)>,
+    /// The protocol face the front of every chest state faces
+    chest_facings: HashMap<usize, i8>,
+    /// Blocks that already burst into particles before the server cleared them
+    burst_blocks: HashSet<IVec3>,
+    /// Whether every state stops rain and snow, full blocks and fluids do
+    precipitation_stopping_states: Vec<bool>,
+    /// The precipitation height of every column asked for since a block in it last changed
+    precipitation_heights: Mutex<HashMap<(i32, i32), i32>>,
 }
 
+/// The inventory type of the window of a chest
+const CHEST_INVENTORY: i8 = 0;
+
+/// Blocks that go away without breaking into debris: air, water, lava and fire
+const NO_DEBRIS_BLOCKS: [u8; 6] = [0, 8, 9, 10, 11, 51];
+
 #[allow(unused)]
 impl TestWorld {
-    pub fn new(height: usize, registry: &Registry) -> Self {
+    pub fn new(height: usize, registry: &FrozenRegistry) -> Self {
         let entity_world = legion::World::default();
         let block_to_state_map = generate_block_to_state_map(registry);
+        let mut state_to_block_map = HashMap::default();
+        for (block, state) in &block_to_state_map {
+            state_to_block_map.entry(*state).or_insert(*block);
+        }
+        let full_block_states = registry.get_blockstate_register().get_elements().iter().map(|state| state.get_block().is_full_block()).collect();
+        let chest_facings = registry.get_blockstate_register().get_elements().iter().enumerate()
+            .filter(|(_, state)| state.get_block_identifier() == &Identifier::from_str("minecraft:chest"))
+            .map(|(index, state)| (index, state.get_property_names(registry.get_property_register()).get("facing").map_or(3, |facing| Chest::facing_from_property(facing))))
+            .collect();
+        let precipitation_stopping_states = registry.get_blockstate_register().get_elements().iter()
+            .map(|state| state.get_block().is_full_block() || Fluid::from_block(state.get_block_identifier()).is_some())
+            .collect();
 
         Self {
             height,
@@ -39,11 +80,22 @@ impl TestWorld {
             spawn_position: BlockPos::new(0, 0, 0),
             dimension_id: 0,
             seed: 0,
-            has_weather: false,
+            climate: NoiseClimate::new(0),
+            weather: WeatherState::default(),
+            particles: ParticleSystem::new(0),
+            block_entities: BlockEntities::new(),
             chunk_storage: ChunkStorage::Planar(ChunkStoragePlanar::new(height)),
             entities: entity_world,
             player: None,
+            entity_ids: HashMap::default(),
             block_to_state_map,
+            state_to_block_map,
+            full_block_states,
+            open_chest: None,
+            chest_facings,
+            burst_blocks: HashSet::default(),
+            precipitation_stopping_states,
+            precipitation_heights: Mutex::default(),
         }
     }
 
@@ -61,10 +113,12 @@ impl TestWorld {
 
     pub fn set_seed(&mut self, seed: i64) {
         self.seed = seed;
+        self.climate = NoiseClimate::new(seed);
+        self.particles = ParticleSystem::new(seed);
     }
 
-    pub fn set_weather(&mut self, has_weather: bool) {
-        self.has_weather = has_weather;
+    pub fn set_weather(&mut self, raining: bool) {
+        self.weather.set_raining(raining);
     }
 
     pub fn get_time(&self) -> u64 {
@@ -83,11 +137,46 @@ impl TestWorld {
         self.seed
     }
 
-    pub fn get_weather(&self) -> bool {
-        self.has_weather
+    pub fn get_climate(&self) -> &dyn ClimateSource {
+        &self.climate
+    }
+
+    pub fn get_weather(&self) -> &WeatherState {
+        &self.weather
+    }
+
+    pub fn get_particles(&self) -> &ParticleSystem {
+        &self.particles
+    }
+
+    pub fn get_block_entities(&self) -> &BlockEntities {
+        &self.block_entities
     }
 
     pub fn tick(&mut self, network_thread: &NetworkThread<Packet>) {
+        // The server only sends the time every second, the day keeps moving between updates
+        self.time += 1;
+        self.weather.tick();
+        let (chunk_storage, full_block_states) = (&self.chunk_storage, &self.full_block_states);
+        self.particles.tick(|block| Self::state_at(chunk_storage, block).is_some_and(|state| full_block_states.get(state).copied().unwrap_or(false)));
+        if let Some(transform) = self.get_player_transform() {
+            let position = transform.position;
+            let center = IVec3::new(position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+            let state_to_block_map = &self.state_to_block_map;
+            self.particles.random_display_tick(center, |block| Self::block_id_at(chunk_storage, state_to_block_map, block));
+            // Only dimensions with a sky have weather
+            let strength = if DimensionLight::from_id(self.dimension_id).has_sky { self.weather.get_strength(0.0) } else { 0.0 };
+            if strength > 0.0 {
+                // The particles are taken out while the rest of the world is read for where the rain lands
+                let mut particles = std::mem::replace(&mut self.particles, ParticleSystem::new(0));
+                particles.add_rain_splashes(center, strength, |x, z| self.get_precipitation_height(x, z), |x, z| Precipitation::from_climate(&self.climate.climate_at(x, z)));
+                self.particles = particles;
+            }
+        }
+        self.block_entities.tick();
+        for entity in self.entity_ids.values() {
+            Self::ecs_set_entity_component::<EntityAnimation, _>(*entity, &mut self.entities, |animation| animation.tick());
+        }
         let (stance, on_ground) = if let Some(controller) = self.get_player_controller() {
             (controller.stance, controller.on_ground)
         } else { (-1.6, false) };
@@ -156,19 +245,19 @@ impl TestWorld {
                     // warn!("Entity Action");
                 },
                 Packet::NamedEntitySpawn { entity, name, x, y, z, rotation, pitch, held_item } => {
-                    // warn!("{name} spawned");
+                    self.spawn_entity(entity, EntityKind::Player { name }, Self::fixed_position(x, y, z), rotation, pitch);
                 },
                 Packet::PickupSpawn { entity, item, count, damage_meta, x, y, z, rotation, pitch, roll } => {
-                    // warn!("Pickup Spawned");
+                    self.spawn_entity(entity, EntityKind::Item { id: item, count, damage: damage_meta }, Self::fixed_position(x, y, z), rotation, pitch);
                 },
                 Packet::CollectItem { item_entity, collector_entity } => {
-                    // warn!("Collect Item");
+                    self.remove_entity(item_entity);
                 },
                 Packet::CreateNonMobEntity { entity, entity_type, x, y, z, unknown } => {
-                    // warn!("Create NonMob Entity");
+                    self.spawn_entity(entity, EntityKind::Object(entity_type), Self::fixed_position(x, y, z), 0, 0);
                 },
                 Packet::SpawnMob { entity, entity_type, x, y, z, yaw, pitch, meta } => {
-                    // warn!("Spawn Mob");
+                    self.spawn_entity(entity, EntityKind::Mob(entity_type), Self::fixed_position(x, y, z), yaw, pitch);
                 },
                 Packet::EntityPaintings { entity, title, x, y, z, direction } => {
                     // warn!("Entity Painting {title}");
@@ -180,22 +269,24 @@ impl TestWorld {
                     // warn!("Entity Velocity");
                 },
                 Packet::DestroyEntity { entity } => {
-                    // warn!("Destroy Entity");
+                    self.remove_entity(entity);
                 },
                 Packet::Entity { entity } => {
                     // warn!("Spawn {entity}");
                 },
                 Packet::EntityMoveRelative { entity, dx, dy, dz } => {
-                    // warn!("Entity Move Rel");
+                    self.move_entity(entity, Self::fixed_position(dx as i32, dy as i32, dz as i32), None);
                 },
                 Packet::EntityLook { entity, yaw, pitch } => {
-                    // warn!("Entitiy Look");
+                    self.move_entity(entity, Vec3::zero(), Some((yaw, pitch)));
                 },
                 Packet::EntityLookMoveRelative { entity, dx, dy, dz, yaw, pitch } => {
-                    // warn!("Entity Move Look");
+                    self.move_entity(entity, Self::fixed_position(dx as i32, dy as i32, dz as i32), Some((yaw, pitch)));
                 },
                 Packet::EntityTeleport { entity, x, y, z, yaw, pitch } => {
-                    // warn!("Entity Teleport");
+                    if let Some(transform) = self.entity_ids.get(&entity).and_then(|handle| Self::ecs_get_entity_component::<EntityTransform>(*handle, &self.entities)) {
+                        self.move_entity(entity, Self::fixed_position(x, y, z) - transform.position, Some((yaw, pitch)));
+                    }
                 },
                 Packet::EntityStatus { entity, status } => {
                     // warn!("Entity Status");
@@ -220,22 +311,60 @@ impl TestWorld {
                     self.set_block(x, y as i32, z, block_type as u8, metadata as u8);
                 },
                 Packet::BlockAction { x, y, z, instrument_or_state, pitch_or_direction } => {
-                    // warn!("Block Action");
+                    self.block_action(IVec3::new(x, y as i32, z), instrument_or_state, pitch_or_direction);
                 },
                 Packet::Explosion { x, y, z, radius, explosion_data } => {
-                    // warn!("Explosion");
+                    // The destroyed blocks are relative to the block the explosion went off in
+                    let origin = IVec3::new(x.floor() as i32, y.floor() as i32, z.floor() as i32);
+                    let blocks: Vec<IVec3> = explosion_data.get_offsets().map(|(x, y, z)| origin + IVec3::new(x as i32, y as i32, z as i32)).collect();
+                    self.particles.add_explosion(Vec3::new(x as f32, y as f32, z as f32), radius, &blocks);
+                    // The blocks are blown away, not broken into debris when the server clears them
+                    for block in blocks {
+                        if self.get_block_id(block).is_some_and(|(id, _)| id != 0) {
+                            self.burst_blocks.insert(block);
+                        }
+                    }
                 },
                 Packet::SoundEffect { effect_id, x, y, z, data } => {
-                    // warn!("Sound effect");
+                    let block = IVec3::new(x, y as i32, z);
+                    match effect_id {
+                        particles::SMOKE_EFFECT => self.particles.add_smoke_effect(block, data),
+                        // Broken blocks only send their id, their particles look like the block without metadata
+                        particles::BLOCK_BREAK_EFFECT => if let Some(state) = self.block_to_state_map.get(&((data & 0xFF) as u16)) {
+                            self.particles.add_block_destroy_effects(block, *state);
+                            self.burst_blocks.insert(block);
+                        },
+                        _ => {},
+                    }
                 },
                 Packet::BedWeatherState { state_reason } => {
-                    // warn!("Weather State or Bed");
+                    // 0 is a bed the player can't sleep in, 1 and 2 start and stop the rain
+                    match state_reason {
+                        1 => self.set_weather(true),
+                        2 => self.set_weather(false),
+                        _ => {},
+                    }
                 },
                 Packet::ThunderBolt { entity, unk_flag, x, y, z } => {
-                    // warn!("Thunder Bolt");
+                    // The position is in fixed point, 32 units to a block
+                    let position = Vec3::new(x as f32, y as f32, z as f32) / 32.0;
+                    self.weather.strike(LightningBolt::new(entity, position, self.time as i64 ^ entity as i64));
+                },
+                Packet::OpenContainerWindow { window_id, inventory_type, title, slot_count } => {
+                    // The window doesn't say where it is, the chest the player opened is the closest one in reach
+                    let player = self.get_player_transform().map(|transform| transform.position);
+                    let chest = player.filter(|_| inventory_type == CHEST_INVENTORY).and_then(|player| self.block_entities.nearest_chest(player, block_entities::CHEST_REACH));
+                    self.close_chest();
+                    if let Some(chest) = chest {
+                        self.block_entities.set_chest_viewers(chest, 1);
+                        self.open_chest = Some((window_id, chest));
+                    }
+                },
+                Packet::CloseContainerWindow { window_id } => {
+                    if self.open_chest.is_some_and(|(open_window, _)| open_window == window_id) {
+                        self.close_chest();
+                    }
                 },
-                Packet::OpenContainerWindow { window_id, inventory_type, title, slot_count } => {  },
-                Packet::CloseContainerWindow { window_id } => {  },
                 Packet::ClickContainerWindow { window_id, slot, right_click, action, shift, item_id, item_count, item_uses } => {  },
                 Packet::SetContainerSlot { window_id, slot, item_data } => {
                     // warn!("Set Slot Item");
@@ -245,7 +374,9 @@ impl TestWorld {
                 },
                 Packet::UpdateProgressBar { window_id, progress_bar, value } => {  },
                 Packet::Transaction { window_id, action_id, accepted } => {  },
-                Packet::UpdateSign { x, y, z, line_1, line_2, line_3, line_4 } => {  },
+                Packet::UpdateSign { x, y, z, line_1, line_2, line_3, line_4 } => {
+                    self.block_entities.set_sign_text(IVec3::new(x, y as i32, z), [&line_1, &line_2, &line_3, &line_4]);
+                },
                 Packet::ItemData { item_type, item_id, item_data } => {
                     // warn!("Item Data");
                 },
@@ -279,6 +410,63 @@ impl TestWorld {
         }
     }
 
+    /// Positions of entities are sent in 32nds of a block
+    fn fixed_position(x: i32, y: i32, z: i32) -> Vec3 {
+        Vec3::new(x as f32, y as f32, z as f32) / 32.0
+    }
+
+    /// Angles of entities are sent in 256ths of a turn, their rotation is yaw then pitch in degrees like the player's
+    fn entity_rotation(yaw: i8, pitch: i8) -> Vec3 {
+        Vec3::new(yaw as f32 * 360.0 / 256.0, pitch as f32 * 360.0 / 256.0, 0.0)
+    }
+
+    /// Spawn an entity the server sent, replacing any entity it had before with the same id
+    pub fn spawn_entity(&mut self, id: i32, kind: EntityKind, position: Vec3, yaw: i8, pitch: i8) {
+        self.remove_entity(id);
+        // Items start bobbing at a point of their own, spread out by the golden angle
+        let hover_start = (id as f32 * 2.399_963).rem_euclid(std::f32::consts::TAU);
+        let transform = EntityTransform::new(position, Self::entity_rotation(yaw, pitch));
+        let entity = self.entities.push((kind, transform, EntityAnimation::new(hover_start)));
+        self.entity_ids.insert(id, entity);
+    }
+
+    pub fn remove_entity(&mut self, id: i32) {
+        if let Some(entity) = self.entity_ids.remove(&id) {
+            self.entities.remove(entity);
+        }
+    }
+
+    /// Move an entity by `delta` blocks and turn it to `look` when it is sent, moving swings its limbs
+    pub fn move_entity(&mut self, id: i32, delta: Vec3, look: Option<(i8, i8)>) {
+        let Some(entity) = self.entity_ids.get(&id).copied() else { return; };
+        Self::ecs_set_entity_component::<EntityTransform, _>(entity, &mut self.entities, |transform| {
+            transform.position += delta;
+            if let Some((yaw, pitch)) = look {
+                transform.rotation = Self::entity_rotation(yaw, pitch);
+            }
+            transform.turn_body(delta);
+        });
+        Self::ecs_set_entity_component::<EntityAnimation, _>(entity, &mut self.entities, |animation| animation.moved(delta));
+    }
+
+    /// The blockstate a dropped item is drawn as, only blocks have one
+    pub fn get_item_state(&self, id: i16, damage: i16) -> Option<usize> {
+        if !(1..256).contains(&id) { return None; }
+        let block = id as u16;
+        self.block_to_state_map.get(&(block | ((damage as u16 & 0xF) << 8))).or_else(|| self.block_to_state_map.get(&block)).copied()
+    }
+
+    /// Every entity the server spawned, without the player
+    pub fn get_entities(&self) -> Vec<(EntityKind, EntityTransform, EntityAnimation)> {
+        self.entity_ids.values().filter_map(|entity| {
+            Some((
+                Self::ecs_get_entity_component::<EntityKind>(*entity, &self.entities)?,
+                Self::ecs_get_entity_component::<EntityTransform>(*entity, &self.entities)?,
+                Self::ecs_get_entity_component::<EntityAnimation>(*entity, &self.entities)?,
+            ))
+        }).collect()
+    }
+
     pub fn set_player_position(&mut self, position: EntityPos) {
         // Self::ecs_set_entity_component::<EntityTransform, _>(self.player.unwrap(), &mut self.entities, |transform: &mut EntityTransform| { transform.position = position; });
         let mut entry = self.entities.entry_mut(self.player.unwrap()).unwrap();
@@ -342,7 +530,98 @@ impl TestWorld {
         self.height
     }
 
+    fn state_at(chunk_storage: &ChunkStorage<Chunk>, block: IVec3) -> Option<usize> {
+        let chunk = chunk_storage.get_chunk(IVec3::new(block.x >> 4, block.y >> 4, block.z >> 4)).ok()?;
+        Some(chunk.get_block_at_pos((block.x & 15) as u32, (block.y & 15) as u32, (block.z & 15) as u32) as usize)
+    }
+
+    /// The state id of the block at a position, none in chunks that aren't loaded
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        Self::state_at(&self.chunk_storage, IVec3::new(x, y, z))
+    }
+
+    /// The sky and block light at a position, none in chunks that aren't loaded
+    pub fn get_light(&self, block: IVec3) -> Option<(u8, u8)> {
+        let chunk = self.chunk_storage.get_chunk(IVec3::new(block.x >> 4, block.y >> 4, block.z >> 4)).ok()?;
+        Some(chunk.get_light_at_pos((block.x & 15) as u32, (block.y & 15) as u32, (block.z & 15) as u32))
+    }
+
+    /**
+     * A block changed to `id`, bursting into debris if it was broken. The server sends the block breaking effect to every
+     * player but the one who broke the block, so blocks that already burst or were blown up by an explosion don't burst again
+     */
+    fn break_block(&mut self, block: IVec3, id: u8) {
+        if id != 0 || self.burst_blocks.remove(&block) { return; }
+        let Some(state) = self.get_block(block.x, block.y, block.z) else { return; };
+        let breaks = self.state_to_block_map.get(&state).is_some_and(|previous| !NO_DEBRIS_BLOCKS.contains(&((previous & 0xFF) as u8)));
+        if breaks {
+            self.particles.add_block_destroy_effects(block, state);
+        }
+    }
+
+    /// The block entity at `block` follows its block, chests turn the way their blockstate `state` faces
+    fn block_entity_changed(block_entities: &mut BlockEntities, chest_facings: &HashMap<usize, i8>, block: IVec3, id: u8, meta: u8, state: usize) {
+        block_entities.block_changed(block, id, meta);
+        if let Some(facing) = chest_facings.get(&state) {
+            block_entities.set_chest_facing(block, *facing);
+        }
+    }
+
+    /// The chest the player had open closes
+    fn close_chest(&mut self) {
+        if let Some((_, chest)) = self.open_chest.take() {
+            self.block_entities.set_chest_viewers(chest, 0);
+        }
+    }
+
+    fn block_id_at(chunk_storage: &ChunkStorage<Chunk>, state_to_block_map: &HashMap<usize, u16>, block: IVec3) -> Option<(u8, u8)> {
+        let state = Self::state_at(chunk_storage, block)?;
+        state_to_block_map.get(&state).map(|block| ((block & 0xFF) as u8, (block >> 8) as u8))
+    }
+
+    /// The block id and metadata at a position, as the server knows it
+    pub fn get_block_id(&self, block: IVec3) -> Option<(u8, u8)> {
+        Self::block_id_at(&self.chunk_storage, &self.state_to_block_map, block)
+    }
+
+    /**
+     * Pistons push their head out with a state of 0 and pull it back with 1, towards the face sent as the pitch.
+     * Chests open while the state is 1 and the pitch counts the players looking inside. Note blocks only make sounds
+     */
+    pub fn block_action(&mut self, block: IVec3, state: i8, pitch_or_direction: i8) {
+        match self.get_block_id(block) {
+            Some((id @ (block_entities::PISTON_BLOCK | block_entities::STICKY_PISTON_BLOCK), _)) => {
+                let Some(direction) = Direction::from_protocol_face(pitch_or_direction) else { return; };
+                let sticky = if id == block_entities::STICKY_PISTON_BLOCK { 8 } else { 0 };
+                let head = block_entities::PISTON_HEAD_BLOCK as u16 | (((pitch_or_direction as u16) | sticky) << 8);
+                if let Some(head_state) = self.block_to_state_map.get(&head) {
+                    self.block_entities.move_piston(block, direction, state == 0, *head_state);
+                }
+            },
+            Some((block_entities::CHEST_BLOCK, _)) if state == 1 => self.block_entities.set_chest_viewers(block, pitch_or_direction.max(0) as u8),
+            _ => {},
+        }
+    }
+
+    /// The y rain and snow fall down to in a column, full blocks and fluids stop them. Columns are only scanned again once a block in them changes
+    pub fn get_precipitation_height(&self, x: i32, z: i32) -> i32 {
+        let mut heights = self.precipitation_heights.lock().unwrap();
+        *heights.entry((x, z)).or_insert_with(|| {
+            let top = (self.height * CHUNK_SECTION_AXIS_SIZE) as i32;
+            weather::precipitation_height(top, |y| {
+                self.get_block(x, y, z).is_some_and(|state| self.precipitation_stopping_states.get(state).copied().unwrap_or(false))
+            })
+        })
+    }
+
+    fn forget_precipitation_height(heights: &mut Mutex<HashMap<(i32, i32), i32>>, x: i32, z: i32) {
+        if let Ok(heights) = heights.get_mut() {
+            heights.remove(&(x, z));
+        }
+    }
+
     pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: u8, meta: u8) {
+        self.break_block(IVec3::new(x, y, z), block);
         let cpos = (x >> 4, y >> 4, z >> 4);
         match self.chunk_storage.get_chunk_mut(cpos.into()) {
             Ok(chunk) => {
@@ -358,6 +637,8 @@ impl TestWorld {
                 // warn!("Block Change 2: ({ix}, {iy}, {iz})|({:?}) <- {block}|{meta}", cpos);
                 chunk.set_block_at_pos(ix as u32, iy as u32, iz as u32, block_data as TBlockData);
                 chunk.set_dirty(true);
+                Self::block_entity_changed(&mut self.block_entities, &self.chest_facings, IVec3::new(x, y, z), block, meta, block_data);
+                Self::forget_precipitation_height(&mut self.precipitation_heights, x, z);
             },
             _ => {}
         }
@@ -370,6 +651,7 @@ impl TestWorld {
             let z = ((coords >> 8) & 0b0000000000001111) as u32;
             let y = (coords & 0b0000000011111111) as i32;
             let meta = data.metadata[index];
+            self.break_block(IVec3::new((cx << 4) + x as i32, y, (cz << 4) + z as i32), block as u8);
             // println!("Setting ({x}, {y}, {z})|() <- {block} |{}", data.metadata[index]);
             let block_data = block as u16 | ((meta as u16) << 8);
 
@@ -381,6 +663,8 @@ impl TestWorld {
             if let Ok(chunk) = self.chunk_storage.get_chunk_mut(IVec3::new(cx, y >> 4, cz)) {
                 chunk.set_block_at_pos(x, (y & 15) as u32, z, block_data as TBlockData);
                 chunk.set_dirty(true);
+                Self::block_entity_changed(&mut self.block_entities, &self.chest_facings, IVec3::new((cx << 4) + x as i32, y, (cz << 4) + z as i32), block as u8, meta as u8, block_data);
+                Self::forget_precipitation_height(&mut self.precipitation_heights, (cx << 4) + x as i32, (cz << 4) + z as i32);
             }
         }
     }
@@ -448,6 +732,8 @@ impl TestWorld {
                     let x = chunk_x_start + x as u32;
                     let y = local_y as u32;
                     let z = chunk_z_start + z as u32;
+                    Self::block_entity_changed(&mut self.block_entities, &self.chest_facings, IVec3::new(chunk_x_real + x as i32, actual_y as i32, chunk_z_real + z as i32), block_bytes[block_index], meta, data);
+                    Self::forget_precipitation_height(&mut self.precipitation_heights, chunk_x_real + x as i32, chunk_z_real + z as i32);
                     chunk.set_block_at_pos(x, y, z, data as TBlockData);
                     chunk.set_blocklight_at_pos(x, y, z, block_light);
                     chunk.set_skylight_at_pos(x, y, z, sky_light);
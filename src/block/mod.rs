@@ -1,14 +1,14 @@
 pub mod block_factory;
 pub mod properties;
 
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
+use std::sync::{Arc, OnceLock, Weak};
 
 use rustc_hash::FxHashMap as HashMap;
 
 use crate::minecraft::identifier::Identifier;
 use crate::direction::Direction;
-use crate::minecraft::registry::{Registerable, Registry};
+use crate::minecraft::registry::{Registerable, Registry, PropertyRegister};
+use crate::models::condition::StatePropertyNames;
 
 use self::block_factory::BlockSettings;
 use self::properties::{PropertyValueType, PropertyDefinition};
@@ -36,7 +36,8 @@ pub struct Block {
 
     side_cull_fn: SideCullFunctionType,
 
-    state_manager: RefCell<StateManager>,
+    /// Set once the states of the block are mapped
+    state_manager: OnceLock<StateManager>,
 }
 
 impl Block {
@@ -69,7 +70,7 @@ impl Block {
             full_block,
             properties,
             side_cull_fn,
-            state_manager: RefCell::new(StateManager::new()),
+            state_manager: OnceLock::new(),
         }
     }
 
@@ -98,11 +99,11 @@ impl Block {
         f(dir)
     }
 
-    pub fn get_default_state(&self) -> Rc<BlockState> {
-        self.state_manager.borrow().get_default()
+    pub fn get_default_state(&self) -> Arc<BlockState> {
+        self.state_manager.get().expect("The states of the block were never mapped").get_default()
     }
 
-    pub fn map_states(block: Rc<Self>, registry: &mut Registry) -> Vec<Rc<BlockState>> {
+    pub fn map_states(block: Arc<Self>, registry: &mut Registry) -> Vec<Arc<BlockState>> {
 
         // let block = self;
         let properties = &block.properties;
@@ -149,14 +150,17 @@ impl Block {
             (varient_name, varient)
         }).collect::<Vec<_>>();
 
-        let states = state_varients.into_iter().map(|(id, properties)| Rc::new(BlockState::new(block.clone(), block_id, id, properties))).collect::<Vec<_>>();
+        let states = state_varients.into_iter().map(|(id, properties)| Arc::new(BlockState::new(block.clone(), block_id, id, properties))).collect::<Vec<_>>();
 
-        let weak_states = states.iter().map(|state| Rc::downgrade(state)).collect::<Vec<_>>();
+        let weak_states = states.iter().map(Arc::downgrade).collect::<Vec<_>>();
         {
-            let mut state_manager = block.state_manager.borrow_mut();
+            let mut state_manager = StateManager::new();
             state_manager.siblings = weak_states;
             state_manager.state_indicies = varient_indexs;
-            state_manager.properties = properties.iter().map(|(name, def)| (name.clone(), registry.get_property_register().get_element_from_identifier(&def).unwrap().clone())).collect::<Vec<(String, Rc<PropertyDefinition>)>>();
+            state_manager.properties = properties.iter().map(|(name, def)| (name.clone(), registry.get_property_register().get_element_from_identifier(&def).unwrap().clone())).collect::<Vec<(String, Arc<PropertyDefinition>)>>();
+            if block.state_manager.set(state_manager).is_err() {
+                log::error!("The states of {} were already mapped", block.identifier);
+            }
         }
         
         states
@@ -175,7 +179,7 @@ pub type BlockStatePropertyMap = HashMap<String, (PropertyValueType, Identifier)
 pub struct StateManager {
     siblings: Vec<Weak<BlockState>>,
     state_indicies: Vec<usize>,
-    properties: Vec<(String, Rc<PropertyDefinition>)>,
+    properties: Vec<(String, Arc<PropertyDefinition>)>,
     default_index: usize,
 }
 
@@ -189,11 +193,11 @@ impl StateManager {
         }
     }
 
-    pub fn with<S: AsRef<str>>(&self, old_properties: &BlockStatePropertyMap, name: S, value: S) -> Rc<BlockState> {
+    pub fn with<S: AsRef<str>>(&self, old_properties: &BlockStatePropertyMap, name: S, value: S) -> Arc<BlockState> {
         self.inner_with(old_properties, name.as_ref(), value.as_ref())
     }
 
-    fn inner_with(&self, old_properties: &BlockStatePropertyMap, name: &str, value: &str) -> Rc<BlockState> {
+    fn inner_with(&self, old_properties: &BlockStatePropertyMap, name: &str, value: &str) -> Arc<BlockState> {
         let state_index = self.inner_get_state_index(old_properties, name, value);
         self.siblings[state_index].upgrade().unwrap()
     }
@@ -220,7 +224,7 @@ impl StateManager {
         self.default_index = 0;
     }
 
-    pub fn get_default(&self) -> Rc<BlockState> {
+    pub fn get_default(&self) -> Arc<BlockState> {
         self.siblings[self.default_index].upgrade().unwrap()
     }
 }
@@ -228,7 +232,7 @@ impl StateManager {
 
 #[derive(Clone)]
 pub struct BlockState {
-    block: Rc<Block>,
+    block: Arc<Block>,
     block_id: usize,
     state_identifier: Identifier,
     property_map: BlockStatePropertyMap,
@@ -236,7 +240,7 @@ pub struct BlockState {
 
 impl BlockState {
 
-    pub fn new(block: Rc<Block>, block_id: usize, varient: Identifier, property_map: BlockStatePropertyMap) -> Self {
+    pub fn new(block: Arc<Block>, block_id: usize, varient: Identifier, property_map: BlockStatePropertyMap) -> Self {
         
         Self {
             block,
@@ -258,8 +262,17 @@ impl BlockState {
         &self.property_map
     }
 
-    pub fn get_block(&self) -> Rc<Block> {
-        self.block.clone()
+    /// The name of the value of every property, as written in blockstate files
+    pub fn get_property_names(&self, properties: &PropertyRegister) -> StatePropertyNames {
+        self.property_map.iter().filter_map(|(name, (value, definition))| {
+            let definition = properties.get_element_from_identifier(definition)?;
+            let value_name = definition.value_to_name(*value).ok()?;
+            Some((name.clone(), value_name.clone()))
+        }).collect()
+    }
+
+    pub fn get_block(&self) -> &Arc<Block> {
+        &self.block
     }
 
     pub fn get_block_id(&self) -> usize {
@@ -274,8 +287,8 @@ impl BlockState {
         &self.state_identifier
     }
 
-    pub fn with<S: AsRef<str>>(&self, name: S, value: S) -> Rc<Self> {
-        self.block.state_manager.borrow().with(&self.property_map, name, value)
+    pub fn with<S: AsRef<str>>(&self, name: S, value: S) -> Arc<Self> {
+        self.block.state_manager.get().expect("The states of the block were never mapped").with(&self.property_map, name, value)
     }
 }
 
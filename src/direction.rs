@@ -32,6 +32,24 @@ impl Direction {
         }
     }
 
+    /// The direction pointing along an axis aligned unit vector
+    pub fn from_int_vector(vector: IVec3) -> Option<Direction> {
+        DIRECTIONS.iter().find(|direction| direction.get_int_vector() == vector).copied()
+    }
+
+    /// The direction of a block face sent by the server, faces count -y, +y, -z, +z, -x, +x
+    pub fn from_protocol_face(face: i8) -> Option<Direction> {
+        match face {
+            0 => Some(Direction::Down),
+            1 => Some(Direction::Up),
+            2 => Some(Direction::East),
+            3 => Some(Direction::West),
+            4 => Some(Direction::North),
+            5 => Some(Direction::South),
+            _ => None,
+        }
+    }
+
     pub fn get_float_vector(&self) -> Vec3 {
         match self {
             Direction::North => Vec3::new(-1.0, 0.0, 0.0),
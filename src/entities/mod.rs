@@ -2,10 +2,42 @@ use ultraviolet::Vec3;
 
 /// Entities are collections of components, able to function in an ECS
 ///
+/// How far the head of an entity turns away from its body before the body follows, in degrees
+const MAX_HEAD_TURN: f32 = 75.0;
+/// Entities moving less than this many blocks at a time don't turn their body to where they go
+const BODY_TURN_DISTANCE: f32 = 0.05;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct EntityTransform {
     pub position: Vec3,
+    /// Yaw and pitch in degrees, where the head looks
     pub rotation: Vec3,
+    /// Where the body faces, it trails the head and turns to where the entity walks as in EntityLiving
+    pub body_yaw: f32,
+}
+
+impl EntityTransform {
+    pub fn new(position: Vec3, rotation: Vec3) -> Self {
+        Self { position, rotation, body_yaw: rotation.x }
+    }
+
+    /// How far the head is turned away from the body, in degrees
+    pub fn get_head_yaw(&self) -> f32 {
+        wrap_degrees(self.rotation.x - self.body_yaw)
+    }
+
+    /// The entity moved by `delta` blocks, its body turns part of the way to where it goes and keeps up with its head
+    pub fn turn_body(&mut self, delta: Vec3) {
+        let distance = (delta.x * delta.x + delta.z * delta.z).sqrt();
+        let target = if distance > BODY_TURN_DISTANCE { delta.z.atan2(delta.x).to_degrees() - 90.0 } else { self.body_yaw };
+        self.body_yaw += wrap_degrees(target - self.body_yaw) * 0.3;
+        self.body_yaw = self.rotation.x - self.get_head_yaw().clamp(-MAX_HEAD_TURN, MAX_HEAD_TURN);
+    }
+}
+
+/// An angle in degrees brought into -180 to 180
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -24,3 +56,67 @@ pub struct EntityController {
     pub on_ground: bool,
     pub stance: f64,
 }
+
+/// What an entity sent by the server is, which decides how it is drawn
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntityKind {
+    Player { name: String },
+    /// A mob of the type sent in `SpawnMob`
+    Mob(i8),
+    /// An object of the type sent in `CreateNonMobEntity`, like minecarts, boats and arrows
+    Object(i8),
+    /// A dropped stack of items
+    Item { id: i16, count: i8, damage: i16 },
+}
+
+/// How far the limbs of an entity have swung, animated from how far it moves every tick as in EntityLiving
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityAnimation {
+    pub limb_swing: f32,
+    pub limb_swing_amount: f32,
+    /// Ticks since the entity was spawned
+    pub age: f32,
+    /// Where in its bobbing a dropped item started, in radians
+    pub hover_start: f32,
+    /// How far the entity moved across the ground since the last tick
+    distance: f32,
+}
+
+impl EntityAnimation {
+    pub fn new(hover_start: f32) -> Self {
+        Self { hover_start, ..Default::default() }
+    }
+
+    /// The entity moved by `delta` blocks, only moving across the ground swings its limbs
+    pub fn moved(&mut self, delta: Vec3) {
+        self.distance += (delta.x * delta.x + delta.z * delta.z).sqrt();
+    }
+
+    pub fn tick(&mut self) {
+        let target = (self.distance * 4.0).min(1.0);
+        self.limb_swing_amount += (target - self.limb_swing_amount) * 0.4;
+        self.limb_swing += self.limb_swing_amount;
+        self.age += 1.0;
+        self.distance = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies_trail_their_heads() {
+        let mut transform = EntityTransform::new(Vec3::zero(), Vec3::new(100.0, 0.0, 0.0));
+        assert_eq!(transform.get_head_yaw(), 0.0);
+        // Looking far to the side drags the body along, but only so far
+        transform.rotation.x = 200.0;
+        transform.turn_body(Vec3::zero());
+        assert!((transform.get_head_yaw() - MAX_HEAD_TURN).abs() < 1e-4);
+        // Walking towards +x turns the body to face it, a yaw of -90
+        for _ in 0..50 { transform.turn_body(Vec3::new(0.2, 0.0, 0.0)); }
+        transform.rotation.x = -90.0;
+        transform.turn_body(Vec3::new(0.2, 0.0, 0.0));
+        assert!(transform.get_head_yaw().abs() < 1e-3);
+    }
+}
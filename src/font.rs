@@ -0,0 +1,419 @@
+use image::RgbaImage;
+use ultraviolet::{Vec2, Vec3};
+
+use crate::minecraft::{asset_loader::AssetLoader, identifier::Identifier};
+
+/// Glyphs are laid out on a grid of sixteen by sixteen cells
+pub const GLYPHS_PER_ROW: usize = 16;
+/// Text is measured in units of a pixel of a glyph of the default font, cells are this many units wide
+pub const GLYPH_SIZE: f32 = 8.0;
+/// Lines of text are this many units apart
+pub const LINE_HEIGHT: f32 = 9.0;
+/// Characters of font.txt, the glyph of each one is the index in here plus 32
+pub const ALLOWED_CHARACTERS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_'abcdefghijklmnopqrstuvwxyz{|}~⌂ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒáíóúñÑªº¿®¬½¼¡«»";
+/// The character starting a formatting code, the character after it picks the color or format of the text that follows
+pub const FORMATTING_CODE: char = '§';
+/// Where the font sheet is among the sprites, `font/default` of b1.7.3 jars and resource packs, then `font/ascii` of newer resource packs
+pub const FONT_SHEET_IDENTIFIERS: [&str; 2] = ["minecraft:font/default", "minecraft:font/ascii"];
+const FIRST_ALLOWED_GLYPH: usize = 32;
+/// Spaces have no pixels, they are as wide as a narrow glyph
+const SPACE_WIDTH: f32 = 4.0;
+/// Shadows are drawn this far right and down of their text, a quarter as bright
+const SHADOW_OFFSET: f32 = 1.0;
+const SHADOW_BRIGHTNESS: f32 = 0.25;
+/// Bold glyphs are drawn a second time this far to the right and take up as much more room
+const BOLD_OFFSET: f32 = 1.0;
+/// How far the top of an italic glyph leans to the right
+const ITALIC_SKEW: f32 = 1.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FontError {
+    #[error("None of the resource packs have a font sheet")]
+    Missing,
+    #[error("Font sheet of {width}x{height} is not a square grid of 16x16 glyphs")]
+    InvalidSize { width: u32, height: u32 },
+}
+
+/// The color of a `§0` to `§f` code, the sixteen colors of FontRenderer
+pub fn color_of_code(code: char) -> Option<Vec3> {
+    let index = code.to_digit(16)?;
+    let bright = ((index >> 3) & 1) as f32 * 85.0;
+    let channel = |bit: u32| ((index >> bit) & 1) as f32 * 170.0 + bright;
+    // Dark yellow is gold instead
+    let red = if index == 6 { channel(2) + 85.0 } else { channel(2) };
+    Some(Vec3::new(red, channel(1), channel(0)) / 255.0)
+}
+
+/// Text without its formatting codes
+pub fn strip_formatting(text: &str) -> String {
+    styled_chars(text, TextStyle::new(Vec3::one())).into_iter().map(|(character, _)| character).collect()
+}
+
+/**
+ * The formatting codes in effect at the end of `text`, to carry them over to the next line of wrapped text.
+ * Colors end every format before them like `§r` does
+ */
+pub fn active_formatting(text: &str) -> String {
+    let mut codes = String::new();
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        if character != FORMATTING_CODE { continue; }
+        let Some(code) = chars.next().map(|code| code.to_ascii_lowercase()) else { break; };
+        if code == 'r' || color_of_code(code).is_some() {
+            codes.clear();
+        }
+        if code != 'r' {
+            codes.extend([FORMATTING_CODE, code]);
+        }
+    }
+    codes
+}
+
+/// How a character of text is drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: Vec3,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl TextStyle {
+    pub fn new(color: Vec3) -> Self {
+        Self { color, bold: false, italic: false }
+    }
+
+    /// Change the style by the code after a `§`, codes that change nothing that can be drawn like obfuscated or underlined are left out
+    fn apply_code(&mut self, code: char, default: TextStyle) {
+        match code.to_ascii_lowercase() {
+            'l' => self.bold = true,
+            'o' => self.italic = true,
+            'r' => *self = default,
+            code => if let Some(color) = color_of_code(code) {
+                *self = TextStyle::new(color);
+            },
+        }
+    }
+}
+
+/// The characters of text with the style each one is drawn with, formatting codes are taken out
+pub fn styled_chars(text: &str, default: TextStyle) -> Vec<(char, TextStyle)> {
+    let mut style = default;
+    let mut styled = vec![];
+    let mut chars = text.chars();
+    while let Some(character) = chars.next() {
+        if character == FORMATTING_CODE {
+            if let Some(code) = chars.next() {
+                style.apply_code(code, default);
+            }
+            continue;
+        }
+        styled.push((character, style));
+    }
+    styled
+}
+
+/// A glyph placed in text, corners are in text units with y pointing down
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+    pub color: Vec3,
+    /// How far the top edge leans to the right of the bottom edge
+    pub skew: f32,
+}
+
+impl GlyphQuad {
+    /// The position and uv of each corner, top left, top right, bottom left then bottom right
+    pub fn corners(&self) -> [(Vec2, Vec2); 4] {
+        [
+            (Vec2::new(self.min.x + self.skew, self.min.y), self.uv_min),
+            (Vec2::new(self.max.x + self.skew, self.min.y), Vec2::new(self.uv_max.x, self.uv_min.y)),
+            (Vec2::new(self.min.x, self.max.y), Vec2::new(self.uv_min.x, self.uv_max.y)),
+            (self.max, self.uv_max),
+        ]
+    }
+}
+
+/**
+ * The default font of b1.7.3, a sheet of glyphs whose widths are measured from their pixels as FontRenderer does.
+ * Sheets of a higher resolution are measured in the same units as the 128x128 sheet
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitmapFont {
+    widths: [f32; GLYPHS_PER_ROW * GLYPHS_PER_ROW],
+}
+
+impl BitmapFont {
+    /// Measure every glyph of a font sheet, a glyph is as wide as its rightmost column with a pixel plus one unit of spacing
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let cell = (image.width() as usize / GLYPHS_PER_ROW).max(1);
+        let mut widths = [0.0; GLYPHS_PER_ROW * GLYPHS_PER_ROW];
+        for (glyph, width) in widths.iter_mut().enumerate() {
+            let (cell_x, cell_y) = ((glyph % GLYPHS_PER_ROW) * cell, (glyph / GLYPHS_PER_ROW) * cell);
+            let column_has_pixel = |column: usize| (0..cell).any(|row| {
+                let (x, y) = ((cell_x + column) as u32, (cell_y + row) as u32);
+                x < image.width() && y < image.height() && image.get_pixel(x, y)[3] > 0
+            });
+            let columns = (0..cell).rev().find(|column| column_has_pixel(*column)).map_or(0, |column| column + 1);
+            *width = (columns as f32 * GLYPH_SIZE / cell as f32).ceil() + 1.0;
+        }
+        widths[FIRST_ALLOWED_GLYPH] = SPACE_WIDTH;
+        Self { widths }
+    }
+
+    /// Find the font sheet among the sprites of the resource packs and measure its glyphs, the sheet is returned to be uploaded
+    pub fn load(asset_loader: &AssetLoader) -> Result<(Self, RgbaImage), FontError> {
+        let sheet = FONT_SHEET_IDENTIFIERS.iter()
+            .find_map(|identifier| asset_loader.sprites().get(&Identifier::from_str(identifier)))
+            .ok_or(FontError::Missing)?
+            .to_rgba8();
+        let (width, height) = sheet.dimensions();
+        if width == 0 || width != height || !(width as usize).is_multiple_of(GLYPHS_PER_ROW) {
+            return Err(FontError::InvalidSize { width, height });
+        }
+        Ok((Self::from_image(&sheet), sheet))
+    }
+
+    /// Every glyph the same width, for when there is no font sheet to measure
+    pub fn monospace() -> Self {
+        let mut widths = [GLYPH_SIZE - 2.0; GLYPHS_PER_ROW * GLYPHS_PER_ROW];
+        widths[FIRST_ALLOWED_GLYPH] = SPACE_WIDTH;
+        Self { widths }
+    }
+
+    /// The glyph of a character, characters outside of font.txt have none and aren't drawn
+    pub fn glyph_index(character: char) -> Option<usize> {
+        ALLOWED_CHARACTERS.chars().position(|allowed| allowed == character).map(|index| index + FIRST_ALLOWED_GLYPH)
+    }
+
+    pub fn get_char_width(&self, character: char) -> f32 {
+        Self::glyph_index(character).map_or(0.0, |glyph| self.widths[glyph])
+    }
+
+    fn get_styled_width(&self, character: char, style: &TextStyle) -> f32 {
+        match Self::glyph_index(character) {
+            Some(glyph) if style.bold => self.widths[glyph] + BOLD_OFFSET,
+            Some(glyph) => self.widths[glyph],
+            None => 0.0,
+        }
+    }
+
+    /// How wide text is drawn, formatting codes take no room and bold glyphs take a unit more
+    pub fn get_string_width(&self, text: &str) -> f32 {
+        styled_chars(text, TextStyle::new(Vec3::one())).iter().map(|(character, style)| self.get_styled_width(*character, style)).sum()
+    }
+
+    /// Where a glyph is on the sheet, in texture coordinates
+    pub fn glyph_uv_bounds(glyph: usize) -> (Vec2, Vec2) {
+        let cell = 1.0 / GLYPHS_PER_ROW as f32;
+        let min = Vec2::new((glyph % GLYPHS_PER_ROW) as f32, (glyph / GLYPHS_PER_ROW) as f32) * cell;
+        (min, min + Vec2::broadcast(cell))
+    }
+
+    /**
+     * The glyphs of a line of text starting at `origin` in `color` until a formatting code changes it.
+     * Each glyph covers its whole cell and the next starts after its width
+     */
+    pub fn layout_line(&self, text: &str, origin: Vec2, color: Vec3) -> Vec<GlyphQuad> {
+        let mut x = origin.x;
+        let mut glyphs = vec![];
+        for (character, style) in styled_chars(text, TextStyle::new(color)) {
+            let Some(glyph) = Self::glyph_index(character) else { continue; };
+            let (uv_min, uv_max) = Self::glyph_uv_bounds(glyph);
+            let skew = if style.italic { ITALIC_SKEW } else { 0.0 };
+            // Spaces are advanced over but not drawn
+            if glyph != FIRST_ALLOWED_GLYPH {
+                let quad = GlyphQuad { min: Vec2::new(x, origin.y), max: Vec2::new(x + GLYPH_SIZE, origin.y + GLYPH_SIZE), uv_min, uv_max, color: style.color, skew };
+                glyphs.push(quad);
+                if style.bold {
+                    glyphs.push(GlyphQuad { min: quad.min + Vec2::new(BOLD_OFFSET, 0.0), max: quad.max + Vec2::new(BOLD_OFFSET, 0.0), ..quad });
+                }
+            }
+            x += self.get_styled_width(character, &style);
+        }
+        glyphs
+    }
+
+    /// A line of text over its shadow, the shadow glyphs come first so they are drawn underneath
+    pub fn layout_shadowed_line(&self, text: &str, origin: Vec2, color: Vec3) -> Vec<GlyphQuad> {
+        let mut glyphs = self.layout_line(text, origin + Vec2::broadcast(SHADOW_OFFSET), color);
+        for glyph in &mut glyphs {
+            glyph.color *= SHADOW_BRIGHTNESS;
+        }
+        glyphs.extend(self.layout_line(text, origin, color));
+        glyphs
+    }
+
+    /// How many bytes of `text` fit in `max_width`, at least one character is always taken so wrapping moves on
+    fn fitting_length(&self, text: &str, max_width: f32) -> usize {
+        let mut width = 0.0;
+        let mut style = TextStyle::new(Vec3::one());
+        let mut chars = text.char_indices();
+        let mut took_any = false;
+        while let Some((index, character)) = chars.next() {
+            if character == FORMATTING_CODE {
+                if let Some((_, code)) = chars.next() {
+                    style.apply_code(code, TextStyle::new(Vec3::one()));
+                }
+                continue;
+            }
+            width += self.get_styled_width(character, &style);
+            if width > max_width && took_any {
+                return index;
+            }
+            took_any = true;
+        }
+        text.len()
+    }
+
+    /**
+     * Break text into lines no wider than `max_width`, at newlines and between words when it can.
+     * Words too long for a line are broken up, the formatting of a line carries over to the next
+     */
+    pub fn wrap(&self, text: &str, max_width: f32) -> Vec<String> {
+        let mut lines = vec![];
+        let mut carried = String::new();
+        for paragraph in text.split('\n') {
+            let mut rest = format!("{carried}{paragraph}");
+            loop {
+                let fit = self.fitting_length(&rest, max_width);
+                if fit >= rest.len() {
+                    carried = active_formatting(&rest);
+                    lines.push(rest);
+                    break;
+                }
+                let (line, next) = match rest[..fit].rfind(' ') {
+                    _ if rest[fit..].starts_with(' ') => (&rest[..fit], &rest[fit + 1..]),
+                    Some(space) if space > 0 => (&rest[..space], &rest[space + 1..]),
+                    _ => (&rest[..fit], &rest[fit..]),
+                };
+                let next = next.trim_start_matches(' ');
+                // A break after nothing but formatting codes would leave an empty line, the codes go with the next words instead.
+                // The space broken at is dropped so this always gets shorter
+                if strip_formatting(line).is_empty() && line.len() + next.len() < rest.len() {
+                    rest = format!("{line}{next}");
+                    continue;
+                }
+                let next = format!("{}{}", active_formatting(line), next);
+                lines.push(line.to_string());
+                rest = next;
+            }
+        }
+        lines
+    }
+
+    /// Wrapped text with its lines one under the other from `origin`, with shadows when `shadow` is set
+    pub fn layout_wrapped(&self, text: &str, origin: Vec2, max_width: f32, color: Vec3, shadow: bool) -> Vec<GlyphQuad> {
+        self.wrap(text, max_width).iter().enumerate().flat_map(|(index, line)| {
+            let origin = origin + Vec2::new(0.0, index as f32 * LINE_HEIGHT);
+            if shadow { self.layout_shadowed_line(line, origin, color) } else { self.layout_line(line, origin, color) }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource_loader::ResourceSystem;
+
+    #[test]
+    fn glyph_widths_are_measured_from_pixels() {
+        let mut sheet = RgbaImage::new(128, 128);
+        // An 'A' three pixels wide and an 'i' one pixel wide
+        let (a, i) = (BitmapFont::glyph_index('A').unwrap(), BitmapFont::glyph_index('i').unwrap());
+        for x in 0..3 {
+            sheet.put_pixel((a % 16 * 8 + x) as u32, (a / 16 * 8 + 2) as u32, image::Rgba([255; 4]));
+        }
+        sheet.put_pixel((i % 16 * 8) as u32, (i / 16 * 8 + 4) as u32, image::Rgba([255; 4]));
+        let font = BitmapFont::from_image(&sheet);
+        assert_eq!(font.get_char_width('A'), 4.0);
+        assert_eq!(font.get_char_width('i'), 2.0);
+        assert_eq!(font.get_char_width(' '), 4.0);
+        assert_eq!(font.get_char_width('\u{1F600}'), 0.0);
+        assert_eq!(font.get_string_width("Ai A"), 14.0);
+
+        // A sheet of twice the resolution measures the same
+        let doubled = image::imageops::resize(&sheet, 256, 256, image::imageops::FilterType::Nearest);
+        assert_eq!(BitmapFont::from_image(&doubled), font);
+    }
+
+    #[test]
+    fn lines_advance_by_glyph_width() {
+        let font = BitmapFont::monospace();
+        let glyphs = font.layout_line("a b", Vec2::new(-10.0, 5.0), Vec3::one());
+        assert_eq!(glyphs.len(), 2);
+        assert_eq!(glyphs[0].min, Vec2::new(-10.0, 5.0));
+        assert_eq!(glyphs[1].min, Vec2::new(0.0, 5.0));
+        assert_eq!(glyphs[0].max - glyphs[0].min, Vec2::broadcast(GLYPH_SIZE));
+        let (uv_min, uv_max) = BitmapFont::glyph_uv_bounds(BitmapFont::glyph_index('a').unwrap());
+        assert_eq!((glyphs[0].uv_min, glyphs[0].uv_max), (uv_min, uv_max));
+        assert_eq!(BitmapFont::glyph_index('a'), Some(97));
+    }
+
+    #[test]
+    fn formatting_codes_color_and_style_text() {
+        let font = BitmapFont::monospace();
+        assert_eq!(color_of_code('c'), Some(Vec3::new(1.0, 85.0 / 255.0, 85.0 / 255.0)));
+        assert_eq!(color_of_code('6'), Some(Vec3::new(1.0, 170.0 / 255.0, 0.0)));
+        assert_eq!(color_of_code('z'), None);
+        assert_eq!(strip_formatting("§cRed §lbold§r"), "Red bold");
+        assert_eq!(font.get_string_width("§cab"), 12.0);
+        assert_eq!(font.get_string_width("§lab"), 14.0);
+
+        let glyphs = font.layout_line("a§9b§lc§rd§oe", Vec2::zero(), Vec3::one());
+        let colors: Vec<Vec3> = glyphs.iter().map(|glyph| glyph.color).collect();
+        let blue = color_of_code('9').unwrap();
+        // The bold c is drawn twice, a unit apart
+        assert_eq!(colors, vec![Vec3::one(), blue, blue, blue, Vec3::one(), Vec3::one()]);
+        assert_eq!(glyphs[3].min.x - glyphs[2].min.x, 1.0);
+        assert_eq!(glyphs[4].min.x, 19.0);
+        assert_eq!(glyphs[5].skew, ITALIC_SKEW);
+        assert_eq!(glyphs[5].corners()[0].0, Vec2::new(25.0 + ITALIC_SKEW, 0.0));
+
+        let shadowed = font.layout_shadowed_line("§ca", Vec2::zero(), Vec3::one());
+        assert_eq!(shadowed[0].min, Vec2::one());
+        assert_eq!(shadowed[0].color, color_of_code('c').unwrap() * 0.25);
+        assert_eq!(shadowed[1].min, Vec2::zero());
+    }
+
+    #[test]
+    fn text_wraps_between_words_and_keeps_its_formatting() {
+        let font = BitmapFont::monospace();
+        // Words of three glyphs are 18 units wide, two with a space between them are 40
+        assert_eq!(font.wrap("abc def ghi", 40.0), vec!["abc def", "ghi"]);
+        assert_eq!(font.wrap("abc def ghi", 39.0), vec!["abc", "def", "ghi"]);
+        assert_eq!(font.wrap("abcdefgh", 24.0), vec!["abcd", "efgh"]);
+        assert_eq!(font.wrap("ab\n\ncd", 100.0), vec!["ab", "", "cd"]);
+        assert_eq!(font.wrap("§cabc §ldef", 22.0), vec!["§cabc", "§c§ldef"]);
+        assert_eq!(font.wrap("§cab §rcd", 12.0), vec!["§cab", "§c§rcd"]);
+        assert_eq!(font.wrap("§c abcd", 22.0), vec!["§cabc", "§cd"]);
+        assert_eq!(active_formatting("§l§cred§oitalic"), "§c§o");
+
+        let glyphs = font.layout_wrapped("abc def", Vec2::zero(), 20.0, Vec3::one(), false);
+        assert_eq!(glyphs.len(), 6);
+        assert_eq!(glyphs[3].min, Vec2::new(0.0, LINE_HEIGHT));
+    }
+
+    #[test]
+    fn font_sheets_load_from_resource_packs() {
+        let mut asset_loader = AssetLoader::new();
+        assert!(matches!(BitmapFont::load(&asset_loader), Err(FontError::Missing)));
+
+        let mut png = vec![];
+        let mut sheet = RgbaImage::new(128, 128);
+        // One column of an 'A'
+        sheet.put_pixel(8, 32, image::Rgba([255; 4]));
+        image::DynamicImage::ImageRgba8(sheet).write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+        asset_loader.try_load_file("textures", "minecraft", "font/ascii", "png", &png);
+        let (font, sheet) = BitmapFont::load(&asset_loader).unwrap();
+        assert_eq!(sheet.dimensions(), (128, 128));
+        assert_eq!(font.get_char_width('A'), 2.0);
+
+        let mut wide = vec![];
+        image::DynamicImage::ImageRgba8(RgbaImage::new(128, 64)).write_to(&mut std::io::Cursor::new(&mut wide), image::ImageOutputFormat::Png).unwrap();
+        asset_loader.try_load_file("textures", "minecraft", "font/default", "png", &wide);
+        assert!(matches!(BitmapFont::load(&asset_loader), Err(FontError::InvalidSize { width: 128, height: 64 })));
+    }
+}
@@ -11,6 +11,7 @@ pub mod resource_loader;
 pub mod models;
 pub mod minecraft;
 pub mod sprites;
+pub mod font;
 
 use std::path::PathBuf;
 use winit::window::Icon;
@@ -3,9 +3,10 @@ use std::{path::PathBuf, io::{Cursor, Read}, ffi::OsStr};
 use image::DynamicImage;
 use rustc_hash::FxHashMap as HashMap;
 
+use crate::models::entity::EntityModel;
 use crate::resource_loader::{ResourceSystem, ResourceCategory};
 
-use super::{filetypes::{MCModel, MCBlockstateType, mcmeta::{MCMeta, atlas::SpriteAtlas}, MCAtlasConfig}, identifier::Identifier};
+use super::{filetypes::{MCModel, MCBlockstateType, mcmeta::{MCMeta, atlas::SpriteAtlas, pack::PackLanguage}, MCAtlasConfig}, identifier::Identifier, lang::{self, LangTable, Translator}};
 
 type AssetResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -25,11 +26,16 @@ pub struct AssetLoader {
     categories: Vec<ResourceCategory>,
 
     model_files: HashMap<Identifier, MCModel>,
+    /// Models under `models/entity`, made of boxes instead of elements
+    entity_models: HashMap<Identifier, EntityModel>,
     blockstate_files: HashMap<Identifier, MCBlockstateType>,
     sprites: HashMap<Identifier, DynamicImage>,
     mcmeta: HashMap<Identifier, MCMeta>,
     shaders: HashMap<Identifier, String>,
-    atlases: HashMap<Identifier, MCAtlasConfig>
+    atlases: HashMap<Identifier, MCAtlasConfig>,
+    /// Locale -> translations, merged across namespaces and packs
+    lang: HashMap<String, LangTable>,
+    languages: HashMap<String, PackLanguage>,
 }
 
 impl AssetLoader {
@@ -42,16 +48,19 @@ impl AssetLoader {
                 ResourceCategory::new("textures", vec!["png", "mcmeta"]),
                 ResourceCategory::new("models", vec!["json"]),
                 ResourceCategory::new("blockstates", vec!["json"]),
-                ResourceCategory::new("lang", vec!["json"]),
+                ResourceCategory::new("lang", vec!["json", "lang"]),
                 ResourceCategory::new("shaders", vec!["wgsl"]),
                 ResourceCategory::new("atlases", vec!["json"]),
             ],
             model_files: HashMap::default(),
+            entity_models: HashMap::default(),
             blockstate_files: HashMap::default(),
             sprites,
             mcmeta: HashMap::default(),
             shaders: HashMap::default(),
             atlases: HashMap::default(),
+            lang: HashMap::default(),
+            languages: HashMap::default(),
         }
     }
 
@@ -65,6 +74,10 @@ impl AssetLoader {
         &self.model_files
     }
     
+    pub fn entity_models(&self) -> &HashMap<Identifier, EntityModel> {
+        &self.entity_models
+    }
+
     pub fn blockstates(&self) -> &HashMap<Identifier, MCBlockstateType> {
         &self.blockstate_files
     }
@@ -85,12 +98,34 @@ impl AssetLoader {
         &self.atlases
     }
 
+    pub fn lang(&self) -> &HashMap<String, LangTable> {
+        &self.lang
+    }
+
+    pub fn languages(&self) -> &HashMap<String, PackLanguage> {
+        &self.languages
+    }
+
+    /// Create a translator over every loaded lang file, translating into `locale`
+    pub fn translator(&self, locale: &str) -> Translator {
+        Translator::new(self.lang.clone(), self.languages.clone(), locale)
+    }
+
     fn try_load_model(&mut self, namespace: &str, file_name: &str, contents: &[u8]) {
-        if let Ok(model) = serde_json::from_slice(contents) {
+        if file_name.starts_with("entity/") {
+            self.try_load_entity_model(namespace, file_name, contents);
+        } else if let Ok(model) = serde_json::from_slice(contents) {
             self.model_files.insert(Identifier::new(namespace, file_name), model);
         }
     }
 
+    fn try_load_entity_model(&mut self, namespace: &str, file_name: &str, contents: &[u8]) {
+        match EntityModel::from_json(&String::from_utf8_lossy(contents)) {
+            Ok(model) => { self.entity_models.insert(Identifier::new(namespace, file_name), model); },
+            Err(e) => log::warn!("Failed to load entity model {}:{}: {}", namespace, file_name, e),
+        }
+    }
+
     fn try_load_blockstate(&mut self, namespace: &str, file_name: &str, contents: &[u8]) {
         if let Ok(blockstate) = serde_json::from_slice(contents) {
             self.blockstate_files.insert(Identifier::new(namespace, file_name), blockstate);
@@ -116,8 +151,15 @@ impl AssetLoader {
         Ok(())
     }
 
-    fn try_load_lang(&mut self, namespace: &str, file_name: &str, contents: &[u8]) {
-
+    /// Later packs override keys from earlier ones, namespaces share a single table per locale
+    fn try_load_lang(&mut self, _namespace: &str, file_name: &str, file_extension: &str, contents: &[u8]) -> AssetResult {
+        let table = match file_extension {
+            "lang" => lang::parse_legacy_lang(&String::from_utf8_lossy(contents)),
+            _ => lang::parse_json_lang(contents)?,
+        };
+        let locale = lang::normalize_locale(file_name);
+        self.lang.entry(locale).or_default().extend(table);
+        Ok(())
     }
 
     fn try_load_atlas(&mut self, namespace: &str, file_name: &str, contents: &[u8]) {
@@ -165,7 +207,9 @@ impl AssetLoader {
             } else if extension.eq_ignore_ascii_case("txt") {
                 let bytes_read = file.read_to_end(&mut buffer)?;
             } else if extension.eq_ignore_ascii_case("lang") {
-
+                file.read_to_end(&mut buffer)?;
+                let stem = enclosed_name.file_stem().map(crate::util::os_str_to_string).ok_or("Stem could not be acquired")?;
+                self.try_load_lang("minecraft", &stem, "lang", &buffer)?;
             }
         }
 
@@ -195,9 +239,19 @@ impl ResourceSystem for AssetLoader {
             },
             "blockstates" => { self.try_load_blockstate(namespace, file_name, contents); },
             "shaders" => { self.try_load_shaders(namespace, file_name, contents); }
-            "lang" => { self.try_load_lang(namespace, file_name, contents); },
+            "lang" => {
+                if let Err(e) = self.try_load_lang(namespace, file_name, file_extension, contents) {
+                    log::warn!("Failed to load lang file {}:{}: {}", namespace, file_name, e);
+                }
+            },
             "atlases" => { self.try_load_atlas(namespace, file_name, contents); },
             _ => {},
         }
     }
+    fn try_load_pack_meta(&mut self, contents: &[u8]) {
+        let languages = serde_json::from_slice::<MCMeta>(contents).ok().and_then(MCMeta::as_pack_languages);
+        for (locale, language) in languages.into_iter().flatten() {
+            self.languages.insert(lang::normalize_locale(&locale), language);
+        }
+    }
 }
@@ -51,24 +51,28 @@ pub fn blocks() -> Vec<Block> {
                 .transparent(true)
                 .side_cull_fn(non_full_cull)
                 .full_block(false)
+                .properties(&vec![("level", "minecraft:count_7"), ("falling", "minecraft:boolean")])
                 .build(),
             BlockFactory::new("still_water")
                 .hardness(100.0)
                 .transparent(true)
                 .side_cull_fn(non_full_cull)
                 .full_block(false)
+                .properties(&vec![("level", "minecraft:count_7"), ("falling", "minecraft:boolean")])
                 .build(),
             BlockFactory::new("flowing_lava")
                 .hardness(0.0)
                 .transparent(true)
                 .side_cull_fn(non_full_cull)
                 .full_block(false)
+                .properties(&vec![("level", "minecraft:count_7"), ("falling", "minecraft:boolean")])
                 .build(),
             BlockFactory::new("still_lava")
                 .hardness(100.0)
                 .transparent(true)
                 .side_cull_fn(non_full_cull)
                 .full_block(false)
+                .properties(&vec![("level", "minecraft:count_7"), ("falling", "minecraft:boolean")])
                 .build(),
             BlockFactory::new("sand")
                 .hardness(0.5)
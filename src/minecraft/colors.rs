@@ -0,0 +1,194 @@
+use image::RgbaImage;
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::Vec3;
+
+use crate::{models::condition::StatePropertyNames, world::biome::Climate};
+
+use super::identifier::Identifier;
+
+/// Where packs keep the colormaps, newer packs first
+pub const GRASS_COLORMAP_SPRITES: [&str; 2] = ["minecraft:colormap/grass", "minecraft:misc/grasscolor.png"];
+pub const FOLIAGE_COLORMAP_SPRITES: [&str; 2] = ["minecraft:colormap/foliage", "minecraft:misc/foliagecolor.png"];
+
+pub const GRASS_FALLBACK_COLOR: u32 = 0x91BD59;
+pub const FOLIAGE_FALLBACK_COLOR: u32 = 0x48B518;
+pub const BIRCH_FOLIAGE_COLOR: u32 = 0x80A755;
+pub const SPRUCE_FOLIAGE_COLOR: u32 = 0x619961;
+pub const WATER_COLOR: u32 = 0x3F76E4;
+
+/// An 0xRRGGBB color as rgb between 0 and 1
+pub fn rgb(color: u32) -> Vec3 {
+    Vec3::new(((color >> 16) & 0xFF) as f32, ((color >> 8) & 0xFF) as f32, (color & 0xFF) as f32) / 255.0
+}
+
+/**
+ * A colormap like grass.png, hot and humid climates are at the top left corner.
+ * Humidity is scaled by temperature so only the lower left triangle is used
+ */
+#[derive(Clone, Debug)]
+pub struct Colormap {
+    image: Option<RgbaImage>,
+    fallback: Vec3,
+}
+
+impl Colormap {
+    pub fn new(image: RgbaImage, fallback: Vec3) -> Self {
+        let image = (image.width() > 0 && image.height() > 0).then_some(image);
+        Self { image, fallback }
+    }
+
+    /// A colormap missing from the loaded packs, every climate has the same color
+    pub fn fallback(fallback: Vec3) -> Self {
+        Self { image: None, fallback }
+    }
+
+    pub fn lookup(&self, climate: Climate) -> Vec3 {
+        let Some(image) = &self.image else { return self.fallback; };
+        let humidity = climate.humidity * climate.temperature;
+        let x = ((1.0 - climate.temperature) * (image.width() - 1) as f32) as u32;
+        let y = ((1.0 - humidity) * (image.height() - 1) as f32) as u32;
+        let pixel = image.get_pixel(x, y);
+        Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.0
+    }
+}
+
+/// How the tinted faces of a block are colored
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockColorProvider {
+    Grass,
+    Foliage,
+    /// Fluids ignore tint indices, every face of water is colored
+    Water,
+    Fixed(Vec3),
+    /// Picks a provider by the value of a property, like leaves by their tree
+    ByProperty { name: String, values: HashMap<String, BlockColorProvider>, default: Box<BlockColorProvider> },
+}
+
+impl BlockColorProvider {
+    pub fn depends_on_properties(&self) -> bool {
+        matches!(self, Self::ByProperty { .. })
+    }
+
+    /// The provider for the properties of a blockstate, never [`BlockColorProvider::ByProperty`]
+    pub fn resolve(&self, properties: &StatePropertyNames) -> &Self {
+        match self {
+            Self::ByProperty { name, values, default } => {
+                properties.get(name).and_then(|value| values.get(value)).unwrap_or(default).resolve(properties)
+            },
+            _ => self,
+        }
+    }
+
+    pub fn applies_to(&self, tint_index: i32) -> bool {
+        matches!(self, Self::Water) || tint_index >= 0
+    }
+}
+
+/// The colormaps of the loaded packs, along with the color provider of every tinted block
+#[derive(Clone, Debug)]
+pub struct BlockColors {
+    grass: Colormap,
+    foliage: Colormap,
+    water: Vec3,
+    providers: HashMap<Identifier, BlockColorProvider>,
+}
+
+impl Default for BlockColors {
+    fn default() -> Self {
+        Self {
+            grass: Colormap::fallback(rgb(GRASS_FALLBACK_COLOR)),
+            foliage: Colormap::fallback(rgb(FOLIAGE_FALLBACK_COLOR)),
+            water: rgb(WATER_COLOR),
+            providers: HashMap::default(),
+        }
+    }
+}
+
+impl BlockColors {
+    pub fn with_provider(mut self, block: Identifier, provider: BlockColorProvider) -> Self {
+        self.providers.insert(block, provider);
+        self
+    }
+
+    pub fn with_provider_nc(&mut self, block: Identifier, provider: BlockColorProvider) {
+        self.providers.insert(block, provider);
+    }
+
+    pub fn set_colormaps(&mut self, grass: Colormap, foliage: Colormap) {
+        self.grass = grass;
+        self.foliage = foliage;
+    }
+
+    /// Go back to the fallback colors, the providers are kept
+    pub fn clear_colormaps(&mut self) {
+        let defaults = Self::default();
+        self.set_colormaps(defaults.grass, defaults.foliage);
+    }
+
+    pub fn get_provider(&self, block: &Identifier) -> Option<&BlockColorProvider> {
+        self.providers.get(block)
+    }
+
+    /// The color of a resolved provider in a climate
+    pub fn color(&self, provider: &BlockColorProvider, climate: Climate) -> Vec3 {
+        match provider {
+            BlockColorProvider::Grass => self.grass.lookup(climate),
+            BlockColorProvider::Foliage => self.foliage.lookup(climate),
+            BlockColorProvider::Water => self.water,
+            BlockColorProvider::Fixed(color) => *color,
+            BlockColorProvider::ByProperty { .. } => Vec3::one(),
+        }
+    }
+}
+
+/// The tinted blocks of the game
+pub fn block_color_providers() -> Vec<(Identifier, BlockColorProvider)> {
+    let leaves = BlockColorProvider::ByProperty {
+        name: String::from("tree"),
+        values: [
+            (String::from("spruce"), BlockColorProvider::Fixed(rgb(SPRUCE_FOLIAGE_COLOR))),
+            (String::from("birch"), BlockColorProvider::Fixed(rgb(BIRCH_FOLIAGE_COLOR))),
+        ].into_iter().collect(),
+        default: Box::new(BlockColorProvider::Foliage),
+    };
+    vec![
+        (Identifier::from_str("minecraft:grass"), BlockColorProvider::Grass),
+        (Identifier::from_str("minecraft:tall_grass"), BlockColorProvider::Grass),
+        (Identifier::from_str("minecraft:leaves"), leaves),
+        (Identifier::from_str("minecraft:flowing_water"), BlockColorProvider::Water),
+        (Identifier::from_str("minecraft:still_water"), BlockColorProvider::Water),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn colormap_lookup_uses_vanilla_corners() {
+        let mut image = RgbaImage::new(256, 256);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(127, 191, Rgba([0, 255, 0, 255]));
+        image.put_pixel(255, 255, Rgba([0, 0, 255, 255]));
+        let colormap = Colormap::new(image, Vec3::zero());
+        assert_eq!(colormap.lookup(Climate::new(1.0, 1.0)), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(colormap.lookup(Climate::new(0.5, 0.5)), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(colormap.lookup(Climate::new(0.0, 1.0)), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(Colormap::fallback(rgb(0xFF0000)).lookup(Climate::default()), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn leaves_are_colored_by_tree() {
+        let colors = block_color_providers().into_iter()
+            .fold(BlockColors::default(), |colors, (block, provider)| colors.with_provider(block, provider));
+        let leaves = colors.get_provider(&Identifier::from_str("minecraft:leaves")).unwrap();
+        assert!(leaves.depends_on_properties());
+        let tree = |tree: &str| [(String::from("tree"), String::from(tree)), (String::from("decay"), String::from("0"))].into_iter().collect();
+        assert_eq!(colors.color(leaves.resolve(&tree("birch")), Climate::default()), rgb(BIRCH_FOLIAGE_COLOR));
+        assert_eq!(leaves.resolve(&tree("oak")), &BlockColorProvider::Foliage);
+        assert!(BlockColorProvider::Water.applies_to(-1));
+        assert!(!BlockColorProvider::Grass.applies_to(-1));
+    }
+}
@@ -17,7 +17,7 @@ pub mod mcmeta {
         Pack {
             pack: pack::PackInformation,
             language: Option<HashMap<String, pack::PackLanguage>>,
-            filter: pack::PackFilter,
+            filter: Option<pack::PackFilter>,
         },
         Animation {
             animation: animation::Animation
@@ -48,9 +48,15 @@ pub mod mcmeta {
         pub fn as_villager(self) -> Option<Villager> {
             match self { Self::Villager { villager } => Some(villager), _ => { None } }
         }
+        pub fn as_pack_languages(self) -> Option<HashMap<String, pack::PackLanguage>> {
+            match self { Self::Pack { language, .. } => language, _ => { None } }
+        }
         pub fn as_properties(self) -> Option<Propterties> {
             match self { Self::Properties { texture } => Some(texture), _ => { None } }
         }
+        pub fn get_properties(&self) -> Option<&Propterties> {
+            match self { Self::Properties { texture } => Some(texture), _ => { None } }
+        }
     }
 
     pub mod pack {
@@ -70,13 +76,29 @@ pub mod mcmeta {
             Tag(Value),
         }
 
-        #[derive(Serialize, Deserialize)]
+        #[derive(Clone, Debug, Serialize, Deserialize)]
         pub struct PackLanguage {
             name: String,
             region: String,
+            #[serde(alias = "bidirectional")]
             bidirection: Option<bool>,
         }
 
+        impl PackLanguage {
+            pub fn name(&self) -> &str {
+                &self.name
+            }
+
+            pub fn region(&self) -> &str {
+                &self.region
+            }
+
+            /// Language is written right to left, default false
+            pub fn bidirectional(&self) -> bool {
+                self.bidirection.unwrap_or(false)
+            }
+        }
+
         #[derive(Serialize, Deserialize)]
         pub struct PackFilter {
             block: Vec<FilterPattern>
@@ -215,7 +237,23 @@ pub mod mcmeta {
             blur: Option<bool>,
             /// Texture is streatched instead of tiled, default false
             clamp: Option<bool>,
-            mipmaps: Vec<u32>,
+            /// The mip levels the texture is filtered into, levels not listed are point sampled
+            /// from the level above, default all levels
+            mipmaps: Option<Vec<u32>>,
+        }
+
+        impl Propterties {
+            pub fn blur(&self) -> bool {
+                self.blur.unwrap_or(false)
+            }
+
+            pub fn clamp(&self) -> bool {
+                self.clamp.unwrap_or(false)
+            }
+
+            pub fn mipmaps(&self) -> Option<&Vec<u32>> {
+                self.mipmaps.as_ref()
+            }
         }
     }
 }
@@ -392,11 +430,18 @@ impl MCModelFace {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MCModelDisplay {
+    #[serde(default)]
     pub rotation: [f32; 3],
+    #[serde(default)]
     pub translation: [f32; 3],
+    #[serde(default = "MCModelDisplay::default_scale")]
     pub scale: [f32; 3],
 }
 
+impl MCModelDisplay {
+    fn default_scale() -> [f32; 3] { [1.0, 1.0, 1.0] }
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum MCBlockstateType {
     #[serde(rename="variants")]
@@ -0,0 +1,225 @@
+use rustc_hash::FxHashMap as HashMap;
+
+use super::{filetypes::mcmeta::pack::PackLanguage, identifier::Identifier};
+
+/// Translation key -> translated text
+pub type LangTable = HashMap<String, String>;
+
+/// The locale every fallback chain ends in, the only locale a vanilla jar is guaranteed to contain
+pub const DEFAULT_LOCALE: &str = "en_us";
+
+/// Beta era jars ship extra legacy lang files whose name is not a locale
+const LEGACY_LANG_ALIASES: &[(&str, &str)] = &[("stats_us", "en_us")];
+
+/// Locales are compared lowercase with an underscore, "en_US" and "en-us" both become "en_us"
+pub fn normalize_locale(locale: &str) -> String {
+    let locale = locale.to_ascii_lowercase().replace('-', "_");
+    LEGACY_LANG_ALIASES.iter()
+        .find(|(alias, _)| *alias == locale)
+        .map(|(_, target)| target.to_string())
+        .unwrap_or(locale)
+}
+
+/**
+ * Parse a legacy `key=value` .lang file.
+ * Blank lines and lines starting with # are skipped, the key ends at the first =
+ */
+pub fn parse_legacy_lang(contents: &str) -> LangTable {
+    contents.lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .collect()
+}
+
+/// Parse a modern flat json lang file
+pub fn parse_json_lang(contents: &[u8]) -> Result<LangTable, serde_json::Error> {
+    serde_json::from_slice(contents)
+}
+
+/**
+ * Substitute the arguments into a java style format string.
+ * Supports `%s`, `%d`, positional `%1$s` and the escape `%%`,
+ * specifiers without a matching argument are left as is
+ */
+pub fn format_translation(template: &str, args: &[&str]) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut next_arg = 0;
+    let mut chars = template.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        let mut digits = String::new();
+        while let Some((_, d)) = chars.peek().filter(|(_, d)| d.is_ascii_digit()) {
+            digits.push(*d);
+            chars.next();
+        }
+        let positional = !digits.is_empty() && chars.peek().map(|(_, d)| *d) == Some('$');
+        if positional {
+            chars.next();
+        }
+        match chars.next() {
+            Some((_, '%')) if digits.is_empty() => result.push('%'),
+            Some((end, 's' | 'd')) if digits.is_empty() || positional => {
+                let index = if positional {
+                    digits.parse::<usize>().unwrap_or(0).checked_sub(1)
+                } else {
+                    next_arg += 1;
+                    Some(next_arg - 1)
+                };
+                match index.and_then(|index| args.get(index)) {
+                    Some(arg) => result.push_str(arg),
+                    None => result.push_str(&template[start..=end]),
+                }
+            },
+            Some((end, _)) => result.push_str(&template[start..=end]),
+            None => result.push_str(&template[start..]),
+        }
+    }
+    result
+}
+
+/// Every loaded locale along with the chain of locales used to find a key
+pub struct Translator {
+    locales: HashMap<String, LangTable>,
+    languages: HashMap<String, PackLanguage>,
+    fallback_chain: Vec<String>,
+}
+
+impl Translator {
+    pub fn new(locales: HashMap<String, LangTable>, languages: HashMap<String, PackLanguage>, locale: &str) -> Self {
+        let mut translator = Self { locales, languages, fallback_chain: vec![] };
+        translator.set_locale(locale);
+        translator
+    }
+
+    /**
+     * Select the locale to translate into.
+     * Keys missing from it are looked up in the primary locale of the same language (de_at -> de_de),
+     * then in the default locale
+     */
+    pub fn set_locale(&mut self, locale: &str) {
+        let locale = normalize_locale(locale);
+        let mut chain = vec![locale.clone()];
+        if let Some((language, _)) = locale.split_once('_') {
+            let primary = format!("{language}_{language}");
+            if self.locales.contains_key(&primary) {
+                chain.push(primary);
+            }
+        }
+        chain.push(DEFAULT_LOCALE.to_string());
+        chain.dedup();
+        self.fallback_chain = chain;
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.fallback_chain[0]
+    }
+
+    pub fn fallback_chain(&self) -> &[String] {
+        &self.fallback_chain
+    }
+
+    /// Languages declared by the loaded pack.mcmeta files, keyed by normalized locale
+    pub fn languages(&self) -> &HashMap<String, PackLanguage> {
+        &self.languages
+    }
+
+    /// Whether the selected locale is written right to left
+    pub fn is_bidirectional(&self) -> bool {
+        self.languages.get(self.locale()).is_some_and(|language| language.bidirectional())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fallback_chain.iter()
+            .filter_map(|locale| self.locales.get(locale))
+            .find_map(|table| table.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn has_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Translate a key and format the arguments into it, unknown keys are returned untranslated
+    pub fn translate(&self, key: &str, args: &[&str]) -> String {
+        match self.get(key) {
+            Some(template) => format_translation(template, args),
+            None => key.to_string(),
+        }
+    }
+
+    /// The display name of a block, tries the modern `block.<namespace>.<name>` key then the legacy `tile.<name>.name`
+    pub fn block_name(&self, block: &Identifier) -> String {
+        let modern = format!("block.{}.{}", block.get_namespace(), block.get_name().replace('/', "."));
+        let legacy = format!("tile.{}.name", block.get_name());
+        self.get(&modern).or_else(|| self.get(&legacy)).map(str::to_string).unwrap_or(modern)
+    }
+
+    /// The display name of an item, tries the modern `item.<namespace>.<name>` key then the legacy `item.<name>.name`
+    pub fn item_name(&self, item: &Identifier) -> String {
+        let modern = format!("item.{}.{}", item.get_namespace(), item.get_name().replace('/', "."));
+        let legacy = format!("item.{}.name", item.get_name());
+        self.get(&modern).or_else(|| self.get(&legacy)).map(str::to_string).unwrap_or(modern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(entries: &[(&str, &str)]) -> LangTable {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn legacy_lang_parsing() {
+        let lang = parse_legacy_lang("# comment\r\n\nmenu.quit=Quit Game\r\nchat.type=<%s> a=b\n");
+        assert_eq!(lang.len(), 2);
+        assert_eq!(lang["menu.quit"], "Quit Game");
+        assert_eq!(lang["chat.type"], "<%s> a=b");
+    }
+
+    #[test]
+    fn locale_normalization() {
+        assert_eq!(normalize_locale("en_US"), "en_us");
+        assert_eq!(normalize_locale("pt-BR"), "pt_br");
+        assert_eq!(normalize_locale("stats_US"), "en_us");
+    }
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_translation("%s joined %s", &["a", "b"]), "a joined b");
+        assert_eq!(format_translation("%2$s then %1$s", &["a", "b"]), "b then a");
+        assert_eq!(format_translation("100%% %d", &["5"]), "100% 5");
+        assert_eq!(format_translation("%s and %s", &["a"]), "a and %s");
+        assert_eq!(format_translation("trailing %", &[]), "trailing %");
+    }
+
+    #[test]
+    fn fallback_chain() {
+        let mut locales = HashMap::default();
+        locales.insert("en_us".to_string(), table(&[("a", "en a"), ("b", "en b"), ("c", "en c")]));
+        locales.insert("de_de".to_string(), table(&[("a", "de a"), ("b", "de b")]));
+        locales.insert("de_at".to_string(), table(&[("a", "at a")]));
+        let translator = Translator::new(locales, HashMap::default(), "de_AT");
+        assert_eq!(translator.fallback_chain(), &["de_at", "de_de", "en_us"]);
+        assert_eq!(translator.translate("a", &[]), "at a");
+        assert_eq!(translator.translate("b", &[]), "de b");
+        assert_eq!(translator.translate("c", &[]), "en c");
+        assert_eq!(translator.translate("missing.key", &[]), "missing.key");
+    }
+
+    #[test]
+    fn block_names() {
+        let mut locales = HashMap::default();
+        locales.insert("en_us".to_string(), table(&[("tile.stone.name", "Stone"), ("block.minecraft.dirt", "Dirt")]));
+        let translator = Translator::new(locales, HashMap::default(), DEFAULT_LOCALE);
+        assert_eq!(translator.block_name(&Identifier::from_str("minecraft:stone")), "Stone");
+        assert_eq!(translator.block_name(&Identifier::from_str("minecraft:dirt")), "Dirt");
+        assert_eq!(translator.block_name(&Identifier::from_str("minecraft:glass")), "block.minecraft.glass");
+    }
+}
@@ -4,3 +4,6 @@ pub mod registry;
 pub mod prot14;
 pub mod filetypes;
 pub mod asset_loader;
+pub mod lang;
+pub mod colors;
+pub mod skins;
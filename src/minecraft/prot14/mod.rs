@@ -2,7 +2,7 @@ use rustc_hash::FxHashMap as HashMap;
 use serde_derive::Deserialize;
 use serde_json::Value;
 
-use super::{registry::Registry, identifier::Identifier};
+use super::{registry::FrozenRegistry, identifier::Identifier};
 
 #[derive(Deserialize)]
 struct JsonBlockMap {
@@ -24,7 +24,7 @@ struct JsonBlockMapping {
 /// Jukebox? ()
 /// Portal -> Orientation (90)
 /// Locked Chest -> Rotation (95)
-pub fn generate_block_to_state_map(registry: &Registry) -> HashMap<u16, usize> {
+pub fn generate_block_to_state_map(registry: &FrozenRegistry) -> HashMap<u16, usize> {
     let blocks = registry.get_block_register();
     let mut map = HashMap::default();
     let mapping_data: JsonBlockMapping = serde_json::from_str(std::fs::read_to_string("block_id_map.json").unwrap_or("".to_string()).as_str()).expect("No valid block_id_map.json");
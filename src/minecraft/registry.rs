@@ -1,9 +1,12 @@
-use std::rc::Rc;
+use std::marker::PhantomData;
+use std::sync::Arc;
 
 use crate::block::BlockState;
 use crate::block::properties::PropertyDefinition;
-use crate::models::model::BakedModel;
+use crate::minecraft::colors::{BlockColorProvider, BlockColors};
+use crate::models::{item::ItemModel, model::BlockstateModel};
 use crate::sprites::Sprite;
+use crate::world::fluid::{Fluid, FluidState};
 use crate::{block::Block, minecraft::identifier::Identifier};
 
 use rustc_hash::FxHashMap as HashMap;
@@ -16,7 +19,11 @@ pub type SpriteRegister = HashMap<Identifier, Sprite>;
 pub type BlockRegister = Register<Block>;
 pub type PropertyRegister = Register<PropertyDefinition>;
 pub type BlockStateRegister = Register<BlockState>;
-pub type ModelRegister = HashMap<Identifier, BakedModel>;
+pub type ModelRegister = HashMap<Identifier, BlockstateModel>;
+/// Item models by the identifier of their model file, like minecraft:item/diamond
+pub type ItemModelRegister = HashMap<Identifier, ItemModel>;
+pub type BlockHandle = Handle<Block>;
+pub type StateHandle = Handle<BlockState>;
 
 pub struct Registry {
     // items: Vec<Item>,
@@ -25,6 +32,8 @@ pub struct Registry {
     properties: PropertyRegister,
     blockstates: BlockStateRegister,
     models: ModelRegister,
+    item_models: ItemModelRegister,
+    block_colors: BlockColors,
     // dimension: Vec<Dimension>,
 }
 
@@ -39,7 +48,9 @@ impl Registry {
         let properties = Register::<PropertyDefinition>::new(256);
         let blockstates = Register::<BlockState>::new(256);
         let models = HashMap::default();
-        Self { blocks, sprites: textures, properties, blockstates, models }
+        let item_models = HashMap::default();
+        let block_colors = BlockColors::default();
+        Self { blocks, sprites: textures, properties, blockstates, models, item_models, block_colors }
     }
 
     pub fn get_block_register(&self) -> &BlockRegister {
@@ -82,9 +93,164 @@ impl Registry {
         &mut self.models
     }
 
+    pub fn get_item_model_register(&self) -> &ItemModelRegister {
+        &self.item_models
+    }
+
+    pub fn get_item_model_register_mut(&mut self) -> &mut ItemModelRegister {
+        &mut self.item_models
+    }
+
+    pub fn get_block_colors(&self) -> &BlockColors {
+        &self.block_colors
+    }
+
+    pub fn get_block_colors_mut(&mut self) -> &mut BlockColors {
+        &mut self.block_colors
+    }
+
     pub fn reset(&mut self) {
         self.blocks.clear();
     }
+
+    /**
+     * Stop registering and baking, the models and color providers are looked up for every blockstate once
+     * so the frozen registry can index them by handle
+     */
+    pub fn freeze(mut self) -> FrozenRegistry {
+        let state_models = self.blockstates.get_elements().iter()
+            .map(|state| self.models.remove(state.get_state_identifier()))
+            .collect();
+        let state_colors = self.blockstates.get_elements().iter().map(|state| {
+            let provider = self.block_colors.get_provider(state.get_block_identifier())?;
+            let property_names = if provider.depends_on_properties() { state.get_property_names(&self.properties) } else { Default::default() };
+            Some(provider.resolve(&property_names).clone())
+        }).collect();
+        let state_fluids = self.blockstates.get_elements().iter().map(|state| {
+            Fluid::from_block(state.get_block_identifier())?;
+            FluidState::from_properties(state.get_block_identifier(), &state.get_property_names(&self.properties))
+        }).collect();
+        FrozenRegistry {
+            blocks: self.blocks,
+            sprites: self.sprites,
+            properties: self.properties,
+            blockstates: self.blockstates,
+            state_models,
+            state_colors,
+            state_fluids,
+            item_models: self.item_models,
+            block_colors: self.block_colors,
+        }
+    }
+}
+
+/**
+ * The registry once content is registered and resources are baked, it can't change anymore.
+ * Unlike the [`Registry`] it is built from, it can be shared with other threads for meshing, lighting and ticking
+ */
+pub struct FrozenRegistry {
+    blocks: BlockRegister,
+    sprites: SpriteRegister,
+    properties: PropertyRegister,
+    blockstates: BlockStateRegister,
+    /// Indexed by the blockstate
+    state_models: Vec<Option<BlockstateModel>>,
+    /// Indexed by the blockstate, already resolved for its properties
+    state_colors: Vec<Option<BlockColorProvider>>,
+    /// Indexed by the blockstate, fluids are tessellated from these instead of a model
+    state_fluids: Vec<Option<FluidState>>,
+    item_models: ItemModelRegister,
+    block_colors: BlockColors,
+}
+
+impl FrozenRegistry {
+    pub fn get_block_register(&self) -> &BlockRegister {
+        &self.blocks
+    }
+
+    pub fn get_sprite_register(&self) -> &SpriteRegister {
+        &self.sprites
+    }
+
+    pub fn get_property_register(&self) -> &PropertyRegister {
+        &self.properties
+    }
+
+    pub fn get_blockstate_register(&self) -> &BlockStateRegister {
+        &self.blockstates
+    }
+
+    pub fn get_item_model_register(&self) -> &ItemModelRegister {
+        &self.item_models
+    }
+
+    pub fn get_block_colors(&self) -> &BlockColors {
+        &self.block_colors
+    }
+
+    /// The state stored in chunks as `state_id`
+    pub fn get_state(&self, state_id: usize) -> Option<&BlockState> {
+        self.blockstates.get_handle_from_index(state_id).map(|state| self.blockstates.get(state))
+    }
+
+    pub fn get_state_model(&self, state: StateHandle) -> Option<&BlockstateModel> {
+        self.state_models.get(state.index()).and_then(Option::as_ref)
+    }
+
+    pub fn get_state_color(&self, state: StateHandle) -> Option<&BlockColorProvider> {
+        self.state_colors.get(state.index()).and_then(Option::as_ref)
+    }
+
+    pub fn get_state_fluid(&self, state: StateHandle) -> Option<&FluidState> {
+        self.state_fluids.get(state.index()).and_then(Option::as_ref)
+    }
+}
+
+/**
+ * A cheap index of an element in a register, only meaningful for the register that handed it out.
+ * Registers never remove single elements, so a handle stays valid until the register is cleared
+ */
+pub struct Handle<T> {
+    index: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize) -> Self {
+        Self { index: index as u32, marker: PhantomData }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
 }
 
 /** Represents types that can be registered for indexing by identifiers
@@ -99,7 +265,7 @@ pub trait Registerable {
  *  index,
  */
 pub struct Register<T: Registerable> {
-    collection: Vec<Rc<T>>,
+    collection: Vec<Arc<T>>,
     id_map: HashMap<String, usize>,
     current_id: usize,
 }
@@ -124,7 +290,7 @@ impl<T: Registerable> Register<T> {
         let index = self.current_id;
         let identifier = registerable.get_identifier().to_string();
         self.current_id += 1;
-        self.collection.push(Rc::new(registerable));
+        self.collection.push(Arc::new(registerable));
         self.id_map.insert(identifier, index);
         index
     }
@@ -132,7 +298,7 @@ impl<T: Registerable> Register<T> {
     /** Puts the registerable into the register
      *
      */
-    pub fn insert_pointer(&mut self, registerable: Rc<T>) -> usize {
+    pub fn insert_pointer(&mut self, registerable: Arc<T>) -> usize {
         let index = self.current_id;
         let identifier = registerable.get_identifier().to_string();
         self.current_id += 1;
@@ -152,11 +318,11 @@ impl<T: Registerable> Register<T> {
         self.id_map.clear();
     }
 
-    pub fn get_element_from_identifier(&self, ident: &Identifier) -> Option<Rc<T>> {
+    pub fn get_element_from_identifier(&self, ident: &Identifier) -> Option<Arc<T>> {
         self.collection.get(self.get_index_from_identifier(ident)).cloned()
     }
 
-    pub fn get_element_from_index(&self, index: usize) -> Option<Rc<T>> {
+    pub fn get_element_from_index(&self, index: usize) -> Option<Arc<T>> {
         self.collection.get(index).cloned()
     }
 
@@ -166,11 +332,68 @@ impl<T: Registerable> Register<T> {
             .unwrap_or(&0)
     }
 
-    pub fn get_elements(&self) -> &Vec<Rc<T>> {
+    pub fn get_elements(&self) -> &Vec<Arc<T>> {
         &self.collection
     }
 
     pub fn get_next_index(&self) -> usize {
         self.current_id
     }
+
+    pub fn get_handle(&self, ident: &Identifier) -> Option<Handle<T>> {
+        self.id_map.get(&ident.to_string()).map(|index| Handle::new(*index))
+    }
+
+    pub fn get_handle_from_index(&self, index: usize) -> Option<Handle<T>> {
+        (index < self.collection.len()).then(|| Handle::new(index))
+    }
+
+    /// Panics if the handle is from another register
+    pub fn get(&self, handle: Handle<T>) -> &T {
+        &self.collection[handle.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::block_factory::BlockFactory;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn registry_with_blocks(names: &[&str]) -> Registry {
+        let mut registry = Registry::new();
+        for name in names {
+            let block_id = registry.get_block_register_mut().insert(BlockFactory::new(*name).build());
+            let block = registry.get_block_register().get_element_from_index(block_id).unwrap();
+            for state in Block::map_states(block, &mut registry) {
+                registry.get_blockstate_register_mut().insert_pointer(state);
+            }
+        }
+        registry
+    }
+
+    #[test]
+    fn handles_index_their_register() {
+        let registry = registry_with_blocks(&["air", "stone"]);
+        let blocks = registry.get_block_register();
+        let stone = blocks.get_handle(&Identifier::from_str("minecraft:stone")).unwrap();
+        assert_eq!(stone.index(), 1);
+        assert_eq!(blocks.get(stone).get_identifier(), &Identifier::from_str("minecraft:stone"));
+        assert_eq!(blocks.get_handle_from_index(1), Some(stone));
+        assert!(blocks.get_handle_from_index(2).is_none());
+        assert!(blocks.get_handle(&Identifier::from_str("minecraft:dirt")).is_none());
+    }
+
+    #[test]
+    fn frozen_registry_indexes_states() {
+        assert_send_sync::<FrozenRegistry>();
+        let frozen = registry_with_blocks(&["air", "stone"]).freeze();
+        let stone = frozen.get_state(1).unwrap();
+        assert_eq!(stone.get_block().get_identifier(), &Identifier::from_str("minecraft:stone"));
+        assert!(frozen.get_state(2).is_none());
+        let handle = frozen.get_blockstate_register().get_handle_from_index(1).unwrap();
+        assert!(frozen.get_state_model(handle).is_none());
+        assert!(frozen.get_state_color(handle).is_none());
+    }
 }
@@ -0,0 +1,187 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use image::RgbaImage;
+
+/// The layout of skins before b1.8, models are unwrapped onto a texture this size
+pub const LEGACY_SKIN_WIDTH: u32 = 64;
+pub const LEGACY_SKIN_HEIGHT: u32 = 32;
+/// Players longer than this can't join a server, so they have no skin
+const MAX_NAME_LENGTH: usize = 16;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(thiserror::Error, Debug)]
+pub enum SkinError {
+    #[error("{0:?} is not a player name")]
+    InvalidName(String),
+    #[error("Skin of {width}x{height} is not a skin layout")]
+    InvalidSize { width: u32, height: u32 },
+    #[error("No skin for {0}")]
+    NotFound(String),
+    #[error("Could not read skin: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not decode skin: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Could not download skin: {0}")]
+    Http(String),
+}
+
+/// Names are only letters, digits and underscores, which also keeps names sent by a server inside the skins folder
+pub fn is_valid_player_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LENGTH && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/**
+ * Make a skin fit the legacy layout of b1.7.3. Skins are 64x32 or a multiple of it, the bottom half of newer 64x64
+ * skins only holds layers the legacy models don't have and is cut off. Like the skins of old, a hat layer without any
+ * transparent pixel is cleared instead of covering the head
+ */
+pub fn normalize_skin(mut image: RgbaImage) -> Result<RgbaImage, SkinError> {
+    let (width, height) = image.dimensions();
+    let scale = width / LEGACY_SKIN_WIDTH;
+    let is_layout = scale > 0 && width % LEGACY_SKIN_WIDTH == 0 && (height == LEGACY_SKIN_HEIGHT * scale || height == width);
+    if !is_layout {
+        return Err(SkinError::InvalidSize { width, height });
+    }
+    if height == width {
+        image = image::imageops::crop_imm(&image, 0, 0, width, LEGACY_SKIN_HEIGHT * scale).to_image();
+    }
+
+    let (hat_x, hat_width, hat_height) = (32 * scale, 32 * scale, 16 * scale);
+    let hat_is_opaque = (0..hat_height).all(|y| (hat_x..hat_x + hat_width).all(|x| image.get_pixel(x, y)[3] == 255));
+    if hat_is_opaque {
+        for y in 0..hat_height {
+            for x in hat_x..hat_x + hat_width {
+                image.put_pixel(x, y, image::Rgba([0; 4]));
+            }
+        }
+    }
+    Ok(image)
+}
+
+/**
+ * Where the skins of players come from, a png named after the player in the skins folder.
+ * Skins not in the folder are downloaded from the url template when there is one, with `{name}` replaced by the name of the player
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkinSource {
+    directory: PathBuf,
+    url_template: Option<String>,
+}
+
+impl SkinSource {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory, url_template: None }
+    }
+
+    pub fn with_url_template(mut self, url_template: Option<String>) -> Self {
+        self.url_template = url_template;
+        self
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn skin_path(&self, name: &str) -> Option<PathBuf> {
+        is_valid_player_name(name).then(|| self.directory.join(format!("{name}.png")))
+    }
+
+    pub fn skin_url(&self, name: &str) -> Option<String> {
+        let template = self.url_template.as_ref()?;
+        is_valid_player_name(name).then(|| template.replace("{name}", name))
+    }
+
+    /// Load and normalize the skin of a player, from the skins folder first
+    pub fn load(&self, name: &str) -> Result<RgbaImage, SkinError> {
+        let path = self.skin_path(name).ok_or_else(|| SkinError::InvalidName(name.to_string()))?;
+        let image = if path.exists() {
+            image::open(path)?.to_rgba8()
+        } else {
+            let url = self.skin_url(name).ok_or_else(|| SkinError::NotFound(name.to_string()))?;
+            image::load_from_memory(&http_get(&url)?)?.to_rgba8()
+        };
+        normalize_skin(image)
+    }
+}
+
+/// Get the body of a plain `http://` url, enough for a skin server running next to a local server
+pub fn http_get(url: &str) -> Result<Vec<u8>, SkinError> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| SkinError::Http(format!("only http urls are supported, not {url}")))?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let address = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+    let socket_address = address.to_socket_addrs()?.next().ok_or_else(|| SkinError::Http(format!("could not resolve {host}")))?;
+
+    let mut stream = TcpStream::connect_timeout(&socket_address, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.write_all(format!("GET {path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n").as_bytes())?;
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+
+    let header_end = response.windows(4).position(|window| window == b"\r\n\r\n").ok_or_else(|| SkinError::Http("response has no body".to_string()))?;
+    let status_line = String::from_utf8_lossy(&response[..header_end]).lines().next().unwrap_or_default().to_string();
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(response.split_off(header_end + 4)),
+        _ => Err(SkinError::Http(status_line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn names_stay_in_the_skins_folder() {
+        let source = SkinSource::new(PathBuf::from("skins")).with_url_template(Some("http://localhost:8080/skins/{name}.png".to_string()));
+        assert_eq!(source.skin_path("Notch_2"), Some(PathBuf::from("skins/Notch_2.png")));
+        assert_eq!(source.skin_url("Notch_2").as_deref(), Some("http://localhost:8080/skins/Notch_2.png"));
+        assert_eq!(source.skin_path("../options"), None);
+        assert_eq!(source.skin_url(""), None);
+        assert!(!is_valid_player_name("a_name_far_too_long"));
+        assert!(matches!(SkinSource::new(PathBuf::from("skins")).load("Nobody"), Err(SkinError::NotFound(_))));
+    }
+
+    #[test]
+    fn skins_fit_the_legacy_layout() {
+        let legacy = normalize_skin(RgbaImage::from_pixel(64, 32, image::Rgba([10, 20, 30, 255]))).unwrap();
+        assert_eq!(legacy.dimensions(), (64, 32));
+        // A hat with nothing see through was never meant to be drawn
+        assert_eq!(legacy.get_pixel(40, 8)[3], 0);
+        assert_eq!(legacy.get_pixel(8, 8)[3], 255);
+
+        let mut modern = RgbaImage::from_pixel(128, 128, image::Rgba([10, 20, 30, 255]));
+        modern.put_pixel(64, 0, image::Rgba([0; 4]));
+        let modern = normalize_skin(modern).unwrap();
+        assert_eq!(modern.dimensions(), (128, 64));
+        assert_eq!(modern.get_pixel(80, 16)[3], 255);
+
+        assert!(matches!(normalize_skin(RgbaImage::new(64, 48)), Err(SkinError::InvalidSize { width: 64, height: 48 })));
+        assert!(normalize_skin(RgbaImage::new(32, 16)).is_err());
+    }
+
+    #[test]
+    fn skins_download_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut png = vec![];
+        image::DynamicImage::ImageRgba8(RgbaImage::new(64, 32)).write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+        let server = std::thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 512];
+                let read = stream.read(&mut request).unwrap();
+                assert!(request[..read].starts_with(b"GET /Steve.png HTTP/1.0\r\n"));
+                stream.write_all(format!("HTTP/1.0 {status}\r\nContent-Type: image/png\r\n\r\n").as_bytes()).unwrap();
+                stream.write_all(&png).unwrap();
+            }
+        });
+        let source = SkinSource::new(PathBuf::from("missing_skins")).with_url_template(Some(format!("http://127.0.0.1:{port}/{{name}}.png")));
+        assert_eq!(source.load("Steve").unwrap().dimensions(), (64, 32));
+        assert!(matches!(source.load("Steve"), Err(SkinError::Http(status)) if status.contains("404")));
+        server.join().unwrap();
+    }
+}
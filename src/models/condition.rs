@@ -0,0 +1,137 @@
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::Value;
+
+use super::{BlockstateParseError, BlockstateParseResult};
+
+/// The properties of a blockstate by name, with the name of their value
+pub type StatePropertyNames = HashMap<String, String>;
+
+/**
+ * The `when` of a multipart case.
+ * A property test passes when the value is one of the `|` separated values,
+ * a leading `!` inverts the test
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum MultipartCondition {
+    Always,
+    Property { name: String, values: Vec<String>, inverted: bool },
+    And(Vec<MultipartCondition>),
+    Or(Vec<MultipartCondition>),
+}
+
+/// Packs sometimes write booleans and numbers without quotes
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_condition_list(list: &Value) -> BlockstateParseResult<Vec<MultipartCondition>> {
+    match list {
+        Value::Array(conditions) => conditions.iter().map(MultipartCondition::parse).collect(),
+        _ => Err(BlockstateParseError::InvalidCondition(format!("expected a list of conditions, found {list}"))),
+    }
+}
+
+impl MultipartCondition {
+    /// Every property of an object has to pass, unless the object is a single OR or AND list
+    pub fn parse(when: &Value) -> BlockstateParseResult<Self> {
+        let object = match when {
+            Value::Null => return Ok(Self::Always),
+            Value::Object(object) => object,
+            _ => return Err(BlockstateParseError::InvalidCondition(format!("expected an object, found {when}"))),
+        };
+        let conditions = object.iter().map(|(key, value)| {
+            match key.as_str() {
+                "OR" => parse_condition_list(value).map(Self::Or),
+                "AND" => parse_condition_list(value).map(Self::And),
+                _ => {
+                    let test = value_to_string(value).ok_or_else(|| BlockstateParseError::InvalidCondition(format!("{key} has the value {value}")))?;
+                    let (test, inverted) = match test.strip_prefix('!') {
+                        Some(test) => (test, true),
+                        None => (test.as_str(), false),
+                    };
+                    let values = test.split('|').map(str::to_string).collect();
+                    Ok(Self::Property { name: key.clone(), values, inverted })
+                },
+            }
+        }).collect::<BlockstateParseResult<Vec<_>>>()?;
+        Ok(match conditions.len() {
+            0 => Self::Always,
+            1 => conditions.into_iter().next().unwrap(),
+            _ => Self::And(conditions),
+        })
+    }
+
+    pub fn matches(&self, properties: &StatePropertyNames) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Property { name, values, inverted } => {
+                let matched = properties.get(name).is_some_and(|value| values.contains(value));
+                matched != *inverted
+            },
+            Self::And(conditions) => conditions.iter().all(|condition| condition.matches(properties)),
+            Self::Or(conditions) => conditions.iter().any(|condition| condition.matches(properties)),
+        }
+    }
+}
+
+/**
+ * Whether the key of a variant like `facing=east,half=top` describes a blockstate.
+ * Every listed property has to have the listed value, an empty key matches every state
+ * and a key without properties like `normal` matches a block without properties
+ */
+pub fn variant_matches(variant_key: &str, properties: &StatePropertyNames) -> bool {
+    variant_key.split(',').filter(|pair| !pair.is_empty()).all(|pair| {
+        match pair.split_once('=') {
+            Some((name, value)) => properties.get(name).is_some_and(|state_value| state_value == value),
+            None => properties.is_empty(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(entries: &[(&str, &str)]) -> StatePropertyNames {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn condition(json: &str) -> MultipartCondition {
+        MultipartCondition::parse(&serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn properties_are_compared_exactly() {
+        let fence = properties(&[("north", "false"), ("east", "true"), ("in_wall_north", "true")]);
+        assert!(!condition(r#"{"north": "true"}"#).matches(&fence));
+        assert!(condition(r#"{"east": true}"#).matches(&fence));
+        assert!(condition(r#"{"north": "false|none"}"#).matches(&fence));
+        assert!(condition(r#"{"north": "!true"}"#).matches(&fence));
+        assert!(!condition(r#"{"north": "false", "east": "false"}"#).matches(&fence));
+    }
+
+    #[test]
+    fn or_and_lists() {
+        let dust = properties(&[("north", "none"), ("east", "side"), ("south", "none"), ("west", "up")]);
+        assert!(condition(r#"{"OR": [{"north": "side|up"}, {"west": "side|up"}]}"#).matches(&dust));
+        assert!(!condition(r#"{"AND": [{"east": "side"}, {"south": "side"}]}"#).matches(&dust));
+        assert!(condition(r#"{"OR": [{"AND": [{"east": "side"}, {"west": "up"}]}, {"north": "up"}]}"#).matches(&dust));
+        assert!(MultipartCondition::parse(&serde_json::json!({"OR": {"north": "up"}})).is_err());
+    }
+
+    #[test]
+    fn variant_keys() {
+        let slab = properties(&[("type", "top"), ("waterlogged", "false")]);
+        assert!(variant_matches("type=top", &slab));
+        assert!(!variant_matches("type=top,waterlogged=true", &slab));
+        assert!(!variant_matches("ype=top", &slab));
+        assert!(variant_matches("", &slab));
+        assert!(!variant_matches("normal", &slab));
+        assert!(variant_matches("normal", &properties(&[])));
+    }
+}
@@ -0,0 +1,631 @@
+use std::f32::consts::PI;
+
+use rustc_hash::FxHashMap as HashMap;
+use serde_derive::{Deserialize, Serialize};
+use ultraviolet::{Mat4, Vec2, Vec3};
+
+use crate::minecraft::identifier::Identifier;
+use crate::util::java_random::JavaRandom;
+
+/// Entity models are made in pixels, sixteen to a block
+const PIXELS_PER_BLOCK: f32 = 16.0;
+/// How fast limbs swing back and forth with the distance walked, from b1.7.3
+const WALK_FREQUENCY: f32 = 0.6662;
+/// The pivot of entity models is this far above the feet, in blocks
+const MODEL_ORIGIN_HEIGHT: f32 = 1.5;
+/// Every entity texture of b1.7.3 is laid out on 64x32 texels
+const LEGACY_TEXTURE_SIZE: [f32; 2] = [64.0, 32.0];
+
+#[derive(thiserror::Error, Debug)]
+pub enum EntityModelError {
+    #[error("Invalid entity model: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+}
+
+/**
+ * How a part moves on top of its rotation as the entity is posed.
+ * Written in model files as an object with a `type` of `look`, `walk` or `sway`
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PartAnimation {
+    /// Turns with the head of the entity
+    Look,
+    /// Swings back and forth as the entity walks, `phase` is in radians, legs and arms on opposite sides are half a swing apart
+    Walk { phase: f32, amplitude: f32 },
+    /// Arms swaying a little while standing, `side` is 1 for the right arm and -1 for the left
+    Sway { side: f32 },
+    /// Swings up as a lid does as the block opens
+    Open,
+}
+
+/**
+ * A cuboid of a model part in pixels, relative to the pivot of its part, with y pointing down as in b1.7.3.
+ * Its faces are unwrapped onto the texture from `uv` the way ModelBox does, `inflate` grows it without changing its uvs
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelBox {
+    pub uv: [f32; 2],
+    pub origin: [f32; 3],
+    pub size: [f32; 3],
+    #[serde(default)]
+    pub inflate: f32,
+    /// Mirrored boxes flip their texture across x, for the limbs on the left
+    #[serde(default)]
+    pub mirror: bool,
+}
+
+impl ModelBox {
+    pub fn new(uv: [f32; 2], origin: [f32; 3], size: [f32; 3]) -> Self {
+        Self { uv, origin, size, inflate: 0.0, mirror: false }
+    }
+
+    pub fn with_inflate(mut self, inflate: f32) -> Self {
+        self.inflate = inflate;
+        self
+    }
+
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Add the six faces of the box, transformed by the matrix of its part
+    fn emit(&self, matrix: Mat4, mesh: &mut EntityMesh) {
+        let (origin, size) = (Vec3::from(self.origin), Vec3::from(self.size));
+        let (mut min, mut max) = (origin - Vec3::broadcast(self.inflate), origin + size + Vec3::broadcast(self.inflate));
+        if self.mirror {
+            std::mem::swap(&mut min.x, &mut max.x);
+        }
+        let corners = [
+            Vec3::new(min.x, min.y, min.z), Vec3::new(max.x, min.y, min.z), Vec3::new(max.x, max.y, min.z), Vec3::new(min.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z), Vec3::new(max.x, min.y, max.z), Vec3::new(max.x, max.y, max.z), Vec3::new(min.x, max.y, max.z),
+        ].map(|corner| matrix.transform_point3(corner / PIXELS_PER_BLOCK));
+        let center = matrix.transform_point3((origin + size * 0.5) / PIXELS_PER_BLOCK);
+
+        let ([u, v], [width, height, depth]) = (self.uv, self.size);
+        let faces: [([usize; 4], [f32; 4]); 6] = [
+            ([5, 1, 2, 6], [u + depth + width, v + depth, u + depth + width + depth, v + depth + height]),
+            ([0, 4, 7, 3], [u, v + depth, u + depth, v + depth + height]),
+            ([5, 4, 0, 1], [u + depth, v, u + depth + width, v + depth]),
+            ([2, 3, 7, 6], [u + depth + width, v, u + depth + width + width, v + depth]),
+            ([1, 0, 3, 2], [u + depth, v + depth, u + depth + width, v + depth + height]),
+            ([4, 5, 6, 7], [u + depth + width + depth, v + depth, u + depth + width + depth + width, v + depth + height]),
+        ];
+        for (indices, [u1, v1, u2, v2]) in faces {
+            let mut positions = indices.map(|index| corners[index]);
+            let mut uvs = [Vec2::new(u2, v1), Vec2::new(u1, v1), Vec2::new(u1, v2), Vec2::new(u2, v2)];
+            if self.mirror {
+                positions.reverse();
+                uvs.reverse();
+            }
+            let face_center = positions.iter().fold(Vec3::zero(), |sum, position| sum + *position) / 4.0;
+            let mut normal = (positions[1] - positions[0]).cross(positions[3] - positions[0]);
+            if normal.dot(face_center - center) < 0.0 {
+                normal = -normal;
+            }
+            let normal = if normal.mag_sq() > 0.0 { normal.normalized() } else { normal };
+            let first = mesh.vertices.len() as u32;
+            mesh.vertices.extend(positions.iter().zip(uvs).map(|(position, uv)| EntityVertex { position: *position, uv, normal }));
+            mesh.indices.extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+        }
+    }
+}
+
+/// A part of a model turning around its pivot, its children turn with it. Rotations are in degrees, applied z then y then x
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelPart {
+    pub name: String,
+    pub pivot: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3],
+    #[serde(default)]
+    pub boxes: Vec<ModelBox>,
+    #[serde(default)]
+    pub animations: Vec<PartAnimation>,
+    #[serde(default)]
+    pub children: Vec<ModelPart>,
+}
+
+impl ModelPart {
+    pub fn new(name: &str, pivot: [f32; 3]) -> Self {
+        Self { name: name.to_string(), pivot, rotation: [0.0; 3], boxes: vec![], animations: vec![], children: vec![] }
+    }
+
+    pub fn with_rotation(mut self, rotation: [f32; 3]) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_box(mut self, model_box: ModelBox) -> Self {
+        self.boxes.push(model_box);
+        self
+    }
+
+    pub fn with_animation(mut self, animation: PartAnimation) -> Self {
+        self.animations.push(animation);
+        self
+    }
+
+    pub fn with_child(mut self, child: ModelPart) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// The rotation of the part in radians once its animations are applied
+    pub fn posed_rotation(&self, pose: &EntityPose) -> Vec3 {
+        let mut rotation = Vec3::from(self.rotation.map(f32::to_radians));
+        for animation in &self.animations {
+            match *animation {
+                PartAnimation::Look => {
+                    rotation.x += pose.head_pitch.to_radians();
+                    rotation.y += pose.head_yaw.to_radians();
+                },
+                PartAnimation::Walk { phase, amplitude } => {
+                    rotation.x += (pose.limb_swing * WALK_FREQUENCY + phase).cos() * amplitude * pose.limb_swing_amount;
+                },
+                PartAnimation::Open => {
+                    rotation.x -= pose.openness * PI / 2.0;
+                },
+                PartAnimation::Sway { side } => {
+                    rotation.z += side * ((pose.age * 0.09).cos() * 0.05 + 0.05);
+                    rotation.x += side * (pose.age * 0.067).sin() * 0.05;
+                },
+            }
+        }
+        rotation
+    }
+
+    fn evaluate(&self, pose: &EntityPose, parent: Mat4, mesh: &mut EntityMesh) {
+        let rotation = self.posed_rotation(pose);
+        let matrix = parent
+            * Mat4::from_translation(Vec3::from(self.pivot) / PIXELS_PER_BLOCK)
+            * Mat4::from_rotation_z(rotation.z)
+            * Mat4::from_rotation_y(rotation.y)
+            * Mat4::from_rotation_x(rotation.x);
+        for model_box in &self.boxes {
+            model_box.emit(matrix, mesh);
+        }
+        for child in &self.children {
+            child.evaluate(pose, matrix, mesh);
+        }
+    }
+}
+
+/// How an entity is posed this frame, angles are in degrees and the head turns relative to the body
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityPose {
+    /// How far the limbs have swung, it grows with the distance walked
+    pub limb_swing: f32,
+    /// How far the limbs swing, from 0 standing still to 1 walking
+    pub limb_swing_amount: f32,
+    /// Ticks the entity has been alive, for idle movement
+    pub age: f32,
+    pub head_yaw: f32,
+    pub head_pitch: f32,
+    /// How far lids are open, from 0 closed to 1 open
+    pub openness: f32,
+}
+
+/// A vertex of an evaluated model, uvs are in texels of the entity texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityVertex {
+    pub position: Vec3,
+    pub uv: Vec2,
+    pub normal: Vec3,
+}
+
+/// The triangles of a posed model, two to a face
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityMesh {
+    pub vertices: Vec<EntityVertex>,
+    pub indices: Vec<u32>,
+}
+
+fn default_texture_size() -> [f32; 2] {
+    LEGACY_TEXTURE_SIZE
+}
+
+/**
+ * A model made of boxes on parts, as the Model classes of b1.7.3 are.
+ * Models can be written as json, a list of parts under `parts`
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityModel {
+    /// The size of the texture the uvs of boxes are in, textures of a higher resolution are sampled at the same place
+    #[serde(default = "default_texture_size")]
+    pub texture_size: [f32; 2],
+    pub parts: Vec<ModelPart>,
+}
+
+impl EntityModel {
+    pub fn from_json(json: &str) -> Result<Self, EntityModelError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn get_part(&self, name: &str) -> Option<&ModelPart> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+
+    /// Pose the model and place it with `transform`, which takes the pixels of the model scaled down to blocks
+    pub fn evaluate(&self, pose: &EntityPose, transform: Mat4) -> EntityMesh {
+        let mut mesh = EntityMesh::default();
+        for part in &self.parts {
+            part.evaluate(pose, transform, &mut mesh);
+        }
+        mesh
+    }
+
+    /// ModelBiped, `arm_width` is 4 for players and zombies and 2 for skeletons
+    fn biped(arm_width: f32, arm_rotation: f32, arms_walk: bool) -> Self {
+        let offset = -arm_width / 2.0;
+        let arm = |name: &str, pivot_x: f32, box_x: f32, mirror: bool, phase: f32, side: f32| {
+            let part = ModelPart::new(name, [pivot_x, 2.0, 0.0])
+                .with_rotation([arm_rotation, 0.0, 0.0])
+                .with_box(ModelBox::new([40.0, 16.0], [box_x, -2.0, offset], [arm_width, 12.0, arm_width]).with_mirror(mirror))
+                .with_animation(PartAnimation::Sway { side });
+            if arms_walk { part.with_animation(PartAnimation::Walk { phase, amplitude: 1.0 }) } else { part }
+        };
+        let leg = |name: &str, pivot_x: f32, mirror: bool, phase: f32| {
+            ModelPart::new(name, [pivot_x, 12.0, 0.0])
+                .with_box(ModelBox::new([0.0, 16.0], [offset, 0.0, offset], [arm_width, 12.0, arm_width]).with_mirror(mirror))
+                .with_animation(PartAnimation::Walk { phase, amplitude: 1.4 })
+        };
+        // Thin arms hang from the same shoulders
+        let (right_arm_x, left_arm_x) = if arm_width == 4.0 { (-3.0, -1.0) } else { (-1.0, -1.0) };
+        Self {
+            texture_size: LEGACY_TEXTURE_SIZE,
+            parts: vec![
+                ModelPart::new("head", [0.0, 0.0, 0.0])
+                    .with_box(ModelBox::new([0.0, 0.0], [-4.0, -8.0, -4.0], [8.0, 8.0, 8.0]))
+                    .with_box(ModelBox::new([32.0, 0.0], [-4.0, -8.0, -4.0], [8.0, 8.0, 8.0]).with_inflate(0.5))
+                    .with_animation(PartAnimation::Look),
+                ModelPart::new("body", [0.0, 0.0, 0.0]).with_box(ModelBox::new([16.0, 16.0], [-4.0, 0.0, -2.0], [8.0, 12.0, 4.0])),
+                arm("right_arm", -5.0, right_arm_x, false, PI, 1.0),
+                arm("left_arm", 5.0, left_arm_x, true, 0.0, -1.0),
+                leg("right_leg", -2.0, false, 0.0),
+                leg("left_leg", 2.0, true, PI),
+            ],
+        }
+    }
+
+    /// ModelQuadruped with a head and body of its own, legs are `leg_height` pixels tall
+    fn quadruped(leg_height: f32, head: ModelPart, body: ModelPart) -> Self {
+        let top = 24.0 - leg_height;
+        let leg = |name: &str, x: f32, z: f32, phase: f32| {
+            ModelPart::new(name, [x, top, z])
+                .with_box(ModelBox::new([0.0, 16.0], [-2.0, 0.0, -2.0], [4.0, leg_height, 4.0]))
+                .with_animation(PartAnimation::Walk { phase, amplitude: 1.4 })
+        };
+        Self {
+            texture_size: LEGACY_TEXTURE_SIZE,
+            parts: vec![
+                head.with_animation(PartAnimation::Look),
+                body.with_rotation([90.0, 0.0, 0.0]),
+                leg("back_right_leg", -3.0, 7.0, 0.0),
+                leg("back_left_leg", 3.0, 7.0, PI),
+                leg("front_right_leg", -3.0, -5.0, PI),
+                leg("front_left_leg", 3.0, -5.0, 0.0),
+            ],
+        }
+    }
+
+    pub fn player() -> Self {
+        Self::biped(4.0, 0.0, true)
+    }
+
+    /// Zombies hold their arms out in front of them
+    pub fn zombie() -> Self {
+        Self::biped(4.0, -90.0, false)
+    }
+
+    pub fn skeleton() -> Self {
+        Self::biped(2.0, -90.0, false)
+    }
+
+    pub fn pig() -> Self {
+        let head = ModelPart::new("head", [0.0, 12.0, -6.0])
+            .with_box(ModelBox::new([0.0, 0.0], [-4.0, -4.0, -8.0], [8.0, 8.0, 8.0]))
+            .with_box(ModelBox::new([16.0, 16.0], [-2.0, 0.0, -9.0], [4.0, 3.0, 1.0]));
+        let body = ModelPart::new("body", [0.0, 11.0, 2.0]).with_box(ModelBox::new([28.0, 8.0], [-5.0, -10.0, -7.0], [10.0, 16.0, 8.0]));
+        Self::quadruped(6.0, head, body)
+    }
+
+    pub fn cow() -> Self {
+        let head = ModelPart::new("head", [0.0, 4.0, -8.0])
+            .with_box(ModelBox::new([0.0, 0.0], [-4.0, -4.0, -6.0], [8.0, 8.0, 6.0]))
+            .with_box(ModelBox::new([22.0, 0.0], [-5.0, -5.0, -4.0], [1.0, 3.0, 1.0]))
+            .with_box(ModelBox::new([22.0, 0.0], [4.0, -5.0, -4.0], [1.0, 3.0, 1.0]));
+        let body = ModelPart::new("body", [0.0, 5.0, 2.0])
+            .with_box(ModelBox::new([18.0, 4.0], [-6.0, -10.0, -7.0], [12.0, 18.0, 10.0]))
+            .with_box(ModelBox::new([52.0, 0.0], [-2.0, 2.0, -8.0], [4.0, 6.0, 1.0]));
+        Self::quadruped(12.0, head, body)
+    }
+
+    pub fn sheep() -> Self {
+        let head = ModelPart::new("head", [0.0, 6.0, -8.0]).with_box(ModelBox::new([0.0, 0.0], [-3.0, -4.0, -6.0], [6.0, 6.0, 8.0]));
+        let body = ModelPart::new("body", [0.0, 5.0, 2.0]).with_box(ModelBox::new([28.0, 8.0], [-4.0, -10.0, -7.0], [8.0, 16.0, 6.0]));
+        Self::quadruped(12.0, head, body)
+    }
+
+    pub fn creeper() -> Self {
+        let leg = |name: &str, x: f32, z: f32, phase: f32| {
+            ModelPart::new(name, [x, 16.0, z])
+                .with_box(ModelBox::new([0.0, 16.0], [-2.0, 0.0, -2.0], [4.0, 6.0, 4.0]))
+                .with_animation(PartAnimation::Walk { phase, amplitude: 1.4 })
+        };
+        Self {
+            texture_size: LEGACY_TEXTURE_SIZE,
+            parts: vec![
+                ModelPart::new("head", [0.0, 4.0, 0.0])
+                    .with_box(ModelBox::new([0.0, 0.0], [-4.0, -8.0, -4.0], [8.0, 8.0, 8.0]))
+                    .with_animation(PartAnimation::Look),
+                ModelPart::new("body", [0.0, 4.0, 0.0]).with_box(ModelBox::new([16.0, 16.0], [-4.0, 0.0, -2.0], [8.0, 12.0, 4.0])),
+                leg("back_right_leg", -2.0, 4.0, 0.0),
+                leg("back_left_leg", 2.0, 4.0, PI),
+                leg("front_right_leg", -2.0, -4.0, PI),
+                leg("front_left_leg", 2.0, -4.0, 0.0),
+            ],
+        }
+    }
+
+    /// ModelSign, a board on a stick. Signs on walls have no stick
+    pub fn sign(standing: bool) -> Self {
+        let mut parts = vec![ModelPart::new("board", [0.0; 3]).with_box(ModelBox::new([0.0, 0.0], [-12.0, -14.0, -1.0], [24.0, 12.0, 2.0]))];
+        if standing {
+            parts.push(ModelPart::new("stick", [0.0; 3]).with_box(ModelBox::new([0.0, 14.0], [-1.0, -2.0, -1.0], [2.0, 14.0, 2.0])));
+        }
+        Self { texture_size: LEGACY_TEXTURE_SIZE, parts }
+    }
+
+    /// ModelChest, the lid and its knob swing open around the back edge
+    pub fn chest() -> Self {
+        Self {
+            texture_size: [64.0, 64.0],
+            parts: vec![
+                ModelPart::new("lid", [1.0, 7.0, 15.0]).with_box(ModelBox::new([0.0, 0.0], [0.0, -5.0, -14.0], [14.0, 5.0, 14.0])).with_animation(PartAnimation::Open),
+                ModelPart::new("knob", [8.0, 7.0, 15.0]).with_box(ModelBox::new([0.0, 0.0], [-1.0, -2.0, -15.0], [2.0, 4.0, 1.0])).with_animation(PartAnimation::Open),
+                ModelPart::new("bottom", [1.0, 6.0, 1.0]).with_box(ModelBox::new([0.0, 19.0], [0.0, 0.0, 0.0], [14.0, 10.0, 14.0])),
+            ],
+        }
+    }
+
+    /// ModelMinecart, a floor with four sides turned up around it
+    pub fn minecart() -> Self {
+        let side = |name: &str, pivot: [f32; 3], yaw: f32| {
+            ModelPart::new(name, pivot).with_rotation([0.0, yaw, 0.0]).with_box(ModelBox::new([0.0, 0.0], [-8.0, -9.0, -1.0], [16.0, 8.0, 2.0]))
+        };
+        Self {
+            texture_size: LEGACY_TEXTURE_SIZE,
+            parts: vec![
+                ModelPart::new("bottom", [0.0, 4.0, 0.0]).with_rotation([90.0, 0.0, 0.0]).with_box(ModelBox::new([0.0, 10.0], [-10.0, -8.0, -1.0], [20.0, 16.0, 2.0])),
+                side("right", [-9.0, 4.0, 0.0], 270.0),
+                side("left", [9.0, 4.0, 0.0], 90.0),
+                side("back", [0.0, 4.0, -7.0], 180.0),
+                side("front", [0.0, 4.0, 7.0], 0.0),
+                ModelPart::new("floor", [0.0, 4.0, 0.0]).with_rotation([-90.0, 0.0, 0.0]).with_box(ModelBox::new([44.0, 10.0], [-9.0, -7.0, -1.0], [18.0, 14.0, 1.0])),
+            ],
+        }
+    }
+}
+
+/// The entities with a model, each drawn with a texture of its own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EntityModelKind {
+    Player,
+    Zombie,
+    Skeleton,
+    Pig,
+    Cow,
+    Sheep,
+    Creeper,
+    Minecart,
+}
+
+pub const ENTITY_MODEL_KINDS: [EntityModelKind; 8] = [
+    EntityModelKind::Player,
+    EntityModelKind::Zombie,
+    EntityModelKind::Skeleton,
+    EntityModelKind::Pig,
+    EntityModelKind::Cow,
+    EntityModelKind::Sheep,
+    EntityModelKind::Creeper,
+    EntityModelKind::Minecart,
+];
+
+impl EntityModelKind {
+    /// The model of a mob type sent in `SpawnMob`, pig zombies and giants look like zombies
+    pub fn from_mob_type(mob_type: i8) -> Option<Self> {
+        match mob_type {
+            50 => Some(Self::Creeper),
+            51 => Some(Self::Skeleton),
+            53 | 54 | 57 => Some(Self::Zombie),
+            90 => Some(Self::Pig),
+            91 => Some(Self::Sheep),
+            92 => Some(Self::Cow),
+            _ => None,
+        }
+    }
+
+    /// The model of a mob by the name mob spawners know it by
+    pub fn from_mob_name(name: &str) -> Option<Self> {
+        match name {
+            "Creeper" => Some(Self::Creeper),
+            "Skeleton" => Some(Self::Skeleton),
+            "Zombie" | "PigZombie" | "Giant" => Some(Self::Zombie),
+            "Pig" => Some(Self::Pig),
+            "Sheep" => Some(Self::Sheep),
+            "Cow" => Some(Self::Cow),
+            _ => None,
+        }
+    }
+
+    /// The model of an object type sent in `CreateNonMobEntity`, every kind of minecart is a minecart
+    pub fn from_object_type(object_type: i8) -> Option<Self> {
+        match object_type {
+            10..=12 => Some(Self::Minecart),
+            _ => None,
+        }
+    }
+
+    /// Where resource packs put the model, under `models`
+    pub fn model_name(&self) -> &'static str {
+        match self {
+            Self::Player => "minecraft:entity/player",
+            Self::Zombie => "minecraft:entity/zombie",
+            Self::Skeleton => "minecraft:entity/skeleton",
+            Self::Pig => "minecraft:entity/pig",
+            Self::Cow => "minecraft:entity/cow",
+            Self::Sheep => "minecraft:entity/sheep",
+            Self::Creeper => "minecraft:entity/creeper",
+            Self::Minecart => "minecraft:entity/minecart",
+        }
+    }
+
+    /// The model a resource pack gives the entity, the built in one when no pack has it
+    pub fn load_model(&self, models: &HashMap<Identifier, EntityModel>) -> EntityModel {
+        models.get(&Identifier::from_str(self.model_name())).cloned().unwrap_or_else(|| self.model())
+    }
+
+    pub fn model(&self) -> EntityModel {
+        match self {
+            Self::Player => EntityModel::player(),
+            Self::Zombie => EntityModel::zombie(),
+            Self::Skeleton => EntityModel::skeleton(),
+            Self::Pig => EntityModel::pig(),
+            Self::Cow => EntityModel::cow(),
+            Self::Sheep => EntityModel::sheep(),
+            Self::Creeper => EntityModel::creeper(),
+            Self::Minecart => EntityModel::minecart(),
+        }
+    }
+
+    /// The sprite the model is textured with
+    pub fn texture(&self) -> &'static str {
+        match self {
+            Self::Player => "minecraft:entity/steve",
+            Self::Zombie => "minecraft:entity/zombie/zombie",
+            Self::Skeleton => "minecraft:entity/skeleton/skeleton",
+            Self::Pig => "minecraft:entity/pig/pig",
+            Self::Cow => "minecraft:entity/cow/cow",
+            Self::Sheep => "minecraft:entity/sheep/sheep",
+            Self::Creeper => "minecraft:entity/creeper/creeper",
+            Self::Minecart => "minecraft:entity/minecart",
+        }
+    }
+}
+
+/**
+ * Where a model of an entity standing at `position` and facing `body_yaw` degrees is placed.
+ * Models are upside down and mirrored in their own space, as in RenderLiving
+ */
+pub fn entity_transform(position: Vec3, body_yaw: f32) -> Mat4 {
+    Mat4::from_translation(position)
+        * Mat4::from_rotation_y((180.0 - body_yaw).to_radians())
+        * Mat4::from_nonuniform_scale(Vec3::new(-1.0, -1.0, 1.0))
+        * Mat4::from_translation(Vec3::new(0.0, -MODEL_ORIGIN_HEIGHT, 0.0))
+}
+
+/// How many copies of an item a dropped stack is drawn as
+pub fn item_drop_copies(stack_size: i8) -> usize {
+    match stack_size {
+        size if size > 20 => 4,
+        size if size > 5 => 3,
+        size if size > 1 => 2,
+        _ => 1,
+    }
+}
+
+/**
+ * The sprites of a dropped item, relative to the item and facing the camera along its `right` and `up`.
+ * Corners are ordered top left, top right, bottom left, bottom right. The item bobs up and down as it ages,
+ * bigger stacks are drawn as a few copies scattered around it as RenderItem does
+ */
+pub fn item_drop_billboards(age: f32, hover_start: f32, stack_size: i8, right: Vec3, up: Vec3) -> Vec<[Vec3; 4]> {
+    let bob = (age / 10.0 + hover_start).sin() * 0.1 + 0.1;
+    let mut random = JavaRandom::new(187);
+    (0..item_drop_copies(stack_size)).map(|copy| {
+        let mut offset = Vec3::new(0.0, bob, 0.0);
+        if copy > 0 {
+            offset += Vec3::new(random.next_float() * 2.0 - 1.0, random.next_float() * 2.0 - 1.0, random.next_float() * 2.0 - 1.0) * 0.3 * 0.5;
+        }
+        let (right, up) = (right * 0.25, up * 0.25);
+        // The sprite stands on the item, its bottom a little below it
+        let bottom = offset - up * 0.5;
+        [bottom - right + up * 2.0, bottom + right + up * 2.0, bottom - right, bottom + right]
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_unwrap_onto_the_texture() {
+        let model = EntityModel { texture_size: LEGACY_TEXTURE_SIZE, parts: vec![ModelPart::new("cube", [0.0; 3]).with_box(ModelBox::new([0.0, 0.0], [0.0, 0.0, 0.0], [8.0, 8.0, 8.0]))] };
+        let mesh = model.evaluate(&EntityPose::default(), Mat4::identity());
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+        // The third face is the top, y is down in model space
+        let top = &mesh.vertices[8..12];
+        assert!(top.iter().all(|vertex| vertex.position.y == 0.0 && vertex.normal == Vec3::new(0.0, -1.0, 0.0)));
+        let (min, max) = top.iter().fold((Vec2::broadcast(f32::MAX), Vec2::zero()), |(min, max), vertex| (min.min_by_component(vertex.uv), max.max_by_component(vertex.uv)));
+        assert_eq!((min, max), (Vec2::new(8.0, 0.0), Vec2::new(16.0, 8.0)));
+        // Every normal points out of the cube
+        let center = Vec3::broadcast(0.25);
+        assert!(mesh.vertices.chunks(4).all(|face| face[0].normal.dot(face[0].position - center) > 0.0));
+    }
+
+    #[test]
+    fn walking_swings_opposite_limbs() {
+        let model = EntityModel::player();
+        let pose = EntityPose { limb_swing: 1.0, limb_swing_amount: 1.0, ..Default::default() };
+        let right = model.get_part("right_leg").unwrap().posed_rotation(&pose);
+        let left = model.get_part("left_leg").unwrap().posed_rotation(&pose);
+        assert!(right.x.abs() > 0.1);
+        assert!((right.x + left.x).abs() < 1e-5);
+        let arm = model.get_part("right_arm").unwrap().posed_rotation(&pose);
+        assert!(arm.x * right.x < 0.0, "arms swing against the legs on their side");
+
+        let look = EntityPose { head_yaw: 90.0, ..Default::default() };
+        assert!((model.get_part("head").unwrap().posed_rotation(&look).y - PI / 2.0).abs() < 1e-5);
+        assert_eq!(model.get_part("body").unwrap().posed_rotation(&pose), Vec3::zero());
+
+        // Placed upright in the world, the head is above the feet
+        let mesh = model.evaluate(&EntityPose::default(), entity_transform(Vec3::new(10.0, 64.0, 10.0), 0.0));
+        let (lowest, highest) = mesh.vertices.iter().fold((f32::MAX, f32::MIN), |(low, high), vertex| (low.min(vertex.position.y), high.max(vertex.position.y)));
+        assert!((lowest - 64.0).abs() < 1e-4);
+        assert!((highest - (64.0 + 2.0 + 0.5 / 16.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn models_load_from_json() {
+        let json = r#"{ "parts": [ { "name": "head", "pivot": [0, 4, 0], "rotation": [0, 45, 0],
+            "boxes": [ { "uv": [0, 0], "origin": [-4, -8, -4], "size": [8, 8, 8], "mirror": true } ],
+            "animations": [ { "type": "look" }, { "type": "walk", "phase": 1.5, "amplitude": 1.4 } ] } ] }"#;
+        let model = EntityModel::from_json(json).unwrap();
+        let head = model.get_part("head").unwrap();
+        assert!(head.boxes[0].mirror);
+        assert_eq!(head.animations, vec![PartAnimation::Look, PartAnimation::Walk { phase: 1.5, amplitude: 1.4 }]);
+        assert_eq!(model.texture_size, [64.0, 32.0]);
+        assert_eq!(model.evaluate(&EntityPose::default(), Mat4::identity()).vertices.len(), 24);
+        assert!(EntityModel::from_json(r#"{ "parts": [ { "name": "head" } ] }"#).is_err());
+
+        // Packs replace the built in models they have
+        let models = HashMap::from_iter([(Identifier::from_str("minecraft:entity/pig"), model.clone())]);
+        assert_eq!(EntityModelKind::Pig.load_model(&models), model);
+        assert_eq!(EntityModelKind::Cow.load_model(&models), EntityModel::cow());
+
+        assert_eq!(item_drop_billboards(0.0, 0.0, 64, Vec3::unit_x(), Vec3::unit_y()).len(), 4);
+        assert_eq!(EntityModelKind::from_mob_type(54), Some(EntityModelKind::Zombie));
+        assert_eq!(EntityModelKind::from_object_type(10), Some(EntityModelKind::Minecart));
+        assert_eq!(EntityModelKind::from_mob_name("Pig"), Some(EntityModelKind::Pig));
+
+        // Opening a chest turns its lid back around its hinge
+        let open = EntityPose { openness: 1.0, ..Default::default() };
+        let chest = EntityModel::chest();
+        assert!((chest.get_part("lid").unwrap().posed_rotation(&open).x + PI / 2.0).abs() < 1e-5);
+        assert_eq!(chest.get_part("bottom").unwrap().posed_rotation(&open), Vec3::zero());
+        assert_eq!(EntityModel::sign(false).evaluate(&EntityPose::default(), Mat4::identity()).vertices.len(), 24);
+    }
+}
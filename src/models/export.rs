@@ -0,0 +1,269 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use serde_json::{json, Value};
+use ultraviolet::{Vec2, Vec3};
+
+use super::model::BakedModel;
+
+/// The name of the single material every exported object uses, the game atlas
+const ATLAS_MATERIAL: &str = "atlas";
+
+/// Triangles with their vertex attributes, ready to be written out to a file
+#[derive(Clone, Debug, Default)]
+pub struct ExportMesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    colors: Vec<Vec3>,
+    indices: Vec<u32>,
+}
+
+impl ExportMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Add one vertex, returns its index for add_triangle
+    pub fn add_vertex(&mut self, position: Vec3, normal: Vec3, uv: Vec2, color: Vec3) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        self.colors.push(color);
+        self.positions.len() as u32 - 1
+    }
+
+    pub fn add_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend([a, b, c]);
+    }
+
+    /// A quad with its vertices in model order (top left, top right, bottom left, bottom right)
+    pub fn add_quad(&mut self, pos: [Vec3; 4], uvs: [Vec2; 4], normal: Vec3, color: Vec3) {
+        let first = self.positions.len() as u32;
+        for (pos, uv) in pos.iter().zip(uvs.iter()) {
+            self.add_vertex(*pos, normal, *uv, color);
+        }
+        self.add_triangle(first, first + 2, first + 3);
+        self.add_triangle(first, first + 3, first + 1);
+    }
+
+    /// Add every quad of a model, moved by offset
+    pub fn add_model(&mut self, model: &BakedModel, offset: Vec3) {
+        for quad in model.shapes() {
+            self.add_quad(quad.pos.map(|pos| pos + offset), quad.uvs, quad.normal, quad.color);
+        }
+    }
+
+    pub fn append(&mut self, other: &ExportMesh) {
+        let first = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.colors.extend_from_slice(&other.colors);
+        self.indices.extend(other.indices.iter().map(|index| index + first));
+    }
+}
+
+/**
+ * Write named objects to a Wavefront OBJ file.
+ * OBJ indices are shared by the whole file, and its uvs start at the bottom of the texture
+ */
+pub fn write_obj<W: Write>(writer: &mut W, objects: &[(&str, &ExportMesh)], material_library: &str) -> io::Result<()> {
+    writeln!(writer, "mtllib {material_library}")?;
+    let mut first = 1;
+    for (name, mesh) in objects {
+        writeln!(writer, "o {name}")?;
+        writeln!(writer, "usemtl {ATLAS_MATERIAL}")?;
+        for position in &mesh.positions {
+            writeln!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+        }
+        for uv in &mesh.uvs {
+            writeln!(writer, "vt {} {}", uv.x, 1.0 - uv.y)?;
+        }
+        for normal in &mesh.normals {
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] + first, triangle[1] + first, triangle[2] + first];
+            writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+        first += mesh.positions.len() as u32;
+    }
+    Ok(())
+}
+
+/// The material library of write_obj, textured by the atlas with its alpha as a cutout
+pub fn write_mtl<W: Write>(writer: &mut W, texture: &str) -> io::Result<()> {
+    writeln!(writer, "newmtl {ATLAS_MATERIAL}")?;
+    writeln!(writer, "Kd 1 1 1")?;
+    writeln!(writer, "map_Kd {texture}")?;
+    writeln!(writer, "map_d {texture}")
+}
+
+fn push_floats(buffer: &mut Vec<u8>, values: impl Iterator<Item = f32>) {
+    for value in values {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/**
+ * Build a glTF document for named objects and the binary buffer it refers to as `buffer_uri`.
+ * Every object is a node with its own mesh, textured by the image at `texture_uri`
+ */
+pub fn gltf(objects: &[(&str, &ExportMesh)], buffer_uri: &str, texture_uri: &str) -> (Value, Vec<u8>) {
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+    const NEAREST: u32 = 9728;
+
+    let mut buffer = vec![];
+    let mut buffer_views = vec![];
+    let mut accessors = vec![];
+    let mut meshes = vec![];
+    let mut nodes = vec![];
+    for (name, mesh) in objects.iter().filter(|(_, mesh)| !mesh.is_empty()) {
+        let mut view = |buffer: &mut Vec<u8>, start: usize, target: u32| {
+            buffer_views.push(json!({ "buffer": 0, "byteOffset": start, "byteLength": buffer.len() - start, "target": target }));
+            buffer_views.len() - 1
+        };
+        let count = mesh.positions.len();
+        let (min, max) = mesh.positions.iter().fold((Vec3::broadcast(f32::MAX), Vec3::broadcast(f32::MIN)), |(min, max), pos| (min.min_by_component(*pos), max.max_by_component(*pos)));
+
+        let start = buffer.len();
+        push_floats(&mut buffer, mesh.positions.iter().flat_map(|pos| [pos.x, pos.y, pos.z]));
+        let position_view = view(&mut buffer, start, ARRAY_BUFFER);
+        let start = buffer.len();
+        push_floats(&mut buffer, mesh.normals.iter().flat_map(|normal| [normal.x, normal.y, normal.z]));
+        let normal_view = view(&mut buffer, start, ARRAY_BUFFER);
+        let start = buffer.len();
+        push_floats(&mut buffer, mesh.uvs.iter().flat_map(|uv| [uv.x, uv.y]));
+        let uv_view = view(&mut buffer, start, ARRAY_BUFFER);
+        let start = buffer.len();
+        push_floats(&mut buffer, mesh.colors.iter().flat_map(|color| [color.x, color.y, color.z]));
+        let color_view = view(&mut buffer, start, ARRAY_BUFFER);
+        let start = buffer.len();
+        for index in &mesh.indices {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+        let index_view = view(&mut buffer, start, ELEMENT_ARRAY_BUFFER);
+
+        let first_accessor = accessors.len();
+        accessors.extend([
+            json!({ "bufferView": position_view, "componentType": FLOAT, "count": count, "type": "VEC3", "min": [min.x, min.y, min.z], "max": [max.x, max.y, max.z] }),
+            json!({ "bufferView": normal_view, "componentType": FLOAT, "count": count, "type": "VEC3" }),
+            json!({ "bufferView": uv_view, "componentType": FLOAT, "count": count, "type": "VEC2" }),
+            json!({ "bufferView": color_view, "componentType": FLOAT, "count": count, "type": "VEC3" }),
+            json!({ "bufferView": index_view, "componentType": UNSIGNED_INT, "count": mesh.indices.len(), "type": "SCALAR" }),
+        ]);
+        meshes.push(json!({
+            "name": name,
+            "primitives": [{
+                "attributes": { "POSITION": first_accessor, "NORMAL": first_accessor + 1, "TEXCOORD_0": first_accessor + 2, "COLOR_0": first_accessor + 3 },
+                "indices": first_accessor + 4,
+                "material": 0,
+            }],
+        }));
+        nodes.push(json!({ "name": name, "mesh": meshes.len() - 1 }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "orange-rs" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [{ "uri": buffer_uri, "byteLength": buffer.len() }],
+        "materials": [{
+            "name": ATLAS_MATERIAL,
+            "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 }, "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+            "alphaMode": "MASK",
+            "doubleSided": false,
+        }],
+        "textures": [{ "source": 0, "sampler": 0 }],
+        "samplers": [{ "magFilter": NEAREST, "minFilter": NEAREST }],
+        "images": [{ "uri": texture_uri }],
+    });
+    (document, buffer)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Write objects to `path` as an OBJ file, with its material library next to it
+pub fn export_obj(path: &Path, objects: &[(&str, &ExportMesh)], texture: &str) -> io::Result<()> {
+    let material_path = path.with_extension("mtl");
+    let mut obj = BufWriter::new(File::create(path)?);
+    write_obj(&mut obj, objects, &file_name(&material_path))?;
+    obj.flush()?;
+    let mut mtl = BufWriter::new(File::create(&material_path)?);
+    write_mtl(&mut mtl, texture)?;
+    mtl.flush()
+}
+
+/// Write objects to `path` as a glTF file, with its binary buffer next to it
+pub fn export_gltf(path: &Path, objects: &[(&str, &ExportMesh)], texture: &str) -> io::Result<()> {
+    let buffer_path = path.with_extension("bin");
+    let (document, buffer) = gltf(objects, &file_name(&buffer_path), texture);
+    std::fs::write(&buffer_path, buffer)?;
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad_mesh() -> ExportMesh {
+        let mut mesh = ExportMesh::new();
+        let pos = [Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)];
+        let uvs = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0)];
+        mesh.add_quad(pos, uvs, Vec3::unit_z(), Vec3::one());
+        mesh
+    }
+
+    #[test]
+    fn obj_indices_continue_across_objects() {
+        let mesh = quad_mesh();
+        let mut output = vec![];
+        write_obj(&mut output, &[("a", &mesh), ("b", &mesh)], "models.mtl").unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("mtllib models.mtl\n"));
+        assert_eq!(output.lines().filter(|line| line.starts_with("v ")).count(), 8);
+        assert!(output.contains("f 1/1/1 3/3/3 4/4/4"));
+        assert!(output.contains("f 5/5/5 7/7/7 8/8/8"));
+        assert!(output.contains("vt 0 0\n"));
+    }
+
+    #[test]
+    fn gltf_buffer_matches_accessors() {
+        let mesh = quad_mesh();
+        let mut merged = mesh.clone();
+        merged.append(&mesh);
+        assert_eq!((merged.vertex_count(), merged.triangle_count()), (8, 4));
+        let (document, buffer) = gltf(&[("quad", &mesh), ("empty", &ExportMesh::new())], "quad.bin", "atlas.png");
+        // 4 vertices of 11 floats and 6 indices
+        assert_eq!(buffer.len(), 4 * 11 * 4 + 6 * 4);
+        assert_eq!(document["buffers"][0]["byteLength"], buffer.len());
+        assert_eq!(document["nodes"].as_array().unwrap().len(), 1);
+        assert_eq!(document["accessors"][0]["max"], json!([1.0, 1.0, 0.0]));
+        let views = document["bufferViews"].as_array().unwrap();
+        let total: u64 = views.iter().map(|view| view["byteLength"].as_u64().unwrap()).sum();
+        assert_eq!(total as usize, buffer.len());
+    }
+}
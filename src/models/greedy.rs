@@ -0,0 +1,158 @@
+use ultraviolet::{Vec2, Vec3};
+
+use crate::direction::Direction;
+
+use super::model::ModelQuad;
+
+/// The axis a face points along, followed by the two axes it spans
+pub fn face_axes(direction: Direction) -> (usize, usize, usize) {
+    match direction {
+        Direction::North | Direction::South => (0, 1, 2),
+        Direction::Up | Direction::Down => (1, 2, 0),
+        Direction::East | Direction::West => (2, 0, 1),
+    }
+}
+
+/**
+ * The direction of a quad covering a whole side of its block, quads inside the block or smaller than a side are not full faces.
+ * Only faces that can be culled count, so a full face always touches the neighbour it faces
+ */
+pub fn full_face(quad: &ModelQuad) -> Option<Direction> {
+    let direction = quad.cullface?;
+    let (normal_axis, u_axis, v_axis) = face_axes(direction);
+    let plane = if direction.get_float_vector()[normal_axis] > 0.0 { 1.0 } else { 0.0 };
+    let on_plane = quad.pos.iter().all(|pos| pos[normal_axis] == plane);
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+    let covers_side = corners.iter().all(|&(u, v)| quad.pos.iter().any(|pos| pos[u_axis] == u && pos[v_axis] == v));
+    (on_plane && covers_side).then_some(direction)
+}
+
+/**
+ * How the texture of a full face maps onto it, uv = origin + u * u_axis + v * v_axis for a point (u, v) of the face.
+ * A face stretched over several blocks repeats its texture by taking the fraction of (u, v)
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaceTiling {
+    pub origin: Vec2,
+    pub u_axis: Vec2,
+    pub v_axis: Vec2,
+}
+
+impl FaceTiling {
+    /// The tiling of a full face, none when its uvs are not a rectangle of the texture
+    pub fn from_quad(quad: &ModelQuad, direction: Direction) -> Option<Self> {
+        let (_, u_axis, v_axis) = face_axes(direction);
+        let uv_at = |u: f32, v: f32| quad.pos.iter().position(|pos| pos[u_axis] == u && pos[v_axis] == v).map(|corner| quad.uvs[corner]);
+        let origin = uv_at(0.0, 0.0)?;
+        let tiling = Self { origin, u_axis: uv_at(1.0, 0.0)? - origin, v_axis: uv_at(0.0, 1.0)? - origin };
+        let far = uv_at(1.0, 1.0)?;
+        let expected = tiling.origin + tiling.u_axis + tiling.v_axis;
+        ((far - expected).mag() < 1e-6).then_some(tiling)
+    }
+
+    /// The uv of a point of the face, without repeating
+    pub fn uv(&self, u: f32, v: f32) -> Vec2 {
+        self.origin + self.u_axis * u + self.v_axis * v
+    }
+}
+
+/// The position of a corner of a full face when the face is stretched over `width` by `height` blocks
+pub fn stretch_corner(corner: Vec3, direction: Direction, width: usize, height: usize) -> Vec3 {
+    let (_, u_axis, v_axis) = face_axes(direction);
+    let mut corner = corner;
+    corner[u_axis] *= width as f32;
+    corner[v_axis] *= height as f32;
+    corner
+}
+
+/// A rectangle of equal faces in a slice
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreedyRect<T> {
+    pub u: usize,
+    pub v: usize,
+    pub width: usize,
+    pub height: usize,
+    pub face: T,
+}
+
+/**
+ * Merge a square slice of faces, indexed by v * size + u, into as few rectangles of equal faces as the greedy sweep finds.
+ * Each rectangle grows along u first, then along v while every face of the next row matches. The slice is emptied
+ */
+pub fn merge_slice<T: PartialEq>(slice: &mut [Option<T>], size: usize) -> Vec<GreedyRect<T>> {
+    let mut rects = vec![];
+    for v in 0..size {
+        let mut u = 0;
+        while u < size {
+            let Some(face) = slice[v * size + u].take() else { u += 1; continue; };
+            let mut width = 1;
+            while u + width < size && slice[v * size + u + width].as_ref() == Some(&face) {
+                width += 1;
+            }
+            let mut height = 1;
+            while v + height < size && (u..u + width).all(|row_u| slice[(v + height) * size + row_u].as_ref() == Some(&face)) {
+                height += 1;
+            }
+            for covered_v in v..v + height {
+                for covered_u in u..u + width {
+                    slice[covered_v * size + covered_u] = None;
+                }
+            }
+            rects.push(GreedyRect { u, v, width, height, face });
+            u += width;
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn up_face(uvs: [Vec2; 4]) -> ModelQuad {
+        ModelQuad {
+            pos: [Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 1.0)],
+            uvs,
+            normal: Vec3::unit_y(),
+            color: Vec3::one(),
+            cullface: Some(Direction::Up),
+            ao_face: Some(Direction::Up),
+            tint_index: -1,
+        }
+    }
+
+    #[test]
+    fn full_faces_tile_their_texture() {
+        let quad = up_face([Vec2::new(0.25, 0.5), Vec2::new(0.25, 0.75), Vec2::new(0.5, 0.5), Vec2::new(0.5, 0.75)]);
+        assert_eq!(full_face(&quad), Some(Direction::Up));
+        let tiling = FaceTiling::from_quad(&quad, Direction::Up).unwrap();
+        // Up faces span z then x
+        assert_eq!(tiling.uv(1.0, 0.0), Vec2::new(0.25, 0.75));
+        assert_eq!(tiling.uv(0.0, 1.0), Vec2::new(0.5, 0.5));
+        assert_eq!(stretch_corner(quad.pos[3], Direction::Up, 3, 2), Vec3::new(2.0, 1.0, 3.0));
+
+        let mut slab_top = quad;
+        slab_top.pos.iter_mut().for_each(|pos| pos.y = 0.5);
+        assert!(full_face(&slab_top).is_none());
+        let mut skewed = quad;
+        skewed.uvs[3] = Vec2::new(0.6, 0.75);
+        assert!(FaceTiling::from_quad(&skewed, Direction::Up).is_none());
+    }
+
+    #[test]
+    fn slices_merge_into_rectangles() {
+        let size = 4;
+        // 1 1 2 .
+        // 1 1 2 .
+        // 1 1 1 1
+        let mut slice: Vec<Option<u8>> = [1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 1, 1, 0, 0, 0, 0].iter()
+            .map(|&face| (face != 0).then_some(face)).collect();
+        let rects = merge_slice(&mut slice, size);
+        assert_eq!(rects, vec![
+            GreedyRect { u: 0, v: 0, width: 2, height: 3, face: 1 },
+            GreedyRect { u: 2, v: 0, width: 1, height: 2, face: 2 },
+            GreedyRect { u: 2, v: 2, width: 2, height: 1, face: 1 },
+        ]);
+        assert!(slice.iter().all(Option::is_none));
+    }
+}
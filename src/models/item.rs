@@ -0,0 +1,276 @@
+use image::RgbaImage;
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::{Mat4, Vec2, Vec3};
+
+use crate::{direction::Direction, minecraft::filetypes::MCModelDisplay};
+
+use super::model::{BakedModel, VoxelElement, VoxelFace};
+
+/// The places an item model can be drawn in, each with its own transform
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DisplayContext {
+    ThirdPersonRightHand,
+    ThirdPersonLeftHand,
+    FirstPersonRightHand,
+    FirstPersonLeftHand,
+    Gui,
+    Head,
+    Ground,
+    Fixed,
+}
+
+pub const DISPLAY_CONTEXTS: [DisplayContext; 8] = [
+    DisplayContext::ThirdPersonRightHand,
+    DisplayContext::ThirdPersonLeftHand,
+    DisplayContext::FirstPersonRightHand,
+    DisplayContext::FirstPersonLeftHand,
+    DisplayContext::Gui,
+    DisplayContext::Head,
+    DisplayContext::Ground,
+    DisplayContext::Fixed,
+];
+
+impl DisplayContext {
+    /// The name used in model files, older files only have `thirdperson` and `firstperson` for the right hand
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "thirdperson_righthand" | "thirdperson" => Some(Self::ThirdPersonRightHand),
+            "thirdperson_lefthand" => Some(Self::ThirdPersonLeftHand),
+            "firstperson_righthand" | "firstperson" => Some(Self::FirstPersonRightHand),
+            "firstperson_lefthand" => Some(Self::FirstPersonLeftHand),
+            "gui" => Some(Self::Gui),
+            "head" => Some(Self::Head),
+            "ground" => Some(Self::Ground),
+            "fixed" => Some(Self::Fixed),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ThirdPersonRightHand => "thirdperson_righthand",
+            Self::ThirdPersonLeftHand => "thirdperson_lefthand",
+            Self::FirstPersonRightHand => "firstperson_righthand",
+            Self::FirstPersonLeftHand => "firstperson_lefthand",
+            Self::Gui => "gui",
+            Self::Head => "head",
+            Self::Ground => "ground",
+            Self::Fixed => "fixed",
+        }
+    }
+
+    /// A left hand without its own transform uses the right hand one
+    fn fallback(&self) -> Option<Self> {
+        match self {
+            Self::ThirdPersonLeftHand => Some(Self::ThirdPersonRightHand),
+            Self::FirstPersonLeftHand => Some(Self::FirstPersonRightHand),
+            _ => None,
+        }
+    }
+}
+
+/// Rotation in degrees, translation in sixteenths of a block and scale of a model in a display context
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelTransform {
+    pub rotation: Vec3,
+    pub translation: Vec3,
+    pub scale: Vec3,
+}
+
+impl ModelTransform {
+    pub const IDENTITY: Self = Self { rotation: Vec3::new(0.0, 0.0, 0.0), translation: Vec3::new(0.0, 0.0, 0.0), scale: Vec3::new(1.0, 1.0, 1.0) };
+
+    /// Translation is limited to 80 and scale to 4 in each axis, as the vanilla client does
+    pub fn new(rotation: Vec3, translation: Vec3, scale: Vec3) -> Self {
+        Self {
+            rotation,
+            translation: translation.clamped(Vec3::broadcast(-80.0), Vec3::broadcast(80.0)),
+            scale: scale.clamped(Vec3::broadcast(-4.0), Vec3::broadcast(4.0)),
+        }
+    }
+
+    /// Scales, rotates around z, x then y, and translates a model centered on the origin
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_translation(self.translation / 16.0)
+            * Mat4::from_rotation_y(self.rotation.y.to_radians())
+            * Mat4::from_rotation_x(self.rotation.x.to_radians())
+            * Mat4::from_rotation_z(self.rotation.z.to_radians())
+            * Mat4::from_nonuniform_scale(self.scale)
+    }
+}
+
+impl Default for ModelTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<&MCModelDisplay> for ModelTransform {
+    fn from(display: &MCModelDisplay) -> Self {
+        Self::new(display.rotation.into(), display.translation.into(), display.scale.into())
+    }
+}
+
+/// The transforms of a model by context, contexts without one are drawn untransformed
+#[derive(Clone, Debug, Default)]
+pub struct DisplayTransforms {
+    transforms: HashMap<DisplayContext, ModelTransform>,
+}
+
+impl DisplayTransforms {
+    pub fn set(&mut self, context: DisplayContext, transform: ModelTransform) {
+        self.transforms.insert(context, transform);
+    }
+
+    pub fn get(&self, context: DisplayContext) -> ModelTransform {
+        self.transforms.get(&context)
+            .or_else(|| context.fallback().and_then(|fallback| self.transforms.get(&fallback)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// An item model baked once for every display context, centered on the origin
+pub struct ItemModel {
+    contexts: HashMap<DisplayContext, BakedModel>,
+}
+
+impl ItemModel {
+    pub fn bake(model: &BakedModel, display: &DisplayTransforms) -> Self {
+        let center = Mat4::from_translation(Vec3::broadcast(-0.5));
+        let contexts = DISPLAY_CONTEXTS.iter()
+            .map(|context| (*context, model.transformed(display.get(*context).matrix() * center)))
+            .collect();
+        Self { contexts }
+    }
+
+    pub fn get(&self, context: DisplayContext) -> Option<&BakedModel> {
+        self.contexts.get(&context)
+    }
+}
+
+/// Where the items of builtin/generated are drawn, a pixel thick in the middle of the block
+const GENERATED_DEPTH: (f32, f32) = (7.5, 8.5);
+
+/// Consecutive pixels along a row or column that share an exposed edge
+fn runs(length: u32, exposed: impl Fn(u32) -> bool) -> Vec<(u32, u32)> {
+    let mut runs = vec![];
+    let mut start = None;
+    for i in 0..=length {
+        match (start, i < length && exposed(i)) {
+            (None, true) => start = Some(i),
+            (Some(first), false) => { runs.push((first, i - 1)); start = None; },
+            _ => {},
+        }
+    }
+    runs
+}
+
+/**
+ * The elements of a builtin/generated item, every layer is a flat sprite with a front and back face,
+ * and the edges between opaque and transparent pixels are extruded so the item has depth.
+ * `layers` are the texture variable of each layer along with its sprite
+ */
+pub fn generate_item_elements(layers: &[(String, RgbaImage)]) -> Vec<VoxelElement> {
+    let (near, far) = GENERATED_DEPTH;
+    let mut elements = vec![];
+    for (layer_index, (texture_variable, sprite)) in layers.iter().enumerate() {
+        let tint = layer_index as i32;
+        let face = || VoxelFace::new(format!("#{texture_variable}")).with_tint(tint);
+        // +Z shows the sprite as it is, -Z is mirrored so that both sides line up
+        elements.push(VoxelElement::new([0.0, 0.0, near], [16.0, 16.0, far])
+            .with_face(face(), Direction::West)
+            .with_face(face().with_uv([16.0, 0.0], [0.0, 16.0]), Direction::East));
+
+        let (width, height) = sprite.dimensions();
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let opaque = |x: i64, y: i64| x >= 0 && y >= 0 && x < width as i64 && y < height as i64 && sprite.get_pixel(x as u32, y as u32)[3] > 0;
+        let (pixel_width, pixel_height) = (16.0 / width as f32, 16.0 / height as f32);
+        // The box covering pixels x0..=x1, y0..=y1, the top row of the sprite is the top of the model
+        let pixel_box = |x0: u32, x1: u32, y0: u32, y1: u32| {
+            VoxelElement::new(
+                [x0 as f32 * pixel_width, 16.0 - (y1 + 1) as f32 * pixel_height, near],
+                [(x1 + 1) as f32 * pixel_width, 16.0 - y0 as f32 * pixel_height, far],
+            )
+        };
+        let texel = |x0: u32, x1: u32, y0: u32, y1: u32| {
+            (Vec2::new(x0 as f32 * pixel_width, y0 as f32 * pixel_height), Vec2::new((x1 + 1) as f32 * pixel_width, (y1 + 1) as f32 * pixel_height))
+        };
+
+        for y in 0..height {
+            for (direction, neighbour) in [(Direction::Up, -1), (Direction::Down, 1)] {
+                let exposed = |x: u32| opaque(x as i64, y as i64) && !opaque(x as i64, y as i64 + neighbour);
+                for (x0, x1) in runs(width, exposed) {
+                    let (uv_min, uv_max) = texel(x0, x1, y, y);
+                    // The bottom face runs along -x
+                    let uv = if direction == Direction::Down { (Vec2::new(uv_max.x, uv_min.y), Vec2::new(uv_min.x, uv_max.y)) } else { (uv_min, uv_max) };
+                    elements.push(pixel_box(x0, x1, y, y).with_face(face().with_uv(uv.0, uv.1), direction));
+                }
+            }
+        }
+        for x in 0..width {
+            for (direction, neighbour) in [(Direction::North, -1), (Direction::South, 1)] {
+                let exposed = |y: u32| opaque(x as i64, y as i64) && !opaque(x as i64 + neighbour, y as i64);
+                for (y0, y1) in runs(height, exposed) {
+                    let (uv_min, uv_max) = texel(x, x, y0, y1);
+                    elements.push(pixel_box(x, x, y0, y1).with_face(face().with_uv(uv_min, uv_max), direction));
+                }
+            }
+        }
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn sprite(width: u32, height: u32, opaque: &[(u32, u32)]) -> RgbaImage {
+        let mut image = RgbaImage::new(width, height);
+        for (x, y) in opaque {
+            image.put_pixel(*x, *y, Rgba([255, 255, 255, 255]));
+        }
+        image
+    }
+
+    #[test]
+    fn context_names_and_fallbacks() {
+        assert_eq!(DisplayContext::from_name("thirdperson"), Some(DisplayContext::ThirdPersonRightHand));
+        assert_eq!(DisplayContext::from_name("gui").map(|context| context.name()), Some("gui"));
+        let mut display = DisplayTransforms::default();
+        let held = ModelTransform::new(Vec3::new(0.0, 90.0, 0.0), Vec3::new(0.0, 200.0, 0.0), Vec3::broadcast(0.5));
+        display.set(DisplayContext::FirstPersonRightHand, held);
+        assert_eq!(display.get(DisplayContext::FirstPersonLeftHand), held);
+        assert_eq!(display.get(DisplayContext::Gui), ModelTransform::IDENTITY);
+        assert_eq!(held.translation.y, 80.0);
+    }
+
+    #[test]
+    fn transform_scales_then_translates() {
+        let transform = ModelTransform::new(Vec3::zero(), Vec3::new(16.0, 0.0, 0.0), Vec3::broadcast(0.5));
+        let point = transform.matrix().transform_point3(Vec3::new(1.0, 1.0, 1.0));
+        assert!((point - Vec3::new(1.5, 0.5, 0.5)).mag() < 1e-5);
+    }
+
+    #[test]
+    fn generated_edges_are_merged() {
+        let single = generate_item_elements(&[("layer0".into(), sprite(2, 2, &[(0, 0)]))]);
+        assert_eq!(single.len(), 5);
+        let full = generate_item_elements(&[("layer0".into(), sprite(2, 2, &[(0, 0), (1, 0), (0, 1), (1, 1)]))]);
+        assert_eq!(full.len(), 5);
+        let diagonal = generate_item_elements(&[("layer0".into(), sprite(2, 2, &[(0, 0), (1, 1)]))]);
+        assert_eq!(diagonal.len(), 9);
+        let empty = generate_item_elements(&[("layer0".into(), sprite(2, 2, &[]))]);
+        assert_eq!(empty.len(), 1);
+    }
+
+    #[test]
+    fn runs_split_on_gaps() {
+        assert_eq!(runs(5, |i| i != 2), vec![(0, 1), (3, 4)]);
+        assert_eq!(runs(3, |_| false), vec![]);
+    }
+}
@@ -1,12 +1,18 @@
+pub mod condition;
+pub mod entity;
+pub mod export;
+pub mod greedy;
+pub mod item;
 pub mod model;
 pub mod model_builder;
+pub mod validation;
 
-use model::BakedModel;
+use model::{BlockstateModel, WeightedBakedModel};
 use rustc_hash::FxHashMap as HashMap;
 use serde_json::Value;
 use crate::minecraft::{filetypes::MCBlockstateType, identifier::Identifier};
 
-use self::model::{VoxelModel, VoxelRotation};
+use self::{model::{VoxelModel, VariantRotation}, condition::{MultipartCondition, StatePropertyNames, variant_matches}};
 
 use super::sprites::Sprite;
 
@@ -20,33 +26,23 @@ pub enum BlockstateParseError {
     ModelIdentifierNotValid(String),
     #[error("No variants were matched")]
     NoMatchedVariants,
+    #[error("Weighted model list is empty")]
+    EmptyModelList,
+    #[error("Invalid multipart condition: {0}")]
+    InvalidCondition(String),
 }
 
 pub type BlockstateParseResult<T> = Result<T, BlockstateParseError>;
 
-pub fn state_contains_property(property: &String, conditions: &String, blockstate_identifier_string: &String) -> bool {
-    for condition in conditions.split("|") {
-        let propeqcondition = format!("{}={}", property, condition);
-        if blockstate_identifier_string.contains(&propeqcondition) {
-            return true;
-        }
-    } 
-    false
-}
-
-fn extract_model_rotation(model: &Value) -> Option<(u8, f64)> {
-    if let Value::Number(x) = &model["x"] {
-        Some((0, x.as_f64()))
-    } else if let Value::Number(y) = &model["y"] {
-        Some((1, y.as_f64()))
-    } else if let Value::Number(z) = &model["z"] {
-        Some((2, z.as_f64()))
-    } else {
-        None
-    }.map(|(axis, angle)| (axis, angle.unwrap_or(0.0)))
+/// The x and y rotations of a variant are applied together, x first
+fn extract_model_rotation(model: &Value) -> VariantRotation {
+    let x = model["x"].as_f64().unwrap_or(0.0);
+    let y = model["y"].as_f64().unwrap_or(0.0);
+    let uvlock = model["uvlock"].as_bool().unwrap_or(false);
+    VariantRotation::new(x as f32, y as f32, uvlock)
 }
 
-fn parse_model_value_to_identifier_and_rotation(variant_model: &Value) -> BlockstateParseResult<(Identifier, Option<(u8, f64)>)> {
+fn parse_model_value_to_identifier_and_rotation(variant_model: &Value) -> BlockstateParseResult<(Identifier, VariantRotation)> {
     variant_model["model"].as_str().map(|model_name| {
         let mut ident = Identifier::from(model_name);
         if !ident.get_name().starts_with("block/") {
@@ -57,68 +53,64 @@ fn parse_model_value_to_identifier_and_rotation(variant_model: &Value) -> Blocks
     }).ok_or(BlockstateParseError::ModelIdentifierNotString)
 }
 
-fn parse_variant_identifier_and_rotation(state_variants: &HashMap<String, Value>, blockstate_identifier_string: &String) 
-    -> BlockstateParseResult<(Identifier, Option<(u8, f64)>)> {
+/// A model entry is a single model, or a list of models where each has a weight (1 by default)
+fn parse_weighted_model_values(model_entry: &Value) -> BlockstateParseResult<Vec<(Identifier, VariantRotation, u32)>> {
+    match model_entry {
+        Value::Array(models) if models.is_empty() => Err(BlockstateParseError::EmptyModelList),
+        Value::Array(models) => models.iter().map(|model| {
+            let (identifier, rotation) = parse_model_value_to_identifier_and_rotation(model)?;
+            let weight = model["weight"].as_u64().unwrap_or(1).clamp(1, u32::MAX as u64) as u32;
+            Ok((identifier, rotation, weight))
+        }).collect(),
+        _ => parse_model_value_to_identifier_and_rotation(model_entry).map(|(identifier, rotation)| vec![(identifier, rotation, 1)]),
+    }
+}
+
+/// Bake every alternative of a model entry
+fn bake_weighted_models(models: Vec<(Identifier, VariantRotation, u32)>, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>)
+    -> BlockstateParseResult<WeightedBakedModel> {
+    let alternatives = models.into_iter().map(|(model_identifier, rotation, weight)| {
+        let model = voxel_models.get(&model_identifier).cloned().ok_or(BlockstateParseError::ModelIdentifierNotValid(model_identifier.to_string()))?;
+        Ok((model.bake_with_rotate(rotation, textures), weight))
+    }).collect::<BlockstateParseResult<Vec<_>>>()?;
+    Ok(WeightedBakedModel::new(alternatives))
+}
+
+fn parse_variant_identifier_and_rotation(state_variants: &HashMap<String, Value>, state_properties: &StatePropertyNames) 
+    -> BlockstateParseResult<Vec<(Identifier, VariantRotation, u32)>> {
     for (variant_properties, variant_model) in state_variants {
-        let mut valid_variant = true;
-        for variant_property in variant_properties.split(",") {
-            valid_variant &= blockstate_identifier_string.contains(variant_property);
-        }
-        if valid_variant {
-            return parse_model_value_to_identifier_and_rotation(variant_model);
+        if variant_matches(variant_properties, state_properties) {
+            return parse_weighted_model_values(variant_model);
         }
     }
     Err(BlockstateParseError::NoMatchedVariants)
 }
 
-fn generate_variant_blockstate_model(state_variants: &HashMap<String, Value>, blockstate_identifier_string: &String, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>)
-    -> BlockstateParseResult<BakedModel> {
-    let (model_identifier, variant_rotation) = parse_variant_identifier_and_rotation(state_variants, blockstate_identifier_string)?;
-    let model = voxel_models.get(&model_identifier).cloned().ok_or(BlockstateParseError::ModelIdentifierNotValid(model_identifier.to_string()))?;
-    let rotation = variant_rotation.map(|(axis, angle)| { VoxelRotation::new(angle as f32, axis, [8., 8., 8.], false) });
-    Ok(model.bake_with_rotate(rotation, &textures))
+fn generate_variant_blockstate_model(state_variants: &HashMap<String, Value>, state_properties: &StatePropertyNames, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>)
+    -> BlockstateParseResult<BlockstateModel> {
+    let models = parse_variant_identifier_and_rotation(state_variants, state_properties)?;
+    Ok(BlockstateModel::new(vec![bake_weighted_models(models, voxel_models, textures)?]))
 }
 
-fn generate_multipart_blockstate_model(multiparts: &Vec<Value>, blockstate_identifier_string: &String, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>) 
-    -> BlockstateParseResult<BakedModel> {
-    let mut applied_models: Vec<(Identifier, Option<VoxelRotation>)> = vec![];
+fn generate_multipart_blockstate_model(multiparts: &Vec<Value>, state_properties: &StatePropertyNames, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>) 
+    -> BlockstateParseResult<BlockstateModel> {
+    let mut parts = vec![];
     for part in multiparts {
-        let conditions_passed: bool = match &part["when"] {
-            Value::Object(values) => {
-                let mut passed = false;
-                for (property, conditions_list) in values {
-                    if let Value::String(conditions) = conditions_list {
-                        let property_passed = state_contains_property(property, conditions, blockstate_identifier_string);
-                        passed |= property_passed;
-                    }
-                }
-                passed
-            },
-            Value::Null => true,
-            _ => false,
-        };
-        if !conditions_passed { continue; }
-        let (model_identifier, model_rotation) = parse_model_value_to_identifier_and_rotation(&part["apply"])?;
-        let rotation = model_rotation.map(|(axis, angle)| { VoxelRotation::new(angle as f32, axis, [8., 8., 8.], false) });
-        applied_models.push((model_identifier, rotation));
-    }
-
-    let mut model = VoxelModel::new().bake(textures);
-    for applied_model in applied_models {
-        let baked_model = voxel_models[&applied_model.0].clone().bake_with_rotate(applied_model.1, textures);
-        model.combine(&baked_model);
+        if !MultipartCondition::parse(&part["when"])?.matches(state_properties) { continue; }
+        let models = parse_weighted_model_values(&part["apply"])?;
+        parts.push(bake_weighted_models(models, voxel_models, textures)?);
     }
-    Ok(model)
+    Ok(BlockstateModel::new(parts))
 }
  
 
-pub fn generate_blockstate_model(blockstate_file: &MCBlockstateType, blockstate_identifier_string: &String, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>) -> BlockstateParseResult<BakedModel> {
+pub fn generate_blockstate_model(blockstate_file: &MCBlockstateType, state_properties: &StatePropertyNames, voxel_models: &HashMap<Identifier, VoxelModel>, textures: &HashMap<Identifier, Sprite>) -> BlockstateParseResult<BlockstateModel> {
     match &blockstate_file {
         MCBlockstateType::Variants(state_variants) => {
-            generate_variant_blockstate_model(state_variants, blockstate_identifier_string, voxel_models, textures)
+            generate_variant_blockstate_model(state_variants, state_properties, voxel_models, textures)
         },
         MCBlockstateType::Multipart(multiparts) => {
-            generate_multipart_blockstate_model(multiparts, blockstate_identifier_string, voxel_models, textures)
+            generate_multipart_blockstate_model(multiparts, state_properties, voxel_models, textures)
         }
     }
 
@@ -1,8 +1,9 @@
 use std::ops::Rem;
-use ultraviolet::{Mat4, Vec2, Vec3};
+use ultraviolet::{IVec3, Mat4, Vec2, Vec3};
 use rustc_hash::FxHashMap as HashMap;
 
-use crate::{direction::{Direction, DIRECTIONS}, sprites::Sprite, minecraft::{identifier::Identifier, registry::SpriteRegister}};
+use super::item::{DisplayContext, DisplayTransforms, ModelTransform};
+use crate::{direction::{Direction, DIRECTIONS}, sprites::Sprite, minecraft::{identifier::Identifier, registry::SpriteRegister}, util::pos::{BlockPos, position_random}};
 
 const ONE_SIXTEENTH: f32 = 1.0 / 16.0;
 
@@ -24,6 +25,7 @@ impl<const SIZE: usize> ModelPoly<SIZE> {
 pub type ModelQuad = ModelPoly<4>;
 pub type ModelTriangle = ModelPoly<3>;
 
+#[derive(Clone)]
 pub struct BakedModel {
     quads: Vec<ModelQuad>,
     ambient_occlusion: bool,
@@ -46,6 +48,103 @@ impl BakedModel {
     pub fn combine(&mut self, other: &Self) {
         self.quads.extend(other.quads.clone());
     }
+
+    /// A copy moved by a matrix, the result is no longer aligned to the block grid so nothing is culled
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let quads = self.quads.iter().map(|quad| ModelQuad {
+            pos: quad.pos.map(|pos| matrix.transform_point3(pos)),
+            normal: matrix.transform_vec3(quad.normal).normalized(),
+            cullface: None,
+            ao_face: None,
+            ..*quad
+        }).collect();
+        Self { quads, ambient_occlusion: self.ambient_occlusion }
+    }
+}
+
+/// Alternative models of a blockstate with their weights, one is picked by the position of the block
+#[derive(Clone)]
+pub struct WeightedBakedModel {
+    alternatives: Vec<(BakedModel, u32)>,
+    total_weight: u32,
+}
+
+impl WeightedBakedModel {
+    pub fn new(alternatives: Vec<(BakedModel, u32)>) -> Self {
+        let alternatives: Vec<(BakedModel, u32)> = alternatives.into_iter().map(|(model, weight)| (model, weight.max(1))).collect();
+        let total_weight = alternatives.iter().map(|(_, weight)| weight).sum();
+        Self { alternatives, total_weight }
+    }
+
+    pub fn alternatives(&self) -> &[(BakedModel, u32)] {
+        &self.alternatives
+    }
+
+    /// Pick a model the way vanilla does from the value of position_random
+    pub fn select(&self, random: i64) -> Option<&BakedModel> {
+        if self.total_weight == 0 {
+            return None;
+        }
+        let mut weight = ((random as i32) >> 16).wrapping_abs() % self.total_weight as i32;
+        for (model, model_weight) in &self.alternatives {
+            weight -= *model_weight as i32;
+            if weight < 0 {
+                return Some(model);
+            }
+        }
+        self.alternatives.last().map(|(model, _)| model)
+    }
+}
+
+/**
+ * Every model drawn for a blockstate.
+ * A variant has a single part, a multipart has one for each case that applies,
+ * each part picks its own alternative from the same position
+ */
+#[derive(Clone)]
+pub struct BlockstateModel {
+    parts: Vec<WeightedBakedModel>,
+}
+
+impl BlockstateModel {
+    pub fn new(parts: Vec<WeightedBakedModel>) -> Self {
+        Self { parts }
+    }
+
+    pub fn parts(&self) -> &[WeightedBakedModel] {
+        &self.parts
+    }
+
+    /// The models to draw for a block at a position
+    pub fn select(&self, pos: &BlockPos) -> impl Iterator<Item = &BakedModel> {
+        let random = position_random(pos);
+        self.parts.iter().filter_map(move |part| part.select(random))
+    }
+}
+
+impl From<BakedModel> for BlockstateModel {
+    fn from(model: BakedModel) -> Self {
+        Self::new(vec![WeightedBakedModel::new(vec![(model, 1)])])
+    }
+}
+
+/// Models that are not made of elements in a file, they are filled in by the baker
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuiltinModel {
+    /// An item extruded from its layer textures
+    Generated,
+    /// Drawn by an entity renderer instead of a model
+    Entity,
+}
+
+impl BuiltinModel {
+    pub fn from_identifier(identifier: &Identifier) -> Option<Self> {
+        match identifier.get_name().as_str() {
+            "builtin/generated" => Some(Self::Generated),
+            "builtin/entity" => Some(Self::Entity),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -53,6 +152,32 @@ pub struct VoxelModel {
     textures: HashMap<String, String>,
     elements: Vec<VoxelElement>,
     ambient_occlusion: bool,
+    display: DisplayTransforms,
+    builtin: Option<BuiltinModel>,
+}
+
+/// The 8 corners of a cuboid, in the order expected by get_face_vertices_on_cuboid
+fn get_cuboid_corners(min_pos: Vec3, max_pos: Vec3) -> [Vec3; 8] {
+    [
+        Vec3::new(min_pos.x, max_pos.y, min_pos.z), // 0
+        Vec3::new(min_pos.x, max_pos.y, max_pos.z), // 1
+        Vec3::new(min_pos.x, min_pos.y, min_pos.z), // 2
+        Vec3::new(min_pos.x, min_pos.y, max_pos.z), // 3
+        Vec3::new(max_pos.x, max_pos.y, max_pos.z), // 4
+        Vec3::new(max_pos.x, max_pos.y, min_pos.z), // 5
+        Vec3::new(max_pos.x, min_pos.y, max_pos.z), // 6
+        Vec3::new(max_pos.x, min_pos.y, min_pos.z), // 7
+    ]
+}
+
+/**
+ * Project a point of a block (0..1) onto the texture space of a face (0..16),
+ * the first vertex of the face is the minimum uv and the last is the maximum
+ */
+fn project_uv_on_face(face: Direction, point: Vec3) -> Vec2 {
+    let corners = get_face_vertices_on_cuboid(face, &get_cuboid_corners(Vec3::zero(), Vec3::one()));
+    let offset = point - corners[0];
+    Vec2::new((corners[1] - corners[0]).dot(offset), (corners[2] - corners[0]).dot(offset)) * 16.0
 }
 
 fn get_face_vertices_on_cuboid(face: Direction, points: &[Vec3; 8]) -> [Vec3; 4] {
@@ -102,6 +227,8 @@ impl VoxelModel {
             textures: HashMap::default(),
             elements: Vec::new(),
             ambient_occlusion: true,
+            display: DisplayTransforms::default(),
+            builtin: None,
         }
     }
     pub fn from_template(template: &VoxelModel) -> Self {
@@ -135,6 +262,34 @@ impl VoxelModel {
     pub fn with_ambient_occlusion_nc(&mut self, ambient_occlusion: bool) {
         self.ambient_occlusion = ambient_occlusion;
     }
+    pub fn with_display(mut self, context: DisplayContext, transform: ModelTransform) -> Self {
+        self.display.set(context, transform);
+        self
+    }
+    pub fn with_builtin(mut self, builtin: BuiltinModel) -> Self {
+        self.builtin = Some(builtin);
+        self
+    }
+    pub fn with_display_nc(&mut self, context: DisplayContext, transform: ModelTransform) {
+        self.display.set(context, transform);
+    }
+
+    pub fn display(&self) -> &DisplayTransforms {
+        &self.display
+    }
+
+    pub fn builtin(&self) -> Option<BuiltinModel> {
+        self.builtin
+    }
+
+    pub fn has_texture(&self, texture_variable: &str) -> bool {
+        self.textures.contains_key(texture_variable)
+    }
+
+    /// The sprite a texture variable resolves to
+    pub fn resolve_texture(&self, texture_variable: &str) -> Identifier {
+        Identifier::from(Self::find_texture_in_map(&self.textures, format!("#{texture_variable}")))
+    }
 
     fn rotate_points(points: &mut [Vec3; 8], rescale: bool, angle: f32, axis: u8, origin: Vec3) {
         let angle = angle.to_radians();
@@ -164,17 +319,6 @@ impl VoxelModel {
         }
     }
 
-    fn rotate_direction(dir: Option<Direction>, angle: u8) -> Option<Direction> {
-        dir.map(|dir| {
-            match angle {
-                1 => { dir.ccw() },
-                2 => { dir.reverse_horizontal() },
-                3 => { dir.cw() },
-                _ => { dir },
-            }
-        })
-    }
-
     pub fn flatten_angle_to_index(angle: f32) -> u8 {
         ((angle as u32) / 90u32).rem(4).try_into().unwrap_or(0)
     }
@@ -193,48 +337,45 @@ impl VoxelModel {
         self.elements.clear();
     }
 
+    /**
+     * The uvs of a face without explicit uvs, the part of the texture the face covers on a full block,
+     * so that partial elements like slabs and torches are not stretched
+     */
+    fn default_face_uv(face: Direction, corners: &[Vec3; 8]) -> (Vec2, Vec2) {
+        let face_corners = get_face_vertices_on_cuboid(face, corners);
+        (project_uv_on_face(face, face_corners[0]), project_uv_on_face(face, face_corners[3]))
+    }
+
     pub fn bake(self, textures: &SpriteRegister) -> BakedModel {
-        self.bake_with_rotate(None, textures)
+        self.bake_with_rotate(VariantRotation::default(), textures)
     }
 
-    pub fn bake_with_rotate(self, variant_rotation: Option<VoxelRotation>, texture_register: &SpriteRegister) -> BakedModel {
+    pub fn bake_with_rotate(self, variant_rotation: VariantRotation, texture_register: &SpriteRegister) -> BakedModel {
         let mut quads = vec![];
         let textures = self.textures;
         for element in &self.elements {
             let min_pos = element.from * ONE_SIXTEENTH;
             let max_pos = element.to * ONE_SIXTEENTH;
 
-            let points = &mut [
-                Vec3::new(min_pos.x, max_pos.y, min_pos.z), // 0
-                Vec3::new(min_pos.x, max_pos.y, max_pos.z), // 1
-                Vec3::new(min_pos.x, min_pos.y, min_pos.z), // 2
-                Vec3::new(min_pos.x, min_pos.y, max_pos.z), // 3
-                Vec3::new(max_pos.x, max_pos.y, max_pos.z), // 4
-                Vec3::new(max_pos.x, max_pos.y, min_pos.z), // 5
-                Vec3::new(max_pos.x, min_pos.y, max_pos.z), // 6
-                Vec3::new(max_pos.x, min_pos.y, min_pos.z), // 7
-            ];
+            let corners = get_cuboid_corners(min_pos, max_pos);
+            let points = &mut corners.clone();
 
             if let Some(VoxelRotation{rescale , angle, axis, origin }) = &element.rotation {
                 Self::rotate_points(points, *rescale, *angle, *axis, *origin);
             }
-            let variant_rotation_angle =
-            if let Some(VoxelRotation{rescale , angle, axis, origin }) = variant_rotation {
-                Self::rotate_points(points, rescale, angle, axis, origin);
-                Self::flatten_angle_to_index(angle)
-            } else {
-                0u8
-            };
+            for point in points.iter_mut() {
+                *point = variant_rotation.rotate_point(*point);
+            }
 
             for (index, face) in element.faces.iter().enumerate() {
                 if let Some(face) = face {
                     let face_direction = DIRECTIONS[index];
+                    let rotated_direction = variant_rotation.rotate_direction(face_direction);
                     let pos = get_face_vertices_on_cuboid(face_direction, points);
                     let (uv_min, uv_max) = if let Some(uv) = face.uv {
                         (uv[0], uv[1])
                     } else {
-                        // TODO: default uv's to the size of the face
-                        ((0.0, 0.0).into(), (16.0, 16.0).into())
+                        Self::default_face_uv(face_direction, &corners)
                     };
                     let uvs = {
                         let texture = Self::find_texture_in_map(&textures, face.texture_variable.clone());
@@ -246,12 +387,14 @@ impl VoxelModel {
                             (Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0)) 
                         };
                         let uv_range = texture_extent_max - texture_extent_min;
-                        let (quad_uv_min, quad_uv_max) = (uv_min * ONE_SIXTEENTH, uv_max * ONE_SIXTEENTH);
-
-                        // let (uv_min, uv_max) = (uv_min + uv_range * quad_uv_min, uv_min + uv_range * quad_uv_max);
-                        let (uv_min, uv_max) = (texture_extent_min + uv_range * quad_uv_min, texture_extent_min + uv_range * quad_uv_max);
+                        let face_uvs: [Vec2; 4] = [uv_min, (uv_max.x, uv_min.y).into(), (uv_min.x, uv_max.y).into(), uv_max];
+                        let face_uvs = if variant_rotation.uvlock() {
+                            face_uvs.map(|uv| variant_rotation.lock_uv(face_direction, uv))
+                        } else {
+                            face_uvs
+                        };
 
-                        [uv_min, (uv_max.x, uv_min.y).into(), (uv_min.x, uv_max.y).into(), uv_max]
+                        face_uvs.map(|uv| texture_extent_min + uv_range * (uv * ONE_SIXTEENTH))
                     };
                     let uvs = match face.rotation {
                         1 => { [ uvs[2], uvs[0], uvs[3], uvs[1] ] },
@@ -259,10 +402,11 @@ impl VoxelModel {
                         3 => { [ uvs[1], uvs[3], uvs[0], uvs[2] ] },
                         _ => { uvs },
                     };
+                    let (pos, uvs) = variant_rotation.reorder_face(face_direction, pos, uvs);
                     let color = (1.0, 1.0, 1.0).into();
-                    let cullface = Self::rotate_direction(face.cullface, variant_rotation_angle);
+                    let cullface = face.cullface.map(|cullface| variant_rotation.rotate_direction(cullface));
                     let ao_face = if element.rotation.is_some() { None } else { cullface.clone() };
-                    let normal = face_direction.get_float_vector();
+                    let normal = rotated_direction.get_float_vector();
                     let tint_index = face.tint_index;
                     quads.push( ModelQuad { 
                         pos, 
@@ -409,3 +553,142 @@ impl VoxelRotation {
         }
     }
 }
+
+/**
+ * The rotation applied to a whole model by a blockstate variant, around the center of the block.
+ * Rotates by x first then y, both in steps of 90 degrees
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VariantRotation {
+    x: u8,
+    y: u8,
+    uvlock: bool,
+}
+
+impl VariantRotation {
+    /// Angles are in degrees and snapped down to a multiple of 90
+    pub fn new(x: f32, y: f32, uvlock: bool) -> Self {
+        Self {
+            x: VoxelModel::flatten_angle_to_index(x),
+            y: VoxelModel::flatten_angle_to_index(y),
+            uvlock,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.x == 0 && self.y == 0
+    }
+
+    /// Textures keep their orientation in the world instead of rotating with the model
+    pub fn uvlock(&self) -> bool {
+        self.uvlock
+    }
+
+    /// Rotate a vector around the origin, exact for quarter turns unlike a rotation matrix
+    pub fn rotate_vector(&self, vector: Vec3) -> Vec3 {
+        let mut vector = vector;
+        for _ in 0..self.x {
+            vector = Vec3::new(vector.x, -vector.z, vector.y);
+        }
+        for _ in 0..self.y {
+            vector = Vec3::new(vector.z, vector.y, -vector.x);
+        }
+        vector
+    }
+
+    /// Rotate a point of the model around the center of the block
+    pub fn rotate_point(&self, point: Vec3) -> Vec3 {
+        let center = Vec3::broadcast(0.5);
+        self.rotate_vector(point - center) + center
+    }
+
+    pub fn rotate_direction(&self, direction: Direction) -> Direction {
+        let rotated = self.rotate_vector(direction.get_float_vector());
+        let rotated = IVec3::new(rotated.x.round() as i32, rotated.y.round() as i32, rotated.z.round() as i32);
+        Direction::from_int_vector(rotated).unwrap_or(direction)
+    }
+
+    /**
+     * Where a uv of `face` lands for uvlock, the texture stays aligned to the world
+     * so the point the uv covers is rotated and projected onto the face it now faces
+     */
+    fn lock_uv(&self, face: Direction, uv: Vec2) -> Vec2 {
+        let corners = get_face_vertices_on_cuboid(face, &get_cuboid_corners(Vec3::zero(), Vec3::one()));
+        let point = corners[0] + (corners[1] - corners[0]) * (uv.x / 16.0) + (corners[2] - corners[0]) * (uv.y / 16.0);
+        project_uv_on_face(self.rotate_direction(face), self.rotate_point(point))
+    }
+
+    /**
+     * Move the vertices of a rotated face into the vertex order of the face it now points to,
+     * ambient occlusion is sampled per vertex in that order
+     */
+    fn reorder_face(&self, face: Direction, pos: [Vec3; 4], uvs: [Vec2; 4]) -> ([Vec3; 4], [Vec2; 4]) {
+        if self.is_identity() {
+            return (pos, uvs);
+        }
+        let unit_cube = get_cuboid_corners(Vec3::zero(), Vec3::one());
+        let source_corners = get_face_vertices_on_cuboid(face, &unit_cube);
+        let target_corners = get_face_vertices_on_cuboid(self.rotate_direction(face), &unit_cube);
+        let (mut new_pos, mut new_uvs) = (pos, uvs);
+        for (index, corner) in source_corners.iter().enumerate() {
+            let rotated = self.rotate_point(*corner);
+            if let Some(target) = target_corners.iter().position(|target| (*target - rotated).mag_sq() < 1e-4) {
+                new_pos[target] = pos[index];
+                new_uvs[target] = uvs[index];
+            }
+        }
+        (new_pos, new_uvs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_rotation_composes_x_then_y() {
+        let rotation = VariantRotation::new(90.0, 90.0, false);
+        assert_eq!(rotation.rotate_direction(Direction::Up), VariantRotation::new(0.0, 90.0, false).rotate_direction(Direction::West));
+        assert_eq!(VariantRotation::new(0.0, 90.0, false).rotate_direction(Direction::North), Direction::North.ccw());
+        assert_eq!(VariantRotation::new(180.0, 0.0, false).rotate_direction(Direction::Up), Direction::Down);
+        assert_eq!(rotation.rotate_point(Vec3::broadcast(0.5)), Vec3::broadcast(0.5));
+    }
+
+    #[test]
+    fn weighted_selection_follows_weights() {
+        let model = WeightedBakedModel::new(vec![(BakedModel::new(), 3), (BakedModel::new(), 1)]);
+        let first = &model.alternatives()[0].0 as *const BakedModel;
+        let picked_first = (0..64).flat_map(|x| (0..64).map(move |z| BlockPos::new(x, 64, z)))
+            .filter(|pos| std::ptr::eq(model.select(position_random(pos)).unwrap(), first))
+            .count();
+        assert!((2600..3500).contains(&picked_first), "{picked_first}");
+        let pos = BlockPos::new(-12, 70, 5);
+        assert!(std::ptr::eq(model.select(position_random(&pos)).unwrap(), model.select(position_random(&pos)).unwrap()));
+    }
+
+    #[test]
+    fn default_uvs_follow_element_bounds() {
+        let slab = get_cuboid_corners(Vec3::zero(), Vec3::new(1.0, 0.5, 1.0));
+        assert_eq!(VoxelModel::default_face_uv(Direction::Up, &slab), (Vec2::new(0.0, 0.0), Vec2::new(16.0, 16.0)));
+        assert_eq!(VoxelModel::default_face_uv(Direction::North, &slab), (Vec2::new(0.0, 8.0), Vec2::new(16.0, 16.0)));
+        let torch = get_cuboid_corners(Vec3::new(7.0, 0.0, 7.0) / 16.0, Vec3::new(9.0, 10.0, 9.0) / 16.0);
+        let (min, max) = VoxelModel::default_face_uv(Direction::South, &torch);
+        assert!((min - Vec2::new(7.0, 6.0)).mag() < 1e-4 && (max - Vec2::new(9.0, 16.0)).mag() < 1e-4, "{min:?} {max:?}");
+    }
+
+    #[test]
+    fn uvlock_projects_onto_rotated_face() {
+        assert_eq!(project_uv_on_face(Direction::Up, Vec3::new(0.0, 1.0, 0.0)), Vec2::new(0.0, 0.0));
+        assert_eq!(project_uv_on_face(Direction::Up, Vec3::new(1.0, 1.0, 1.0)), Vec2::new(16.0, 16.0));
+        let rotation = VariantRotation::new(0.0, 90.0, true);
+        let corners = get_face_vertices_on_cuboid(Direction::Up, &get_cuboid_corners(Vec3::zero(), Vec3::one()));
+        let uvs = [Vec2::new(0.0, 0.0), Vec2::new(16.0, 0.0), Vec2::new(0.0, 16.0), Vec2::new(16.0, 16.0)];
+        let (pos, reordered) = rotation.reorder_face(Direction::Up, corners.map(|c| rotation.rotate_point(c)), uvs.map(|uv| rotation.lock_uv(Direction::Up, uv)));
+        assert_eq!(pos, corners);
+        assert_eq!(reordered, uvs);
+        // An explicit uv keeps covering the same half of the block in the world
+        let half = [Vec2::new(0.0, 0.0), Vec2::new(8.0, 0.0), Vec2::new(0.0, 16.0), Vec2::new(8.0, 16.0)];
+        let locked = half.map(|uv| rotation.lock_uv(Direction::Up, uv));
+        assert_eq!(locked, [Vec2::new(0.0, 16.0), Vec2::new(0.0, 8.0), Vec2::new(16.0, 16.0), Vec2::new(16.0, 8.0)]);
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde_json::Value;
+
+use crate::minecraft::{filetypes::{MCModel, MCBlockstateType}, identifier::Identifier, registry::{BlockStateRegister, PropertyRegister}};
+
+use super::{condition::MultipartCondition, parse_variant_identifier_and_rotation, parse_weighted_model_values, BlockstateParseError};
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+pub enum ValidationIssue {
+    #[error("Model {model} has a parent {parent} that does not exist")]
+    MissingParent { model: Identifier, parent: Identifier },
+    #[error("Model parents form a cycle: {}", format_chain(.chain))]
+    ParentCycle { chain: Vec<Identifier> },
+    #[error("Model {model} uses the undefined texture variable #{variable}")]
+    UndefinedTextureVariable { model: Identifier, variable: String },
+    #[error("Model {model} uses the unknown sprite {sprite}")]
+    UnknownSprite { model: Identifier, sprite: Identifier },
+    #[error("Blockstate {blockstate} uses the unknown model {model}")]
+    UnknownModel { blockstate: Identifier, model: Identifier },
+    #[error("Blockstate {blockstate} has an invalid model entry: {reason}")]
+    InvalidModelEntry { blockstate: Identifier, reason: String },
+    #[error("Block {block} has no blockstate file")]
+    MissingBlockstateFile { block: Identifier },
+    #[error("State {state} is not covered by any variant")]
+    UncoveredState { state: Identifier },
+}
+
+fn format_chain(chain: &[Identifier]) -> String {
+    chain.iter().map(Identifier::to_string).collect::<Vec<_>>().join(" -> ")
+}
+
+/// Every issue found in a set of resources, in the order they were found and without duplicates
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, issue: ValidationIssue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        write!(f, "{} issue(s) found", self.issues.len())
+    }
+}
+
+/**
+ * Follow the parents of a model.
+ * Returns the chain from the model up to its root, the root is missing if it has no model file,
+ * or Err with the looping part of the chain if the parents form a cycle
+ */
+pub fn parent_chain(model: &Identifier, model_files: &HashMap<Identifier, MCModel>) -> Result<Vec<Identifier>, Vec<Identifier>> {
+    let mut chain = vec![model.clone()];
+    let mut current = model_files.get(model);
+    while let Some(parent) = current.and_then(MCModel::get_parent) {
+        let parent = Identifier::from(parent);
+        if let Some(start) = chain.iter().position(|visited| *visited == parent) {
+            let mut cycle = chain.split_off(start);
+            cycle.push(parent);
+            return Err(cycle);
+        }
+        current = model_files.get(&parent);
+        chain.push(parent);
+    }
+    Ok(chain)
+}
+
+/// Rotate a cycle so it starts at its smallest identifier, the same cycle found from different models is reported once
+fn normalize_cycle(mut cycle: Vec<Identifier>) -> Vec<Identifier> {
+    cycle.pop();
+    let start = cycle.iter().enumerate().min_by_key(|(_, id)| id.to_string()).map(|(index, _)| index).unwrap_or(0);
+    cycle.rotate_left(start);
+    if let Some(first) = cycle.first().cloned() {
+        cycle.push(first);
+    }
+    cycle
+}
+
+/// Resolve a #variable through the texture map, Err with the variable that is undefined or loops
+fn resolve_texture(textures: &HashMap<String, String>, texture: &str) -> Result<String, String> {
+    let mut current = texture.to_string();
+    for _ in 0..=textures.len() {
+        match current.strip_prefix('#') {
+            Some(variable) => {
+                current = textures.get(variable).cloned().ok_or_else(|| variable.to_string())?;
+            },
+            None => return Ok(current),
+        }
+    }
+    Err(texture.trim_start_matches('#').to_string())
+}
+
+/**
+ * Check the parents and textures of every model.
+ * Texture variables are only checked on models that no other model inherits from,
+ * templates like block/cube are expected to leave them to their children
+ */
+pub fn validate_models(model_files: &HashMap<Identifier, MCModel>, sprites: &HashSet<Identifier>, report: &mut ValidationReport) {
+    let parents: HashSet<Identifier> = model_files.values().filter_map(MCModel::get_parent).map(Identifier::from).collect();
+    let mut model_ids: Vec<&Identifier> = model_files.keys().collect();
+    model_ids.sort_by_key(|id| id.to_string());
+    for model_id in model_ids {
+        let chain = match parent_chain(model_id, model_files) {
+            Ok(chain) => chain,
+            Err(cycle) => {
+                report.push(ValidationIssue::ParentCycle { chain: normalize_cycle(cycle) });
+                continue;
+            }
+        };
+        if let [.., child, root] = chain.as_slice() {
+            if !model_files.contains_key(root) {
+                report.push(ValidationIssue::MissingParent { model: child.clone(), parent: root.clone() });
+                continue;
+            }
+        }
+        if parents.contains(model_id) {
+            continue;
+        }
+
+        // Children override the textures of their parents, the nearest model with elements provides them
+        let mut textures = HashMap::default();
+        for model in chain.iter().rev().filter_map(|id| model_files.get(id)) {
+            textures.extend(model.textures());
+        }
+        let elements = chain.iter().filter_map(|id| model_files.get(id)).map(MCModel::elements).find(|elements| !elements.is_empty()).unwrap_or_default();
+        for face in elements.iter().flat_map(|element| element.faces.values()) {
+            match resolve_texture(&textures, &face.texture) {
+                Ok(sprite) => {
+                    let sprite = Identifier::from(sprite);
+                    if !sprites.contains(&sprite) {
+                        report.push(ValidationIssue::UnknownSprite { model: model_id.clone(), sprite });
+                    }
+                },
+                Err(variable) => report.push(ValidationIssue::UndefinedTextureVariable { model: model_id.clone(), variable }),
+            }
+        }
+    }
+}
+
+fn check_model_entry(blockstate: &Identifier, entry: &Value, model_files: &HashMap<Identifier, MCModel>, report: &mut ValidationReport) {
+    match parse_weighted_model_values(entry) {
+        Ok(models) => {
+            for (model, _, _) in models.into_iter().filter(|(model, _, _)| !model_files.contains_key(model)) {
+                report.push(ValidationIssue::UnknownModel { blockstate: blockstate.clone(), model });
+            }
+        },
+        Err(e) => report.push(ValidationIssue::InvalidModelEntry { blockstate: blockstate.clone(), reason: e.to_string() }),
+    }
+}
+
+/// Check that every registered state has a blockstate file whose variants cover it, and that every used model exists
+pub fn validate_blockstates(blockstate_files: &HashMap<Identifier, MCBlockstateType>, model_files: &HashMap<Identifier, MCModel>, states: &BlockStateRegister, properties: &PropertyRegister, report: &mut ValidationReport) {
+    let mut blockstate_ids: Vec<&Identifier> = blockstate_files.keys().collect();
+    blockstate_ids.sort_by_key(|id| id.to_string());
+    for blockstate_id in blockstate_ids {
+        match &blockstate_files[blockstate_id] {
+            MCBlockstateType::Variants(variants) => {
+                for entry in variants.values() {
+                    check_model_entry(blockstate_id, entry, model_files, report);
+                }
+            },
+            MCBlockstateType::Multipart(parts) => {
+                for part in parts {
+                    check_model_entry(blockstate_id, &part["apply"], model_files, report);
+                    if let Err(e) = MultipartCondition::parse(&part["when"]) {
+                        report.push(ValidationIssue::InvalidModelEntry { blockstate: blockstate_id.clone(), reason: e.to_string() });
+                    }
+                }
+            },
+        }
+    }
+
+    for state in states.get_elements() {
+        let block = state.get_block_identifier();
+        match blockstate_files.get(block) {
+            Some(MCBlockstateType::Variants(variants)) => {
+                if let Err(BlockstateParseError::NoMatchedVariants) = parse_variant_identifier_and_rotation(variants, &state.get_property_names(properties)) {
+                    report.push(ValidationIssue::UncoveredState { state: state.get_state_identifier().clone() });
+                }
+            },
+            Some(MCBlockstateType::Multipart(_)) => {},
+            None => report.push(ValidationIssue::MissingBlockstateFile { block: block.clone() }),
+        }
+    }
+}
+
+/// Run every check over a set of loaded resources
+pub fn validate_resources(model_files: &HashMap<Identifier, MCModel>, blockstate_files: &HashMap<Identifier, MCBlockstateType>, sprites: &HashSet<Identifier>, states: &BlockStateRegister, properties: &PropertyRegister) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    validate_models(model_files, sprites, &mut report);
+    validate_blockstates(blockstate_files, model_files, states, properties, &mut report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block::{block_factory::BlockFactory, properties::PropertyDefinition, Block}, minecraft::registry::Registry};
+
+    fn models(json: &[(&str, &str)]) -> HashMap<Identifier, MCModel> {
+        json.iter().map(|(id, json)| (Identifier::from_str(id), serde_json::from_str(json).unwrap())).collect()
+    }
+
+    const CUBE: &str = r##"{"elements": [{"from": [0, 0, 0], "to": [16, 16, 16], "faces": {"up": {"texture": "#top"}, "down": {"texture": "#bottom"}}}]}"##;
+
+    #[test]
+    fn parent_cycles_are_found() {
+        let files = models(&[
+            ("block/a", r#"{"parent": "block/b"}"#),
+            ("block/b", r#"{"parent": "block/c"}"#),
+            ("block/c", r#"{"parent": "block/a"}"#),
+        ]);
+        assert!(parent_chain(&Identifier::from_str("block/a"), &files).is_err());
+        let mut report = ValidationReport::default();
+        validate_models(&files, &HashSet::default(), &mut report);
+        assert_eq!(report.issues().len(), 1);
+        assert!(matches!(&report.issues()[0], ValidationIssue::ParentCycle { chain } if chain.len() == 4));
+    }
+
+    #[test]
+    fn missing_parents_and_textures() {
+        let files = models(&[
+            ("block/cube", CUBE),
+            ("block/stone", r#"{"parent": "block/cube", "textures": {"top": "block/stone"}}"#),
+            ("block/orphan", r#"{"parent": "block/nothing"}"#),
+        ]);
+        let sprites: HashSet<Identifier> = [Identifier::from_str("block/stone")].into_iter().collect();
+        let mut report = ValidationReport::default();
+        validate_models(&files, &sprites, &mut report);
+        let issues = report.issues();
+        assert!(issues.contains(&ValidationIssue::MissingParent { model: Identifier::from_str("block/orphan"), parent: Identifier::from_str("block/nothing") }));
+        assert!(issues.contains(&ValidationIssue::UndefinedTextureVariable { model: Identifier::from_str("block/stone"), variable: "bottom".into() }));
+        assert_eq!(issues.len(), 2);
+    }
+
+    #[test]
+    fn unknown_sprites_and_texture_loops() {
+        let files = models(&[
+            ("block/cube", CUBE),
+            ("block/dirt", r##"{"parent": "block/cube", "textures": {"top": "block/dirt", "bottom": "#side", "side": "#bottom"}}"##),
+        ]);
+        let mut report = ValidationReport::default();
+        validate_models(&files, &HashSet::default(), &mut report);
+        let issues = report.issues();
+        assert!(issues.contains(&ValidationIssue::UnknownSprite { model: Identifier::from_str("block/dirt"), sprite: Identifier::from_str("block/dirt") }));
+        assert!(issues.iter().any(|issue| matches!(issue, ValidationIssue::UndefinedTextureVariable { .. })));
+    }
+
+    #[test]
+    fn uncovered_states_and_missing_blockstate_files() {
+        let mut registry = Registry::new();
+        registry.get_property_register_mut().insert(PropertyDefinition::new("minecraft:block_half".into(), &["bottom", "top"]));
+        for block in [BlockFactory::new("slab").properties(&[("half", "minecraft:block_half")]).build(), BlockFactory::new("stone").build()] {
+            let block_id = registry.get_block_register_mut().insert(block);
+            let block = registry.get_block_register().get_element_from_index(block_id).unwrap();
+            for state in Block::map_states(block, &mut registry) {
+                registry.get_blockstate_register_mut().insert_pointer(state);
+            }
+        }
+        let blockstate_files: HashMap<Identifier, MCBlockstateType> = [
+            (Identifier::from_str("slab"), serde_json::from_str(r#"{"variants": {"half=bottom": {"model": "block/cube"}}}"#).unwrap()),
+        ].into_iter().collect();
+        let model_files = models(&[("block/cube", CUBE)]);
+
+        let mut report = ValidationReport::default();
+        validate_blockstates(&blockstate_files, &model_files, registry.get_blockstate_register(), registry.get_property_register(), &mut report);
+        let top_slab = registry.get_blockstate_register().get_elements().iter()
+            .find(|state| state.get_property_names(registry.get_property_register()).get("half").map(String::as_str) == Some("top"))
+            .unwrap();
+        let issues = report.issues();
+        assert!(issues.contains(&ValidationIssue::UncoveredState { state: top_slab.get_state_identifier().clone() }));
+        assert!(issues.contains(&ValidationIssue::MissingBlockstateFile { block: Identifier::from_str("stone") }));
+        assert_eq!(issues.len(), 2);
+    }
+}
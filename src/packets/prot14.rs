@@ -169,6 +169,13 @@ pub struct ExplosionData {
 #[derive(Pod, Zeroable, Debug, Clone, Copy)]
 pub struct ExplosionBlockData { x: i8, y: i8, z: i8 }
 
+impl ExplosionData {
+    /// The blocks the explosion destroyed, relative to the block it went off in
+    pub fn get_offsets(&self) -> impl Iterator<Item = (i8, i8, i8)> + '_ {
+        self.blocks.iter().map(|block| (block.x, block.y, block.z))
+    }
+}
+
 impl PacketParseable for ExplosionData {
     fn to_packet_bytes(&self) -> Vec<u8> {
         let d1 = (self.blocks.len() as i32).to_packet_bytes();
@@ -22,8 +22,12 @@ pub trait ResourceSystem {
     fn domain(&self) -> &str;
     fn categories(&self) -> &[ResourceCategory];
     fn try_load_file(&mut self, category: &str, namespace: &str, file_name: &str, file_extension: &str, contents: &[u8]);
+    /// Called with the contents of the pack.mcmeta at the root of every pack before its files are loaded
+    fn try_load_pack_meta(&mut self, _contents: &[u8]) {}
 }
 
+const PACK_META_FILE_NAME: &str = "pack.mcmeta";
+
 #[derive(Clone, Debug)]
 pub enum ResourceSource {
     Zip(PathBuf),
@@ -80,6 +84,9 @@ impl ResourceLoader {
             log::warn!("Path {} is an invald folder", pack_path.display());
             return;
         }
+        if let Ok(contents) = std::fs::read(pack_path.join(PACK_META_FILE_NAME)) {
+            system.try_load_pack_meta(&contents);
+        }
         let domain_path = pack_path.join(system.domain());
         if !(domain_path.exists() && domain_path.is_dir()) {
             log::warn!("Domain {} is not found in pack {}", domain_path.display(), pack_path.display());
@@ -124,6 +131,10 @@ impl ResourceLoader {
         let zip_bytes = std::fs::read(&pack_path)?;
         let mut zip = zip::ZipArchive::new(Cursor::new(zip_bytes))?;
         let mut buffer = vec![];
+        if let Ok(mut file) = zip.by_name(PACK_META_FILE_NAME) {
+            std::io::Read::read_to_end(&mut file, &mut buffer)?;
+            system.try_load_pack_meta(&buffer);
+        }
         for i in 0..zip.len() {
             let mut file = zip.by_index(i)?;
             if file.is_dir() {
@@ -0,0 +1,234 @@
+use image::{Rgba, RgbaImage};
+
+use crate::minecraft::filetypes::mcmeta::properties::Propterties;
+
+/// Per-sprite settings that decide how a sprite is bled and filtered into lower mip levels
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpriteMipProperties {
+    /// Keep soft alpha edges instead of preserving binary cutouts
+    pub blur: bool,
+    /// Bleed the cell around the sprite by repeating edge pixels instead of wrapping around the sprite
+    pub clamp: bool,
+    /// Levels that are box filtered, every other level is point sampled from the level above.
+    /// None filters every level
+    pub filtered_levels: Option<Vec<u32>>,
+}
+
+impl SpriteMipProperties {
+    pub fn filters_level(&self, level: u32) -> bool {
+        self.filtered_levels.as_ref().is_none_or(|levels| levels.contains(&level))
+    }
+}
+
+impl From<&Propterties> for SpriteMipProperties {
+    fn from(properties: &Propterties) -> Self {
+        Self {
+            blur: properties.blur(),
+            clamp: properties.clamp(),
+            filtered_levels: properties.mipmaps().cloned(),
+        }
+    }
+}
+
+/**
+ * An area of the base atlas that is downsampled independently of its neighbours,
+ * should be aligned to 1 << levels so that it maps onto whole pixels in every level
+ */
+#[derive(Clone, Debug)]
+pub struct MipRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub properties: SpriteMipProperties,
+}
+
+/**
+ * Fill every pixel of the `cell` around the sprite at (x, y, width, height) with copies of the sprite,
+ * either by stretching its edges (clamp) or by wrapping around (tile). Lower mip levels only see the cell,
+ * so a filled cell shrinks into the sprite alone
+ */
+pub fn bleed_sprite(atlas: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, cell: &MipRegion) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (x, y, width, height) = (x as i64, y as i64, width as i64, height as i64);
+    let max_x = (cell.x + cell.width).min(atlas.width()) as i64;
+    let max_y = (cell.y + cell.height).min(atlas.height()) as i64;
+    for py in cell.y as i64..max_y {
+        for px in cell.x as i64..max_x {
+            let inside = (x..x + width).contains(&px) && (y..y + height).contains(&py);
+            if inside {
+                continue;
+            }
+            let (sx, sy) = if cell.properties.clamp {
+                (px.clamp(x, x + width - 1), py.clamp(y, y + height - 1))
+            } else {
+                (x + (px - x).rem_euclid(width), y + (py - y).rem_euclid(height))
+            };
+            let pixel = *atlas.get_pixel(sx as u32, sy as u32);
+            atlas.put_pixel(px as u32, py as u32, pixel);
+        }
+    }
+}
+
+/// Alpha weighted average of 4 pixels so that fully transparent pixels don't darken the colour
+fn average_pixels(pixels: [Rgba<u8>; 4], cutout: bool) -> Rgba<u8> {
+    let alpha_sum: u32 = pixels.iter().map(|p| p[3] as u32).sum();
+    let mut color = [0u8; 4];
+    for (channel, value) in color.iter_mut().enumerate().take(3) {
+        let weighted: u32 = pixels.iter().map(|p| p[channel] as u32 * p[3] as u32).sum();
+        let total = (weighted + alpha_sum / 2).checked_div(alpha_sum)
+            .unwrap_or_else(|| (pixels.iter().map(|p| p[channel] as u32).sum::<u32>() + 2) / 4);
+        *value = total as u8;
+    }
+    let alpha = (alpha_sum + 2) / 4;
+    color[3] = if cutout {
+        if alpha >= 128 { 255 } else { 0 }
+    } else {
+        alpha as u8
+    };
+    Rgba(color)
+}
+
+/// A sprite whose every pixel is either opaque or invisible, it should stay that way when shrunk
+fn is_cutout(image: &RgbaImage, region: &MipRegion) -> bool {
+    (region.y..region.y + region.height).all(|y| {
+        (region.x..region.x + region.width).all(|x| matches!(image.get_pixel(x, y)[3], 0 | 255))
+    })
+}
+
+fn downsample_region(previous: &RgbaImage, next: &mut RgbaImage, region: &MipRegion, level: u32, cutout: bool) {
+    let (x, y) = (region.x >> level, region.y >> level);
+    let (width, height) = (region.width >> level, region.height >> level);
+    let filter = region.properties.filters_level(level);
+    for py in y..(y + height).min(next.height()) {
+        for px in x..(x + width).min(next.width()) {
+            let (sx, sy) = (px * 2, py * 2);
+            let pixel = if filter {
+                average_pixels([
+                    *previous.get_pixel(sx, sy),
+                    *previous.get_pixel(sx + 1, sy),
+                    *previous.get_pixel(sx, sy + 1),
+                    *previous.get_pixel(sx + 1, sy + 1),
+                ], cutout)
+            } else {
+                *previous.get_pixel(sx, sy)
+            };
+            next.put_pixel(px, py, pixel);
+        }
+    }
+}
+
+/**
+ * Generate `levels` mip levels below `base`, the returned images start at level 1.
+ * Each region is downsampled on its own so that packed sprites never mix,
+ * pixels outside of every region are left transparent.
+ */
+pub fn generate_mip_levels(base: &RgbaImage, levels: u32, regions: &[MipRegion]) -> Vec<RgbaImage> {
+    let cutouts: Vec<bool> = regions.iter().map(|region| !region.properties.blur && is_cutout(base, region)).collect();
+    let mut mips: Vec<RgbaImage> = Vec::with_capacity(levels as usize);
+    for level in 1..=levels {
+        let previous = mips.last().unwrap_or(base);
+        let (width, height) = ((base.width() >> level).max(1), (base.height() >> level).max(1));
+        if previous.width() < 2 || previous.height() < 2 {
+            break;
+        }
+        let mut next = RgbaImage::new(width, height);
+        for (region, cutout) in regions.iter().zip(cutouts.iter()) {
+            downsample_region(previous, &mut next, region, level, *cutout);
+        }
+        mips.push(next);
+    }
+    mips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x: u32, y: u32, width: u32, height: u32) -> MipRegion {
+        MipRegion { x, y, width, height, properties: SpriteMipProperties::default() }
+    }
+
+    #[test]
+    fn mip_chain_dimensions() {
+        let base = RgbaImage::new(64, 32);
+        let mips = generate_mip_levels(&base, 4, &[region(0, 0, 64, 32)]);
+        let sizes: Vec<(u32, u32)> = mips.iter().map(|m| m.dimensions()).collect();
+        assert_eq!(sizes, vec![(32, 16), (16, 8), (8, 4), (4, 2)]);
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_darken() {
+        let mut base = RgbaImage::new(2, 2);
+        base.put_pixel(0, 0, Rgba([200, 100, 50, 255]));
+        base.put_pixel(1, 1, Rgba([200, 100, 50, 128]));
+        let properties = SpriteMipProperties { blur: true, ..Default::default() };
+        let mips = generate_mip_levels(&base, 1, &[MipRegion { x: 0, y: 0, width: 2, height: 2, properties }]);
+        assert_eq!(*mips[0].get_pixel(0, 0), Rgba([200, 100, 50, 96]));
+    }
+
+    #[test]
+    fn cutout_alpha_stays_binary() {
+        let mut base = RgbaImage::new(4, 4);
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (2, 2)] {
+            base.put_pixel(x, y, Rgba([10, 20, 30, 255]));
+        }
+        let mips = generate_mip_levels(&base, 1, &[region(0, 0, 4, 4)]);
+        assert_eq!(mips[0].get_pixel(0, 0)[3], 255);
+        assert_eq!(mips[0].get_pixel(1, 1)[3], 0);
+    }
+
+    #[test]
+    fn regions_do_not_mix() {
+        let mut base = RgbaImage::new(4, 2);
+        for y in 0..2 {
+            base.put_pixel(0, y, Rgba([255, 0, 0, 255]));
+            base.put_pixel(1, y, Rgba([255, 0, 0, 255]));
+            base.put_pixel(2, y, Rgba([0, 0, 255, 255]));
+            base.put_pixel(3, y, Rgba([0, 0, 255, 255]));
+        }
+        let mips = generate_mip_levels(&base, 1, &[region(0, 0, 2, 2), region(2, 0, 2, 2)]);
+        assert_eq!(*mips[0].get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*mips[0].get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn unlisted_levels_are_point_sampled() {
+        let mut base = RgbaImage::new(2, 2);
+        base.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        let properties = SpriteMipProperties { blur: true, filtered_levels: Some(vec![2]), ..Default::default() };
+        let mips = generate_mip_levels(&base, 1, &[MipRegion { x: 0, y: 0, width: 2, height: 2, properties }]);
+        assert_eq!(*mips[0].get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn bleed_wraps_or_clamps() {
+        let mut atlas = RgbaImage::new(4, 3);
+        atlas.put_pixel(1, 1, Rgba([1, 0, 0, 255]));
+        atlas.put_pixel(2, 1, Rgba([2, 0, 0, 255]));
+        let mut clamped = atlas.clone();
+        bleed_sprite(&mut atlas, 1, 1, 2, 1, &region(0, 0, 4, 3));
+        let properties = SpriteMipProperties { clamp: true, ..Default::default() };
+        bleed_sprite(&mut clamped, 1, 1, 2, 1, &MipRegion { x: 0, y: 0, width: 4, height: 3, properties });
+        assert_eq!(atlas.get_pixel(0, 1)[0], 2);
+        assert_eq!(atlas.get_pixel(3, 1)[0], 1);
+        assert_eq!(clamped.get_pixel(0, 1)[0], 1);
+        assert_eq!(clamped.get_pixel(3, 0)[0], 2);
+    }
+
+    #[test]
+    fn bled_cells_shrink_into_their_sprite() {
+        // An opaque sprite in the corner of a bigger cell stays opaque and a cutout down to the last level
+        let mut atlas = RgbaImage::new(8, 8);
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            atlas.put_pixel(x, y, Rgba([90, 60, 30, 255]));
+        }
+        let cell = region(0, 0, 8, 8);
+        bleed_sprite(&mut atlas, 0, 0, 2, 2, &cell);
+        assert!(is_cutout(&atlas, &cell));
+        let mips = generate_mip_levels(&atlas, 3, &[cell]);
+        assert_eq!(*mips[2].get_pixel(0, 0), Rgba([90, 60, 30, 255]));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod mipmap;
+
 use ultraviolet::Vec2;
 
 use crate::minecraft::identifier::Identifier;
@@ -0,0 +1,88 @@
+const MULTIPLIER: i64 = 0x5_DEEC_E66D;
+const ADDEND: i64 = 0xB;
+const MASK: i64 = (1 << 48) - 1;
+
+/**
+ * The linear congruential generator of `java.util.Random`, for anything that has to come out exactly as it did in b1.7.3.
+ * The same seed gives the same sequence as java does
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    pub fn new(seed: i64) -> Self {
+        Self { seed: (seed ^ MULTIPLIER) & MASK }
+    }
+
+    /// The next `bits` random bits, at most 32
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(MULTIPLIER).wrapping_add(ADDEND) & MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    /// A number from 0 up to but not including `bound`, which has to be positive
+    pub fn next_int_bounded(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            // Reject the top of the range that doesn't divide evenly, as java does
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+
+    /// A number from 0 up to but not including 1
+    pub fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+
+    /// A number from 0 up to but not including 1
+    pub fn next_double(&mut self) -> f64 {
+        let high = (self.next(26) as i64) << 27;
+        let low = self.next(27) as i64;
+        (high + low) as f64 / (1i64 << 53) as f64
+    }
+
+    pub fn next_long(&mut self) -> i64 {
+        ((self.next(32) as i64) << 32).wrapping_add(self.next(32) as i64)
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next(1) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequences_match_java() {
+        assert_eq!(JavaRandom::new(0).next_int(), -1155484576);
+        assert_eq!(JavaRandom::new(0).next_float(), 0.73096776);
+        assert!((JavaRandom::new(0).next_double() - 0.730967787376657).abs() < 1e-15);
+        assert_eq!(JavaRandom::new(42).next_int_bounded(10), 0);
+        assert_eq!(JavaRandom::new(42).next_int(), -1170105035);
+        assert_eq!(JavaRandom::new(42).next_int_bounded(16), 11);
+    }
+
+    #[test]
+    fn bounded_numbers_stay_in_range() {
+        let mut random = JavaRandom::new(10842);
+        for _ in 0..1000 {
+            assert!((0..7).contains(&random.next_int_bounded(7)));
+            assert!((0.0..1.0).contains(&random.next_float()));
+        }
+    }
+}
@@ -5,6 +5,8 @@ pub mod pos;
 pub mod workers;
 pub mod frustrum;
 pub mod nibble;
+pub mod quad_sort;
+pub mod java_random;
 
 pub fn os_str_to_string(s: &OsStr) -> String {
     s.to_string_lossy().to_string()
@@ -18,6 +18,16 @@ pub type InnerChunkPos = (UVec3, u32);
 #[derive(Clone, Copy)]
 pub struct NewChunkPosition { pub vec: IVec3 }
 
+/**
+ * The random value vanilla derives from a block position to pick models and offsets,
+ * the same position always gives the same value
+ */
+pub fn position_random(pos: &BlockPos) -> i64 {
+    let l = (pos.x.wrapping_mul(3129871) as i64) ^ (pos.z as i64).wrapping_mul(116129781) ^ (pos.y as i64);
+    let l = l.wrapping_mul(l).wrapping_mul(42317861).wrapping_add(l.wrapping_mul(11));
+    l >> 16
+}
+
 impl Position for EntityPos {
     fn to_entity_pos(&self) -> EntityPos {
         self.clone()
@@ -0,0 +1,102 @@
+use ultraviolet::Vec3;
+
+/// Indices of a quad, two triangles
+pub const INDICES_PER_QUAD: usize = 6;
+/// How far the camera moves from where the quads were last sorted before they are sorted again
+pub const RESORT_DISTANCE: f32 = 1.0;
+
+/**
+ * The transparent quads of a section kept on the cpu, so their indices can be sorted back to front around the camera.
+ * They are sorted again once the camera moves [`RESORT_DISTANCE`] away from where they were last sorted
+ */
+#[derive(Clone, Debug, Default)]
+pub struct TransparentQuads {
+    indices: Vec<u32>,
+    /// The center of every quad, in the same order as the indices
+    centroids: Vec<Vec3>,
+    sorted_from: Option<Vec3>,
+}
+
+impl TransparentQuads {
+    /// The quads of an index buffer made of two triangles per quad, over the positions of their vertices
+    pub fn new(positions: &[Vec3], indices: Vec<u32>) -> Self {
+        let centroids = indices.chunks_exact(INDICES_PER_QUAD).map(|quad| {
+            // The two corners shared by the triangles are counted twice, which still lands on the center of a parallelogram
+            quad.iter().fold(Vec3::zero(), |sum, index| sum + positions[*index as usize]) / INDICES_PER_QUAD as f32
+        }).collect();
+        Self { indices, centroids, sorted_from: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Whether the camera moved far enough from where the quads were last sorted, quads that were never sorted always need it
+    pub fn needs_sort(&self, camera: Vec3) -> bool {
+        !self.is_empty() && self.sorted_from.is_none_or(|sorted_from| (camera - sorted_from).mag_sq() >= RESORT_DISTANCE * RESORT_DISTANCE)
+    }
+
+    /// Order the quads from the farthest to the nearest to the camera
+    pub fn sort(&mut self, camera: Vec3) {
+        let mut order: Vec<(f32, usize)> = self.centroids.iter().enumerate().map(|(quad, centroid)| ((*centroid - camera).mag_sq(), quad)).collect();
+        order.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+        let indices = order.iter().flat_map(|(_, quad)| &self.indices[quad * INDICES_PER_QUAD..(quad + 1) * INDICES_PER_QUAD]).copied().collect();
+        self.centroids = order.iter().map(|(_, quad)| self.centroids[*quad]).collect();
+        self.indices = indices;
+        self.sorted_from = Some(camera);
+    }
+
+    /// Sort the quads if the camera moved far enough, true when the indices changed order
+    pub fn sort_if_moved(&mut self, camera: Vec3) -> bool {
+        let needs_sort = self.needs_sort(camera);
+        if needs_sort {
+            self.sort(camera);
+        }
+        needs_sort
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quads facing up at the given heights, each with its own four vertices
+    fn stacked_quads(heights: &[f32]) -> TransparentQuads {
+        let mut positions = vec![];
+        let mut indices = vec![];
+        for height in heights {
+            let first = positions.len() as u32;
+            positions.extend([Vec3::new(0.0, *height, 0.0), Vec3::new(0.0, *height, 1.0), Vec3::new(1.0, *height, 0.0), Vec3::new(1.0, *height, 1.0)]);
+            indices.extend([0, 2, 3, 0, 3, 1].map(|index| first + index));
+        }
+        TransparentQuads::new(&positions, indices)
+    }
+
+    #[test]
+    fn quads_sort_back_to_front() {
+        let mut quads = stacked_quads(&[1.0, 3.0, 2.0]);
+        quads.sort(Vec3::new(0.5, 10.0, 0.5));
+        // The quad at 1 is the farthest from above, its vertices start at 0
+        assert_eq!(&quads.indices()[..6], &[0, 2, 3, 0, 3, 1]);
+        assert_eq!(&quads.indices()[6..12], &[8, 10, 11, 8, 11, 9]);
+        assert_eq!(&quads.indices()[12..], &[4, 6, 7, 4, 7, 5]);
+
+        quads.sort(Vec3::new(0.5, -10.0, 0.5));
+        assert_eq!(&quads.indices()[..6], &[4, 6, 7, 4, 7, 5]);
+        assert_eq!(&quads.indices()[12..], &[0, 2, 3, 0, 3, 1]);
+    }
+
+    #[test]
+    fn quads_sort_again_after_moving() {
+        let mut quads = stacked_quads(&[1.0, 2.0]);
+        let camera = Vec3::new(0.5, 10.0, 0.5);
+        assert!(quads.sort_if_moved(camera));
+        assert!(!quads.sort_if_moved(camera + Vec3::new(0.5, 0.0, 0.0)));
+        assert!(quads.sort_if_moved(camera + Vec3::new(0.0, 0.0, RESORT_DISTANCE)));
+        assert!(!stacked_quads(&[]).needs_sort(camera));
+    }
+}
@@ -0,0 +1,121 @@
+/// The temperature and humidity of a column, both between 0 and 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+impl Climate {
+    pub fn new(temperature: f32, humidity: f32) -> Self {
+        Self { temperature: temperature.clamp(0.0, 1.0), humidity: humidity.clamp(0.0, 1.0) }
+    }
+}
+
+impl Default for Climate {
+    /// The climate of plains
+    fn default() -> Self {
+        Self::new(0.8, 0.4)
+    }
+}
+
+/**
+ * Anything that knows the climate of a world column, like a terrain generator.
+ * Worlds without one use a [`NoiseClimate`] seeded from the world seed
+ */
+pub trait ClimateSource {
+    fn climate_at(&self, x: i32, z: i32) -> Climate;
+}
+
+/// Blocks between the lattice points of the first octave
+const TEMPERATURE_SCALE: f32 = 1.0 / 320.0;
+const HUMIDITY_SCALE: f32 = 1.0 / 256.0;
+const CLIMATE_OCTAVES: u32 = 4;
+
+/// A number between -1 and 1 for every lattice point
+fn lattice_value(seed: i64, x: i32, z: i32) -> f32 {
+    let mut hash = (seed as u64)
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    (hash >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+/// Smoothly interpolated lattice values
+pub(crate) fn value_noise(seed: i64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let near = lerp(lattice_value(seed, x0, z0), lattice_value(seed, x0 + 1, z0), tx);
+    let far = lerp(lattice_value(seed, x0, z0 + 1), lattice_value(seed, x0 + 1, z0 + 1), tx);
+    lerp(near, far, tz)
+}
+
+/// Octaves of value noise, each twice as detailed and half as strong as the last, between -1 and 1
+fn octave_noise(seed: i64, x: f32, z: f32, octaves: u32) -> f32 {
+    let (mut total, mut amplitude, mut frequency, mut range) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves {
+        total += value_noise(seed.wrapping_add(octave as i64), x * frequency, z * frequency) * amplitude;
+        range += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / range
+}
+
+/// The climate of a world without a terrain generator, smooth noise seeded by the world seed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoiseClimate {
+    temperature_seed: i64,
+    humidity_seed: i64,
+}
+
+impl NoiseClimate {
+    pub fn new(world_seed: i64) -> Self {
+        Self {
+            temperature_seed: world_seed.wrapping_mul(9871),
+            humidity_seed: world_seed.wrapping_mul(39811),
+        }
+    }
+}
+
+impl ClimateSource for NoiseClimate {
+    /// Temperatures lean warm and humidities centered, as in vanilla worlds
+    fn climate_at(&self, x: i32, z: i32) -> Climate {
+        let (x, z) = (x as f32, z as f32);
+        let temperature = octave_noise(self.temperature_seed, x * TEMPERATURE_SCALE, z * TEMPERATURE_SCALE, CLIMATE_OCTAVES);
+        let humidity = octave_noise(self.humidity_seed, x * HUMIDITY_SCALE, z * HUMIDITY_SCALE, CLIMATE_OCTAVES);
+        let temperature = (0.7 + temperature * 0.5).clamp(0.0, 1.0);
+        let temperature = 1.0 - (1.0 - temperature) * (1.0 - temperature);
+        Climate::new(temperature, 0.5 + humidity * 0.6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_climate_is_seeded_and_bounded() {
+        let climate = NoiseClimate::new(1234);
+        assert_eq!(climate.climate_at(100, -40), NoiseClimate::new(1234).climate_at(100, -40));
+        let samples: Vec<Climate> = (-20..20).map(|i| climate.climate_at(i * 97, i * -53)).collect();
+        assert!(samples.iter().all(|c| (0.0..=1.0).contains(&c.temperature) && (0.0..=1.0).contains(&c.humidity)));
+        assert!(samples.iter().any(|c| *c != samples[0]));
+        let other = NoiseClimate::new(4321);
+        assert!((-20..20).any(|i| other.climate_at(i * 97, i * -53) != samples[(i + 20) as usize]));
+    }
+
+    #[test]
+    fn neighbouring_columns_have_similar_climates() {
+        let climate = NoiseClimate::new(-8);
+        for x in -64..64 {
+            let (a, b) = (climate.climate_at(x, 7), climate.climate_at(x + 1, 7));
+            assert!((a.temperature - b.temperature).abs() < 0.05);
+            assert!((a.humidity - b.humidity).abs() < 0.05);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use rustc_hash::FxHashMap as HashMap;
+use ultraviolet::{IVec3, Vec3};
+
+use crate::direction::Direction;
+use crate::minecraft::identifier::Identifier;
+
+pub const MOB_SPAWNER_BLOCK: u8 = 52;
+pub const CHEST_BLOCK: u8 = 54;
+pub const SIGN_POST_BLOCK: u8 = 63;
+pub const WALL_SIGN_BLOCK: u8 = 68;
+pub const STICKY_PISTON_BLOCK: u8 = 29;
+pub const PISTON_BLOCK: u8 = 33;
+pub const PISTON_HEAD_BLOCK: u8 = 34;
+
+pub const SIGN_LINES: usize = 4;
+/// Signs only hold this many characters a line
+pub const MAX_SIGN_LINE_LENGTH: usize = 15;
+/// How far a chest lid opens or closes a tick, from closed at 0 to open at 1
+const CHEST_LID_SPEED: f32 = 0.1;
+/// How far a piston moves a tick, pistons take two ticks to move a block
+const PISTON_SPEED: f32 = 0.5;
+/// How far the mob in a spawner turns a tick, in degrees. Spawners turn faster as they get close to spawning, the client doesn't know when that is
+const SPAWNER_SPIN_PER_TICK: f32 = 10000.0 / 220.0;
+/// The server never says which mob a spawner spawns, every spawner shows this one
+pub const DEFAULT_SPAWNER_MOB: &str = "Pig";
+/// How far from the player a chest they open can be
+pub const CHEST_REACH: f32 = 8.0;
+
+/// Whether the block is drawn by its block entity instead of its block model, so terrain meshing leaves it out
+pub fn draws_block(block: &Identifier) -> bool {
+    block.get_namespace() == "minecraft" && matches!(block.get_name().as_str(), "chest" | "sign" | "wall_sign")
+}
+
+/// The text of a sign, lines longer than a sign holds are cut short
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sign {
+    pub lines: [String; SIGN_LINES],
+    /// Sign posts stand on the ground, the others hang on a wall
+    pub standing: bool,
+    /// The block metadata, sixteenths of a turn for sign posts and the face of the wall for wall signs
+    pub rotation: u8,
+}
+
+impl Sign {
+    /// How far the sign is turned around y, in degrees
+    pub fn get_yaw(&self) -> f32 {
+        if self.standing {
+            self.rotation as f32 * 360.0 / 16.0
+        } else {
+            face_yaw(self.rotation as i8)
+        }
+    }
+}
+
+/// The angle a model facing a protocol face is turned by, faces -z, +z, -x, +x turn 180, 0, 90 and -90 degrees
+pub fn face_yaw(face: i8) -> f32 {
+    match face {
+        2 => 180.0,
+        4 => 90.0,
+        5 => -90.0,
+        _ => 0.0,
+    }
+}
+
+/// The lid of a chest, opening while players look inside it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Chest {
+    pub viewers: u8,
+    /// The protocol face the front of the chest faces, from the `facing` property of its blockstate
+    pub facing: i8,
+    openness: f32,
+    previous_openness: f32,
+}
+
+impl Chest {
+    /// How far the lid is open, eased so it slows down as it opens
+    pub fn get_openness(&self, partial_tick: f32) -> f32 {
+        let openness = self.previous_openness + (self.openness - self.previous_openness) * partial_tick;
+        1.0 - (1.0 - openness).powi(3)
+    }
+
+    fn tick(&mut self) {
+        self.previous_openness = self.openness;
+        self.openness = if self.viewers > 0 { (self.openness + CHEST_LID_SPEED).min(1.0) } else { (self.openness - CHEST_LID_SPEED).max(0.0) };
+    }
+
+    /// The protocol face a value of the `facing` blockstate property points to, unknown values face +z
+    pub fn facing_from_property(facing: &str) -> i8 {
+        match facing {
+            "north" => 2,
+            "west" => 4,
+            "east" => 5,
+            _ => 3,
+        }
+    }
+}
+
+/// A piston head sliding out of or back into its piston
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PistonHead {
+    /// The blockstate of the head, drawn with its block model
+    pub state: usize,
+    pub direction: Direction,
+    pub extending: bool,
+    progress: f32,
+    previous_progress: f32,
+}
+
+impl PistonHead {
+    /// How far the head is from the block it is at, in blocks. Heads are placed where they are extended to
+    pub fn get_offset(&self, partial_tick: f32) -> Vec3 {
+        let progress = self.previous_progress + (self.progress - self.previous_progress) * partial_tick;
+        let distance = if self.extending { progress - 1.0 } else { -progress };
+        self.direction.get_float_vector() * distance
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.previous_progress >= 1.0
+    }
+
+    fn tick(&mut self) {
+        self.previous_progress = self.progress;
+        self.progress = (self.progress + PISTON_SPEED).min(1.0);
+    }
+}
+
+/// The mob turning inside a mob spawner
+#[derive(Clone, Debug, PartialEq)]
+pub struct MobSpawner {
+    pub mob: String,
+    rotation: f32,
+    previous_rotation: f32,
+}
+
+impl MobSpawner {
+    pub fn new(mob: &str) -> Self {
+        Self { mob: mob.to_string(), rotation: 0.0, previous_rotation: 0.0 }
+    }
+
+    /// How far the mob has turned, in degrees
+    pub fn get_rotation(&self, partial_tick: f32) -> f32 {
+        self.previous_rotation + (self.rotation - self.previous_rotation) * partial_tick
+    }
+
+    fn tick(&mut self) {
+        self.previous_rotation = self.rotation;
+        self.rotation += SPAWNER_SPIN_PER_TICK;
+        // Both turn back a full turn together so the mob doesn't spin back between them
+        if self.rotation >= 360.0 {
+            self.rotation -= 360.0;
+            self.previous_rotation -= 360.0;
+        }
+    }
+}
+
+/// Blocks with more to them than their blockstate, drawn by special renderers on top of the terrain
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockEntity {
+    Sign(Sign),
+    Chest(Chest),
+    Piston(PistonHead),
+    MobSpawner(MobSpawner),
+}
+
+/**
+ * The block entities of a world by the position of their block.
+ * Signs, chests and mob spawners come and go with their blocks, piston heads only while they move
+ */
+#[derive(Clone, Debug, Default)]
+pub struct BlockEntities {
+    entities: HashMap<IVec3, BlockEntity>,
+}
+
+impl BlockEntities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, block: IVec3) -> Option<&BlockEntity> {
+        self.entities.get(&block)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&IVec3, &BlockEntity)> {
+        self.entities.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// The block at `block` changed to `id`, creating the block entity it has or removing the one it had
+    pub fn block_changed(&mut self, block: IVec3, id: u8, meta: u8) {
+        match (id, self.entities.get_mut(&block)) {
+            (SIGN_POST_BLOCK | WALL_SIGN_BLOCK, Some(BlockEntity::Sign(sign))) => {
+                sign.standing = id == SIGN_POST_BLOCK;
+                sign.rotation = meta;
+            },
+            (SIGN_POST_BLOCK | WALL_SIGN_BLOCK, _) => {
+                self.entities.insert(block, BlockEntity::Sign(Sign { standing: id == SIGN_POST_BLOCK, rotation: meta, ..Default::default() }));
+            },
+            (CHEST_BLOCK, Some(BlockEntity::Chest(_))) | (MOB_SPAWNER_BLOCK, Some(BlockEntity::MobSpawner(_))) => {},
+            (CHEST_BLOCK, _) => { self.entities.insert(block, BlockEntity::Chest(Chest { facing: 3, ..Default::default() })); },
+            (MOB_SPAWNER_BLOCK, _) => { self.entities.insert(block, BlockEntity::MobSpawner(MobSpawner::new(DEFAULT_SPAWNER_MOB))); },
+            // Moving heads outlive the blocks under them until they stop
+            (_, Some(BlockEntity::Piston(_))) => {},
+            (_, Some(_)) => { self.entities.remove(&block); },
+            (_, None) => {},
+        }
+    }
+
+    /// Set the text of the sign at `block`, the sign is made if its block hasn't arrived yet
+    pub fn set_sign_text(&mut self, block: IVec3, lines: [&str; SIGN_LINES]) {
+        let lines = lines.map(|line| line.chars().take(MAX_SIGN_LINE_LENGTH).collect::<String>());
+        match self.entities.get_mut(&block) {
+            Some(BlockEntity::Sign(sign)) => sign.lines = lines,
+            _ => { self.entities.insert(block, BlockEntity::Sign(Sign { lines, standing: true, rotation: 0 })); },
+        }
+    }
+
+    pub fn set_chest_viewers(&mut self, block: IVec3, viewers: u8) {
+        if let Some(BlockEntity::Chest(chest)) = self.entities.get_mut(&block) {
+            chest.viewers = viewers;
+        }
+    }
+
+    pub fn set_chest_facing(&mut self, block: IVec3, facing: i8) {
+        if let Some(BlockEntity::Chest(chest)) = self.entities.get_mut(&block) {
+            chest.facing = facing;
+        }
+    }
+
+    /// The chest closest to `position` within `reach` of it
+    pub fn nearest_chest(&self, position: Vec3, reach: f32) -> Option<IVec3> {
+        let distance = |block: &IVec3| (Vec3::new(block.x as f32, block.y as f32, block.z as f32) + Vec3::broadcast(0.5) - position).mag_sq();
+        self.entities.iter()
+            .filter(|(block, entity)| matches!(entity, BlockEntity::Chest(_)) && distance(block) <= reach * reach)
+            .min_by(|(a, _), (b, _)| distance(a).total_cmp(&distance(b)))
+            .map(|(block, _)| *block)
+    }
+
+    /// A piston at `piston` pushes its head out or pulls it back in, the head is drawn with the blockstate `head_state`
+    pub fn move_piston(&mut self, piston: IVec3, direction: Direction, extending: bool, head_state: usize) {
+        let head = PistonHead { state: head_state, direction, extending, progress: 0.0, previous_progress: 0.0 };
+        self.entities.insert(piston + direction.get_int_vector(), BlockEntity::Piston(head));
+    }
+
+    pub fn tick(&mut self) {
+        for entity in self.entities.values_mut() {
+            match entity {
+                BlockEntity::Chest(chest) => chest.tick(),
+                BlockEntity::Piston(head) => head.tick(),
+                BlockEntity::MobSpawner(spawner) => spawner.tick(),
+                BlockEntity::Sign(_) => {},
+            }
+        }
+        self.entities.retain(|_, entity| !matches!(entity, BlockEntity::Piston(head) if head.is_finished()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_entities_follow_their_blocks() {
+        let mut entities = BlockEntities::new();
+        let block = IVec3::new(3, 64, -2);
+        entities.set_sign_text(block, ["Hello", "a line far too long for a sign", "", ""]);
+        entities.block_changed(block, WALL_SIGN_BLOCK, 4);
+        let Some(BlockEntity::Sign(sign)) = entities.get(block) else { panic!("sign was lost") };
+        assert_eq!(sign.lines[0], "Hello");
+        assert_eq!(sign.lines[1].len(), MAX_SIGN_LINE_LENGTH);
+        assert!(!sign.standing);
+        assert_eq!(sign.get_yaw(), 90.0);
+
+        entities.block_changed(block, 0, 0);
+        assert!(entities.is_empty());
+        entities.block_changed(block, MOB_SPAWNER_BLOCK, 0);
+        assert!(matches!(entities.get(block), Some(BlockEntity::MobSpawner(spawner)) if spawner.mob == DEFAULT_SPAWNER_MOB));
+        entities.block_changed(block, 1, 0);
+        entities.block_changed(block, SIGN_POST_BLOCK, 4);
+        assert!(matches!(entities.get(block), Some(BlockEntity::Sign(sign)) if sign.get_yaw() == 90.0 && sign.lines[0].is_empty()));
+    }
+
+    #[test]
+    fn chest_lids_ease_open_and_closed() {
+        let mut entities = BlockEntities::new();
+        let block = IVec3::new(0, 64, 0);
+        entities.block_changed(block, CHEST_BLOCK, 0);
+        entities.set_chest_viewers(block, 1);
+        entities.tick();
+        let Some(BlockEntity::Chest(chest)) = entities.get(block).cloned() else { panic!("no chest") };
+        assert_eq!(chest.get_openness(0.0), 0.0);
+        assert!((chest.get_openness(1.0) - (1.0 - 0.9f32.powi(3))).abs() < 1e-5);
+        for _ in 0..20 { entities.tick(); }
+        assert!(matches!(entities.get(block), Some(BlockEntity::Chest(chest)) if chest.get_openness(0.5) == 1.0));
+        entities.set_chest_viewers(block, 0);
+        for _ in 0..20 { entities.tick(); }
+        assert!(matches!(entities.get(block), Some(BlockEntity::Chest(chest)) if chest.get_openness(0.5) == 0.0));
+
+        // Chests face the way their blockstate does, a chest opened nearby is the one in reach
+        entities.set_chest_facing(block, Chest::facing_from_property("west"));
+        assert!(matches!(entities.get(block), Some(BlockEntity::Chest(chest)) if chest.facing == 4));
+        assert_eq!(entities.nearest_chest(Vec3::new(2.0, 65.6, 0.5), CHEST_REACH), Some(block));
+        assert_eq!(entities.nearest_chest(Vec3::new(20.0, 65.6, 0.5), CHEST_REACH), None);
+        assert!(draws_block(&Identifier::from_str("minecraft:chest")));
+        assert!(!draws_block(&Identifier::from_str("minecraft:mob_spawner")));
+    }
+
+    #[test]
+    fn piston_heads_slide_and_stop() {
+        let mut entities = BlockEntities::new();
+        let piston = IVec3::new(0, 64, 0);
+        entities.move_piston(piston, Direction::Up, true, 7);
+        let head = IVec3::new(0, 65, 0);
+        let offset = |entities: &BlockEntities, partial| match entities.get(head) { Some(BlockEntity::Piston(head)) => head.get_offset(partial), _ => panic!("no head") };
+        assert_eq!(offset(&entities, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        entities.tick();
+        assert_eq!(offset(&entities, 1.0), Vec3::new(0.0, -0.5, 0.0));
+        // The head outlives the block change to air while it moves
+        entities.block_changed(head, 0, 0);
+        entities.tick();
+        assert_eq!(offset(&entities, 1.0), Vec3::zero());
+        entities.tick();
+        assert!(entities.get(head).is_none());
+
+        entities.move_piston(piston, Direction::Up, false, 7);
+        entities.tick();
+        assert_eq!(offset(&entities, 1.0), Vec3::new(0.0, -0.5, 0.0));
+    }
+}
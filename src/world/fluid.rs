@@ -0,0 +1,213 @@
+use ultraviolet::Vec2;
+
+use crate::{direction::Direction, minecraft::identifier::Identifier, models::condition::StatePropertyNames};
+
+/// The property holding how far a fluid has flowed from its source, 0 for a source
+pub const LEVEL_PROPERTY: &str = "level";
+/// The property of fluids flowing down from above, they are as high as a source
+pub const FALLING_PROPERTY: &str = "falling";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    /// Flowing and still variants of a block are the same fluid
+    pub fn from_block(block: &Identifier) -> Option<Self> {
+        if block.get_namespace() != "minecraft" { return None; }
+        match block.get_name().as_str() {
+            "flowing_water" | "still_water" => Some(Self::Water),
+            "flowing_lava" | "still_lava" => Some(Self::Lava),
+            _ => None,
+        }
+    }
+
+    /// The sprite of the top of a fluid that isn't flowing
+    pub fn still_sprite(&self) -> Identifier {
+        match self {
+            Self::Water => Identifier::from_str("minecraft:block/water_still"),
+            Self::Lava => Identifier::from_str("minecraft:block/lava_still"),
+        }
+    }
+
+    /// The sprite of the sides of a fluid and the top of a flowing one, only a rotated half of it is shown at a time
+    pub fn flow_sprite(&self) -> Identifier {
+        match self {
+            Self::Water => Identifier::from_str("minecraft:block/water_flow"),
+            Self::Lava => Identifier::from_str("minecraft:block/lava_flow"),
+        }
+    }
+}
+
+/// The fluid of a blockstate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FluidState {
+    pub fluid: Fluid,
+    pub level: u8,
+    pub falling: bool,
+}
+
+impl FluidState {
+    pub fn from_properties(block: &Identifier, properties: &StatePropertyNames) -> Option<Self> {
+        let fluid = Fluid::from_block(block)?;
+        let level = properties.get(LEVEL_PROPERTY).and_then(|level| level.parse().ok()).unwrap_or(0);
+        let falling = properties.get(FALLING_PROPERTY).is_some_and(|falling| falling == "true");
+        Some(Self { fluid, level, falling })
+    }
+
+    /// How far the fluid has flowed from a source, falling fluid counts as a source
+    pub fn effective_level(&self) -> i32 {
+        if self.falling { 0 } else { self.level as i32 }
+    }
+
+    /// How much of the cell above the fluid is empty
+    fn height_percent(&self) -> f32 {
+        (self.effective_level() + 1) as f32 / 9.0
+    }
+}
+
+/// What a fluid sees in a neighbouring cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FluidNeighbour {
+    /// The same fluid, flowing or still
+    Same(FluidState),
+    /// A block fluids can't flow into
+    Solid,
+    /// Air, or anything else a fluid could flow into
+    Open,
+}
+
+/**
+ * The heights of the top corners of a fluid cell, ordered (-x -z), (-x +z), (+x -z), (+x +z).
+ * `neighbour` is given offsets from the cell, every corner is the weighted average of the four cells around it, as in b1.7.3.
+ * Sources and falling fluid weigh ten times as much, and a corner under the same fluid is full
+ */
+pub fn corner_heights(neighbour: impl Fn(i32, i32, i32) -> FluidNeighbour) -> [f32; 4] {
+    let corner_height = |corner_x: i32, corner_z: i32| {
+        let mut total = 0.0;
+        let mut weight = 0.0;
+        for x in corner_x - 1..=corner_x {
+            for z in corner_z - 1..=corner_z {
+                if matches!(neighbour(x, 1, z), FluidNeighbour::Same(_)) {
+                    return 1.0;
+                }
+                match neighbour(x, 0, z) {
+                    FluidNeighbour::Same(state) if state.effective_level() == 0 => {
+                        total += state.height_percent() * 10.0;
+                        weight += 10.0;
+                    },
+                    FluidNeighbour::Same(state) => {
+                        total += state.height_percent();
+                        weight += 1.0;
+                    },
+                    FluidNeighbour::Open => {
+                        total += 1.0;
+                        weight += 1.0;
+                    },
+                    FluidNeighbour::Solid => {},
+                }
+            }
+        }
+        if weight == 0.0 { 1.0 } else { 1.0 - total / weight }
+    };
+    [corner_height(0, 0), corner_height(0, 1), corner_height(1, 0), corner_height(1, 1)]
+}
+
+/**
+ * The direction a fluid flows along the ground, as (x, z), zero for still fluid.
+ * The fluid flows away from neighbours closer to a source and towards neighbours further from one, or dropping down
+ */
+pub fn flow_direction(state: FluidState, neighbour: impl Fn(i32, i32, i32) -> FluidNeighbour) -> Vec2 {
+    let level = state.effective_level();
+    let mut flow = Vec2::zero();
+    for direction in [Direction::North, Direction::South, Direction::East, Direction::West] {
+        let offset = direction.get_int_vector();
+        let difference = match neighbour(offset.x, 0, offset.z) {
+            FluidNeighbour::Same(side) => side.effective_level() - level,
+            // Fluid below an open side drains there
+            FluidNeighbour::Open => match neighbour(offset.x, -1, offset.z) {
+                FluidNeighbour::Same(below) => below.effective_level() - (level - 8),
+                _ => continue,
+            },
+            FluidNeighbour::Solid => continue,
+        };
+        flow += Vec2::new(offset.x as f32, offset.z as f32) * difference as f32;
+    }
+    if flow.mag_sq() > 0.0 { flow.normalized() } else { flow }
+}
+
+/**
+ * Where the top corners of a fluid sample its sprite, as fractions of the sprite in the same corner order as [`corner_heights`].
+ * Still fluid shows the whole sprite, flowing fluid a half sized square of the flow sprite rotated to the flow
+ */
+pub fn top_uvs(flow: Vec2) -> [Vec2; 4] {
+    if flow.mag_sq() == 0.0 {
+        return [Vec2::new(0.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0)];
+    }
+    let angle = flow.y.atan2(flow.x) - std::f32::consts::FRAC_PI_2;
+    let (sin, cos) = (angle.sin() * 0.25, angle.cos() * 0.25);
+    let center = Vec2::broadcast(0.5);
+    [
+        center + Vec2::new(-cos - sin, -cos + sin),
+        center + Vec2::new(-cos + sin, cos + sin),
+        center + Vec2::new(cos - sin, -cos - sin),
+        center + Vec2::new(cos + sin, cos - sin),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn water(level: u8) -> FluidNeighbour {
+        FluidNeighbour::Same(FluidState { fluid: Fluid::Water, level, falling: false })
+    }
+
+    #[test]
+    fn fluid_states_come_from_properties() {
+        let properties: StatePropertyNames = [(String::from("level"), String::from("3")), (String::from("falling"), String::from("true"))].into_iter().collect();
+        let state = FluidState::from_properties(&Identifier::from_str("minecraft:still_water"), &properties).unwrap();
+        assert_eq!(state, FluidState { fluid: Fluid::Water, level: 3, falling: true });
+        assert_eq!(state.effective_level(), 0);
+        assert!(FluidState::from_properties(&Identifier::from_str("minecraft:stone"), &properties).is_none());
+    }
+
+    #[test]
+    fn corners_slope_towards_flowing_fluid() {
+        // A pool of sources is level, a little below the top of the cell
+        let pool = corner_heights(|_, y, _| if y == 0 { water(0) } else { FluidNeighbour::Open });
+        assert!(pool.iter().all(|height| (height - 8.0 / 9.0).abs() < 1e-6));
+
+        // A source with flowing water towards +x is lower on the +x corners
+        let edge = corner_heights(|x, y, _| match (x, y) {
+            (_, 1) => FluidNeighbour::Open,
+            (x, _) if x > 0 => water(4),
+            _ => water(0),
+        });
+        assert!(edge[2] < edge[0] && edge[3] < edge[1]);
+
+        // Water below more water fills its cell
+        assert_eq!(corner_heights(|_, _, _| water(0)), [1.0; 4]);
+    }
+
+    #[test]
+    fn fluid_flows_away_from_its_source() {
+        let state = FluidState { fluid: Fluid::Water, level: 2, falling: false };
+        // The source side is north (-x), the water flows south (+x)
+        let flow = flow_direction(state, |x, _, _| match x {
+            -1 => water(1),
+            1 => water(3),
+            _ => FluidNeighbour::Solid,
+        });
+        assert_eq!(flow, Vec2::new(1.0, 0.0));
+        assert_eq!(flow_direction(state, |_, _, _| FluidNeighbour::Solid), Vec2::zero());
+
+        assert_eq!(top_uvs(Vec2::zero())[3], Vec2::one());
+        // Every rotation of the flowing square stays inside the sprite
+        for uv in top_uvs(Vec2::new(0.6, 0.8)) {
+            assert!((0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y));
+        }
+    }
+}
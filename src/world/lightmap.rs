@@ -0,0 +1,123 @@
+use super::biome::value_noise;
+
+pub const TICKS_PER_DAY: u64 = 24000;
+/// Light levels of sky and block light, the lightmap has a cell for every pair
+pub const LIGHTMAP_SIZE: usize = 16;
+/// How many light levels the sky loses at midnight
+pub const MAX_SKY_DARKENING: f32 = 11.0;
+/// How much of its brightness block light loses at the darkest of a flicker
+pub const TORCH_FLICKER_STRENGTH: f32 = 0.1;
+/// Ticks between the points the flicker wanders between
+const TORCH_FLICKER_PERIOD: f32 = 4.0;
+const TORCH_FLICKER_SEED: i64 = 0x7043_11C4;
+
+/// How light the darkest places of a dimension are, the nether is never fully dark and has no sky
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DimensionLight {
+    pub ambient: f32,
+    pub has_sky: bool,
+}
+
+impl DimensionLight {
+    pub const OVERWORLD: Self = Self { ambient: 0.05, has_sky: true };
+    pub const NETHER: Self = Self { ambient: 0.1, has_sky: false };
+
+    /// The lighting of a dimension by its id in the login and respawn packets, unknown dimensions light like the overworld
+    pub fn from_id(dimension_id: i8) -> Self {
+        match dimension_id {
+            -1 => Self::NETHER,
+            _ => Self::OVERWORLD,
+        }
+    }
+}
+
+/**
+ * Where the sun is in the sky, 0 at noon, 0.25 at sunset, 0.5 at midnight and 0.75 at sunrise.
+ * Like b1.7.3 the sun lingers a little around noon and midnight instead of moving at a constant speed
+ */
+pub fn celestial_angle(time: u64, partial_tick: f32) -> f32 {
+    let day_fraction = ((time % TICKS_PER_DAY) as f32 + partial_tick) / TICKS_PER_DAY as f32 - 0.25;
+    let linear = day_fraction.rem_euclid(1.0);
+    let eased = 1.0 - ((linear * std::f32::consts::PI).cos() + 1.0) / 2.0;
+    linear + (eased - linear) / 3.0
+}
+
+/// How many light levels the sky has lost at a celestial angle, from 0 through the day to [`MAX_SKY_DARKENING`] through the night
+pub fn sky_darkening(celestial_angle: f32) -> f32 {
+    let darkness = 1.0 - ((celestial_angle * std::f32::consts::TAU).cos() * 2.0 + 0.5);
+    darkness.clamp(0.0, 1.0) * MAX_SKY_DARKENING
+}
+
+/// The brightness of a light level from 0 to 15, the curve of b1.7.3 lifted to the ambient light of the dimension
+pub fn brightness(level: f32, ambient: f32) -> f32 {
+    let darkness = 1.0 - level.clamp(0.0, 15.0) / 15.0;
+    (1.0 - darkness) / (darkness * 3.0 + 1.0) * (1.0 - ambient) + ambient
+}
+
+/// How bright block light is at a moment, between 1 - [`TORCH_FLICKER_STRENGTH`] and 1, wandering smoothly from tick to tick
+pub fn torch_flicker(time: u64, partial_tick: f32) -> f32 {
+    let wander = value_noise(TORCH_FLICKER_SEED, ((time % TICKS_PER_DAY) as f32 + partial_tick) / TORCH_FLICKER_PERIOD, 0.0);
+    1.0 - TORCH_FLICKER_STRENGTH * (wander + 1.0) / 2.0
+}
+
+/**
+ * The brightness of every pair of sky and block light at a time of day, indexed by sky * [`LIGHTMAP_SIZE`] + block.
+ * The brighter of the darkened sky light and the flickering block light wins, as in b1.7.3 where light levels are never added
+ */
+pub fn generate_lightmap(time: u64, partial_tick: f32, dimension: DimensionLight) -> [f32; LIGHTMAP_SIZE * LIGHTMAP_SIZE] {
+    let darkening = sky_darkening(celestial_angle(time, partial_tick));
+    let flicker = torch_flicker(time, partial_tick);
+    let mut lightmap = [0.0; LIGHTMAP_SIZE * LIGHTMAP_SIZE];
+    for sky in 0..LIGHTMAP_SIZE {
+        for block in 0..LIGHTMAP_SIZE {
+            let sky_level = if dimension.has_sky { (sky as f32 - darkening).max(0.0) } else { 0.0 };
+            let block_level = block as f32 * flicker;
+            lightmap[sky * LIGHTMAP_SIZE + block] = brightness(sky_level.max(block_level), dimension.ambient);
+        }
+    }
+    lightmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOON: u64 = 6000;
+    const MIDNIGHT: u64 = 18000;
+
+    #[test]
+    fn the_sky_darkens_at_night() {
+        assert!(celestial_angle(NOON, 0.0).abs() < 1e-6);
+        assert!((celestial_angle(MIDNIGHT, 0.0) - 0.5).abs() < 1e-6);
+        assert!((celestial_angle(NOON + TICKS_PER_DAY, 0.0) - celestial_angle(NOON, 0.0)).abs() < 1e-6);
+        assert_eq!(sky_darkening(celestial_angle(NOON, 0.0)), 0.0);
+        assert_eq!(sky_darkening(celestial_angle(MIDNIGHT, 0.0)), MAX_SKY_DARKENING);
+
+        let noon = generate_lightmap(NOON, 0.0, DimensionLight::OVERWORLD);
+        let midnight = generate_lightmap(MIDNIGHT, 0.0, DimensionLight::OVERWORLD);
+        let full_sky = 15 * LIGHTMAP_SIZE;
+        assert_eq!(noon[full_sky], 1.0);
+        assert!(midnight[full_sky] < 0.3);
+        // Torches still light the night, only flickering
+        assert!(midnight[15] >= brightness(15.0 * (1.0 - TORCH_FLICKER_STRENGTH), DimensionLight::OVERWORLD.ambient));
+        assert!(midnight[full_sky + 15] > midnight[full_sky]);
+    }
+
+    #[test]
+    fn nether_is_lit_by_ambient_and_blocks_only() {
+        let nether = generate_lightmap(NOON, 0.0, DimensionLight::from_id(-1));
+        assert_eq!(nether[15 * LIGHTMAP_SIZE], DimensionLight::NETHER.ambient);
+        assert_eq!(nether[0], nether[15 * LIGHTMAP_SIZE]);
+        assert!(nether[15] > nether[0]);
+        assert_eq!(generate_lightmap(MIDNIGHT, 0.0, DimensionLight::OVERWORLD)[0], DimensionLight::OVERWORLD.ambient);
+    }
+
+    #[test]
+    fn torches_flicker_gently() {
+        let flickers: Vec<f32> = (0..200).map(|tick| torch_flicker(tick, 0.0)).collect();
+        assert!(flickers.iter().all(|flicker| (1.0 - TORCH_FLICKER_STRENGTH..=1.0).contains(flicker)));
+        assert!(flickers.iter().any(|flicker| *flicker != flickers[0]));
+        assert!(flickers.windows(2).all(|pair| (pair[0] - pair[1]).abs() < TORCH_FLICKER_STRENGTH / 2.0));
+        assert_eq!(torch_flicker(42, 0.5), torch_flicker(42, 0.5));
+    }
+}
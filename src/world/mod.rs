@@ -3,6 +3,15 @@ use rustc_hash::FxHashMap as HashMap;
 use ultraviolet::IVec3;
 use crate::direction::DIRECTIONS;
 pub mod chunk;
+pub mod biome;
+pub mod block_entities;
+pub mod fluid;
+pub mod lightmap;
+pub mod particles;
+pub mod sky;
+pub mod snapshot;
+pub mod visibility;
+pub mod weather;
 
 #[derive(Debug)]
 pub enum ChunkAccessError {
@@ -97,6 +106,14 @@ impl<ChunkType> ChunkStorageTrait<ChunkType> for ChunkStorage<ChunkType> {
             Self::Cubic(cubic) => { cubic.get_nearby_chunks(position) },
         }
     }
+
+    fn for_each_chunk<F: FnMut(IVec3, &ChunkType)>(&self, f: F) {
+        match self {
+            Self::Planar(planar) => { planar.for_each_chunk(f) },
+            Self::PlanarLimited(planar_limited) => { planar_limited.for_each_chunk(f) },
+            Self::Cubic(cubic) => { cubic.for_each_chunk(f) },
+        }
+    }
 }
 
 pub trait ChunkStorageTrait<ChunkType> {
@@ -129,6 +146,11 @@ pub trait ChunkStorageTrait<ChunkType> {
     Get the optional chunks surrounding the chunk at position, does not guarantee that all chunks exist
      */
     fn get_nearby_chunks(&self, position: IVec3) -> Vec<Option<&ChunkType>>;
+
+    /**
+    Apply a function to every chunk in the storage along with its position, in no particular order
+     */
+    fn for_each_chunk<F: FnMut(IVec3, &ChunkType)>(&self, f: F);
 }
 
 pub struct ChunkStoragePlanar<ChunkType> {
@@ -253,6 +275,19 @@ impl<ChunkType> ChunkStorageTrait<ChunkType> for ChunkStoragePlanar<ChunkType> {
                 self.get_chunk(position).ok()
             }).collect()
     }
+
+    fn for_each_chunk<F: FnMut(IVec3, &ChunkType)>(&self, mut f: F) {
+        for (&hash, &stack_index) in &self.stack_pos_to_index_map {
+            // Undo generate_hash
+            let x: i32 = bytemuck::cast(hash as u32);
+            let z: i32 = bytemuck::cast((hash >> 32) as u32);
+            for (y, chunk) in self.chunk_stacks[stack_index].chunks.iter().enumerate() {
+                if let Some(chunk) = chunk {
+                    f(IVec3::new(x, y as i32, z), chunk);
+                }
+            }
+        }
+    }
 }
 
 pub struct ChunkStoragePlanarLimited<ChunkType> {
@@ -291,6 +326,10 @@ impl<ChunkType> ChunkStorageTrait<ChunkType> for ChunkStoragePlanarLimited<Chunk
     fn get_nearby_chunks(&self, position: IVec3) -> Vec<Option<&ChunkType>> {
         todo!()
     }
+
+    fn for_each_chunk<F: FnMut(IVec3, &ChunkType)>(&self, _f: F) {
+        // Chunks can't be stored here yet, so there are none to visit
+    }
 }
 
 pub struct ChunkStorageCubic<ChunkType> {
@@ -329,4 +368,8 @@ impl<ChunkType> ChunkStorageTrait<ChunkType> for ChunkStorageCubic<ChunkType> {
     fn get_nearby_chunks(&self, position: IVec3) -> Vec<Option<&ChunkType>> {
         todo!()
     }
+
+    fn for_each_chunk<F: FnMut(IVec3, &ChunkType)>(&self, _f: F) {
+        // Chunks can't be stored here yet, so there are none to visit
+    }
 }
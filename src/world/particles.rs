@@ -0,0 +1,528 @@
+use std::collections::VecDeque;
+
+use ultraviolet::{IVec3, Vec2, Vec3};
+
+use crate::util::java_random::JavaRandom;
+
+use super::weather::{self, Precipitation};
+
+/// The oldest particles make way for new ones past this many
+pub const MAX_PARTICLES: usize = 4000;
+/// The effect id of a `SoundEffect` that puffs smoke out of a dispenser, the data is the direction it faces
+pub const SMOKE_EFFECT: i32 = 2000;
+/// The effect id of a `SoundEffect` for a block broken by another player, the data is the id of the block
+pub const BLOCK_BREAK_EFFECT: i32 = 2001;
+/// Particles are squares this size at a scale of 1, in blocks
+const PARTICLE_SIZE: f32 = 0.1;
+/// The particle sheet is a grid of this many cells on each side
+const SHEET_CELLS: u8 = 16;
+/// Every tick this many random blocks near the player get to show their effects, up to this far away along each axis
+const DISPLAY_TICKS: u32 = 1000;
+const DISPLAY_RANGE: i32 = 16;
+/// The blocks with effects of their own, torches and lit furnaces smoke and burn, powered redstone gives off dust
+pub const TORCH_BLOCK: u8 = 50;
+pub const REDSTONE_WIRE_BLOCK: u8 = 55;
+pub const LIT_FURNACE_BLOCK: u8 = 62;
+pub const LIT_REDSTONE_TORCH_BLOCK: u8 = 76;
+/// Debris shows a square this much of the sprite of its block wide
+const DEBRIS_SPRITE_FRACTION: f32 = 0.25;
+/// How many debris particles a broken block breaks into along each axis
+const DEBRIS_PER_AXIS: i32 = 4;
+
+/// The cells of the particle sheet, the puffs count down from [`PUFF_FRAMES`] - 1 to 0 as they age
+const PUFF_FRAMES: u8 = 8;
+const SPLASH_CELL: u8 = 19;
+const FLAME_CELL: u8 = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticleKind {
+    /// A bit of a broken or dug block, falling to the ground
+    Debris,
+    Smoke,
+    Flame,
+    /// A drop of water bouncing off the ground
+    Splash,
+    Explosion,
+    RedstoneDust,
+}
+
+/// Where a particle gets its picture from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParticleTexture {
+    /// A cell of the particle sheet, counted row by row
+    Sheet(u8),
+    /// A square of the sprite of a blockstate, `offset` is where in the sprite it starts
+    Block { state: usize, offset: Vec2 },
+}
+
+impl ParticleTexture {
+    /**
+     * The corners of the picture of a particle, as fractions of the particle sheet or of the sprite of the block.
+     * Block sprites still have to be placed in the atlas
+     */
+    pub fn uv_bounds(&self) -> (Vec2, Vec2) {
+        match self {
+            Self::Sheet(cell) => {
+                let cell_size = 1.0 / SHEET_CELLS as f32;
+                let min = Vec2::new((cell % SHEET_CELLS) as f32, (cell / SHEET_CELLS) as f32) * cell_size;
+                (min, min + Vec2::broadcast(cell_size))
+            },
+            Self::Block { offset, .. } => (*offset, *offset + Vec2::broadcast(DEBRIS_SPRITE_FRACTION)),
+        }
+    }
+}
+
+/// A particle moving through the world, following the particles of b1.7.3
+#[derive(Clone, Debug, PartialEq)]
+pub struct Particle {
+    pub kind: ParticleKind,
+    pub texture: ParticleTexture,
+    pub position: Vec3,
+    pub previous_position: Vec3,
+    pub velocity: Vec3,
+    pub color: Vec3,
+    pub scale: f32,
+    pub age: u32,
+    pub max_age: u32,
+    /// How strongly the particle is pulled down every tick
+    gravity: f32,
+    on_ground: bool,
+}
+
+impl Particle {
+    /// Where the particle is between the last two ticks
+    pub fn get_position(&self, partial_tick: f32) -> Vec3 {
+        self.previous_position + (self.position - self.previous_position) * partial_tick
+    }
+
+    /// Flames glow, they aren't darkened by the light around them
+    pub fn is_emissive(&self) -> bool {
+        self.kind == ParticleKind::Flame
+    }
+
+    /// How big the particle is drawn, smoke puffs up when it appears and flames shrink as they burn out
+    pub fn get_render_scale(&self, partial_tick: f32) -> f32 {
+        let life = (self.age as f32 + partial_tick) / self.max_age as f32;
+        match self.kind {
+            ParticleKind::Smoke | ParticleKind::RedstoneDust => self.scale * (life * 32.0).clamp(0.0, 1.0),
+            ParticleKind::Flame => self.scale * (1.0 - life * life * 0.5),
+            _ => self.scale,
+        }
+    }
+
+    /**
+     * The corners of the square the particle is drawn as, turned to face the camera along its `right` and `up`.
+     * Ordered top left, top right, bottom left, bottom right
+     */
+    pub fn billboard(&self, partial_tick: f32, right: Vec3, up: Vec3) -> [Vec3; 4] {
+        let center = self.get_position(partial_tick);
+        let size = PARTICLE_SIZE * self.get_render_scale(partial_tick);
+        let (right, up) = (right * size, up * size);
+        [center - right + up, center + right + up, center - right - up, center + right - up]
+    }
+
+    /// Move by the velocity, stopping along every axis that runs into a solid block
+    fn move_by_velocity(&mut self, is_solid: &impl Fn(IVec3) -> bool) {
+        let block_of = |position: Vec3| IVec3::new(position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+        let mut next = self.position;
+        next.y += self.velocity.y;
+        if is_solid(block_of(next)) {
+            self.on_ground = self.velocity.y < 0.0;
+            self.velocity.y = 0.0;
+            next.y = self.position.y;
+        } else {
+            self.on_ground = false;
+        }
+        next.x += self.velocity.x;
+        if is_solid(block_of(next)) {
+            self.velocity.x = 0.0;
+            next.x = self.position.x;
+        }
+        next.z += self.velocity.z;
+        if is_solid(block_of(next)) {
+            self.velocity.z = 0.0;
+            next.z = self.position.z;
+        }
+        self.position = next;
+    }
+
+    /// The cell of a puff of smoke, dust or explosion, which fades out as it ages
+    fn puff_cell(&self) -> ParticleTexture {
+        let frame = (self.age * PUFF_FRAMES as u32 / self.max_age.max(1)).min(PUFF_FRAMES as u32 - 1) as u8;
+        ParticleTexture::Sheet(PUFF_FRAMES - 1 - frame)
+    }
+
+    /// Advance the particle by a tick, false once it is gone
+    fn tick(&mut self, random: &mut JavaRandom, is_solid: &impl Fn(IVec3) -> bool) -> bool {
+        self.previous_position = self.position;
+        self.age += 1;
+        if self.age >= self.max_age {
+            return false;
+        }
+        match self.kind {
+            ParticleKind::Smoke | ParticleKind::RedstoneDust | ParticleKind::Explosion => {
+                self.texture = self.puff_cell();
+                self.velocity.y += 0.004;
+                self.move_by_velocity(is_solid);
+                // Puffs stuck under a ceiling spread out sideways
+                if self.kind != ParticleKind::Explosion && self.position.y == self.previous_position.y {
+                    self.velocity.x *= 1.1;
+                    self.velocity.z *= 1.1;
+                }
+                self.velocity *= if self.kind == ParticleKind::Explosion { 0.9 } else { 0.96 };
+            },
+            ParticleKind::Flame => {
+                self.move_by_velocity(is_solid);
+                self.velocity *= 0.96;
+            },
+            ParticleKind::Debris | ParticleKind::Splash => {
+                self.velocity.y -= self.gravity;
+                self.move_by_velocity(is_solid);
+                self.velocity *= 0.98;
+                // Half the drops are gone as soon as they land
+                if self.kind == ParticleKind::Splash && self.on_ground && random.next_float() < 0.5 {
+                    return false;
+                }
+            },
+        }
+        if self.on_ground {
+            self.velocity.x *= 0.7;
+            self.velocity.z *= 0.7;
+        }
+        true
+    }
+}
+
+/**
+ * Every particle of a world, moved on the cpu every tick.
+ * All randomness comes from one seeded random, so the same seed and events give the same particles
+ */
+#[derive(Clone, Debug)]
+pub struct ParticleSystem {
+    particles: VecDeque<Particle>,
+    random: JavaRandom,
+}
+
+impl ParticleSystem {
+    pub fn new(seed: i64) -> Self {
+        Self { particles: VecDeque::new(), random: JavaRandom::new(seed) }
+    }
+
+    pub fn get_particles(&self) -> &VecDeque<Particle> {
+        &self.particles
+    }
+
+    /// Advance every particle by a tick, `is_solid` is whether particles collide with the block at a position
+    pub fn tick(&mut self, is_solid: impl Fn(IVec3) -> bool) {
+        let random = &mut self.random;
+        self.particles.retain_mut(|particle| particle.tick(random, &is_solid));
+    }
+
+    fn add(&mut self, particle: Particle) {
+        if self.particles.len() >= MAX_PARTICLES {
+            self.particles.pop_front();
+        }
+        self.particles.push_back(particle);
+    }
+
+    fn next_signed(&mut self) -> f32 {
+        self.random.next_float() * 2.0 - 1.0
+    }
+
+    /// What every particle starts as, flying off in a random direction near `velocity` and a little up
+    fn base(&mut self, kind: ParticleKind, texture: ParticleTexture, position: Vec3, velocity: Vec3) -> Particle {
+        let mut direction = velocity + Vec3::new(self.next_signed(), self.next_signed(), self.next_signed()) * 0.4;
+        let speed = (self.random.next_float() + self.random.next_float() + 1.0) * 0.15;
+        let length = direction.mag();
+        if length > 0.0 {
+            direction = direction / length * speed * 0.4;
+        }
+        direction.y += 0.1;
+        let scale = (self.random.next_float() * 0.5 + 0.5) * 2.0;
+        let max_age = (4.0 / (self.random.next_float() * 0.9 + 0.1)) as u32;
+        Particle { kind, texture, position, previous_position: position, velocity: direction, color: Vec3::one(), scale, age: 0, max_age, gravity: 0.0, on_ground: false }
+    }
+
+    /// A bit of the blockstate `state`, showing a random square of its sprite
+    pub fn spawn_debris(&mut self, position: Vec3, velocity: Vec3, state: usize) {
+        let offset = Vec2::new(self.random.next_float(), self.random.next_float()) * (1.0 - DEBRIS_SPRITE_FRACTION);
+        let mut particle = self.base(ParticleKind::Debris, ParticleTexture::Block { state, offset }, position, velocity);
+        particle.color = Vec3::broadcast(0.6);
+        particle.scale /= 2.0;
+        particle.gravity = 0.04;
+        self.add(particle);
+    }
+
+    pub fn spawn_smoke(&mut self, position: Vec3, velocity: Vec3) {
+        let mut particle = self.base(ParticleKind::Smoke, ParticleTexture::Sheet(PUFF_FRAMES - 1), position, Vec3::zero());
+        particle.velocity = particle.velocity * 0.1 + velocity;
+        particle.color = Vec3::broadcast(self.random.next_float() * 0.3);
+        particle.scale *= 0.75;
+        particle.max_age = (8.0 / (self.random.next_float() * 0.8 + 0.2)) as u32;
+        self.add(particle);
+    }
+
+    pub fn spawn_flame(&mut self, position: Vec3, velocity: Vec3) {
+        let mut particle = self.base(ParticleKind::Flame, ParticleTexture::Sheet(FLAME_CELL), position, Vec3::zero());
+        particle.velocity = particle.velocity * 0.01 + velocity;
+        let jitter = Vec3::new(
+            self.random.next_float() - self.random.next_float(),
+            self.random.next_float() - self.random.next_float(),
+            self.random.next_float() - self.random.next_float(),
+        ) * 0.05;
+        particle.position += jitter;
+        particle.previous_position = particle.position;
+        particle.max_age = (8.0 / (self.random.next_float() * 0.8 + 0.2)) as u32 + 4;
+        self.add(particle);
+    }
+
+    pub fn spawn_splash(&mut self, position: Vec3) {
+        let cell = SPLASH_CELL + self.random.next_int_bounded(4) as u8;
+        let mut particle = self.base(ParticleKind::Splash, ParticleTexture::Sheet(cell), position, Vec3::zero());
+        particle.velocity *= 0.3;
+        particle.velocity.y = self.random.next_float() * 0.2 + 0.1;
+        particle.gravity = 0.04;
+        particle.max_age = (8.0 / (self.random.next_float() * 0.8 + 0.2)) as u32;
+        self.add(particle);
+    }
+
+    pub fn spawn_explosion(&mut self, position: Vec3, velocity: Vec3) {
+        let mut particle = self.base(ParticleKind::Explosion, ParticleTexture::Sheet(PUFF_FRAMES - 1), position, Vec3::zero());
+        particle.velocity = velocity + Vec3::new(self.next_signed(), self.next_signed(), self.next_signed()) * 0.05;
+        particle.color = Vec3::broadcast(self.random.next_float() * 0.3 + 0.7);
+        particle.scale = self.random.next_float() * self.random.next_float() * 6.0 + 1.0;
+        particle.max_age = (16.0 / (self.random.next_float() * 0.8 + 0.2)) as u32 + 2;
+        self.add(particle);
+    }
+
+    /// A speck of redstone dust, tinted a little darker or lighter than `color`
+    pub fn spawn_redstone_dust(&mut self, position: Vec3, color: Vec3) {
+        let mut particle = self.base(ParticleKind::RedstoneDust, ParticleTexture::Sheet(PUFF_FRAMES - 1), position, Vec3::zero());
+        particle.velocity *= 0.1;
+        let shade = self.random.next_float() * 0.4 + 0.6;
+        particle.color = Vec3::new(
+            (self.random.next_float() * 0.2 + 0.8) * color.x * shade,
+            (self.random.next_float() * 0.2 + 0.8) * color.y * shade,
+            (self.random.next_float() * 0.2 + 0.8) * color.z * shade,
+        );
+        particle.scale *= 0.75;
+        particle.max_age = (8.0 / (self.random.next_float() * 0.8 + 0.2)) as u32;
+        self.add(particle);
+    }
+
+    /// A broken block bursts into a grid of debris, each bit flying away from the middle of the block
+    pub fn add_block_destroy_effects(&mut self, block: IVec3, state: usize) {
+        let origin = Vec3::new(block.x as f32, block.y as f32, block.z as f32);
+        for x in 0..DEBRIS_PER_AXIS {
+            for y in 0..DEBRIS_PER_AXIS {
+                for z in 0..DEBRIS_PER_AXIS {
+                    let offset = (Vec3::new(x as f32, y as f32, z as f32) + Vec3::broadcast(0.5)) / DEBRIS_PER_AXIS as f32;
+                    self.spawn_debris(origin + offset, offset - Vec3::broadcast(0.5), state);
+                }
+            }
+        }
+    }
+
+    /**
+     * Smoke puffing out of the side of a block, `direction` is the [`SMOKE_EFFECT`] data.
+     * It packs the x and z of the side as a grid of three by three, the middle puffs out of the block itself
+     */
+    pub fn add_smoke_effect(&mut self, block: IVec3, direction: i32) {
+        let (side_x, side_z) = ((direction % 3 - 1) as f32, (direction / 3 % 3 - 1) as f32);
+        let origin = Vec3::new(block.x as f32 + side_x * 0.6 + 0.5, block.y as f32 + 0.5, block.z as f32 + side_z * 0.6 + 0.5);
+        for _ in 0..10 {
+            let speed = self.random.next_float() * 0.2 + 0.01;
+            let position = origin + Vec3::new(
+                side_x * 0.01 + (self.random.next_float() - 0.5) * side_z * 0.5,
+                (self.random.next_float() - 0.5) * 0.5,
+                side_z * 0.01 + (self.random.next_float() - 0.5) * side_x * 0.5,
+            );
+            let velocity = Vec3::new(
+                side_x * speed + self.random.next_float() * 0.01,
+                -0.03 + self.random.next_float() * 0.01,
+                side_z * speed + self.random.next_float() * 0.01,
+            );
+            self.spawn_smoke(position, velocity);
+        }
+    }
+
+    /// An explosion throws a puff and some smoke out of every block it destroyed, faster the closer the block was to its center
+    pub fn add_explosion(&mut self, center: Vec3, radius: f32, blocks: &[IVec3]) {
+        for block in blocks {
+            let position = Vec3::new(block.x as f32, block.y as f32, block.z as f32)
+                + Vec3::new(self.random.next_float(), self.random.next_float(), self.random.next_float());
+            let away = position - center;
+            let distance = away.mag();
+            if distance == 0.0 { continue; }
+            let speed = 0.5 / (distance / radius + 0.1) * (self.random.next_float() * self.random.next_float() + 0.3);
+            let velocity = away / distance * speed;
+            self.spawn_explosion((position + center) * 0.5, velocity);
+            self.spawn_smoke(position, velocity);
+        }
+    }
+
+    /// Rain splashes on the ground around the camera, more of them the harder it rains
+    pub fn add_rain_splashes(&mut self, camera: IVec3, strength: f32, height_of: impl Fn(i32, i32) -> i32, precipitation_of: impl Fn(i32, i32) -> Precipitation) {
+        for splash in weather::rain_splashes(&mut self.random, camera, strength, height_of, precipitation_of) {
+            self.spawn_splash(splash);
+        }
+    }
+
+    /// Where the flame of a torch is, torches on a wall lean out of it by their metadata
+    fn torch_flame_position(origin: Vec3, meta: u8) -> Vec3 {
+        let (up, out) = (0.22, 0.27);
+        let center = origin + Vec3::new(0.5, 0.7, 0.5);
+        match meta {
+            1 => center + Vec3::new(-out, up, 0.0),
+            2 => center + Vec3::new(out, up, 0.0),
+            3 => center + Vec3::new(0.0, up, -out),
+            4 => center + Vec3::new(0.0, up, out),
+            _ => center,
+        }
+    }
+
+    /// The effects a block shows now and then while it is near the player, as the random display ticks of the blocks of b1.7.3
+    pub fn add_display_effects(&mut self, block: IVec3, id: u8, meta: u8) {
+        let origin = Vec3::new(block.x as f32, block.y as f32, block.z as f32);
+        match id {
+            TORCH_BLOCK => {
+                let flame = Self::torch_flame_position(origin, meta);
+                self.spawn_smoke(flame, Vec3::zero());
+                self.spawn_flame(flame, Vec3::zero());
+            },
+            LIT_REDSTONE_TORCH_BLOCK => {
+                let jitter = Vec3::new(self.random.next_float() - 0.5, self.random.next_float() - 0.5, self.random.next_float() - 0.5) * 0.2;
+                self.spawn_redstone_dust(Self::torch_flame_position(origin, meta) + jitter, Vec3::new(1.0, 0.0, 0.0));
+            },
+            // Wire is brighter the more power it carries
+            REDSTONE_WIRE_BLOCK if meta > 0 => {
+                let position = origin + Vec3::new(0.5 + (self.random.next_float() - 0.5) * 0.2, 1.0 / 16.0, 0.5 + (self.random.next_float() - 0.5) * 0.2);
+                let power = meta as f32 / 15.0;
+                let color = Vec3::new(power * 0.6 + 0.4, (power * power * 0.7 - 0.5).max(0.0), (power * power * 0.6 - 0.7).max(0.0));
+                self.spawn_redstone_dust(position, color);
+            },
+            // Furnaces burn out of their front
+            LIT_FURNACE_BLOCK => {
+                let (y, front, along) = (self.random.next_float() * 6.0 / 16.0, 0.52, self.random.next_float() * 0.6 - 0.3);
+                let offset = match meta {
+                    2 => Vec3::new(along, y, -front),
+                    3 => Vec3::new(along, y, front),
+                    4 => Vec3::new(-front, y, along),
+                    5 => Vec3::new(front, y, along),
+                    _ => return,
+                };
+                let position = origin + Vec3::new(0.5, 0.0, 0.5) + offset;
+                self.spawn_smoke(position, Vec3::zero());
+                self.spawn_flame(position, Vec3::zero());
+            },
+            _ => {},
+        }
+    }
+
+    /// Let random blocks around `center` show their effects, `block_at` is the id and metadata of a block
+    pub fn random_display_tick(&mut self, center: IVec3, block_at: impl Fn(IVec3) -> Option<(u8, u8)>) {
+        for _ in 0..DISPLAY_TICKS {
+            let mut offset = || self.random.next_int_bounded(DISPLAY_RANGE) - self.random.next_int_bounded(DISPLAY_RANGE);
+            let block = center + IVec3::new(offset(), offset(), offset());
+            if let Some((id, meta)) = block_at(block) {
+                self.add_display_effects(block, id, meta);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particles_are_deterministic_under_a_seed() {
+        let simulate = |seed| {
+            let mut particles = ParticleSystem::new(seed);
+            particles.add_block_destroy_effects(IVec3::new(3, 64, -2), 7);
+            particles.add_smoke_effect(IVec3::new(0, 64, 0), 5);
+            particles.add_explosion(Vec3::new(0.5, 64.5, 0.5), 4.0, &[IVec3::new(1, 64, 0), IVec3::new(0, 65, 0)]);
+            for _ in 0..5 {
+                particles.tick(|block| block.y < 64);
+            }
+            particles
+        };
+        assert_eq!(simulate(12).get_particles(), simulate(12).get_particles());
+        assert_ne!(simulate(12).get_particles(), simulate(13).get_particles());
+        // Every bit of the broken block shows its blockstate
+        let mut debris = ParticleSystem::new(0);
+        debris.add_block_destroy_effects(IVec3::zero(), 7);
+        assert_eq!(debris.get_particles().len(), 64);
+        assert!(debris.get_particles().iter().all(|particle| matches!(particle.texture, ParticleTexture::Block { state: 7, .. })));
+    }
+
+    #[test]
+    fn debris_lands_on_solid_blocks() {
+        let mut particles = ParticleSystem::new(3);
+        particles.add_block_destroy_effects(IVec3::new(0, 64, 0), 1);
+        for _ in 0..10 {
+            particles.tick(|block| block.y < 64);
+            assert!(particles.get_particles().iter().all(|particle| particle.position.y >= 64.0));
+        }
+        // Nothing lives longer than forty ticks
+        for _ in 0..40 {
+            particles.tick(|block| block.y < 64);
+        }
+        assert!(particles.get_particles().is_empty());
+    }
+
+    #[test]
+    fn puffs_fade_through_the_sheet_and_face_the_camera() {
+        let mut particles = ParticleSystem::new(8);
+        particles.spawn_smoke(Vec3::new(0.0, 70.0, 0.0), Vec3::zero());
+        let max_age = particles.get_particles()[0].max_age;
+        let mut cells = vec![];
+        for _ in 1..max_age {
+            particles.tick(|_| false);
+            let ParticleTexture::Sheet(cell) = particles.get_particles()[0].texture else { panic!("smoke is on the sheet") };
+            cells.push(cell);
+        }
+        assert!(cells.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert_eq!(*cells.last().unwrap(), 0);
+
+        let particle = &particles.get_particles()[0];
+        let corners = particle.billboard(1.0, Vec3::unit_x(), Vec3::unit_y());
+        let center = particle.get_position(1.0);
+        assert!(((corners[0] + corners[3]) * 0.5 - center).mag() < 1e-5);
+        assert!(corners[0].y > corners[2].y && corners[1].x > corners[0].x);
+        assert_eq!(ParticleTexture::Sheet(FLAME_CELL).uv_bounds(), (Vec2::new(0.0, 0.1875), Vec2::new(0.0625, 0.25)));
+    }
+
+    #[test]
+    fn blocks_near_the_player_smoke_and_glow() {
+        let mut particles = ParticleSystem::new(5);
+        let torch = IVec3::new(3, 64, -2);
+        let wire = IVec3::new(0, 64, 0);
+        let block_at = |block: IVec3| match block {
+            block if block == torch => Some((TORCH_BLOCK, 5)),
+            block if block == wire => Some((REDSTONE_WIRE_BLOCK, 0)),
+            _ => None,
+        };
+        for _ in 0..20 {
+            particles.random_display_tick(IVec3::new(0, 64, 0), block_at);
+        }
+        let kinds: Vec<ParticleKind> = particles.get_particles().iter().map(|particle| particle.kind).collect();
+        assert!(kinds.contains(&ParticleKind::Flame) && kinds.contains(&ParticleKind::Smoke));
+        // Unpowered wire gives off nothing
+        assert!(!kinds.contains(&ParticleKind::RedstoneDust));
+        let flame = particles.get_particles().iter().find(|particle| particle.kind == ParticleKind::Flame).unwrap();
+        assert!((flame.position - Vec3::new(3.5, 64.7, -1.5)).mag() < 0.2);
+
+        particles.add_display_effects(wire, REDSTONE_WIRE_BLOCK, 15);
+        let dust = particles.get_particles().back().unwrap();
+        assert_eq!(dust.kind, ParticleKind::RedstoneDust);
+        assert!(dust.color.x > dust.color.y);
+
+        // The oldest particles make way for new ones
+        for _ in 0..MAX_PARTICLES {
+            particles.spawn_splash(Vec3::new(0.0, 65.0, 0.0));
+        }
+        assert_eq!(particles.get_particles().len(), MAX_PARTICLES);
+        assert!(particles.get_particles().iter().all(|particle| particle.kind == ParticleKind::Splash));
+    }
+}
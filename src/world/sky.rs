@@ -0,0 +1,289 @@
+use std::f32::consts::TAU;
+
+use ultraviolet::{Vec2, Vec3, Vec4};
+
+use crate::util::java_random::JavaRandom;
+
+use super::lightmap::{self, DimensionLight, TICKS_PER_DAY};
+
+/// How far the sun, moon and stars are from the camera
+pub const SKY_DISTANCE: f32 = 100.0;
+/// Half the width of the sun
+pub const SUN_SIZE: f32 = 30.0;
+/// Half the width of the moon
+pub const MOON_SIZE: f32 = 20.0;
+/// The seed of the b1.7.3 starfield
+pub const STAR_SEED: i64 = 10842;
+/// Stars tried, the ones that land outside the unit sphere or too near its center are dropped
+const STAR_ATTEMPTS: usize = 1500;
+/// How far above the camera the center of the sky is, the sky falls away from it to just under the horizon
+pub const SKY_DISC_HEIGHT: f32 = 16.0;
+/// The phases of the moon, one a day
+pub const MOON_PHASES: u64 = 8;
+/// The moon phases sprite is a grid of 4 by 2 phases
+const MOON_PHASE_COLUMNS: u64 = 4;
+const SUNRISE_SEGMENTS: usize = 16;
+const SKY_DISC_SEGMENTS: usize = 32;
+const NETHER_FOG_COLOR: Vec3 = Vec3::new(0.2, 0.03, 0.03);
+/// The fog color of a clear day at noon
+const DAY_FOG_COLOR: Vec3 = Vec3::new(0.7529412, 0.84705883, 1.0);
+
+/// How much of the daylight is left at a celestial angle, 1 through the day and 0 through the night
+pub fn daylight(celestial_angle: f32) -> f32 {
+    ((celestial_angle * TAU).cos() * 2.0 + 0.5).clamp(0.0, 1.0)
+}
+
+/// The red, green and blue of a hue, saturation and brightness, as `java.awt.Color.HSBtoRGB` converts them
+pub fn hsb_to_rgb(hue: f32, saturation: f32, brightness: f32) -> Vec3 {
+    let sector = (hue - hue.floor()) * 6.0;
+    let fraction = sector - sector.floor();
+    let p = brightness * (1.0 - saturation);
+    let q = brightness * (1.0 - saturation * fraction);
+    let t = brightness * (1.0 - saturation * (1.0 - fraction));
+    match sector as u32 {
+        0 => Vec3::new(brightness, t, p),
+        1 => Vec3::new(q, brightness, p),
+        2 => Vec3::new(p, brightness, t),
+        3 => Vec3::new(p, q, brightness),
+        4 => Vec3::new(t, p, brightness),
+        _ => Vec3::new(brightness, p, q),
+    }
+}
+
+/// The color of the sky overhead, deeper and more cyan over hot biomes, darkening through the night
+pub fn sky_color(celestial_angle: f32, temperature: f32) -> Vec3 {
+    let warmth = (temperature / 3.0).clamp(-1.0, 1.0);
+    hsb_to_rgb(0.62222224 - warmth * 0.05, 0.5 + warmth * 0.1, 1.0) * daylight(celestial_angle)
+}
+
+/// The color of the fog and the horizon, it never gets fully black
+pub fn fog_color(celestial_angle: f32) -> Vec3 {
+    let daylight = daylight(celestial_angle);
+    DAY_FOG_COLOR * Vec3::new(daylight * 0.94 + 0.06, daylight * 0.94 + 0.06, daylight * 0.91 + 0.09)
+}
+
+/// The glow on the horizon while the sun rises or sets, with its opacity in alpha
+pub fn sunrise_color(celestial_angle: f32) -> Option<Vec4> {
+    let height = (celestial_angle * TAU).cos();
+    if !(-0.4..=0.4).contains(&height) { return None; }
+    let progress = height / 0.4 * 0.5 + 0.5;
+    let alpha = 1.0 - (1.0 - (progress * std::f32::consts::PI).sin()) * 0.99;
+    Some(Vec4::new(progress * 0.3 + 0.7, progress * progress * 0.7 + 0.2, 0.0, alpha * alpha))
+}
+
+/// How bright the stars are, they only come out once the sun is well below the horizon
+pub fn star_brightness(celestial_angle: f32) -> f32 {
+    let darkness = (1.0 - ((celestial_angle * TAU).cos() * 2.0 + 0.75)).clamp(0.0, 1.0);
+    darkness * darkness * 0.5
+}
+
+pub fn moon_phase(time: u64) -> u64 {
+    (time / TICKS_PER_DAY) % MOON_PHASES
+}
+
+/// The corners of a moon phase in the moon phases sprite, as fractions of the sprite
+pub fn moon_phase_uvs(phase: u64) -> (Vec2, Vec2) {
+    let column = (phase % MOON_PHASE_COLUMNS) as f32;
+    let row = ((phase / MOON_PHASE_COLUMNS) % 2) as f32;
+    let size = Vec2::new(1.0 / MOON_PHASE_COLUMNS as f32, 0.5);
+    let min = Vec2::new(column, row) * size;
+    (min, min + size)
+}
+
+/// Turn a point of the sky at noon to where it is at a celestial angle, the sky turns around the z axis and the sun rises towards +x
+pub fn rotate_with_sky(celestial_angle: f32, point: Vec3) -> Vec3 {
+    let (sin, cos) = (celestial_angle * TAU).sin_cos();
+    Vec3::new(point.x * cos - point.y * sin, point.x * sin + point.y * cos, point.z)
+}
+
+/// Where the sun is as seen from the camera, a unit vector
+pub fn sun_direction(celestial_angle: f32) -> Vec3 {
+    rotate_with_sky(celestial_angle, Vec3::unit_y())
+}
+
+/// A square facing the camera from `height` above it at noon, turned with the sky, corners ordered top left, top right, bottom left, bottom right
+fn celestial_quad(celestial_angle: f32, height: f32, size: f32) -> [Vec3; 4] {
+    [
+        Vec3::new(-size, height, -size),
+        Vec3::new(size, height, -size),
+        Vec3::new(-size, height, size),
+        Vec3::new(size, height, size),
+    ].map(|corner| rotate_with_sky(celestial_angle, corner))
+}
+
+pub fn sun_quad(celestial_angle: f32) -> [Vec3; 4] {
+    celestial_quad(celestial_angle, SKY_DISTANCE, SUN_SIZE)
+}
+
+/// The moon is always across the sky from the sun
+pub fn moon_quad(celestial_angle: f32) -> [Vec3; 4] {
+    celestial_quad(celestial_angle, -SKY_DISTANCE, MOON_SIZE)
+}
+
+/**
+ * The b1.7.3 starfield at noon, a small square for every star facing the camera from [`SKY_DISTANCE`] away, each turned at random.
+ * Turn them with [`rotate_with_sky`], corners are ordered top left, top right, bottom left, bottom right
+ */
+pub fn star_quads() -> Vec<[Vec3; 4]> {
+    let mut random = JavaRandom::new(STAR_SEED);
+    let mut stars = vec![];
+    for _ in 0..STAR_ATTEMPTS {
+        let mut direction = [0.0f64; 3].map(|_| random.next_float() as f64 * 2.0 - 1.0);
+        let size = 0.25 + random.next_float() as f64 * 0.25;
+        let length_sq: f64 = direction.iter().map(|axis| axis * axis).sum();
+        if !(0.01..1.0).contains(&length_sq) { continue; }
+        direction = direction.map(|axis| axis / length_sq.sqrt());
+        let [x, y, z] = direction;
+        let (yaw_sin, yaw_cos) = x.atan2(z).sin_cos();
+        let (pitch_sin, pitch_cos) = (x * x + z * z).sqrt().atan2(y).sin_cos();
+        let (spin_sin, spin_cos) = (random.next_double() * std::f64::consts::TAU).sin_cos();
+        let corner = |u: f64, v: f64| {
+            let (u, v) = (u * size, v * size);
+            let spun_u = u * spin_cos - v * spin_sin;
+            let spun_v = v * spin_cos + u * spin_sin;
+            let up = spun_u * pitch_sin;
+            let out = -spun_u * pitch_cos;
+            let distance = SKY_DISTANCE as f64;
+            Vec3::new(
+                (x * distance + out * yaw_sin - spun_v * yaw_cos) as f32,
+                (y * distance + up) as f32,
+                (z * distance + spun_v * yaw_sin + out * yaw_cos) as f32,
+            )
+        };
+        stars.push([corner(-1.0, -1.0), corner(-1.0, 1.0), corner(1.0, -1.0), corner(1.0, 1.0)]);
+    }
+    stars
+}
+
+/**
+ * The dome of the sky, a fan from the sky color overhead down to the fog color just under the horizon.
+ * Returns the center of the fan and its rim, the rim closes on itself
+ */
+pub fn sky_disc() -> (Vec3, Vec<Vec3>) {
+    let rim = (0..=SKY_DISC_SEGMENTS).map(|segment| {
+        let (sin, cos) = (segment as f32 * TAU / SKY_DISC_SEGMENTS as f32).sin_cos();
+        Vec3::new(cos * SKY_DISTANCE, -SKY_DISC_HEIGHT, sin * SKY_DISTANCE)
+    }).collect();
+    (Vec3::new(0.0, SKY_DISC_HEIGHT, 0.0), rim)
+}
+
+/**
+ * The glow of a sunrise or sunset, a fan from the horizon under the sun out to a rim that fades away.
+ * Returns the center of the fan and its rim, the rim closes on itself
+ */
+pub fn sunrise_fan(celestial_angle: f32, alpha: f32) -> (Vec3, Vec<Vec3>) {
+    // The glow sits on the side of the sky the sun is on
+    let side = if sun_direction(celestial_angle).x < 0.0 { -1.0 } else { 1.0 };
+    let center = Vec3::new(side * SKY_DISTANCE, 0.0, 0.0);
+    let rim = (0..=SUNRISE_SEGMENTS).map(|segment| {
+        let (sin, cos) = (segment as f32 * TAU / SUNRISE_SEGMENTS as f32).sin_cos();
+        Vec3::new(side * cos * SKY_DISTANCE * 1.2, cos * 40.0 * alpha, sin * SKY_DISTANCE * 1.2)
+    }).collect();
+    (center, rim)
+}
+
+/// The distances fog starts and ends at, terrain past the end is hidden in the fog color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogRange {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl FogRange {
+    /// Fog that ends at the edge of the loaded chunks and starts a quarter of the way there, as in b1.7.3
+    pub fn for_render_distance(render_distance: u32) -> Self {
+        let end = (render_distance * 16) as f32;
+        Self { start: end * 0.25, end }
+    }
+}
+
+/// Everything the sky looks like at a moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyState {
+    pub celestial_angle: f32,
+    pub sky_color: Vec3,
+    pub fog_color: Vec3,
+    pub sunrise: Option<Vec4>,
+    pub star_brightness: f32,
+    pub moon_phase: u64,
+    /// Dimensions without a sky only have fog, no sun, moon or stars
+    pub has_sky: bool,
+}
+
+impl SkyState {
+    /// The sky at a time of day over a biome of a temperature
+    pub fn at(time: u64, partial_tick: f32, temperature: f32, dimension: DimensionLight) -> Self {
+        let celestial_angle = lightmap::celestial_angle(time, partial_tick);
+        if !dimension.has_sky {
+            return Self { celestial_angle, sky_color: NETHER_FOG_COLOR, fog_color: NETHER_FOG_COLOR, sunrise: None, star_brightness: 0.0, moon_phase: 0, has_sky: false };
+        }
+        Self {
+            celestial_angle,
+            sky_color: sky_color(celestial_angle, temperature),
+            fog_color: fog_color(celestial_angle),
+            sunrise: sunrise_color(celestial_angle),
+            star_brightness: star_brightness(celestial_angle),
+            moon_phase: moon_phase(time),
+            has_sky: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOON: u64 = 6000;
+    const SUNSET: u64 = 12000;
+    const MIDNIGHT: u64 = 18000;
+
+    #[test]
+    fn the_sky_follows_the_time_of_day() {
+        let noon = SkyState::at(NOON, 0.0, 0.5, DimensionLight::OVERWORLD);
+        let sunset = SkyState::at(SUNSET, 0.0, 0.5, DimensionLight::OVERWORLD);
+        let midnight = SkyState::at(MIDNIGHT, 0.0, 0.5, DimensionLight::OVERWORLD);
+        assert!(noon.sky_color.z > noon.sky_color.x && noon.sky_color.z > 0.9);
+        assert_eq!(midnight.sky_color, Vec3::zero());
+        assert!(midnight.fog_color.x > 0.0 && midnight.fog_color.x < noon.fog_color.x);
+        assert!(noon.sunrise.is_none() && midnight.sunrise.is_none());
+        assert!(sunset.sunrise.is_some_and(|glow| glow.w > 0.25));
+        assert_eq!(noon.star_brightness, 0.0);
+        assert_eq!(midnight.star_brightness, 0.5);
+        assert_eq!(SkyState::at(MIDNIGHT + 3 * TICKS_PER_DAY, 0.0, 0.5, DimensionLight::OVERWORLD).moon_phase, 3);
+
+        // Hot biomes have a deeper, more cyan sky than cold ones
+        let hot = sky_color(0.0, 2.0);
+        let cold = sky_color(0.0, 0.0);
+        assert!(hot.x < cold.x && hot.y > cold.y);
+        assert!(!SkyState::at(NOON, 0.0, 0.5, DimensionLight::NETHER).has_sky);
+    }
+
+    #[test]
+    fn the_sun_and_moon_cross_the_sky() {
+        let at = |time: u64| lightmap::celestial_angle(time, 0.0);
+        assert!((sun_direction(at(NOON)) - Vec3::unit_y()).mag() < 1e-5);
+        assert!(sun_direction(at(0)).x > 0.5, "the sun rises towards +x");
+        assert!(sun_direction(at(MIDNIGHT)).y < -0.99);
+        let sun = sun_quad(at(NOON));
+        let moon = moon_quad(at(NOON));
+        assert!(sun.iter().all(|corner| corner.y == SKY_DISTANCE));
+        assert!(moon.iter().all(|corner| corner.y == -SKY_DISTANCE));
+        assert_eq!(moon_phase_uvs(5), (Vec2::new(0.25, 0.5), Vec2::new(0.5, 1.0)));
+        assert_eq!(FogRange::for_render_distance(10), FogRange { start: 40.0, end: 160.0 });
+    }
+
+    #[test]
+    fn stars_are_seeded_and_surround_the_camera() {
+        let stars = star_quads();
+        assert_eq!(stars, star_quads());
+        // About half of the random points land inside the unit sphere
+        assert!((600..1000).contains(&stars.len()));
+        for star in &stars {
+            let center = star.iter().fold(Vec3::zero(), |sum, corner| sum + *corner) / 4.0;
+            assert!((center.mag() - SKY_DISTANCE).abs() < 0.01);
+            let width = (star[0] - star[3]).mag();
+            assert!((0.5..=1.5).contains(&width));
+        }
+        assert!(stars.iter().any(|star| star[0].y > 0.0) && stars.iter().any(|star| star[0].y < 0.0));
+    }
+}
@@ -0,0 +1,123 @@
+use ultraviolet::IVec3;
+
+use super::{ChunkStorage, ChunkStorageTrait, biome::{Climate, ClimateSource}, chunk::{Chunk, TBlockData, TLightData, CHUNK_SECTION_AXIS_SIZE}};
+
+/// A section along with a border of one block from each of its neighbours
+pub const SNAPSHOT_AXIS_SIZE: usize = CHUNK_SECTION_AXIS_SIZE + 2;
+
+/**
+ * A copy of the blocks and lights of a section and the blocks around it, along with the climate of its columns.
+ * Everything needed to mesh a section, so it can be meshed on another thread while the world keeps changing.
+ * Positions are relative to the section, from -1 to 16 on every axis, neighbours that are not loaded are air without light
+ */
+#[derive(Clone)]
+pub struct SectionSnapshot {
+    position: IVec3,
+    blocks: Vec<TBlockData>,
+    /// (sky light, block light)
+    lights: Vec<(TLightData, TLightData)>,
+    climates: Vec<Climate>,
+}
+
+fn snapshot_index(x: i32, y: i32, z: i32) -> usize {
+    let size = SNAPSHOT_AXIS_SIZE as i32;
+    let (x, y, z) = (x.clamp(-1, size - 2) + 1, y.clamp(-1, size - 2) + 1, z.clamp(-1, size - 2) + 1);
+    ((y * size + z) * size + x) as usize
+}
+
+impl SectionSnapshot {
+    /// Copy the section at `position`, if it is loaded
+    pub fn capture(storage: &ChunkStorage<Chunk>, position: IVec3, climate: &dyn ClimateSource) -> Option<Self> {
+        storage.get_chunk(position).ok()?;
+        let axis = CHUNK_SECTION_AXIS_SIZE as i32;
+        // The section and its 26 neighbours, looked up once
+        let mut neighbours: [Option<&Chunk>; 27] = [None; 27];
+        for (index, neighbour) in neighbours.iter_mut().enumerate() {
+            let offset = IVec3::new(index as i32 % 3 - 1, index as i32 / 9 - 1, (index as i32 / 3) % 3 - 1);
+            *neighbour = storage.get_chunk(position + offset).ok();
+        }
+
+        let volume = SNAPSHOT_AXIS_SIZE * SNAPSHOT_AXIS_SIZE * SNAPSHOT_AXIS_SIZE;
+        let mut blocks = vec![0; volume];
+        let mut lights = vec![(0, 0); volume];
+        for y in -1..=axis {
+            for z in -1..=axis {
+                for x in -1..=axis {
+                    let (cx, cy, cz) = (x.div_euclid(axis), y.div_euclid(axis), z.div_euclid(axis));
+                    let Some(chunk) = neighbours[((cy + 1) * 9 + (cz + 1) * 3 + (cx + 1)) as usize] else { continue; };
+                    let (lx, ly, lz) = (x.rem_euclid(axis) as u32, y.rem_euclid(axis) as u32, z.rem_euclid(axis) as u32);
+                    let index = snapshot_index(x, y, z);
+                    blocks[index] = chunk.get_block_at_pos(lx, ly, lz);
+                    lights[index] = chunk.get_light_at_pos(lx, ly, lz);
+                }
+            }
+        }
+
+        let origin = position * axis;
+        let mut climates = Vec::with_capacity(SNAPSHOT_AXIS_SIZE * SNAPSHOT_AXIS_SIZE);
+        for z in -1..=axis {
+            for x in -1..=axis {
+                climates.push(climate.climate_at(origin.x + x, origin.z + z));
+            }
+        }
+
+        Some(Self { position, blocks, lights, climates })
+    }
+
+    /// The position of the section in sections
+    pub fn get_position(&self) -> IVec3 {
+        self.position
+    }
+
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> TBlockData {
+        self.blocks[snapshot_index(x, y, z)]
+    }
+
+    /// (sky light, block light)
+    pub fn get_light(&self, x: i32, y: i32, z: i32) -> (TLightData, TLightData) {
+        self.lights[snapshot_index(x, y, z)]
+    }
+
+    /// The climate of a column relative to the section, columns outside of the border use the nearest border column
+    pub fn get_climate(&self, x: i32, z: i32) -> Climate {
+        let size = SNAPSHOT_AXIS_SIZE as i32;
+        let (x, z) = (x.clamp(-1, size - 2) + 1, z.clamp(-1, size - 2) + 1);
+        self.climates[(z * size + x) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::ChunkStoragePlanar;
+
+    struct ColumnClimate;
+
+    impl ClimateSource for ColumnClimate {
+        fn climate_at(&self, x: i32, z: i32) -> Climate {
+            Climate::new(x as f32 / 64.0, z as f32 / 64.0)
+        }
+    }
+
+    #[test]
+    fn snapshot_copies_the_border_of_neighbours() {
+        let mut storage = ChunkStorage::Planar(ChunkStoragePlanar::new(4));
+        let mut section = Chunk::create_empty();
+        section.set_block_at_pos(0, 0, 0, 1);
+        section.set_blocklight_at_pos(0, 0, 0, 7);
+        storage.set_chunk(section, IVec3::new(0, 1, 0)).unwrap();
+        let mut east = Chunk::create_empty();
+        east.set_block_at_pos(15, 0, 3, 2);
+        storage.set_chunk(east, IVec3::new(-1, 1, 0)).unwrap();
+
+        assert!(SectionSnapshot::capture(&storage, IVec3::new(5, 1, 5), &ColumnClimate).is_none());
+        let snapshot = SectionSnapshot::capture(&storage, IVec3::new(0, 1, 0), &ColumnClimate).unwrap();
+        assert_eq!(snapshot.get_block(0, 0, 0), 1);
+        assert_eq!(snapshot.get_light(0, 0, 0).1, 7);
+        assert_eq!(snapshot.get_block(-1, 0, 3), 2);
+        assert_eq!(snapshot.get_block(0, -1, 0), 0);
+        assert_eq!(snapshot.get_block(16, 16, 16), 0);
+        assert_eq!(snapshot.get_climate(16, 0), Climate::new(0.25, 0.0));
+        assert_eq!(snapshot.get_climate(40, 0), snapshot.get_climate(16, 0));
+    }
+}
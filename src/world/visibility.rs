@@ -0,0 +1,238 @@
+use rustc_hash::FxHashSet as HashSet;
+use std::collections::VecDeque;
+use ultraviolet::IVec3;
+
+use crate::direction::{Direction, DIRECTIONS};
+
+use super::chunk::CHUNK_SECTION_AXIS_SIZE;
+
+const SECTION_VOLUME: usize = CHUNK_SECTION_AXIS_SIZE * CHUNK_SECTION_AXIS_SIZE * CHUNK_SECTION_AXIS_SIZE;
+/// Fewer opaque cells than the area of a side can't wall off anything
+const MIN_OPAQUE_TO_OCCLUDE: usize = CHUNK_SECTION_AXIS_SIZE * CHUNK_SECTION_AXIS_SIZE;
+
+/**
+ * Which sides of a section can see each other through the cells that aren't opaque.
+ * Two sides are connected when a flood fill of open cells touches both of them
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionVisibility {
+    /// A bit for every (from, to) pair of sides, always symmetric
+    connections: u64,
+}
+
+fn cell_index(x: usize, y: usize, z: usize) -> usize {
+    (y * CHUNK_SECTION_AXIS_SIZE + z) * CHUNK_SECTION_AXIS_SIZE + x
+}
+
+/// The sides of the section a cell touches, as direction ordinal bits
+fn touched_sides(x: usize, y: usize, z: usize) -> u32 {
+    let last = CHUNK_SECTION_AXIS_SIZE - 1;
+    let mut sides = 0;
+    if x == 0 { sides |= Direction::North.ordinal_bitwise(); }
+    if x == last { sides |= Direction::South.ordinal_bitwise(); }
+    if z == 0 { sides |= Direction::East.ordinal_bitwise(); }
+    if z == last { sides |= Direction::West.ordinal_bitwise(); }
+    if y == last { sides |= Direction::Up.ordinal_bitwise(); }
+    if y == 0 { sides |= Direction::Down.ordinal_bitwise(); }
+    sides
+}
+
+impl SectionVisibility {
+    /// Every side sees every other side, like an empty section
+    pub fn all() -> Self {
+        Self { connections: (1 << 36) - 1 }
+    }
+
+    /// No side sees another, like a solid section
+    pub fn none() -> Self {
+        Self { connections: 0 }
+    }
+
+    /// Flood fill the open cells of a section, `is_opaque` is given positions from 0 to 15 within the section
+    pub fn compute(is_opaque: impl Fn(i32, i32, i32) -> bool) -> Self {
+        let axis = CHUNK_SECTION_AXIS_SIZE;
+        let mut closed = vec![false; SECTION_VOLUME];
+        let mut opaque_cells = 0;
+        for y in 0..axis {
+            for z in 0..axis {
+                for x in 0..axis {
+                    if is_opaque(x as i32, y as i32, z as i32) {
+                        closed[cell_index(x, y, z)] = true;
+                        opaque_cells += 1;
+                    }
+                }
+            }
+        }
+        if opaque_cells < MIN_OPAQUE_TO_OCCLUDE {
+            return Self::all();
+        }
+
+        let mut visibility = Self::none();
+        let mut stack = vec![];
+        for start in 0..SECTION_VOLUME {
+            if closed[start] { continue; }
+            closed[start] = true;
+            stack.push(start);
+            let mut sides = 0;
+            while let Some(cell) = stack.pop() {
+                let (x, y, z) = (cell % axis, cell / (axis * axis), (cell / axis) % axis);
+                sides |= touched_sides(x, y, z);
+                let neighbours = [
+                    (x > 0).then(|| cell - 1),
+                    (x + 1 < axis).then(|| cell + 1),
+                    (z > 0).then(|| cell - axis),
+                    (z + 1 < axis).then(|| cell + axis),
+                    (y > 0).then(|| cell - axis * axis),
+                    (y + 1 < axis).then(|| cell + axis * axis),
+                ];
+                for neighbour in neighbours.into_iter().flatten() {
+                    if !closed[neighbour] {
+                        closed[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            visibility.connect_sides(sides);
+        }
+        visibility
+    }
+
+    /// Connect every pair of sides in a set of direction ordinal bits
+    fn connect_sides(&mut self, sides: u32) {
+        for from in &DIRECTIONS {
+            if sides & from.ordinal_bitwise() == 0 { continue; }
+            for to in &DIRECTIONS {
+                if sides & to.ordinal_bitwise() != 0 {
+                    self.connections |= 1 << (from.ordinal() * 6 + to.ordinal());
+                }
+            }
+        }
+    }
+
+    pub fn connects(&self, from: Direction, to: Direction) -> bool {
+        self.connections & (1 << (from.ordinal() * 6 + to.ordinal())) != 0
+    }
+}
+
+/**
+ * The sections that can be seen from the section of the camera, nearest first.
+ * A breadth first search that leaves a section only through sides connected to the side it came in from, and never heads back towards the camera.
+ * `visibility` is asked for every reached section, only sections within `bounds` (inclusive) that pass `is_visible`, like the ones in the frustum, are entered.
+ * A camera outside of the bounds, like one flying above the world, looks in through the layers of the bounds that face it
+ */
+pub fn visible_sections(camera_section: IVec3, bounds: (IVec3, IVec3), visibility: impl Fn(IVec3) -> SectionVisibility, is_visible: impl Fn(IVec3) -> bool) -> Vec<IVec3> {
+    let (min, max) = bounds;
+    let in_bounds = |section: IVec3| (0..3).all(|axis| (min[axis]..=max[axis]).contains(&section[axis]));
+    let mut visible = vec![];
+    let mut visited = HashSet::default();
+    // (section, the side it was entered through, every direction travelled to get there)
+    let mut queue: VecDeque<(IVec3, Option<Direction>, u32)> = VecDeque::new();
+    if in_bounds(camera_section) {
+        // The camera is in its section even when the section is not in the frustum, the search starts there without drawing it
+        if is_visible(camera_section) { visible.push(camera_section); }
+        visited.insert(camera_section);
+        queue.push_back((camera_section, None, 0));
+    } else {
+        let nearest = camera_section.clamped(min, max);
+        // The directions from the camera into the bounds, for every axis the camera is outside of
+        let inwards: Vec<Direction> = (0..3).filter(|&axis| nearest[axis] != camera_section[axis]).filter_map(|axis| {
+            let mut vector = IVec3::zero();
+            vector[axis] = (nearest[axis] - camera_section[axis]).signum();
+            Direction::from_int_vector(vector)
+        }).collect();
+        let entered_through = if let [inwards] = inwards.as_slice() { Some(inwards.reverse()) } else { None };
+        let travelled = inwards.iter().fold(0, |travelled, direction| travelled | direction.ordinal_bitwise());
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let section = IVec3::new(x, y, z);
+                    let on_layer = (0..3).any(|axis| nearest[axis] != camera_section[axis] && section[axis] == nearest[axis]);
+                    if !on_layer || !is_visible(section) { continue; }
+                    visible.push(section);
+                    visited.insert(section);
+                    queue.push_back((section, entered_through, travelled));
+                }
+            }
+        }
+    }
+    while let Some((section, entered_through, travelled)) = queue.pop_front() {
+        let section_visibility = visibility(section);
+        for direction in &DIRECTIONS {
+            if travelled & direction.reverse().ordinal_bitwise() != 0 { continue; }
+            if entered_through.is_some_and(|from| !section_visibility.connects(from, *direction)) { continue; }
+            let next = section + direction.get_int_vector();
+            if visited.contains(&next) || !in_bounds(next) || !is_visible(next) { continue; }
+            visited.insert(next);
+            visible.push(next);
+            queue.push_back((next, Some(direction.reverse()), travelled | direction.ordinal_bitwise()));
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walls_split_the_sides_of_a_section() {
+        assert_eq!(SectionVisibility::compute(|_, _, _| false), SectionVisibility::all());
+        assert_eq!(SectionVisibility::compute(|_, _, _| true), SectionVisibility::none());
+
+        // A wall across x splits the north side from the south side
+        let wall = SectionVisibility::compute(|x, _, _| x == 8);
+        assert!(!wall.connects(Direction::North, Direction::South));
+        assert!(wall.connects(Direction::North, Direction::Up));
+        assert!(wall.connects(Direction::South, Direction::East));
+        assert!(wall.connects(Direction::Up, Direction::Down));
+
+        // A thick floor with a hole still connects up and down
+        let floor = SectionVisibility::compute(|x, y, z| (y == 4 || y == 5) && !(x == 3 && z == 3));
+        assert!(floor.connects(Direction::Up, Direction::Down));
+        let sealed = SectionVisibility::compute(|_, y, _| y == 4);
+        assert!(!sealed.connects(Direction::Up, Direction::Down));
+        assert!(sealed.connects(Direction::North, Direction::South));
+    }
+
+    #[test]
+    fn search_stops_behind_solid_sections() {
+        // A row of sections along x, with a solid section at x = 2
+        let row = (IVec3::zero(), IVec3::new(5, 0, 0));
+        let visibility = |section: IVec3| if section.x == 2 { SectionVisibility::none() } else { SectionVisibility::all() };
+        let visible = visible_sections(IVec3::zero(), row, visibility, |_| true);
+        assert_eq!(visible, vec![IVec3::zero(), IVec3::new(1, 0, 0), IVec3::new(2, 0, 0)]);
+
+        // Starting inside a solid section still sees its neighbours
+        let visible = visible_sections(IVec3::new(2, 0, 0), row, visibility, |_| true);
+        assert_eq!(visible.len(), 6);
+
+        // A plane of open sections is seen entirely, without heading back towards the camera
+        let plane = (IVec3::new(-3, 0, -3), IVec3::new(3, 0, 3));
+        let visible = visible_sections(IVec3::zero(), plane, |_| SectionVisibility::all(), |_| true);
+        assert_eq!(visible.len(), 49);
+    }
+
+    #[test]
+    fn cameras_outside_the_bounds_look_in_from_their_side() {
+        // Three layers of 4 by 4 sections, the middle layer has a floor that seals the layers above and below from each other
+        let bounds = (IVec3::zero(), IVec3::new(3, 2, 3));
+        let sealed = SectionVisibility::compute(|_, y, _| y == 4);
+        let visibility = |section: IVec3| if section.y == 1 { sealed } else { SectionVisibility::all() };
+        let in_front = |section: IVec3| section.x < 3;
+
+        // From above the world the floor hides the bottom layer, and what is outside of the frustum is left out
+        let visible = visible_sections(IVec3::new(1, 8, 1), bounds, visibility, in_front);
+        assert_eq!(visible.len(), 24);
+        assert!(visible.iter().all(|section| section.y > 0 && section.x < 3));
+        // From below the world the floor hides the top layer
+        let visible = visible_sections(IVec3::new(1, -3, 1), bounds, visibility, |_| true);
+        assert_eq!(visible.len(), 32);
+        assert!(visible.iter().all(|section| section.y < 2));
+        // Far away along x every layer is seen through its x = 3 side
+        let visible = visible_sections(IVec3::new(20, 0, 1), bounds, visibility, |_| true);
+        assert_eq!(visible.len(), 48);
+        // A camera section outside of the frustum is not drawn, the search still starts from it
+        let visible = visible_sections(IVec3::zero(), bounds, |_| SectionVisibility::all(), |section| section != IVec3::zero());
+        assert_eq!(visible.len(), 47);
+    }
+}
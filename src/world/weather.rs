@@ -0,0 +1,338 @@
+use ultraviolet::{IVec3, Vec2, Vec3};
+
+use crate::util::java_random::JavaRandom;
+
+use super::biome::Climate;
+
+/// How much the rain strengthens or weakens every tick, it takes five seconds to fully start or stop
+pub const RAIN_FADE_PER_TICK: f32 = 0.01;
+/// Columns colder than this get snow instead of rain
+pub const SNOW_TEMPERATURE: f32 = 0.15;
+/// Hot and dry columns, like deserts, get no rain at all
+const DRY_TEMPERATURE: f32 = 0.95;
+const DRY_HUMIDITY: f32 = 0.2;
+/// How far around the camera rain and snow are drawn, in blocks
+pub const WEATHER_RADIUS: i32 = 10;
+/// Rain and snow textures repeat every four blocks down a column
+const WEATHER_TEXTURE_BLOCKS: f32 = 4.0;
+/// Each segment of a lightning bolt is this tall
+const LIGHTNING_SEGMENT_HEIGHT: f32 = 16.0;
+const LIGHTNING_SEGMENTS: usize = 8;
+/// Lightning is drawn as nested shells, each wider than the last
+const LIGHTNING_LAYERS: usize = 4;
+
+/// What falls on a column when it rains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precipitation {
+    None,
+    Rain,
+    Snow,
+}
+
+impl Precipitation {
+    pub fn from_climate(climate: &Climate) -> Self {
+        if climate.temperature < SNOW_TEMPERATURE {
+            Self::Snow
+        } else if climate.temperature > DRY_TEMPERATURE && climate.humidity < DRY_HUMIDITY {
+            Self::None
+        } else {
+            Self::Rain
+        }
+    }
+}
+
+/**
+ * The y rain and snow fall down to in a column, one above the highest block that stops them.
+ * `blocks_precipitation` is asked for every y from `top` down, columns with nothing in them get rain all the way to 0
+ */
+pub fn precipitation_height(top: i32, blocks_precipitation: impl Fn(i32) -> bool) -> i32 {
+    (0..top).rev().find(|y| blocks_precipitation(*y)).map_or(0, |y| y + 1)
+}
+
+/**
+ * A lightning bolt struck by the server, it flashes a few times before it is gone.
+ * Follows EntityLightningBolt of b1.7.3, every flash gets a new shape from `vertex_seed`
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightningBolt {
+    pub entity_id: i32,
+    pub position: Vec3,
+    /// The seed of the shape of the current flash
+    pub vertex_seed: i64,
+    random: JavaRandom,
+    /// Ticks left in the current flash, the bolt is dark while it is negative
+    state: i32,
+    /// Flashes left after the current one
+    flashes_left: i32,
+}
+
+impl LightningBolt {
+    pub fn new(entity_id: i32, position: Vec3, seed: i64) -> Self {
+        let mut random = JavaRandom::new(seed);
+        let vertex_seed = random.next_long();
+        let flashes_left = random.next_int_bounded(3) + 1;
+        Self { entity_id, position, vertex_seed, random, state: 2, flashes_left }
+    }
+
+    /// Advance the bolt by a tick, false once it is gone
+    pub fn tick(&mut self) -> bool {
+        self.state -= 1;
+        if self.state < 0 {
+            if self.flashes_left == 0 {
+                return false;
+            }
+            if self.state < -self.random.next_int_bounded(10) {
+                self.flashes_left -= 1;
+                self.state = 1;
+                self.vertex_seed = self.random.next_long();
+            }
+        }
+        true
+    }
+
+    /// Whether the bolt is flashing, it is only drawn then
+    pub fn is_visible(&self) -> bool {
+        self.state >= 0
+    }
+}
+
+/// The rain of a world, fading in and out as the server starts and stops it, and the lightning striking it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WeatherState {
+    raining: bool,
+    strength: f32,
+    previous_strength: f32,
+    bolts: Vec<LightningBolt>,
+}
+
+impl WeatherState {
+    pub fn set_raining(&mut self, raining: bool) {
+        self.raining = raining;
+    }
+
+    pub fn is_raining(&self) -> bool {
+        self.raining
+    }
+
+    /// How hard it rains between the last two ticks, from 0 to 1
+    pub fn get_strength(&self, partial_tick: f32) -> f32 {
+        self.previous_strength + (self.strength - self.previous_strength) * partial_tick
+    }
+
+    pub fn get_bolts(&self) -> &[LightningBolt] {
+        &self.bolts
+    }
+
+    pub fn strike(&mut self, bolt: LightningBolt) {
+        self.bolts.push(bolt);
+    }
+
+    pub fn tick(&mut self) {
+        self.previous_strength = self.strength;
+        let change = if self.raining { RAIN_FADE_PER_TICK } else { -RAIN_FADE_PER_TICK };
+        self.strength = (self.strength + change).clamp(0.0, 1.0);
+        self.bolts.retain_mut(LightningBolt::tick);
+    }
+}
+
+/// A column of falling rain or snow around the camera
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeatherColumn {
+    pub x: i32,
+    pub z: i32,
+    pub bottom: i32,
+    pub top: i32,
+    pub precipitation: Precipitation,
+}
+
+/**
+ * The columns within [`WEATHER_RADIUS`] of the camera that rain or snow falls in, from the precipitation height or
+ * the radius under the camera, whichever is higher, up to the radius over the camera
+ */
+pub fn weather_columns(camera: IVec3, height_of: impl Fn(i32, i32) -> i32, precipitation_of: impl Fn(i32, i32) -> Precipitation) -> Vec<WeatherColumn> {
+    let mut columns = vec![];
+    for x in camera.x - WEATHER_RADIUS..=camera.x + WEATHER_RADIUS {
+        for z in camera.z - WEATHER_RADIUS..=camera.z + WEATHER_RADIUS {
+            let precipitation = precipitation_of(x, z);
+            if precipitation == Precipitation::None { continue; }
+            let bottom = height_of(x, z).max(camera.y - WEATHER_RADIUS);
+            let top = camera.y + WEATHER_RADIUS;
+            if bottom < top {
+                columns.push(WeatherColumn { x, z, bottom, top, precipitation });
+            }
+        }
+    }
+    columns
+}
+
+/// A number from 0 to 31 that stays the same for a column, so neighbouring columns fall out of step
+fn column_offset(x: i32, z: i32) -> i32 {
+    x.wrapping_mul(x).wrapping_mul(3121)
+        .wrapping_add(x.wrapping_mul(45238971))
+        .wrapping_add(z.wrapping_mul(z).wrapping_mul(418711))
+        .wrapping_add(z.wrapping_mul(13761)) & 31
+}
+
+/**
+ * The sheet of a column, a quad across its middle turned to face the camera, with the uvs of the rain or snow texture scrolled down it.
+ * Corners are ordered top left, top right, bottom left, bottom right, along with how opaque the sheet is as it fades out towards the radius
+ */
+pub fn sheet_quad(column: &WeatherColumn, camera: Vec3, ticks: u64, partial_tick: f32) -> ([Vec3; 4], [Vec2; 4], f32) {
+    let center = Vec2::new(column.x as f32 + 0.5, column.z as f32 + 0.5);
+    let towards = center - Vec2::new(camera.x, camera.z);
+    let distance = towards.mag();
+    let across = if distance > 0.0 { Vec2::new(-towards.y, towards.x) / distance * 0.5 } else { Vec2::new(0.5, 0.0) };
+    let (left, right) = (center - across, center + across);
+    let (bottom, top) = (column.bottom as f32, column.top as f32);
+    let corners = [
+        Vec3::new(left.x, top, left.y),
+        Vec3::new(right.x, top, right.y),
+        Vec3::new(left.x, bottom, left.y),
+        Vec3::new(right.x, bottom, right.y),
+    ];
+
+    let time = (ticks % 4096) as f32 + partial_tick;
+    let offset = column_offset(column.x, column.z);
+    let (u, scroll) = match column.precipitation {
+        // Rain pours down, every column at its own pace
+        Precipitation::Rain => (0.0, -((time + offset as f32) / 32.0) * (3.0 + offset as f32 / 32.0)),
+        // Snow drifts slowly, and sideways a little
+        _ => (offset as f32 / 32.0 + time * 0.001, -time / 512.0),
+    };
+    let v_top = top / WEATHER_TEXTURE_BLOCKS + scroll;
+    let v_bottom = bottom / WEATHER_TEXTURE_BLOCKS + scroll;
+    let uvs = [Vec2::new(u, v_bottom), Vec2::new(u + 1.0, v_bottom), Vec2::new(u, v_top), Vec2::new(u + 1.0, v_top)];
+
+    let fade = distance / WEATHER_RADIUS as f32;
+    let alpha = ((1.0 - fade * fade) * 0.5 + 0.5).clamp(0.0, 1.0);
+    (corners, uvs, alpha)
+}
+
+/**
+ * Where rain splashes on the ground around the camera this tick, more of them the harder it rains.
+ * Snow and dry columns don't splash
+ */
+pub fn rain_splashes(random: &mut JavaRandom, camera: IVec3, strength: f32, height_of: impl Fn(i32, i32) -> i32, precipitation_of: impl Fn(i32, i32) -> Precipitation) -> Vec<Vec3> {
+    let attempts = (100.0 * strength * strength) as i32;
+    let mut splashes = vec![];
+    for _ in 0..attempts {
+        let x = camera.x + random.next_int_bounded(WEATHER_RADIUS * 2 + 1) - WEATHER_RADIUS;
+        let z = camera.z + random.next_int_bounded(WEATHER_RADIUS * 2 + 1) - WEATHER_RADIUS;
+        let (offset_x, offset_z) = (random.next_float(), random.next_float());
+        let y = height_of(x, z);
+        if (y - camera.y).abs() > WEATHER_RADIUS || precipitation_of(x, z) != Precipitation::Rain { continue; }
+        splashes.push(Vec3::new(x as f32 + offset_x, y as f32 + 0.1, z as f32 + offset_z));
+    }
+    splashes
+}
+
+/**
+ * The shells of a flash of lightning around the bolt position, as quads ordered top left, top right, bottom left, bottom right.
+ * The main bolt zigzags down eight segments, with two shorter branches splitting off it, as RenderLightningBolt of b1.7.3 draws them
+ */
+pub fn lightning_quads(vertex_seed: i64) -> Vec<[Vec3; 4]> {
+    let mut offsets_x = [0.0; LIGHTNING_SEGMENTS];
+    let mut offsets_z = [0.0; LIGHTNING_SEGMENTS];
+    let (mut end_x, mut end_z) = (0.0f32, 0.0f32);
+    let mut random = JavaRandom::new(vertex_seed);
+    for segment in (0..LIGHTNING_SEGMENTS).rev() {
+        offsets_x[segment] = end_x;
+        offsets_z[segment] = end_z;
+        end_x += (random.next_int_bounded(11) - 5) as f32;
+        end_z += (random.next_int_bounded(11) - 5) as f32;
+    }
+
+    let mut quads = vec![];
+    for layer in 0..LIGHTNING_LAYERS {
+        let mut random = JavaRandom::new(vertex_seed);
+        for branch in 0..3 {
+            let first = if branch == 0 { LIGHTNING_SEGMENTS - 1 } else { LIGHTNING_SEGMENTS - 1 - branch };
+            let last = if branch == 0 { 0 } else { first - 2 };
+            let (mut x, mut z) = (offsets_x[first] - end_x, offsets_z[first] - end_z);
+            for segment in (last..=first).rev() {
+                let (top_x, top_z) = (x, z);
+                let spread = if branch == 0 { 5 } else { 15 };
+                x += (random.next_int_bounded(spread * 2 + 1) - spread) as f32;
+                z += (random.next_int_bounded(spread * 2 + 1) - spread) as f32;
+                let mut top_width = 0.1 + layer as f32 * 0.2;
+                let mut bottom_width = 0.1 + layer as f32 * 0.2;
+                if branch == 0 {
+                    top_width *= segment as f32 * 0.1 + 1.0;
+                    bottom_width *= (segment as f32 - 1.0) * 0.1 + 1.0;
+                }
+                // The corners of the square around the bolt, going around it
+                let square = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)];
+                let bottom_y = segment as f32 * LIGHTNING_SEGMENT_HEIGHT;
+                let top_y = bottom_y + LIGHTNING_SEGMENT_HEIGHT;
+                let bottom_at = |(u, v): (f32, f32)| Vec3::new(0.5 + u * bottom_width + x, bottom_y, 0.5 + v * bottom_width + z);
+                let top_at = |(u, v): (f32, f32)| Vec3::new(0.5 + u * top_width + top_x, top_y, 0.5 + v * top_width + top_z);
+                for side in square.windows(2) {
+                    quads.push([top_at(side[0]), top_at(side[1]), bottom_at(side[0]), bottom_at(side[1])]);
+                }
+            }
+        }
+    }
+    quads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rain_fades_in_and_out() {
+        let mut weather = WeatherState::default();
+        weather.set_raining(true);
+        for _ in 0..50 { weather.tick(); }
+        assert!((weather.get_strength(1.0) - 0.5).abs() < 1e-4);
+        assert!((weather.get_strength(0.5) - 0.495).abs() < 1e-4);
+        for _ in 0..100 { weather.tick(); }
+        assert_eq!(weather.get_strength(1.0), 1.0);
+        weather.set_raining(false);
+        for _ in 0..100 { weather.tick(); }
+        assert!(weather.get_strength(1.0) < 1e-4);
+
+        weather.strike(LightningBolt::new(7, Vec3::zero(), 42));
+        assert!(weather.get_bolts()[0].is_visible());
+        // Bolts flash at most three times, each flash waits at most ten ticks
+        for _ in 0..60 { weather.tick(); }
+        assert!(weather.get_bolts().is_empty());
+    }
+
+    #[test]
+    fn columns_fall_to_the_ground_by_climate() {
+        assert_eq!(Precipitation::from_climate(&Climate::new(0.05, 0.5)), Precipitation::Snow);
+        assert_eq!(Precipitation::from_climate(&Climate::new(0.5, 0.5)), Precipitation::Rain);
+        assert_eq!(Precipitation::from_climate(&Climate::new(1.0, 0.1)), Precipitation::None);
+        assert_eq!(precipitation_height(128, |y| y == 64 || y == 10), 65);
+        assert_eq!(precipitation_height(128, |_| false), 0);
+
+        let camera = IVec3::new(0, 70, 0);
+        let columns = weather_columns(camera, |x, _| if x > 0 { 100 } else { 64 }, |_, z| if z > 5 { Precipitation::None } else { Precipitation::Rain });
+        // Columns under a roof above the camera have no rain
+        assert!(columns.iter().all(|column| column.x <= 0 && column.z <= 5));
+        assert!(columns.iter().all(|column| column.bottom == 64 && column.top == 80));
+
+        let column = columns.iter().find(|column| column.x == -3 && column.z == 0).unwrap();
+        let (corners, uvs, alpha) = sheet_quad(column, Vec3::new(0.5, 70.0, 0.5), 100, 0.0);
+        assert_eq!(corners[0].y, 80.0);
+        assert_eq!(corners[3].y, 64.0);
+        assert!((uvs[2].y - uvs[0].y - 4.0).abs() < 1e-4, "the texture repeats every four blocks");
+        assert!((0.5..=1.0).contains(&alpha));
+    }
+
+    #[test]
+    fn lightning_and_splashes_are_seeded() {
+        let bolt = lightning_quads(1234);
+        assert_eq!(bolt, lightning_quads(1234));
+        assert_ne!(bolt, lightning_quads(4321));
+        // Four sides for every segment of the main bolt and both branches, in every layer
+        assert_eq!(bolt.len(), LIGHTNING_LAYERS * (8 + 3 + 3) * 4);
+        assert!(bolt.iter().all(|quad| quad[0].y > quad[2].y));
+
+        let splashes = rain_splashes(&mut JavaRandom::new(5), IVec3::new(0, 64, 0), 1.0, |_, _| 64, |_, _| Precipitation::Rain);
+        assert_eq!(splashes.len(), 100);
+        assert!(splashes.iter().all(|splash| splash.y == 64.1 && splash.x.abs() <= 11.0));
+        assert!(rain_splashes(&mut JavaRandom::new(5), IVec3::new(0, 64, 0), 1.0, |_, _| 64, |_, _| Precipitation::Snow).is_empty());
+    }
+}